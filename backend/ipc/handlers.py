@@ -120,7 +120,10 @@ def handle_transcribe_chunk(msg: IPCMessage) -> IPCResponse:
     and returns the transcription result.
 
     Required payload fields: audio_base64.
-    Optional payload fields: initial_prompt, language.
+    Optional payload fields: initial_prompt, language, compression.
+
+    When ``compression`` is ``"gzip"`` the base64 payload decodes to
+    gzip-compressed PCM, which is decompressed before transcription.
     """
     if "audio_base64" not in msg.payload:
         return IPCResponse.error(
@@ -128,8 +131,23 @@ def handle_transcribe_chunk(msg: IPCMessage) -> IPCResponse:
         )
 
     import base64
+    import binascii
 
-    audio_bytes = base64.b64decode(msg.payload["audio_base64"])
+    try:
+        audio_bytes = base64.b64decode(msg.payload["audio_base64"])
+    except (binascii.Error, TypeError, ValueError) as exc:
+        return IPCResponse.error(f"Invalid base64 audio payload: {exc}")
+    compression: str | None = msg.payload.get("compression")
+    if compression == "gzip":
+        import gzip
+        import zlib
+
+        try:
+            audio_bytes = gzip.decompress(audio_bytes)
+        except (OSError, EOFError, zlib.error) as exc:
+            return IPCResponse.error(f"Invalid gzip audio payload: {exc}")
+    elif compression is not None:
+        return IPCResponse.error(f"Unsupported audio compression: {compression}")
     initial_prompt: str = msg.payload.get("initial_prompt", "")
     language: str | None = msg.payload.get("language")
 
@@ -104,6 +104,63 @@ class TestHandleTranscribeChunkWired:
             len(call_kwargs[0]) > 1 and call_kwargs[0][1] == "Alice Bob sprint"
         )
 
+    def test_decompresses_gzip_audio_before_transcribing(self) -> None:
+        """Verify gzip-compressed payloads reach the engine as raw PCM."""
+        import gzip
+
+        from ipc.handlers import handle_transcribe_chunk
+
+        mock_engine_cls = MagicMock()
+        mock_engine_inst = MagicMock()
+        mock_engine_inst.transcribe.return_value = []
+        mock_engine_cls.return_value = mock_engine_inst
+
+        audio_bytes = b"\x00\x01" * 100
+        audio_b64 = base64.b64encode(gzip.compress(audio_bytes)).decode()
+
+        msg = IPCMessage(
+            type=MessageType.TRANSCRIBE_CHUNK,
+            payload={"audio_base64": audio_b64, "compression": "gzip"},
+        )
+        with patch("transcription.engine.TranscriptionEngine", mock_engine_cls):
+            resp = handle_transcribe_chunk(msg)
+
+        assert resp.type == ResponseType.TRANSCRIPTION
+        assert mock_engine_inst.transcribe.call_args[0][0] == audio_bytes
+
+    def test_rejects_corrupt_gzip_audio(self) -> None:
+        """Verify a truncated or malformed gzip payload returns an error."""
+        import gzip
+
+        from ipc.handlers import handle_transcribe_chunk
+
+        truncated = gzip.compress(b"\x00\x01" * 100)[:-8]
+        for payload in (truncated, b"not gzip at all"):
+            msg = IPCMessage(
+                type=MessageType.TRANSCRIBE_CHUNK,
+                payload={
+                    "audio_base64": base64.b64encode(payload).decode(),
+                    "compression": "gzip",
+                },
+            )
+            resp = handle_transcribe_chunk(msg)
+
+            assert resp.type == ResponseType.ERROR
+            assert "gzip" in resp.data["message"]
+
+    def test_rejects_unknown_compression(self) -> None:
+        """Verify an unsupported compression value returns an error."""
+        from ipc.handlers import handle_transcribe_chunk
+
+        msg = IPCMessage(
+            type=MessageType.TRANSCRIBE_CHUNK,
+            payload={"audio_base64": "dGVzdA==", "compression": "zstd"},
+        )
+        resp = handle_transcribe_chunk(msg)
+
+        assert resp.type == ResponseType.ERROR
+        assert "zstd" in resp.data["message"]
+
     def test_returns_empty_text_for_silence(self) -> None:
         """Verify the handler returns empty text when engine returns no segments."""
         from ipc.handlers import handle_transcribe_chunk
@@ -0,0 +1,145 @@
+//! Version details for bug reports.
+//!
+//! Collects the app's own version, the Tauri version it was built against,
+//! the platform, and whatever the Python backend reports about itself, so a
+//! report can quote them all at once.
+
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::protocol::SidecarRequest;
+use crate::sidecar::SidecarManager;
+
+/// How long the sidecar is given to answer a `version` request.
+pub const VERSION_QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Versions of every part of the app, as returned by the `about_info`
+/// command. The backend fields are `None` when the sidecar is not running
+/// or does not answer `version`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct AboutInfo {
+    pub app_version: String,
+    pub tauri_version: String,
+    pub backend_version: Option<String>,
+    pub protocol_version: Option<String>,
+    /// `std::env::consts::OS`, e.g. `"macos"`.
+    pub os: String,
+    /// `std::env::consts::ARCH`, e.g. `"aarch64"`.
+    pub arch: String,
+}
+
+/// What a backend reports in its `version` response.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BackendVersion {
+    pub version: Option<String>,
+    pub protocol_version: Option<String>,
+}
+
+impl BackendVersion {
+    /// Read a `{"type": "version", "version", "protocol_version"}`
+    /// response. Version numbers sent as JSON numbers are accepted too.
+    /// Returns `None` for any other response, e.g. the `error` an older
+    /// backend sends for the unknown request.
+    pub fn from_response(response: &Value) -> Option<Self> {
+        if response.get("type").and_then(Value::as_str) != Some("version") {
+            return None;
+        }
+        let field = |name: &str| match response.get(name)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+        Some(Self {
+            version: field("version"),
+            protocol_version: field("protocol_version"),
+        })
+    }
+}
+
+/// Ask the sidecar for its version, if it is running. Any failure yields
+/// `None` rather than an error, so the rest of the report is still shown.
+pub fn query_backend_version(mgr: &mut SidecarManager) -> Option<BackendVersion> {
+    if !mgr.is_running() {
+        return None;
+    }
+    let message = serde_json::to_value(SidecarRequest::Version).ok()?;
+    let response = mgr
+        .send_message_with_timeout(message, VERSION_QUERY_TIMEOUT)
+        .ok()?;
+    BackendVersion::from_response(&response)
+}
+
+/// Assemble the report from the Tauri version and the backend's answer.
+pub fn about_info(tauri_version: &str, backend: Option<BackendVersion>) -> AboutInfo {
+    let backend = backend.unwrap_or_default();
+    AboutInfo {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        tauri_version: tauri_version.to_string(),
+        backend_version: backend.version,
+        protocol_version: backend.protocol_version,
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_about_info_includes_backend_version_response() {
+        let backend = BackendVersion::from_response(&json!({
+            "type": "version",
+            "version": "0.7.2",
+            "protocol_version": 3,
+        }));
+        let info = about_info("2.1.0", backend);
+
+        assert_eq!(
+            serde_json::to_value(&info).expect("serialize"),
+            json!({
+                "app_version": env!("CARGO_PKG_VERSION"),
+                "tauri_version": "2.1.0",
+                "backend_version": "0.7.2",
+                "protocol_version": "3",
+                "os": std::env::consts::OS,
+                "arch": std::env::consts::ARCH,
+            })
+        );
+    }
+
+    #[test]
+    fn test_about_info_without_backend_has_null_backend_fields() {
+        let info = about_info("2.1.0", None);
+        let json = serde_json::to_value(&info).expect("serialize");
+        assert!(json["backend_version"].is_null());
+        assert!(json["protocol_version"].is_null());
+        assert_eq!(json["app_version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn test_backend_version_ignores_other_responses() {
+        let error = json!({"type": "error", "message": "Unknown message type: version"});
+        assert_eq!(BackendVersion::from_response(&error), None);
+
+        let partial = BackendVersion::from_response(&json!({"type": "version", "version": "1.0"}));
+        assert_eq!(
+            partial,
+            Some(BackendVersion {
+                version: Some("1.0".into()),
+                protocol_version: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_query_backend_version_is_none_when_not_running() {
+        assert_eq!(query_backend_version(&mut SidecarManager::new()), None);
+    }
+}
@@ -0,0 +1,130 @@
+//! Lines up two independently clocked mono tracks as one stereo stream.
+//!
+//! A dual capture (microphone plus system audio) runs two input streams
+//! whose callbacks deliver buffers of different sizes at different times.
+//! [`TrackAligner`] queues each track and releases frames only once both
+//! have audio for them, so the left and right channels of the file stay in
+//! step.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// How far one track may run ahead before the other is taken to have
+/// stalled and is padded with silence, so a stream that stops delivering
+/// cannot hold the recording back indefinitely.
+pub const MAX_TRACK_LAG: Duration = Duration::from_millis(500);
+
+/// One of the two channels of a dual capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Track {
+    Left,
+    Right,
+}
+
+/// Queues two mono tracks and hands out interleaved stereo frames as soon
+/// as both tracks cover them.
+#[derive(Debug, Clone, Default)]
+pub struct TrackAligner {
+    left: VecDeque<i16>,
+    right: VecDeque<i16>,
+    /// Frames one track may be ahead of the other; see [`MAX_TRACK_LAG`].
+    max_lag_frames: usize,
+}
+
+impl TrackAligner {
+    /// An aligner that pads the lagging track once the other is more than
+    /// `max_lag_frames` ahead.
+    pub fn new(max_lag_frames: usize) -> Self {
+        Self {
+            left: VecDeque::new(),
+            right: VecDeque::new(),
+            max_lag_frames,
+        }
+    }
+
+    /// Queue mono `samples` for `track` and return the stereo frames that
+    /// are now complete, interleaved left/right.
+    pub fn push(&mut self, track: Track, samples: &[i16]) -> Vec<i16> {
+        match track {
+            Track::Left => self.left.extend(samples),
+            Track::Right => self.right.extend(samples),
+        }
+        let ready = self.left.len().min(self.right.len());
+        let ahead = self.left.len().max(self.right.len());
+        self.drain(ready.max(ahead.saturating_sub(self.max_lag_frames)))
+    }
+
+    /// Everything still queued, with the shorter track padded with silence.
+    pub fn flush(&mut self) -> Vec<i16> {
+        self.drain(self.left.len().max(self.right.len()))
+    }
+
+    /// Take `frames` frames off the queues, padding a short track with
+    /// silence.
+    fn drain(&mut self, frames: usize) -> Vec<i16> {
+        let mut out = Vec::with_capacity(frames * 2);
+        for _ in 0..frames {
+            out.push(self.left.pop_front().unwrap_or(0));
+            out.push(self.right.pop_front().unwrap_or(0));
+        }
+        out
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_wait_for_both_tracks() {
+        let mut aligner = TrackAligner::new(100);
+        assert!(aligner.push(Track::Left, &[1, 2, 3]).is_empty());
+        assert_eq!(aligner.push(Track::Right, &[-1, -2]), [1, -1, 2, -2]);
+        // The third left sample is still waiting for its partner.
+        assert_eq!(aligner.push(Track::Right, &[-3, -4]), [3, -3]);
+        assert_eq!(aligner.push(Track::Left, &[4, 5, 6]), [4, -4]);
+        assert_eq!(aligner.flush(), [5, 0, 6, 0]);
+        assert!(aligner.flush().is_empty());
+    }
+
+    #[test]
+    fn test_mismatched_buffer_sizes_keep_tracks_in_step() {
+        // Left delivers 160-frame buffers, right 441-frame ones; each
+        // sample is its frame index, so any misalignment shows.
+        let mut aligner = TrackAligner::new(10_000);
+        let mut out = Vec::new();
+        let (mut left, mut right) = (0i16, 0i16);
+        while left < 2_000 || right < 2_000 {
+            if left <= right && left < 2_000 {
+                let buffer: Vec<i16> = (left..(left + 160).min(2_000)).collect();
+                left += buffer.len() as i16;
+                out.extend(aligner.push(Track::Left, &buffer));
+            } else {
+                let buffer: Vec<i16> = (right..(right + 441).min(2_000)).collect();
+                right += buffer.len() as i16;
+                out.extend(aligner.push(Track::Right, &buffer));
+            }
+        }
+        out.extend(aligner.flush());
+
+        assert_eq!(out.len(), 4_000);
+        for (i, frame) in out.chunks(2).enumerate() {
+            assert_eq!(frame, [i as i16, i as i16]);
+        }
+    }
+
+    #[test]
+    fn test_stalled_track_is_padded_after_max_lag() {
+        let mut aligner = TrackAligner::new(4);
+        assert!(aligner.push(Track::Left, &[1, 2, 3, 4]).is_empty());
+        // Two frames past the allowed lag are released with silence on the
+        // right.
+        assert_eq!(aligner.push(Track::Left, &[5, 6]), [1, 0, 2, 0]);
+        // When the right track catches up its audio follows the gap.
+        assert_eq!(aligner.push(Track::Right, &[-1, -2]), [3, -1, 4, -2]);
+    }
+}
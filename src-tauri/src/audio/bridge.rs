@@ -0,0 +1,281 @@
+//! Live audio bridge — streams captured audio to a browser over loopback.
+//!
+//! IPC events are too coarse for drawing a full-resolution waveform, so the
+//! capture thread can also [publish](FrameBroadcast::publish) every buffer
+//! it writes to a [`FrameBroadcast`]. An [`AudioBridge`] serves those buffers
+//! as Server-Sent Events on `127.0.0.1`: each event's `data` is a JSON array
+//! of mono 16-bit samples at [`BRIDGE_SAMPLE_RATE`]. Only loopback is bound,
+//! so nothing off the machine can listen in.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// Rate the bridge's samples are decimated to; plenty for a waveform.
+pub const BRIDGE_SAMPLE_RATE: u32 = 8_000;
+
+/// Events a slow client may fall behind by before new ones are dropped for
+/// it.
+const CLIENT_QUEUE_EVENTS: usize = 64;
+
+/// Fans captured buffers out to every connected bridge client.
+#[derive(Debug, Default)]
+pub struct FrameBroadcast {
+    clients: Mutex<Vec<mpsc::SyncSender<Arc<str>>>>,
+}
+
+impl FrameBroadcast {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send interleaved `samples` of a `channels`-channel, `sample_rate` Hz
+    /// stream to every client, mixed to mono and decimated to
+    /// [`BRIDGE_SAMPLE_RATE`]. Never blocks: a client whose queue is full
+    /// misses the buffer, and one that has gone is forgotten.
+    pub fn publish(&self, samples: &[i16], channels: u16, sample_rate: u32) {
+        let Ok(mut clients) = self.clients.try_lock() else {
+            return;
+        };
+        if clients.is_empty() {
+            return;
+        }
+        let event: Arc<str> = sse_event(&downsample(samples, channels, sample_rate)).into();
+        clients.retain(|client| {
+            !matches!(
+                client.try_send(Arc::clone(&event)),
+                Err(mpsc::TrySendError::Disconnected(_))
+            )
+        });
+    }
+
+    /// A new client's queue of events.
+    fn subscribe(&self) -> mpsc::Receiver<Arc<str>> {
+        let (tx, rx) = mpsc::sync_channel(CLIENT_QUEUE_EVENTS);
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.push(tx);
+        }
+        rx
+    }
+
+    /// Drop every client, ending their streams.
+    fn disconnect_all(&self) {
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.clear();
+        }
+    }
+}
+
+/// Where a running bridge can be reached.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct AudioBridgeInfo {
+    pub url: String,
+}
+
+/// A loopback Server-Sent Events server streaming a [`FrameBroadcast`].
+///
+/// Each client is served on its own thread until it disconnects or the
+/// bridge is [stopped](Self::stop).
+pub struct AudioBridge {
+    addr: SocketAddr,
+    broadcast: Arc<FrameBroadcast>,
+    stop_flag: Arc<AtomicBool>,
+    thread_handle: Option<JoinHandle<()>>,
+}
+
+impl AudioBridge {
+    /// Listen on a free port on `127.0.0.1`.
+    ///
+    /// # Errors
+    /// Returns an error if no port can be bound.
+    pub fn start() -> Result<Self, String> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .map_err(|e| format!("Failed to start audio bridge: {e}"))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to start audio bridge: {e}"))?;
+        let broadcast = Arc::new(FrameBroadcast::new());
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let thread_handle = std::thread::spawn({
+            let broadcast = Arc::clone(&broadcast);
+            let stop_flag = Arc::clone(&stop_flag);
+            move || {
+                for stream in listener.incoming() {
+                    if stop_flag.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let events = broadcast.subscribe();
+                        std::thread::spawn(move || serve_client(stream, events));
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            broadcast,
+            stop_flag,
+            thread_handle: Some(thread_handle),
+        })
+    }
+
+    pub fn info(&self) -> AudioBridgeInfo {
+        AudioBridgeInfo {
+            url: format!("http://{}/", self.addr),
+        }
+    }
+
+    /// The broadcast to hand to the capture thread.
+    pub fn broadcast(&self) -> Arc<FrameBroadcast> {
+        Arc::clone(&self.broadcast)
+    }
+
+    /// Stop accepting clients and end the streams of those connected.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+        self.broadcast.disconnect_all();
+    }
+}
+
+impl Drop for AudioBridge {
+    fn drop(&mut self) {
+        if self.thread_handle.is_some() {
+            self.shutdown();
+        }
+    }
+}
+
+/// Answer a client's request with an event stream and forward `events`
+/// until either side goes away.
+fn serve_client(stream: TcpStream, events: mpsc::Receiver<Arc<str>>) {
+    // The request itself doesn't matter; read past its headers.
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    while reader.read_line(&mut line).is_ok_and(|n| n > 0) && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let mut stream = &stream;
+    let header = "HTTP/1.1 200 OK\r\n\
+        Content-Type: text/event-stream\r\n\
+        Cache-Control: no-cache\r\n\
+        Access-Control-Allow-Origin: *\r\n\r\n";
+    if stream.write_all(header.as_bytes()).is_err() {
+        return;
+    }
+    for event in events {
+        if stream
+            .write_all(event.as_bytes())
+            .and_then(|()| stream.flush())
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Mix interleaved `samples` to mono and keep every n-th frame, n chosen to
+/// bring `sample_rate` down to at most [`BRIDGE_SAMPLE_RATE`].
+fn downsample(samples: &[i16], channels: u16, sample_rate: u32) -> Vec<i16> {
+    let channels = usize::from(channels.max(1));
+    let step = sample_rate.div_ceil(BRIDGE_SAMPLE_RATE).max(1) as usize;
+    samples
+        .chunks_exact(channels)
+        .step_by(step)
+        .map(|frame| {
+            let sum: i32 = frame.iter().map(|&s| i32::from(s)).sum();
+            (sum / channels as i32) as i16
+        })
+        .collect()
+}
+
+/// One Server-Sent Event carrying `samples` as a JSON array.
+fn sse_event(samples: &[i16]) -> String {
+    let data = serde_json::to_string(samples).unwrap_or_else(|_| "[]".into());
+    format!("data: {data}\n\n")
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downsample_mixes_and_decimates() {
+        // 16 kHz stereo: every other frame, channels averaged.
+        let samples = [100, 300, 1, 1, -200, 0, 1, 1];
+        assert_eq!(downsample(&samples, 2, 16_000), vec![200, -100]);
+        // Already at the bridge rate: untouched.
+        assert_eq!(downsample(&[1, 2, 3], 1, 8_000), vec![1, 2, 3]);
+        assert_eq!(sse_event(&[1, -2]), "data: [1,-2]\n\n");
+    }
+
+    #[test]
+    fn test_client_receives_published_frames_over_loopback() {
+        let bridge = AudioBridge::start().expect("start bridge");
+        assert!(bridge.addr.ip().is_loopback());
+        let url = bridge.info().url;
+        let host = url
+            .strip_prefix("http://")
+            .and_then(|rest| rest.strip_suffix('/'))
+            .expect("bridge url");
+        assert!(host.starts_with("127.0.0.1:"), "got {url}");
+
+        let mut client = TcpStream::connect(host).expect("connect");
+        client
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .expect("read timeout");
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .expect("send request");
+        let mut reader = BufReader::new(&client);
+        let mut status = String::new();
+        reader.read_line(&mut status).expect("status line");
+        assert!(status.starts_with("HTTP/1.1 200"), "got {status:?}");
+
+        // The client is subscribed once the accept loop has seen it.
+        let broadcast = bridge.broadcast();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while broadcast.clients.lock().unwrap().is_empty() {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "client never subscribed"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        broadcast.publish(&[100, 300, -200, 0], 2, 8_000);
+
+        let mut line = String::new();
+        while !line.starts_with("data: ") {
+            line.clear();
+            reader.read_line(&mut line).expect("read event");
+            assert!(!line.is_empty(), "stream ended before an event");
+        }
+        assert_eq!(line, "data: [200,-100]\n");
+
+        bridge.stop();
+    }
+
+    #[test]
+    fn test_publish_without_clients_is_a_no_op() {
+        let broadcast = FrameBroadcast::new();
+        broadcast.publish(&[0; 160], 1, 16_000);
+        assert!(broadcast.clients.lock().unwrap().is_empty());
+    }
+}
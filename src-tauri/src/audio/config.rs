@@ -0,0 +1,466 @@
+//! User-adjustable capture settings.
+//!
+//! The config is stored in Tauri state, round-tripped to the frontend via
+//! serde, and handed to the capture engine when a recording starts.
+
+use std::time::Duration;
+
+use crate::audio::capture::{BITS_PER_SAMPLE, CHANNELS, SAMPLE_RATE};
+
+/// Lowest and highest output sample rates accepted by [`AudioCaptureConfig`].
+pub const MIN_SAMPLE_RATE: u32 = 8_000;
+pub const MAX_SAMPLE_RATE: u32 = 192_000;
+
+/// Highest linear gain accepted by [`AudioCaptureConfig`].
+const MAX_GAIN: f32 = 16.0;
+
+/// Shortest segment length accepted by [`AudioCaptureConfig`].
+const MIN_SEGMENT_DURATION: Duration = Duration::from_secs(1);
+
+/// Smallest per-file size cap accepted; anything less would stop a
+/// recording almost at once.
+const MIN_MAX_FILE_BYTES: u64 = 64 * 1024;
+
+/// Longest post-roll accepted, since `stop` waits for it.
+const MAX_POST_ROLL: Duration = Duration::from_secs(10);
+
+/// Default recording format; always compiled in.
+pub const FORMAT_WAV: &str = "wav";
+
+/// Every recording format the app knows about, and whether it is compiled
+/// into this build.
+const KNOWN_FORMATS: &[(&str, bool)] = &[
+    (FORMAT_WAV, true),
+    ("opus", cfg!(feature = "opus")),
+    ("mp3", cfg!(feature = "mp3")),
+];
+
+/// Recording formats available in this build, in preference order.
+pub fn supported_recording_formats() -> Vec<String> {
+    KNOWN_FORMATS
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| (*name).to_string())
+        .collect()
+}
+
+/// What a paused stretch of a recording becomes in the written file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseBehavior {
+    /// Drop the paused time; the audio either side is joined directly.
+    #[default]
+    Splice,
+    /// Fill the paused time with digital silence so the file's timeline
+    /// matches wall-clock time (needed by subtitle aligners).
+    InsertSilence,
+}
+
+/// How recordings are arranged inside the recordings directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectoryLayout {
+    /// Every recording directly inside the recordings directory.
+    #[default]
+    Flat,
+    /// `YYYY/MM/` subfolders (UTC) named after the recording's start time.
+    DateHierarchy,
+}
+
+/// Settings that control how audio is captured and written.
+///
+/// Missing fields deserialize to their defaults so the frontend can send a
+/// partial config.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AudioCaptureConfig {
+    /// Sample rate of the written WAV, in Hz.
+    pub sample_rate: u32,
+    /// Channel count of the written WAV (1 = mono, 2 = stereo).
+    pub channels: u16,
+    /// Linear gain applied to every sample before it is written.
+    pub gain: f32,
+    /// Output file format; one of [`supported_recording_formats`].
+    pub format: String,
+    /// Split the recording into `_partNNN` files of this length. `None`
+    /// writes a single file. Sent over IPC as `segment_duration_ms`.
+    #[serde(rename = "segment_duration_ms", with = "duration_ms")]
+    pub segment_duration: Option<Duration>,
+    /// `fsync` each WAV after it is finalized so a power loss right after
+    /// stopping cannot lose it. Off by default because it adds latency to
+    /// `stop`.
+    pub fsync_on_finalize: bool,
+    /// How a pause is represented in the file on resume.
+    pub pause_behavior: PauseBehavior,
+    /// Write the device's native sample rate and channel count untouched
+    /// instead of converting to `sample_rate` / `channels`, leaving
+    /// resampling to the sidecar. `gain`, `voice_filter` and `agc` still
+    /// apply.
+    pub native_passthrough: bool,
+    /// Where new recordings are placed inside the recordings directory.
+    pub directory_layout: DirectoryLayout,
+    /// Create the recordings directory if it is missing. When off, `start`
+    /// fails instead, so a misconfigured path is not silently created.
+    pub auto_create_dir: bool,
+    /// Band-pass the input to the speech range (see
+    /// [`VoiceFilter`](crate::audio::filter::VoiceFilter)) before it is
+    /// written, to cut rumble and hiss in noisy rooms.
+    pub voice_filter: bool,
+    /// Slow automatic gain control (see
+    /// [`Agc`](crate::audio::filter::Agc)) that keeps the level steady as
+    /// the speaker moves relative to the microphone. Applied after `gain`
+    /// and `voice_filter`.
+    pub agc: bool,
+    /// Cap on the total size of the recordings directory. When set, the
+    /// oldest recordings are deleted after each stop until it fits. `None`
+    /// (the default) keeps everything.
+    pub max_total_bytes: Option<u64>,
+    /// Cap on the size of a recording's WAV, e.g. for an upload limit.
+    /// Capture stops by itself just before the file would grow past it
+    /// (segments count together). `None` (the default) sets no cap.
+    pub max_file_bytes: Option<u64>,
+    /// Warn once (`audio://duration-warning`) when the recording reaches
+    /// this length, without stopping it, so the user can split long
+    /// sessions the transcription model would struggle with. Sent over IPC
+    /// as `soft_duration_warn_ms`.
+    #[serde(rename = "soft_duration_warn_ms", with = "duration_ms")]
+    pub soft_duration_warn: Option<Duration>,
+    /// Keep capturing this long after stop is pressed, so trailing words
+    /// are not cut off by an early stop. `stop` returns once it has been
+    /// written. Sent over IPC as `post_roll_ms`; at most 10 s.
+    #[serde(rename = "post_roll_ms", with = "duration_ms")]
+    pub post_roll: Option<Duration>,
+    /// Ask for exclusive use of the input device (WASAPI exclusive mode)
+    /// for lower latency, keeping other apps off the microphone. Where it
+    /// is unavailable the recording falls back to shared mode; on platforms
+    /// without such a mode it is ignored. See
+    /// [`exclusive_support`](crate::audio::devices::exclusive_support).
+    pub exclusive: bool,
+    /// Add a Broadcast Wave (BWF) `bext` chunk with the origination date,
+    /// time and time reference to each WAV, for placing recordings on a
+    /// DAW timeline.
+    pub broadcast_wave: bool,
+    /// Record only this input channel (0-based) of a multi-channel device,
+    /// e.g. the lav mic on an 8-channel interface, instead of mixing all of
+    /// them. Checked against the device's channel count when capture
+    /// starts. Cannot be combined with `native_passthrough`.
+    pub source_channel: Option<u16>,
+}
+
+impl Default for AudioCaptureConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: SAMPLE_RATE,
+            channels: CHANNELS,
+            gain: 1.0,
+            format: FORMAT_WAV.to_string(),
+            segment_duration: None,
+            fsync_on_finalize: false,
+            pause_behavior: PauseBehavior::Splice,
+            native_passthrough: false,
+            directory_layout: DirectoryLayout::Flat,
+            auto_create_dir: true,
+            voice_filter: false,
+            agc: false,
+            max_total_bytes: None,
+            max_file_bytes: None,
+            soft_duration_warn: None,
+            post_roll: None,
+            exclusive: false,
+            broadcast_wave: false,
+            source_channel: None,
+        }
+    }
+}
+
+impl AudioCaptureConfig {
+    /// Byte rate of the WAV data written with this config.
+    pub fn bytes_per_second(&self) -> u64 {
+        u64::from(self.sample_rate) * u64::from(self.channels) * u64::from(BITS_PER_SAMPLE / 8)
+    }
+
+    /// Number of output frames per segment, or `None` when segmentation is
+    /// off.
+    pub fn segment_frames(&self) -> Option<u64> {
+        self.segment_frames_at(self.sample_rate)
+    }
+
+    /// [`segment_frames`](Self::segment_frames) for a file written at
+    /// `sample_rate` (the device rate under `native_passthrough`).
+    pub fn segment_frames_at(&self, sample_rate: u32) -> Option<u64> {
+        self.segment_duration
+            .map(|d| (d.as_secs_f64() * f64::from(sample_rate)).round() as u64)
+    }
+
+    /// Check that every field is within a supported range.
+    ///
+    /// # Errors
+    /// Returns a message naming the first invalid field.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&self.sample_rate) {
+            return Err(format!(
+                "Invalid sample_rate {}: must be between {MIN_SAMPLE_RATE} and {MAX_SAMPLE_RATE} Hz",
+                self.sample_rate
+            ));
+        }
+        if !(1..=2).contains(&self.channels) {
+            return Err(format!(
+                "Invalid channels {}: must be 1 (mono) or 2 (stereo)",
+                self.channels
+            ));
+        }
+        if !self.gain.is_finite() || self.gain <= 0.0 || self.gain > MAX_GAIN {
+            return Err(format!(
+                "Invalid gain {}: must be greater than 0 and at most {MAX_GAIN}",
+                self.gain
+            ));
+        }
+        match KNOWN_FORMATS.iter().find(|(name, _)| *name == self.format) {
+            None => {
+                return Err(format!(
+                    "Unknown recording format '{}': expected one of {}",
+                    self.format,
+                    supported_recording_formats().join(", ")
+                ));
+            }
+            Some((_, false)) => {
+                return Err(format!(
+                    "Recording format '{}' is not available in this build (supported: {})",
+                    self.format,
+                    supported_recording_formats().join(", ")
+                ));
+            }
+            Some((_, true)) => {}
+        }
+        if let Some(segment) = self.segment_duration {
+            if segment < MIN_SEGMENT_DURATION {
+                return Err(format!(
+                    "Invalid segment_duration_ms {}: must be at least {} ms",
+                    segment.as_millis(),
+                    MIN_SEGMENT_DURATION.as_millis()
+                ));
+            }
+        }
+        if let Some(max) = self.max_file_bytes {
+            if max < MIN_MAX_FILE_BYTES {
+                return Err(format!(
+                    "Invalid max_file_bytes {max}: must be at least {MIN_MAX_FILE_BYTES}"
+                ));
+            }
+        }
+        if let Some(post_roll) = self.post_roll {
+            if post_roll > MAX_POST_ROLL {
+                return Err(format!(
+                    "Invalid post_roll_ms {}: must be at most {} ms",
+                    post_roll.as_millis(),
+                    MAX_POST_ROLL.as_millis()
+                ));
+            }
+        }
+        if self.source_channel.is_some() && self.native_passthrough {
+            return Err(
+                "Invalid source_channel: cannot pick a channel under native_passthrough".into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Serde adapter for `Option<Duration>` as whole milliseconds.
+mod duration_ms {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(d) => s.serialize_some(&(d.as_millis() as u64)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(d)?.map(Duration::from_millis))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_is_speech_format() {
+        let config = AudioCaptureConfig::default();
+        assert_eq!(config.sample_rate, 16_000);
+        assert_eq!(config.channels, 1);
+        assert_eq!(config.bytes_per_second(), 32_000);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_partial_json_fills_defaults() {
+        let config: AudioCaptureConfig =
+            serde_json::from_str(r#"{"sample_rate": 48000}"#).expect("parse");
+        assert_eq!(config.sample_rate, 48_000);
+        assert_eq!(config.channels, 1);
+        assert_eq!(config.gain, 1.0);
+        assert!(!config.fsync_on_finalize);
+        assert_eq!(config.pause_behavior, PauseBehavior::Splice);
+        assert!(!config.native_passthrough);
+        assert_eq!(config.directory_layout, DirectoryLayout::Flat);
+        assert!(config.auto_create_dir);
+        assert!(!config.voice_filter);
+        assert!(!config.agc);
+        assert_eq!(config.max_total_bytes, None);
+        assert_eq!(config.max_file_bytes, None);
+        assert_eq!(config.soft_duration_warn, None);
+        assert_eq!(config.post_roll, None);
+        assert!(!config.exclusive);
+        assert!(!config.broadcast_wave);
+    }
+
+    #[test]
+    fn test_pause_behavior_is_snake_case_on_the_wire() {
+        let config: AudioCaptureConfig =
+            serde_json::from_str(r#"{"pause_behavior": "insert_silence"}"#).expect("parse");
+        assert_eq!(config.pause_behavior, PauseBehavior::InsertSilence);
+
+        let config: AudioCaptureConfig =
+            serde_json::from_str(r#"{"directory_layout": "date_hierarchy"}"#).expect("parse");
+        assert_eq!(config.directory_layout, DirectoryLayout::DateHierarchy);
+    }
+
+    #[test]
+    fn test_config_round_trips_through_json() {
+        let config = AudioCaptureConfig {
+            sample_rate: 44_100,
+            channels: 2,
+            gain: 2.5,
+            format: FORMAT_WAV.to_string(),
+            segment_duration: Some(Duration::from_secs(600)),
+            fsync_on_finalize: true,
+            pause_behavior: PauseBehavior::InsertSilence,
+            native_passthrough: true,
+            directory_layout: DirectoryLayout::DateHierarchy,
+            auto_create_dir: false,
+            voice_filter: true,
+            agc: true,
+            max_total_bytes: Some(10_000_000_000),
+            max_file_bytes: Some(25_000_000),
+            soft_duration_warn: Some(Duration::from_secs(25 * 60)),
+            post_roll: Some(Duration::from_millis(750)),
+            exclusive: true,
+            broadcast_wave: true,
+            source_channel: Some(2),
+        };
+        let json = serde_json::to_string(&config).expect("serialize");
+        let back: AudioCaptureConfig = serde_json::from_str(&json).expect("parse");
+        assert_eq!(back, config);
+    }
+
+    #[test]
+    fn test_validate_rejects_out_of_range_fields() {
+        let bad_rate = AudioCaptureConfig {
+            sample_rate: 100,
+            ..Default::default()
+        };
+        assert!(bad_rate.validate().unwrap_err().contains("sample_rate"));
+
+        let bad_channels = AudioCaptureConfig {
+            channels: 6,
+            ..Default::default()
+        };
+        assert!(bad_channels.validate().unwrap_err().contains("channels"));
+
+        let bad_gain = AudioCaptureConfig {
+            gain: f32::NAN,
+            ..Default::default()
+        };
+        assert!(bad_gain.validate().unwrap_err().contains("gain"));
+
+        let bad_segment = AudioCaptureConfig {
+            segment_duration: Some(Duration::from_millis(10)),
+            ..Default::default()
+        };
+        assert!(bad_segment
+            .validate()
+            .unwrap_err()
+            .contains("segment_duration_ms"));
+
+        let bad_file_cap = AudioCaptureConfig {
+            max_file_bytes: Some(1_000),
+            ..Default::default()
+        };
+        assert!(bad_file_cap
+            .validate()
+            .unwrap_err()
+            .contains("max_file_bytes"));
+
+        let bad_post_roll = AudioCaptureConfig {
+            post_roll: Some(Duration::from_secs(11)),
+            ..Default::default()
+        };
+        assert!(bad_post_roll
+            .validate()
+            .unwrap_err()
+            .contains("post_roll_ms"));
+
+        let bad_source_channel = AudioCaptureConfig {
+            source_channel: Some(2),
+            native_passthrough: true,
+            ..Default::default()
+        };
+        assert!(bad_source_channel
+            .validate()
+            .unwrap_err()
+            .contains("source_channel"));
+    }
+
+    #[test]
+    fn test_wav_is_always_supported() {
+        let formats = supported_recording_formats();
+        assert_eq!(formats.first().map(String::as_str), Some(FORMAT_WAV));
+        assert_eq!(AudioCaptureConfig::default().format, FORMAT_WAV);
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_format() {
+        let config = AudioCaptureConfig {
+            format: "flac".into(),
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(
+            err.contains("Unknown recording format 'flac'"),
+            "got: {err}"
+        );
+    }
+
+    #[cfg(not(feature = "opus"))]
+    #[test]
+    fn test_validate_rejects_format_not_compiled_in() {
+        assert!(!supported_recording_formats().contains(&"opus".to_string()));
+        let config = AudioCaptureConfig {
+            format: "opus".into(),
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.contains("not available in this build"), "got: {err}");
+    }
+
+    #[test]
+    fn test_segment_duration_is_milliseconds_on_the_wire() {
+        let config: AudioCaptureConfig =
+            serde_json::from_str(r#"{"segment_duration_ms": 1500}"#).expect("parse");
+        assert_eq!(config.segment_duration, Some(Duration::from_millis(1500)));
+        assert_eq!(config.segment_frames(), Some(24_000));
+
+        let json = serde_json::to_value(AudioCaptureConfig::default()).expect("serialize");
+        assert!(json["segment_duration_ms"].is_null());
+        assert_eq!(AudioCaptureConfig::default().segment_frames(), None);
+    }
+}
@@ -0,0 +1,159 @@
+//! Lead-in countdown that runs before a recording starts writing.
+//!
+//! The countdown announces the remaining whole seconds ("3… 2… 1…") and
+//! polls a cancellation check between short sleeps so it can be aborted
+//! promptly. Time is abstracted behind [`Clock`] so tests can drive it
+//! without sleeping.
+
+use std::time::Duration;
+
+/// How often the countdown wakes up to check for cancellation.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Source of time for the countdown.
+pub trait Clock: Send {
+    /// Block for `duration`.
+    fn sleep(&mut self, duration: Duration);
+}
+
+/// [`Clock`] backed by `std::thread::sleep`.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn sleep(&mut self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Run a countdown of `lead_in`, calling `on_tick` with the remaining whole
+/// seconds each time that number changes (e.g. 3, 2, 1).
+///
+/// Returns `true` if the countdown ran to completion, or `false` if
+/// `is_cancelled` returned `true` first.
+pub fn run_countdown(
+    lead_in: Duration,
+    clock: &mut dyn Clock,
+    is_cancelled: &dyn Fn() -> bool,
+    on_tick: &mut dyn FnMut(u64),
+) -> bool {
+    let mut remaining = lead_in;
+    let mut announced = None;
+
+    while !remaining.is_zero() {
+        if is_cancelled() {
+            return false;
+        }
+
+        let secs = remaining_secs(remaining);
+        if announced != Some(secs) {
+            on_tick(secs);
+            announced = Some(secs);
+        }
+
+        let step = remaining.min(POLL_INTERVAL);
+        clock.sleep(step);
+        remaining -= step;
+    }
+
+    !is_cancelled()
+}
+
+/// Whole seconds left, rounded up so a 2.5 s lead-in announces "3".
+fn remaining_secs(remaining: Duration) -> u64 {
+    remaining.as_millis().div_ceil(1000) as u64
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    /// Clock that only advances a shared virtual counter.
+    #[derive(Clone, Default)]
+    struct MockClock {
+        elapsed_ms: Arc<AtomicU64>,
+    }
+
+    impl MockClock {
+        fn elapsed(&self) -> Duration {
+            Duration::from_millis(self.elapsed_ms.load(Ordering::SeqCst))
+        }
+    }
+
+    impl Clock for MockClock {
+        fn sleep(&mut self, duration: Duration) {
+            self.elapsed_ms
+                .fetch_add(duration.as_millis() as u64, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_countdown_announces_each_second_then_completes() {
+        let clock = MockClock::default();
+        let mut ticks = Vec::new();
+
+        let completed = run_countdown(
+            Duration::from_secs(3),
+            &mut clock.clone(),
+            &|| false,
+            &mut |s| ticks.push(s),
+        );
+
+        assert!(completed);
+        assert_eq!(ticks, vec![3, 2, 1]);
+        assert_eq!(clock.elapsed(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_countdown_rounds_partial_seconds_up() {
+        let clock = MockClock::default();
+        let mut ticks = Vec::new();
+
+        run_countdown(
+            Duration::from_millis(1_500),
+            &mut clock.clone(),
+            &|| false,
+            &mut |s| ticks.push(s),
+        );
+
+        assert_eq!(ticks, vec![2, 1]);
+        assert_eq!(clock.elapsed(), Duration::from_millis(1_500));
+    }
+
+    #[test]
+    fn test_countdown_stops_when_cancelled() {
+        let clock = MockClock::default();
+        let observer = clock.clone();
+        let mut ticks = Vec::new();
+        let cancel_at = Duration::from_millis(1_500);
+
+        let completed = run_countdown(
+            Duration::from_secs(3),
+            &mut clock.clone(),
+            &|| observer.elapsed() >= cancel_at,
+            &mut |s| ticks.push(s),
+        );
+
+        assert!(!completed);
+        assert_eq!(ticks, vec![3, 2]);
+        assert_eq!(clock.elapsed(), cancel_at);
+    }
+
+    #[test]
+    fn test_zero_lead_in_completes_immediately() {
+        let mut ticks = Vec::new();
+
+        assert!(run_countdown(
+            Duration::ZERO,
+            &mut MockClock::default(),
+            &|| false,
+            &mut |s| ticks.push(s),
+        ));
+        assert!(ticks.is_empty());
+    }
+}
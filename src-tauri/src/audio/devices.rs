@@ -1,6 +1,23 @@
-//! Audio input device enumeration using CPAL.
+//! Audio input device enumeration using CPAL, and output device lookup for
+//! monitoring playback.
+
+use std::sync::mpsc;
+use std::time::Duration;
 
 use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::SampleFormat;
+
+use crate::audio::capture::{CHANNELS, SAMPLE_RATE};
+
+/// How long device enumeration may take before giving up, unless the caller
+/// passes its own deadline.
+pub const DEFAULT_ENUMERATION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Prefix of the error returned when enumeration exceeds its deadline.
+pub const ERR_DEVICE_ENUMERATION_TIMEOUT: &str = "Device enumeration timed out";
+
+/// Prefix of the error returned when a device is not on the allow-list.
+pub const ERR_DEVICE_NOT_ALLOWED: &str = "Input device is not allowed";
 
 /// Information about an available audio input device.
 #[derive(Debug, Clone, serde::Serialize)]
@@ -32,23 +49,378 @@ pub fn list_input_devices() -> Result<Vec<AudioDevice>, String> {
     Ok(result)
 }
 
+/// [`list_input_devices`] bounded by `timeout`.
+///
+/// Some hosts (e.g. Windows with flaky Bluetooth devices) can block inside
+/// CPAL for seconds; this keeps the caller responsive.
+///
+/// # Errors
+/// Returns an [`ERR_DEVICE_ENUMERATION_TIMEOUT`] error if the deadline
+/// passes, or any error from enumeration itself.
+pub fn list_input_devices_with_timeout(timeout: Duration) -> Result<Vec<AudioDevice>, String> {
+    run_with_timeout(timeout, list_input_devices)
+}
+
+/// Run `enumerate` on a worker thread and wait at most `timeout` for it.
+///
+/// CPAL calls cannot be interrupted, so on timeout the worker is left to
+/// finish in the background and its result is discarded.
+fn run_with_timeout<T, F>(timeout: Duration, enumerate: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("audio-devices".into())
+        .spawn(move || {
+            // The receiver is gone if we already timed out; nothing to do.
+            let _ = tx.send(enumerate());
+        })
+        .map_err(|e| format!("Failed to spawn device enumeration thread: {e}"))?;
+
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(format!(
+            "{ERR_DEVICE_ENUMERATION_TIMEOUT} after {} ms",
+            timeout.as_millis()
+        )),
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            Err("Device enumeration thread panicked".into())
+        }
+    }
+}
+
+/// Input devices a deployment lets users record from.
+///
+/// Each entry is an exact device name or a glob where `*` matches any run of
+/// characters and `?` a single character. An empty list allows every
+/// device. Sent over IPC as a plain array of strings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct DeviceAllowList(Vec<String>);
+
+impl DeviceAllowList {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self(patterns)
+    }
+
+    /// Whether `name` matches an entry, or the list is empty.
+    pub fn is_allowed(&self, name: &str) -> bool {
+        self.0.is_empty() || self.0.iter().any(|p| glob_matches(p, name))
+    }
+
+    /// # Errors
+    /// Returns an [`ERR_DEVICE_NOT_ALLOWED`] error naming the device if it
+    /// is not allowed.
+    pub fn check(&self, name: &str) -> Result<(), String> {
+        if self.is_allowed(name) {
+            Ok(())
+        } else {
+            Err(format!("{ERR_DEVICE_NOT_ALLOWED}: '{name}'"))
+        }
+    }
+
+    /// Keep only the allowed devices, preserving order.
+    pub fn filter(&self, devices: Vec<AudioDevice>) -> Vec<AudioDevice> {
+        devices
+            .into_iter()
+            .filter(|d| self.is_allowed(&d.name))
+            .collect()
+    }
+}
+
+/// Whether `name` matches `pattern` in full, where `*` matches any run of
+/// characters (including none) and `?` exactly one. Everything else matches
+/// itself, case-sensitively.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the most recent `*` and the name position it is
+    // currently assumed to cover up to, for backtracking.
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character and retry.
+                Some((after_star, covered)) => {
+                    p = after_star;
+                    n = covered + 1;
+                    star = Some((after_star, covered + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// How a capture stream holds its input device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareMode {
+    /// Mixed with other apps by the OS, as on every platform by default.
+    Shared,
+    /// The stream has the device to itself.
+    Exclusive,
+    /// Exclusive was asked for but is not available for the device, so the
+    /// stream is shared after all.
+    SharedFallback,
+}
+
+/// Whether `device` can be captured from in exclusive mode, or `None` on
+/// platforms that have no such mode.
+///
+/// Only Windows (WASAPI) has one. CPAL opens every WASAPI stream in shared
+/// mode and offers no way to ask for exclusive, so no device supports it
+/// there yet either; this is the one place to change once it does.
+pub fn exclusive_support(_device: &cpal::Device) -> Option<bool> {
+    #[cfg(target_os = "windows")]
+    {
+        Some(false)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        None
+    }
+}
+
+/// The share mode to open a stream in when `exclusive_requested`, given the
+/// device's [`exclusive_support`]. A request is quietly ignored where the
+/// platform has no exclusive mode, and falls back to shared where the
+/// device does not support it.
+pub fn choose_share_mode(exclusive_requested: bool, support: Option<bool>) -> ShareMode {
+    match (exclusive_requested, support) {
+        (false, _) | (true, None) => ShareMode::Shared,
+        (true, Some(true)) => ShareMode::Exclusive,
+        (true, Some(false)) => ShareMode::SharedFallback,
+    }
+}
+
+/// Whether an input device can be opened right now, from a brief test
+/// stream. Serialized as `{ "status": "available" | "in_use" | "error",
+/// "message"? }`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "status", content = "message", rename_all = "snake_case")]
+pub enum DeviceAvailability {
+    Available,
+    /// Held by another application, e.g. in exclusive mode.
+    InUse,
+    Error(String),
+}
+
+/// Lower-cased fragments of backend errors that mean another application
+/// holds the device, e.g. WASAPI's `AUDCLNT_E_DEVICE_IN_USE` (0x8889000A).
+const IN_USE_MARKERS: &[&str] = &["in use", "busy", "exclusive", "0x8889000a"];
+
+/// Classify a failure to build the test stream.
+///
+/// The device has just been found, so CPAL reporting it not available
+/// (ALSA's `EBUSY`, for one) means something else has it open.
+pub fn classify_build_error(err: &cpal::BuildStreamError) -> DeviceAvailability {
+    match err {
+        cpal::BuildStreamError::DeviceNotAvailable => DeviceAvailability::InUse,
+        cpal::BuildStreamError::BackendSpecific { err } => classify_backend_error(err),
+        other => DeviceAvailability::Error(other.to_string()),
+    }
+}
+
+/// Classify a failure to start the test stream.
+pub fn classify_play_error(err: &cpal::PlayStreamError) -> DeviceAvailability {
+    match err {
+        cpal::PlayStreamError::DeviceNotAvailable => DeviceAvailability::InUse,
+        cpal::PlayStreamError::BackendSpecific { err } => classify_backend_error(err),
+    }
+}
+
+/// Classify an error reported by the running test stream.
+pub fn classify_stream_error(err: &cpal::StreamError) -> DeviceAvailability {
+    match err {
+        cpal::StreamError::DeviceNotAvailable => DeviceAvailability::InUse,
+        cpal::StreamError::BackendSpecific { err } => classify_backend_error(err),
+    }
+}
+
+fn classify_backend_error(err: &cpal::BackendSpecificError) -> DeviceAvailability {
+    let description = err.description.to_lowercase();
+    if IN_USE_MARKERS
+        .iter()
+        .any(|marker| description.contains(marker))
+    {
+        DeviceAvailability::InUse
+    } else {
+        DeviceAvailability::Error(err.description.clone())
+    }
+}
+
+/// One range of formats an input device can capture natively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigRange {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: SampleFormat,
+}
+
+impl ConfigRange {
+    /// Whether the range captures `sample_rate` / `channels` as i16 samples,
+    /// so recording in that format needs no conversion.
+    pub fn covers(&self, sample_rate: u32, channels: u16) -> bool {
+        self.channels == channels
+            && self.min_sample_rate <= sample_rate
+            && self.max_sample_rate >= sample_rate
+            && self.sample_format == SampleFormat::I16
+    }
+}
+
+impl From<&cpal::SupportedStreamConfigRange> for ConfigRange {
+    fn from(range: &cpal::SupportedStreamConfigRange) -> Self {
+        Self {
+            channels: range.channels(),
+            min_sample_rate: range.min_sample_rate().0,
+            max_sample_rate: range.max_sample_rate().0,
+            sample_format: range.sample_format(),
+        }
+    }
+}
+
+/// The native formats of `device`, or an empty list if it cannot say.
+pub fn native_ranges(device: &cpal::Device) -> Vec<ConfigRange> {
+    device
+        .supported_input_configs()
+        .map(|configs| configs.map(|range| ConfigRange::from(&range)).collect())
+        .unwrap_or_default()
+}
+
+/// Rates [`suggest_capture_config`] chooses between, lowest first.
+const CANDIDATE_RATES: [u32; 6] = [16_000, 22_050, 24_000, 32_000, 44_100, 48_000];
+
+/// A capture format most of the available devices record natively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct CaptureSuggestion {
+    pub suggested_sample_rate: u32,
+    pub suggested_channels: u16,
+    /// The default input device would have to resample or remix to record
+    /// in the suggested format. `false` if there is no default device.
+    pub default_device_needs_conversion: bool,
+}
+
+/// Suggest the capture format natively supported by the most `devices`
+/// (each given as its [`ConfigRange`]s), to minimize resampling.
+///
+/// Ties go to the speech format (16 kHz mono), then to mono, then to the
+/// lower rate; with no devices at all the speech format is suggested.
+pub fn suggest_capture_config(
+    devices: &[Vec<ConfigRange>],
+    default_device: Option<&[ConfigRange]>,
+) -> CaptureSuggestion {
+    let supporting = |rate: u32, channels: u16| {
+        devices
+            .iter()
+            .filter(|ranges| ranges.iter().any(|r| r.covers(rate, channels)))
+            .count()
+    };
+    let (rate, channels) = CANDIDATE_RATES
+        .iter()
+        .flat_map(|&rate| [(rate, 1), (rate, 2)])
+        .map(|(rate, channels)| {
+            let preferred = (rate, channels) == (SAMPLE_RATE, CHANNELS);
+            // Larger is better: support, then speech format, then mono,
+            // then a lower rate.
+            let score = (
+                supporting(rate, channels),
+                preferred,
+                channels == 1,
+                std::cmp::Reverse(rate),
+            );
+            (score, (rate, channels))
+        })
+        .max_by_key(|&(score, _)| score)
+        .map_or((SAMPLE_RATE, CHANNELS), |(_, format)| format);
+
+    CaptureSuggestion {
+        suggested_sample_rate: rate,
+        suggested_channels: channels,
+        default_device_needs_conversion: default_device
+            .is_some_and(|ranges| !ranges.iter().any(|r| r.covers(rate, channels))),
+    }
+}
+
+/// [`suggest_capture_config`] over the `allowed` input devices of the
+/// default host, and its default input device.
+///
+/// # Errors
+/// Returns an error if the CPAL host cannot enumerate input devices.
+pub fn suggest_host_capture_config(allowed: &DeviceAllowList) -> Result<CaptureSuggestion, String> {
+    let host = cpal::default_host();
+    let devices: Vec<Vec<ConfigRange>> = host
+        .input_devices()
+        .map_err(|e| format!("Failed to enumerate input devices: {e}"))?
+        .filter(|device| device.name().is_ok_and(|name| allowed.is_allowed(&name)))
+        .map(|device| native_ranges(&device))
+        .collect();
+    let default_device = host
+        .default_input_device()
+        .map(|device| native_ranges(&device));
+    Ok(suggest_capture_config(&devices, default_device.as_deref()))
+}
+
+/// [`suggest_host_capture_config`] bounded by `timeout`, like
+/// [`list_input_devices_with_timeout`].
+///
+/// # Errors
+/// Returns an [`ERR_DEVICE_ENUMERATION_TIMEOUT`] error if the deadline
+/// passes, or any error from enumeration itself.
+pub fn suggest_host_capture_config_with_timeout(
+    allowed: DeviceAllowList,
+    timeout: Duration,
+) -> Result<CaptureSuggestion, String> {
+    run_with_timeout(timeout, move || suggest_host_capture_config(&allowed))
+}
+
 /// Find an input device by name, or return the default input device.
 ///
 /// When `device_name` is `None`, the default input device is returned.
 /// When a name is provided, the first device whose name matches exactly is
-/// returned.
+/// returned. Either way the device must be on `allowed`.
 ///
 /// # Errors
-/// Returns an error if no matching device can be found or if CPAL cannot
-/// enumerate devices.
-pub fn find_input_device(device_name: Option<&str>) -> Result<cpal::Device, String> {
+/// Returns an [`ERR_DEVICE_NOT_ALLOWED`] error if the requested (or
+/// default) device is not allowed, or an error if no matching device can be
+/// found or if CPAL cannot enumerate devices.
+pub fn find_input_device(
+    device_name: Option<&str>,
+    allowed: &DeviceAllowList,
+) -> Result<cpal::Device, String> {
     let host = cpal::default_host();
 
     match device_name {
-        None => host
-            .default_input_device()
-            .ok_or_else(|| "No default input device available".to_string()),
+        None => {
+            let device = host
+                .default_input_device()
+                .ok_or_else(|| "No default input device available".to_string())?;
+            if !allowed.0.is_empty() {
+                let name = device
+                    .name()
+                    .map_err(|e| format!("Failed to read default input device name: {e}"))?;
+                allowed.check(&name)?;
+            }
+            Ok(device)
+        }
         Some(name) => {
+            // Checked first so a disallowed name is rejected the same way
+            // whether or not it is plugged in.
+            allowed.check(name)?;
+
             let devices = host
                 .input_devices()
                 .map_err(|e| format!("Failed to enumerate input devices: {e}"))?;
@@ -66,6 +438,26 @@ pub fn find_input_device(device_name: Option<&str>) -> Result<cpal::Device, Stri
     }
 }
 
+/// Look up an output device by name, or the default output device if
+/// `device_name` is `None`. Output devices are not subject to the input
+/// allow-list.
+///
+/// # Errors
+/// Returns an error if no matching device can be found or if CPAL cannot
+/// enumerate devices.
+pub fn find_output_device(device_name: Option<&str>) -> Result<cpal::Device, String> {
+    let host = cpal::default_host();
+    let Some(name) = device_name else {
+        return host
+            .default_output_device()
+            .ok_or_else(|| "No default output device available".to_string());
+    };
+    host.output_devices()
+        .map_err(|e| format!("Failed to enumerate output devices: {e}"))?
+        .find(|device| device.name().is_ok_and(|n| n == name))
+        .ok_or_else(|| format!("Output device '{name}' not found"))
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -74,6 +466,160 @@ pub fn find_input_device(device_name: Option<&str>) -> Result<cpal::Device, Stri
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_classify_stream_errors() {
+        let backend = |description: &str| cpal::BackendSpecificError {
+            description: description.into(),
+        };
+        assert_eq!(
+            classify_build_error(&cpal::BuildStreamError::DeviceNotAvailable),
+            DeviceAvailability::InUse
+        );
+        assert_eq!(
+            classify_build_error(&cpal::BuildStreamError::BackendSpecific {
+                err: backend("The device is already in use. (0x8889000A)"),
+            }),
+            DeviceAvailability::InUse
+        );
+        assert_eq!(
+            classify_build_error(&cpal::BuildStreamError::BackendSpecific {
+                err: backend("Device or resource busy"),
+            }),
+            DeviceAvailability::InUse
+        );
+        assert!(matches!(
+            classify_build_error(&cpal::BuildStreamError::StreamConfigNotSupported),
+            DeviceAvailability::Error(_)
+        ));
+        assert_eq!(
+            classify_build_error(&cpal::BuildStreamError::BackendSpecific {
+                err: backend("Invalid sample rate"),
+            }),
+            DeviceAvailability::Error("Invalid sample rate".into())
+        );
+
+        assert_eq!(
+            classify_play_error(&cpal::PlayStreamError::DeviceNotAvailable),
+            DeviceAvailability::InUse
+        );
+        assert_eq!(
+            classify_stream_error(&cpal::StreamError::BackendSpecific {
+                err: backend("driver crashed"),
+            }),
+            DeviceAvailability::Error("driver crashed".into())
+        );
+    }
+
+    #[test]
+    fn test_device_availability_serializes_with_status_tag() {
+        assert_eq!(
+            serde_json::to_value(DeviceAvailability::InUse).unwrap(),
+            serde_json::json!({"status": "in_use"})
+        );
+        assert_eq!(
+            serde_json::to_value(DeviceAvailability::Error("gone".into())).unwrap(),
+            serde_json::json!({"status": "error", "message": "gone"})
+        );
+    }
+
+    #[test]
+    fn test_choose_share_mode_branches() {
+        // Not asked for: shared, whatever the device can do.
+        assert_eq!(choose_share_mode(false, Some(true)), ShareMode::Shared);
+        assert_eq!(choose_share_mode(false, None), ShareMode::Shared);
+        // Asked for on a platform without the mode: ignored.
+        assert_eq!(choose_share_mode(true, None), ShareMode::Shared);
+        assert_eq!(choose_share_mode(true, Some(true)), ShareMode::Exclusive);
+        assert_eq!(
+            choose_share_mode(true, Some(false)),
+            ShareMode::SharedFallback
+        );
+    }
+
+    fn range(channels: u16, min: u32, max: u32) -> ConfigRange {
+        ConfigRange {
+            channels,
+            min_sample_rate: min,
+            max_sample_rate: max,
+            sample_format: SampleFormat::I16,
+        }
+    }
+
+    #[test]
+    fn test_config_range_covers_only_i16_in_range() {
+        let r = range(1, 8_000, 48_000);
+        assert!(r.covers(16_000, 1));
+        assert!(!r.covers(16_000, 2));
+        assert!(!r.covers(96_000, 1));
+        let float = ConfigRange {
+            sample_format: SampleFormat::F32,
+            ..r
+        };
+        assert!(!float.covers(16_000, 1));
+    }
+
+    #[test]
+    fn test_suggest_prefers_speech_format_when_widely_supported() {
+        let devices = vec![
+            vec![range(1, 8_000, 48_000)],
+            vec![range(1, 16_000, 16_000), range(2, 44_100, 48_000)],
+        ];
+        let suggestion = suggest_capture_config(&devices, Some(&devices[1]));
+        assert_eq!(
+            suggestion,
+            CaptureSuggestion {
+                suggested_sample_rate: 16_000,
+                suggested_channels: 1,
+                default_device_needs_conversion: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_suggest_follows_the_majority_and_flags_default_conversion() {
+        // Two of three devices only do 48 kHz stereo; the default is the
+        // one that does 16 kHz mono.
+        let devices = vec![
+            vec![range(2, 48_000, 48_000)],
+            vec![range(2, 44_100, 48_000)],
+            vec![range(1, 16_000, 16_000)],
+        ];
+        let suggestion = suggest_capture_config(&devices, Some(&devices[2]));
+        assert_eq!(suggestion.suggested_sample_rate, 48_000);
+        assert_eq!(suggestion.suggested_channels, 2);
+        assert!(suggestion.default_device_needs_conversion);
+    }
+
+    #[test]
+    fn test_suggest_breaks_ties_toward_mono_and_lower_rates() {
+        // Every device does 44.1 and 48 kHz in mono and stereo.
+        let devices = vec![
+            vec![range(1, 44_100, 48_000), range(2, 44_100, 48_000)],
+            vec![range(1, 44_100, 96_000), range(2, 44_100, 96_000)],
+        ];
+        let suggestion = suggest_capture_config(&devices, None);
+        assert_eq!(suggestion.suggested_sample_rate, 44_100);
+        assert_eq!(suggestion.suggested_channels, 1);
+        assert!(!suggestion.default_device_needs_conversion);
+    }
+
+    #[test]
+    fn test_suggest_without_devices_is_speech_format() {
+        let float_only = vec![vec![ConfigRange {
+            sample_format: SampleFormat::F32,
+            ..range(2, 48_000, 48_000)
+        }]];
+        for devices in [Vec::new(), float_only] {
+            let suggestion = suggest_capture_config(&devices, devices.first().map(Vec::as_slice));
+            assert_eq!(suggestion.suggested_sample_rate, 16_000);
+            assert_eq!(suggestion.suggested_channels, 1);
+            assert_eq!(
+                suggestion.default_device_needs_conversion,
+                !devices.is_empty()
+            );
+        }
+    }
+
     /// Device listing should not panic even when no audio devices are
     /// available (e.g. headless CI). It either succeeds with a list or
     /// returns a descriptive error.
@@ -101,7 +647,10 @@ mod tests {
     #[test]
     #[ignore]
     fn test_find_device_nonexistent_returns_error() {
-        let result = find_input_device(Some("__nonexistent_device_12345__"));
+        let result = find_input_device(
+            Some("__nonexistent_device_12345__"),
+            &DeviceAllowList::default(),
+        );
         assert!(result.is_err());
         let err = result.err().expect("expected Err variant");
         assert!(
@@ -114,7 +663,7 @@ mod tests {
     /// descriptive error (e.g. on headless CI with no audio hardware).
     #[test]
     fn test_find_default_device_does_not_panic() {
-        match find_input_device(None) {
+        match find_input_device(None, &DeviceAllowList::default()) {
             Ok(device) => {
                 // Sanity-check: the device should have a readable name.
                 assert!(device.name().is_ok());
@@ -137,4 +686,110 @@ mod tests {
         let json = serde_json::to_value(&device).expect("serialize");
         assert_eq!(json["name"], "Built-in Microphone");
     }
+
+    // -- Allow-list tests --
+
+    fn synthetic_devices() -> Vec<AudioDevice> {
+        [
+            "MacBook Pro Microphone",
+            "USB Headset (Jabra Evolve 40)",
+            "USB Headset (Generic)",
+            "BlackHole 2ch",
+        ]
+        .into_iter()
+        .map(|name| AudioDevice { name: name.into() })
+        .collect()
+    }
+
+    fn names(devices: &[AudioDevice]) -> Vec<&str> {
+        devices.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_empty_allow_list_allows_everything() {
+        let allowed = DeviceAllowList::default();
+        assert_eq!(allowed.filter(synthetic_devices()).len(), 4);
+        assert!(allowed.check("Anything").is_ok());
+    }
+
+    #[test]
+    fn test_allow_list_filters_by_exact_name_and_glob() {
+        let allowed =
+            DeviceAllowList::new(vec!["BlackHole 2ch".into(), "USB Headset (Jabra*)".into()]);
+        assert_eq!(
+            names(&allowed.filter(synthetic_devices())),
+            ["USB Headset (Jabra Evolve 40)", "BlackHole 2ch"]
+        );
+
+        // Exact entries do not match prefixes or other cases.
+        assert!(!allowed.is_allowed("BlackHole 2ch Extra"));
+        assert!(!allowed.is_allowed("blackhole 2ch"));
+    }
+
+    #[test]
+    fn test_allow_list_check_names_rejected_device() {
+        let allowed = DeviceAllowList::new(vec!["USB Headset*".into()]);
+        assert!(allowed.check("USB Headset (Generic)").is_ok());
+
+        let err = allowed.check("MacBook Pro Microphone").unwrap_err();
+        assert!(err.starts_with(ERR_DEVICE_NOT_ALLOWED), "got: {err}");
+        assert!(err.contains("MacBook Pro Microphone"), "got: {err}");
+    }
+
+    #[test]
+    fn test_find_input_device_rejects_disallowed_name_before_lookup() {
+        let allowed = DeviceAllowList::new(vec!["Approved Mic".into()]);
+        let err = find_input_device(Some("__nonexistent_device_12345__"), &allowed)
+            .err()
+            .expect("expected Err variant");
+        assert!(err.starts_with(ERR_DEVICE_NOT_ALLOWED), "got: {err}");
+    }
+
+    #[test]
+    fn test_glob_matches_wildcards() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*", "anything"));
+        assert!(glob_matches("Mic ?", "Mic 2"));
+        assert!(!glob_matches("Mic ?", "Mic 12"));
+        assert!(glob_matches("*Headset*", "USB Headset (Generic)"));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(!glob_matches("a*b*c", "aXbYbZ"));
+        assert!(glob_matches("Mikrofon (Ä*)", "Mikrofon (Äußeres)"));
+        assert!(!glob_matches("", "x"));
+    }
+
+    // -- Timeout wrapper tests --
+
+    #[test]
+    fn test_run_with_timeout_returns_fast_result() {
+        let result = run_with_timeout(Duration::from_secs(5), || {
+            Ok(vec![AudioDevice { name: "Mic".into() }])
+        });
+        assert_eq!(result.expect("devices")[0].name, "Mic");
+    }
+
+    #[test]
+    fn test_run_with_timeout_reports_slow_enumeration() {
+        let started = std::time::Instant::now();
+        let result: Result<Vec<AudioDevice>, String> =
+            run_with_timeout(Duration::from_millis(20), || {
+                std::thread::sleep(Duration::from_secs(2));
+                Ok(Vec::new())
+            });
+
+        let err = result.unwrap_err();
+        assert!(
+            err.starts_with(ERR_DEVICE_ENUMERATION_TIMEOUT),
+            "got: {err}"
+        );
+        assert!(err.contains("20 ms"));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_run_with_timeout_passes_through_errors() {
+        let result: Result<(), String> =
+            run_with_timeout(Duration::from_secs(5), || Err("host exploded".into()));
+        assert_eq!(result.unwrap_err(), "host exploded");
+    }
 }
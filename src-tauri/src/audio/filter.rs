@@ -0,0 +1,368 @@
+//! Lightweight filters applied to captured audio in real time.
+//!
+//! [`VoiceFilter`] band-limits the signal to the speech range with a
+//! high-pass and a low-pass [`Biquad`], cutting rumble and hiss before the
+//! audio is written or transcribed. [`Agc`] slowly evens out the level of a
+//! speaker whose distance from the microphone varies.
+
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+/// Cutoff of the voice filter's high-pass stage, in Hz.
+pub const VOICE_HIGH_PASS_HZ: f32 = 80.0;
+
+/// Cutoff of the voice filter's low-pass stage, in Hz. Skipped when it is
+/// too close to the Nyquist frequency of the stream to be meaningful.
+pub const VOICE_LOW_PASS_HZ: f32 = 8_000.0;
+
+/// A second-order IIR filter (RBJ audio-EQ-cookbook coefficients, direct
+/// form I). Keeps its state between calls, so consecutive buffers of one
+/// signal are filtered seamlessly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    /// Butterworth high-pass at `cutoff_hz` for a signal sampled at
+    /// `sample_rate`.
+    pub fn high_pass(sample_rate: u32, cutoff_hz: f32) -> Self {
+        let (cos, alpha) = Self::omega(sample_rate, cutoff_hz);
+        Self::normalized(
+            (1.0 + cos) / 2.0,
+            -(1.0 + cos),
+            (1.0 + cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    /// Butterworth low-pass at `cutoff_hz` for a signal sampled at
+    /// `sample_rate`.
+    pub fn low_pass(sample_rate: u32, cutoff_hz: f32) -> Self {
+        let (cos, alpha) = Self::omega(sample_rate, cutoff_hz);
+        Self::normalized(
+            (1.0 - cos) / 2.0,
+            1.0 - cos,
+            (1.0 - cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    /// Filter one sample.
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let out = self.b0 * sample + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = sample;
+        self.y2 = self.y1;
+        self.y1 = out;
+        out
+    }
+
+    /// `cos(w0)` and `alpha` for a Butterworth (Q = 1/√2) stage.
+    fn omega(sample_rate: u32, cutoff_hz: f32) -> (f32, f32) {
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate as f32;
+        (w0.cos(), w0.sin() / (2.0 * FRAC_1_SQRT_2))
+    }
+
+    fn normalized(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+}
+
+/// Speech band-pass for interleaved audio: a [`VOICE_HIGH_PASS_HZ`]
+/// high-pass cascaded with a [`VOICE_LOW_PASS_HZ`] low-pass, run separately
+/// on each channel.
+#[derive(Debug, Clone)]
+pub struct VoiceFilter {
+    /// One chain of stages per channel.
+    channels: Vec<Vec<Biquad>>,
+}
+
+impl VoiceFilter {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let mut chain = vec![Biquad::high_pass(sample_rate, VOICE_HIGH_PASS_HZ)];
+        // At low sample rates the band already ends below the cutoff.
+        if VOICE_LOW_PASS_HZ < 0.45 * sample_rate as f32 {
+            chain.push(Biquad::low_pass(sample_rate, VOICE_LOW_PASS_HZ));
+        }
+        Self {
+            channels: vec![chain; usize::from(channels.max(1))],
+        }
+    }
+
+    /// Filter interleaved `samples` in place.
+    pub fn process_interleaved(&mut self, samples: &mut [f32]) {
+        let channels = self.channels.len();
+        for (i, sample) in samples.iter_mut().enumerate() {
+            for stage in &mut self.channels[i % channels] {
+                *sample = stage.process(*sample);
+            }
+        }
+    }
+}
+
+/// RMS level (full scale = 1.0) the AGC steers the signal towards.
+pub const AGC_TARGET_LEVEL: f32 = 0.1;
+
+/// Most the AGC will amplify, so a quiet room's noise floor is not brought
+/// up to speech level.
+pub const AGC_MAX_GAIN: f32 = 10.0;
+
+/// Most the AGC will attenuate.
+pub const AGC_MIN_GAIN: f32 = 0.1;
+
+/// Buffers quieter than this are treated as silence: the gain is held
+/// rather than raised towards [`AGC_MAX_GAIN`] between sentences.
+pub const AGC_SILENCE_LEVEL: f32 = 0.001;
+
+/// Fraction of the way the smoothed level moves towards each buffer's
+/// level.
+const AGC_LEVEL_SMOOTHING: f32 = 0.2;
+
+/// Fraction of the way the gain moves towards the gain that would hit the
+/// target on each buffer. Small, so the AGC rides the level over seconds
+/// instead of pumping on every syllable.
+const AGC_GAIN_RATE: f32 = 0.05;
+
+/// Feedback state of the AGC between buffers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AgcState {
+    /// Smoothed RMS level of the non-silent input, before AGC gain; zero
+    /// until the first such buffer.
+    pub level: f32,
+    /// Gain currently applied.
+    pub gain: f32,
+}
+
+impl Default for AgcState {
+    fn default() -> Self {
+        Self {
+            level: 0.0,
+            gain: 1.0,
+        }
+    }
+}
+
+/// One AGC step: given the RMS `level` of the next buffer (before AGC gain)
+/// and the current `state`, return the gain to apply to that buffer and the
+/// new state. Silent buffers leave the state unchanged.
+pub fn agc_update(level: f32, state: AgcState) -> (f32, AgcState) {
+    if level < AGC_SILENCE_LEVEL {
+        return (state.gain, state);
+    }
+    let smoothed = if state.level == 0.0 {
+        level
+    } else {
+        state.level + (level - state.level) * AGC_LEVEL_SMOOTHING
+    };
+    let wanted = (AGC_TARGET_LEVEL / smoothed).clamp(AGC_MIN_GAIN, AGC_MAX_GAIN);
+    let gain = state.gain + (wanted - state.gain) * AGC_GAIN_RATE;
+    (
+        gain,
+        AgcState {
+            level: smoothed,
+            gain,
+        },
+    )
+}
+
+/// Automatic gain control for interleaved audio, driven by [`agc_update`]
+/// once per buffer. Within a buffer the gain ramps linearly from the
+/// previous value to the new one, so steps are inaudible.
+#[derive(Debug, Clone, Default)]
+pub struct Agc {
+    state: AgcState,
+}
+
+impl Agc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the AGC to interleaved `samples` in place.
+    pub fn process_interleaved(&mut self, samples: &mut [f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        let level = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        let from = self.state.gain;
+        let (to, state) = agc_update(level, self.state);
+        self.state = state;
+
+        let step = (to - from) / samples.len() as f32;
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample *= from + step * (i + 1) as f32;
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    fn tone(freq: f32, seconds: f32) -> Vec<f32> {
+        let n = (RATE as f32 * seconds) as usize;
+        (0..n)
+            .map(|i| (2.0 * PI * freq * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    /// RMS of the second half of `samples`, after the filter has settled.
+    fn settled_rms(samples: &[f32]) -> f32 {
+        let tail = &samples[samples.len() / 2..];
+        (tail.iter().map(|s| s * s).sum::<f32>() / tail.len() as f32).sqrt()
+    }
+
+    fn filtered_rms(filter: &mut Biquad, input: &[f32]) -> f32 {
+        let out: Vec<f32> = input.iter().map(|&s| filter.process(s)).collect();
+        settled_rms(&out) / settled_rms(input)
+    }
+
+    #[test]
+    fn test_high_pass_attenuates_low_tone_and_passes_mid_tone() {
+        let low = filtered_rms(&mut Biquad::high_pass(RATE, 80.0), &tone(20.0, 1.0));
+        let mid = filtered_rms(&mut Biquad::high_pass(RATE, 80.0), &tone(1_000.0, 1.0));
+        // Two octaves below a 12 dB/octave cutoff: about -24 dB.
+        assert!(low < 0.1, "20 Hz gain {low}");
+        assert!(mid > 0.95, "1 kHz gain {mid}");
+    }
+
+    #[test]
+    fn test_low_pass_attenuates_high_tone_and_passes_mid_tone() {
+        let high = filtered_rms(&mut Biquad::low_pass(RATE, 2_000.0), &tone(8_000.0, 1.0));
+        let mid = filtered_rms(&mut Biquad::low_pass(RATE, 2_000.0), &tone(200.0, 1.0));
+        assert!(high < 0.1, "8 kHz gain {high}");
+        assert!(mid > 0.95, "200 Hz gain {mid}");
+    }
+
+    #[test]
+    fn test_state_carries_across_buffers() {
+        let input = tone(300.0, 0.1);
+        let mut whole = Biquad::high_pass(RATE, 80.0);
+        let expected: Vec<f32> = input.iter().map(|&s| whole.process(s)).collect();
+
+        let mut split = Biquad::high_pass(RATE, 80.0);
+        let mut actual = Vec::new();
+        for chunk in input.chunks(97) {
+            actual.extend(chunk.iter().map(|&s| split.process(s)));
+        }
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_voice_filter_filters_channels_independently() {
+        // Left carries speech-band audio, right only sub-bass rumble.
+        let speech = tone(1_000.0, 0.5);
+        let rumble = tone(20.0, 0.5);
+        let mut interleaved: Vec<f32> = speech
+            .iter()
+            .zip(&rumble)
+            .flat_map(|(&l, &r)| [l, r])
+            .collect();
+
+        VoiceFilter::new(RATE, 2).process_interleaved(&mut interleaved);
+        let left: Vec<f32> = interleaved.iter().step_by(2).copied().collect();
+        let right: Vec<f32> = interleaved.iter().skip(1).step_by(2).copied().collect();
+        assert!(settled_rms(&left) / settled_rms(&speech) > 0.9);
+        assert!(settled_rms(&right) / settled_rms(&rumble) < 0.1);
+    }
+
+    /// Feed `level` to the AGC `steps` times from the default state.
+    fn settle_agc(level: f32, steps: usize) -> AgcState {
+        (0..steps).fold(AgcState::default(), |state, _| agc_update(level, state).1)
+    }
+
+    #[test]
+    fn test_agc_converges_to_target_for_loud_and_quiet_input() {
+        for level in [0.5, 0.02] {
+            let state = settle_agc(level, 500);
+            let output = level * state.gain;
+            assert!(
+                (output - AGC_TARGET_LEVEL).abs() < 0.005,
+                "input {level}: output level {output}"
+            );
+        }
+        // Loud input is turned down, quiet input up.
+        assert!(settle_agc(0.5, 500).gain < 1.0);
+        assert!(settle_agc(0.02, 500).gain > 1.0);
+    }
+
+    #[test]
+    fn test_agc_moves_gradually_towards_target() {
+        let first = agc_update(0.5, AgcState::default()).0;
+        assert!(first < 1.0 && first > 0.9, "first gain {first}");
+
+        let mut previous = 1.0;
+        let mut state = AgcState::default();
+        for _ in 0..50 {
+            let (gain, next) = agc_update(0.5, state);
+            assert!(gain <= previous, "gain rose from {previous} to {gain}");
+            previous = gain;
+            state = next;
+        }
+    }
+
+    #[test]
+    fn test_agc_gain_is_bounded_for_very_quiet_input() {
+        let state = settle_agc(0.002, 2_000);
+        assert!(state.gain <= AGC_MAX_GAIN);
+        assert!(state.gain > AGC_MAX_GAIN * 0.99, "gain {}", state.gain);
+    }
+
+    #[test]
+    fn test_agc_holds_gain_through_silence() {
+        let speaking = settle_agc(0.02, 500);
+        let mut state = speaking;
+        for _ in 0..500 {
+            state = agc_update(0.0, state).1;
+        }
+        assert_eq!(state, speaking);
+        assert_eq!(settle_agc(0.000_5, 500), AgcState::default());
+    }
+
+    #[test]
+    fn test_agc_ramps_gain_within_a_buffer() {
+        let mut agc = Agc::new();
+        let mut buf = vec![0.5; 100];
+        agc.process_interleaved(&mut buf);
+        // Starts at unity and ends at the new, lower gain.
+        assert!((buf[0] - 0.5).abs() < 0.001);
+        assert!((buf[99] - 0.5 * agc.state.gain).abs() < 1e-6);
+        assert!(buf.windows(2).all(|w| w[1] <= w[0]));
+    }
+
+    #[test]
+    fn test_voice_filter_skips_low_pass_near_nyquist() {
+        assert_eq!(VoiceFilter::new(16_000, 1).channels[0].len(), 1);
+        assert_eq!(VoiceFilter::new(48_000, 1).channels[0].len(), 2);
+    }
+}
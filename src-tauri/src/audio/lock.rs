@@ -0,0 +1,197 @@
+//! Cross-process recording lock.
+//!
+//! `RecordingStatus` only guards against a second recording inside this
+//! process. To stop two app instances from grabbing the microphone at once,
+//! a recording also holds a lockfile containing the owner's PID. A lockfile
+//! whose PID is no longer running is stale and is reclaimed.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the lockfile inside the app data directory.
+pub const LOCK_FILE_NAME: &str = "recording.lock";
+
+/// Prefix of the error returned when another live process holds the lock.
+pub const ERR_ALREADY_RECORDING_ELSEWHERE: &str = "Another instance is already recording";
+
+/// A held recording lock. The lockfile is removed when this is dropped.
+#[derive(Debug)]
+pub struct RecordingLock {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl RecordingLock {
+    /// Take the lock at `path` for the current process.
+    ///
+    /// # Errors
+    /// Returns an [`ERR_ALREADY_RECORDING_ELSEWHERE`] error if a running
+    /// process holds it, or an error if the lockfile cannot be written.
+    pub fn acquire(path: &Path) -> Result<Self, String> {
+        Self::acquire_as(path, std::process::id(), &pid_is_running)
+    }
+
+    /// [`acquire`](Self::acquire) on behalf of `pid`, using `is_running` to
+    /// decide whether an existing holder is still alive.
+    fn acquire_as(path: &Path, pid: u32, is_running: &dyn Fn(u32) -> bool) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create lock directory: {e}"))?;
+        }
+
+        // Two attempts: the second follows removing a stale lock.
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    write!(file, "{pid}")
+                        .map_err(|e| format!("Failed to write '{}': {e}", path.display()))?;
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                        pid,
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(format!("Failed to create '{}': {e}", path.display())),
+            }
+
+            match read_holder(path) {
+                Some(holder) if holder != pid && is_running(holder) => {
+                    return Err(format!(
+                        "{ERR_ALREADY_RECORDING_ELSEWHERE} (process {holder})"
+                    ));
+                }
+                // Ours from an earlier run, a dead process, or unreadable.
+                _ => {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+
+        Err(format!(
+            "Failed to acquire recording lock '{}'",
+            path.display()
+        ))
+    }
+}
+
+impl Drop for RecordingLock {
+    fn drop(&mut self) {
+        // Leave the file alone if it has since been reclaimed by someone else.
+        if read_holder(&self.path) == Some(self.pid) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// PID recorded in the lockfile at `path`, if it exists and parses.
+fn read_holder(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether a process with `pid` exists.
+#[cfg(unix)]
+fn pid_is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 performs only the existence/permission check.
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    // EPERM means the process exists but belongs to someone else.
+    io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a portable liveness check, treat every holder as running so a
+/// live instance is never locked out of its own recording. A stale lock
+/// must then be removed by hand.
+#[cfg(not(unix))]
+fn pid_is_running(_pid: u32) -> bool {
+    true
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("second_test_lock_{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join(LOCK_FILE_NAME)
+    }
+
+    #[test]
+    fn test_acquire_writes_pid_and_drop_releases() {
+        let path = lock_path("acquire");
+        let lock = RecordingLock::acquire_as(&path, 1234, &|_| true).expect("acquire");
+        assert_eq!(read_holder(&path), Some(1234));
+
+        drop(lock);
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_live_holder_blocks_other_pid() {
+        let path = lock_path("live");
+        let _held = RecordingLock::acquire_as(&path, 1234, &|_| true).expect("acquire");
+
+        let err = RecordingLock::acquire_as(&path, 5678, &|_| true).unwrap_err();
+        assert!(
+            err.starts_with(ERR_ALREADY_RECORDING_ELSEWHERE),
+            "got: {err}"
+        );
+        assert!(err.contains("1234"), "got: {err}");
+        assert_eq!(read_holder(&path), Some(1234));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_stale_lock_is_reclaimed() {
+        let path = lock_path("stale");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "1234").unwrap();
+
+        let lock = RecordingLock::acquire_as(&path, 5678, &|pid| pid != 1234).expect("reclaim");
+        assert_eq!(read_holder(&path), Some(5678));
+        drop(lock);
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_own_or_garbage_lock_is_reclaimed() {
+        let path = lock_path("own");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        fs::write(&path, "5678").unwrap();
+        drop(RecordingLock::acquire_as(&path, 5678, &|_| true).expect("own pid"));
+
+        fs::write(&path, "not a pid").unwrap();
+        drop(RecordingLock::acquire_as(&path, 5678, &|_| true).expect("garbage"));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_drop_leaves_reclaimed_lock_alone() {
+        let path = lock_path("reclaimed");
+        let first = RecordingLock::acquire_as(&path, 1234, &|_| true).expect("acquire");
+        // Another instance decides we are dead and takes over.
+        let _second = RecordingLock::acquire_as(&path, 5678, &|_| false).expect("reclaim");
+
+        drop(first);
+        assert_eq!(read_holder(&path), Some(5678));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_current_process_is_running() {
+        assert!(pid_is_running(std::process::id()));
+    }
+}
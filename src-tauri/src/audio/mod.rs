@@ -2,7 +2,20 @@
 //!
 //! Provides device enumeration, audio capture via CPAL, and WAV writing via
 //! hound. The capture runs on a dedicated thread and communicates with the
-//! main thread through shared state protected by `Arc<Mutex<>>`.
+//! main thread through shared state protected by `Arc<Mutex<>>`. Recordings
+//! can be played back through an output device the same way in reverse.
 
+pub mod align;
+pub mod bridge;
 pub mod capture;
+pub mod config;
+pub mod countdown;
 pub mod devices;
+pub mod filter;
+pub mod lock;
+pub mod monitor;
+pub mod permission;
+pub mod schedule;
+pub mod sink;
+pub mod source;
+pub mod wav;
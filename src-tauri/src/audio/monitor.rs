@@ -0,0 +1,422 @@
+//! Monitoring playback — plays a recording through an output device.
+//!
+//! The capture engine in reverse: a dedicated thread reads the WAV a block
+//! at a time, converts each block to the output device's rate and channel
+//! count, and queues it for a CPAL output stream, which plays silence if the
+//! queue runs dry. One recording plays at a time.
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{SampleFormat, StreamConfig};
+
+use crate::audio::capture::convert_frames;
+use crate::audio::devices::find_output_device;
+
+/// Error returned by [`PlaybackManager::play`] when something is already
+/// playing. Kept as a constant so callers can match on it.
+pub const ERR_ALREADY_PLAYING: &str = "A recording is already playing";
+
+/// Audio read from the file and converted in one go.
+const PLAYBACK_BLOCK: Duration = Duration::from_millis(100);
+
+/// How much converted audio may wait for the output stream before reading
+/// pauses.
+const MAX_QUEUED: Duration = Duration::from_millis(500);
+
+/// How long [`PlaybackManager::play`] waits for the output stream to open.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Playback state, reported to the frontend as a snake_case string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackStatus {
+    Idle,
+    Playing,
+}
+
+struct PlaybackInner {
+    status: PlaybackStatus,
+    stop_flag: Arc<Mutex<bool>>,
+    thread_handle: Option<JoinHandle<()>>,
+    /// Bumped by each `play`, so a playback thread that ends after being
+    /// stopped cannot mark a newer playback finished.
+    generation: u64,
+}
+
+/// Thread-safe handle to monitoring playback.
+///
+/// Wrap this in `tauri::State` so all commands share the same instance.
+pub struct PlaybackManager {
+    /// Shared with the playback thread so it can return to Idle when the
+    /// recording ends.
+    inner: Arc<Mutex<PlaybackInner>>,
+}
+
+impl PlaybackManager {
+    /// Create a new, idle playback manager.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(PlaybackInner {
+                status: PlaybackStatus::Idle,
+                stop_flag: Arc::new(Mutex::new(false)),
+                thread_handle: None,
+                generation: 0,
+            })),
+        }
+    }
+
+    /// Current state of the manager.
+    pub fn status(&self) -> Result<PlaybackStatus, String> {
+        let inner = self
+            .inner
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?;
+        Ok(inner.status)
+    }
+
+    /// Play the WAV at `path` through `output_device` (or the default
+    /// output device) on a dedicated thread. Returns once the output stream
+    /// is running; the manager goes back to Idle when the recording ends or
+    /// is [stopped](Self::stop).
+    ///
+    /// # Errors
+    /// Returns an [`ERR_ALREADY_PLAYING`] error if something is playing, or
+    /// an error if the file cannot be read or the output stream cannot be
+    /// opened.
+    pub fn play(&self, path: &Path, output_device: Option<String>) -> Result<(), String> {
+        let reader = hound::WavReader::open(path)
+            .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?;
+        let (stop_flag, generation) = self.begin()?;
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let inner = Arc::clone(&self.inner);
+        let handle = std::thread::spawn(move || {
+            if let Err(e) = run_playback(reader, output_device.as_deref(), &stop_flag, ready_tx) {
+                eprintln!("Playback failed: {e}");
+            }
+            finish(&inner, generation);
+        });
+
+        let started = ready_rx
+            .recv_timeout(STARTUP_TIMEOUT)
+            .unwrap_or_else(|_| Err("Timed out opening the output device".into()));
+        let mut inner = self
+            .inner
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?;
+        if inner.generation == generation && inner.status == PlaybackStatus::Playing {
+            inner.thread_handle = Some(handle);
+        }
+        if started.is_err() {
+            // Let a thread still waiting on the device give up.
+            if let Ok(mut flag) = inner.stop_flag.lock() {
+                *flag = true;
+            }
+            if inner.generation == generation {
+                inner.status = PlaybackStatus::Idle;
+            }
+        }
+        started
+    }
+
+    /// Stop playback and wait for the playback thread to close the stream.
+    ///
+    /// # Errors
+    /// Returns an error if nothing is playing.
+    pub fn stop(&self) -> Result<(), String> {
+        let thread_handle = {
+            let mut inner = self
+                .inner
+                .lock()
+                .map_err(|e| format!("Lock poisoned: {e}"))?;
+            if inner.status == PlaybackStatus::Idle {
+                return Err("Nothing is playing".into());
+            }
+            *inner
+                .stop_flag
+                .lock()
+                .map_err(|e| format!("Lock poisoned: {e}"))? = true;
+            inner.status = PlaybackStatus::Idle;
+            inner.thread_handle.take()
+        };
+        if let Some(handle) = thread_handle {
+            handle
+                .join()
+                .map_err(|_| "Playback thread panicked".to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Move Idle -> Playing, returning the new playback's stop flag and
+    /// generation.
+    fn begin(&self) -> Result<(Arc<Mutex<bool>>, u64), String> {
+        let mut inner = self
+            .inner
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?;
+        if inner.status == PlaybackStatus::Playing {
+            return Err(ERR_ALREADY_PLAYING.into());
+        }
+        inner.status = PlaybackStatus::Playing;
+        inner.stop_flag = Arc::new(Mutex::new(false));
+        inner.generation += 1;
+        Ok((Arc::clone(&inner.stop_flag), inner.generation))
+    }
+}
+
+/// Move Playing -> Idle once playback `generation` has ended, unless it was
+/// already stopped (and possibly replaced by a newer playback).
+fn finish(inner: &Mutex<PlaybackInner>, generation: u64) {
+    let Ok(mut inner) = inner.lock() else {
+        return;
+    };
+    if inner.generation == generation {
+        inner.status = PlaybackStatus::Idle;
+        inner.thread_handle = None;
+    }
+}
+
+/// Open the output stream, report on `ready`, then feed it the recording
+/// until the end or `stop_flag` is set.
+fn run_playback<R: Read>(
+    mut reader: hound::WavReader<R>,
+    output_device: Option<&str>,
+    stop_flag: &Mutex<bool>,
+    ready: mpsc::Sender<Result<(), String>>,
+) -> Result<(), String> {
+    let queue: Arc<Mutex<VecDeque<i16>>> = Arc::default();
+    let opened = open_output(output_device, Arc::clone(&queue));
+    let (stream, config) = match opened {
+        Ok(opened) => {
+            let _ = ready.send(Ok(()));
+            opened
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e.clone()));
+            return Err(e);
+        }
+    };
+
+    let spec = reader.spec();
+    let block_frames = (spec.sample_rate as u128 * PLAYBACK_BLOCK.as_millis() / 1000) as usize;
+    let max_queued = (config.sample_rate.0 as u128 * MAX_QUEUED.as_millis() / 1000) as usize
+        * config.channels as usize;
+    let stopped = || stop_flag.lock().map(|flag| *flag).unwrap_or(true);
+    let queued = || queue.lock().map(|q| q.len()).unwrap_or(0);
+
+    while !stopped() {
+        if queued() >= max_queued {
+            std::thread::sleep(PLAYBACK_BLOCK / 4);
+            continue;
+        }
+        let block = read_block(&mut reader, block_frames.max(1))?;
+        if block.is_empty() {
+            break;
+        }
+        let converted = convert_frames(
+            &block,
+            spec.sample_rate,
+            spec.channels,
+            config.sample_rate.0,
+            config.channels,
+        );
+        queue
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?
+            .extend(converted);
+    }
+    // Let the stream play out what is queued.
+    while !stopped() && queued() > 0 {
+        std::thread::sleep(PLAYBACK_BLOCK / 4);
+    }
+    drop(stream);
+    Ok(())
+}
+
+/// Open and start a stream on the output device in its default config,
+/// playing samples popped off `queue`.
+fn open_output(
+    output_device: Option<&str>,
+    queue: Arc<Mutex<VecDeque<i16>>>,
+) -> Result<(cpal::Stream, StreamConfig), String> {
+    let device = find_output_device(output_device)?;
+    let supported = device
+        .default_output_config()
+        .map_err(|e| format!("Failed to get default output config: {e}"))?;
+    let config = supported.config();
+    let on_error = |err: cpal::StreamError| eprintln!("Audio output stream error: {err}");
+
+    let stream = match supported.sample_format() {
+        SampleFormat::F32 => device.build_output_stream(
+            &config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                fill_output(data, &queue, |s| s as f32 / 32_768.0)
+            },
+            on_error,
+            None,
+        ),
+        SampleFormat::I16 => device.build_output_stream(
+            &config,
+            move |data: &mut [i16], _: &cpal::OutputCallbackInfo| fill_output(data, &queue, |s| s),
+            on_error,
+            None,
+        ),
+        other => return Err(format!("Unsupported output sample format: {other:?}")),
+    }
+    .map_err(|e| format!("Failed to build output stream: {e}"))?;
+    stream
+        .play()
+        .map_err(|e| format!("Failed to start output stream: {e}"))?;
+    Ok((stream, config))
+}
+
+/// Fill an output buffer from `queue`, with silence for whatever it lacks.
+fn fill_output<T: Default>(data: &mut [T], queue: &Mutex<VecDeque<i16>>, convert: fn(i16) -> T) {
+    let mut queue = queue.lock().ok();
+    for sample in data {
+        *sample = queue
+            .as_mut()
+            .and_then(|q| q.pop_front())
+            .map_or_else(T::default, convert);
+    }
+}
+
+/// Read up to `frames` frames from `reader` as interleaved f32 in
+/// [-1.0, 1.0]. Empty at the end of the file.
+fn read_block<R: Read>(
+    reader: &mut hound::WavReader<R>,
+    frames: usize,
+) -> Result<Vec<f32>, String> {
+    let spec = reader.spec();
+    let count = frames * spec.channels as usize;
+    let samples: Result<Vec<f32>, hound::Error> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().take(count).collect(),
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1i64 << (spec.bits_per_sample.max(1) - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .take(count)
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect()
+        }
+    };
+    samples.map_err(|e| format!("Failed to read WAV samples: {e}"))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn wav_reader(samples: &[i16]) -> hound::WavReader<Cursor<Vec<u8>>> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).expect("writer");
+        for &sample in samples {
+            writer.write_sample(sample).expect("write");
+        }
+        writer.finalize().expect("finalize");
+        bytes.set_position(0);
+        hound::WavReader::new(bytes).expect("reader")
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        let manager = PlaybackManager::new();
+        assert_eq!(manager.status().unwrap(), PlaybackStatus::Idle);
+        assert!(manager.stop().is_err());
+
+        let (flag, first) = manager.begin().expect("begin");
+        assert_eq!(manager.status().unwrap(), PlaybackStatus::Playing);
+        assert_eq!(manager.begin().unwrap_err(), ERR_ALREADY_PLAYING);
+
+        // Stopping signals the thread and returns to Idle at once.
+        manager.stop().expect("stop");
+        assert!(*flag.lock().unwrap());
+        assert_eq!(manager.status().unwrap(), PlaybackStatus::Idle);
+
+        // The stopped playback's thread ending late leaves a newer one alone.
+        let (_, second) = manager.begin().expect("begin");
+        finish(&manager.inner, first);
+        assert_eq!(manager.status().unwrap(), PlaybackStatus::Playing);
+        finish(&manager.inner, second);
+        assert_eq!(manager.status().unwrap(), PlaybackStatus::Idle);
+    }
+
+    #[test]
+    fn test_play_missing_file_stays_idle() {
+        let manager = PlaybackManager::new();
+        assert!(manager
+            .play(Path::new("/no/such/recording.wav"), None)
+            .is_err());
+        assert_eq!(manager.status().unwrap(), PlaybackStatus::Idle);
+    }
+
+    #[test]
+    fn test_read_block_scales_and_ends_empty() {
+        let mut reader = wav_reader(&[16_384, -32_768, 0]);
+        assert_eq!(read_block(&mut reader, 2).unwrap(), [0.5, -1.0]);
+        assert_eq!(read_block(&mut reader, 2).unwrap(), [0.0]);
+        assert!(read_block(&mut reader, 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fill_output_pads_with_silence() {
+        let queue = Mutex::new(VecDeque::from([1i16, 2]));
+        let mut out = [9i16; 4];
+        fill_output(&mut out, &queue, |s| s);
+        assert_eq!(out, [1, 2, 0, 0]);
+        assert!(queue.lock().unwrap().is_empty());
+    }
+
+    /// Requires real audio hardware — run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_play_short_recording_on_default_output() {
+        let dir = std::env::temp_dir().join("second_test_monitor_play");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        let path = dir.join("tone.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create");
+        for i in 0..3_200 {
+            let t = i as f32 / 16_000.0;
+            let sample = (t * 440.0 * std::f32::consts::TAU).sin() * 0.1;
+            writer
+                .write_sample((sample * 32_767.0) as i16)
+                .expect("write");
+        }
+        writer.finalize().expect("finalize");
+
+        let manager = PlaybackManager::new();
+        manager.play(&path, None).expect("play");
+        assert_eq!(manager.status().unwrap(), PlaybackStatus::Playing);
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while manager.status().unwrap() == PlaybackStatus::Playing {
+            assert!(std::time::Instant::now() < deadline, "playback never ended");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
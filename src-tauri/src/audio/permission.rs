@@ -0,0 +1,115 @@
+//! Microphone permission status.
+//!
+//! On macOS a denied microphone permission does not make recording fail; the
+//! input stream just delivers silence. Querying the permission lets the UI
+//! explain an all-zero recording instead of it looking like a bug. Platforms
+//! without a permission model report [`MicrophonePermission::Unknown`].
+
+/// Whether the app may record from the microphone, reported to the frontend
+/// as a snake_case string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub enum MicrophonePermission {
+    Granted,
+    /// Denied by the user, or restricted by policy.
+    Denied,
+    /// The user has not been asked yet; the first recording will prompt.
+    Undetermined,
+    /// The platform has no permission API we can query.
+    Unknown,
+}
+
+/// Query the platform's microphone permission.
+pub fn microphone_permission() -> MicrophonePermission {
+    platform::microphone_permission()
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{c_char, c_void};
+
+    use super::MicrophonePermission;
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    extern "C" {
+        /// `NSString *` constant naming the audio media type.
+        static AVMediaTypeAudio: *const c_void;
+    }
+
+    #[link(name = "objc")]
+    extern "C" {
+        fn objc_getClass(name: *const c_char) -> *mut c_void;
+        fn sel_registerName(name: *const c_char) -> *const c_void;
+        fn objc_msgSend();
+    }
+
+    /// `[AVCaptureDevice authorizationStatusForMediaType:AVMediaTypeAudio]`.
+    pub fn microphone_permission() -> MicrophonePermission {
+        // SAFETY: the class and selector names are NUL-terminated, and
+        // `objc_msgSend` is called through the exact signature of
+        // `+authorizationStatusForMediaType:` (class, SEL, NSString *) ->
+        // NSInteger.
+        let status = unsafe {
+            let class = objc_getClass(c"AVCaptureDevice".as_ptr());
+            if class.is_null() {
+                return MicrophonePermission::Unknown;
+            }
+            let selector = sel_registerName(c"authorizationStatusForMediaType:".as_ptr());
+            let send: unsafe extern "C" fn(*mut c_void, *const c_void, *const c_void) -> isize =
+                std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+            send(class, selector, AVMediaTypeAudio)
+        };
+        from_av_status(status)
+    }
+
+    /// Map an `AVAuthorizationStatus` value.
+    fn from_av_status(status: isize) -> MicrophonePermission {
+        match status {
+            0 => MicrophonePermission::Undetermined,
+            1 | 2 => MicrophonePermission::Denied,
+            3 => MicrophonePermission::Granted,
+            _ => MicrophonePermission::Unknown,
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::MicrophonePermission;
+
+    pub fn microphone_permission() -> MicrophonePermission {
+        MicrophonePermission::Unknown
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_serializes_as_snake_case() {
+        let cases = [
+            (MicrophonePermission::Granted, "granted"),
+            (MicrophonePermission::Denied, "denied"),
+            (MicrophonePermission::Undetermined, "undetermined"),
+            (MicrophonePermission::Unknown, "unknown"),
+        ];
+        for (permission, wire) in cases {
+            assert_eq!(
+                serde_json::to_value(permission).expect("serialize"),
+                serde_json::json!(wire)
+            );
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_permission_is_unknown_without_platform_api() {
+        assert_eq!(microphone_permission(), MicrophonePermission::Unknown);
+    }
+}
@@ -0,0 +1,236 @@
+//! Recordings armed to start at a wall-clock time, e.g. when a meeting
+//! begins.
+//!
+//! [`RecordingScheduler`] holds at most one pending start. A timer thread
+//! polls the system clock and runs the start callback once the time comes,
+//! unless the schedule was cancelled first.
+
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::audio::countdown::POLL_INTERVAL;
+
+/// Returned when scheduling while another recording is already scheduled.
+pub const ERR_ALREADY_SCHEDULED: &str = "A recording is already scheduled";
+
+/// Returned when the requested start time has already passed. Starting
+/// late is left to the caller, who can start a recording directly.
+pub const ERR_START_IN_PAST: &str = "Scheduled start time is in the past";
+
+/// A pending scheduled start.
+struct Pending {
+    start_at: SystemTime,
+    /// Raised by [`RecordingScheduler::cancel`]; the timer thread exits
+    /// without starting when it sees it.
+    cancelled: Arc<Mutex<bool>>,
+}
+
+/// At most one recording waiting for its start time.
+#[derive(Default)]
+pub struct RecordingScheduler {
+    pending: Arc<Mutex<Option<Pending>>>,
+}
+
+impl RecordingScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start time of the scheduled recording, as seconds since the Unix
+    /// epoch, or `None` if nothing is scheduled.
+    ///
+    /// # Errors
+    /// Returns an error if the lock is poisoned.
+    pub fn scheduled_at(&self) -> Result<Option<u64>, String> {
+        let pending = self
+            .pending
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?;
+        Ok(pending.as_ref().map(|p| unix_secs(p.start_at)))
+    }
+
+    /// Run `on_due` on a timer thread at `start_at`.
+    ///
+    /// The schedule is cleared just before `on_due` runs, so it may schedule
+    /// the next recording. Whether a recording can actually start then
+    /// (e.g. none was started by hand in the meantime) is for `on_due` to
+    /// check.
+    ///
+    /// # Errors
+    /// Returns [`ERR_ALREADY_SCHEDULED`] if a recording is already
+    /// scheduled, [`ERR_START_IN_PAST`] if `start_at` is not in the future,
+    /// or an error if the timer thread cannot be spawned.
+    pub fn schedule(
+        &self,
+        start_at: SystemTime,
+        on_due: impl FnOnce() + Send + 'static,
+    ) -> Result<(), String> {
+        let mut pending = self
+            .pending
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?;
+        if pending.is_some() {
+            return Err(ERR_ALREADY_SCHEDULED.into());
+        }
+        if start_at <= SystemTime::now() {
+            return Err(format!(
+                "{ERR_START_IN_PAST} ({} is before now)",
+                unix_secs(start_at)
+            ));
+        }
+
+        let cancelled = Arc::new(Mutex::new(false));
+        let thread_cancelled = Arc::clone(&cancelled);
+        let thread_pending = Arc::clone(&self.pending);
+        std::thread::Builder::new()
+            .name("audio-schedule".into())
+            .spawn(move || {
+                if wait_until(start_at, &thread_cancelled)
+                    && take_if_current(&thread_pending, &thread_cancelled)
+                {
+                    on_due();
+                }
+            })
+            .map_err(|e| format!("Failed to spawn schedule thread: {e}"))?;
+
+        *pending = Some(Pending {
+            start_at,
+            cancelled,
+        });
+        Ok(())
+    }
+
+    /// Cancel the scheduled recording, if any. Returns whether one was
+    /// scheduled.
+    ///
+    /// # Errors
+    /// Returns an error if a lock is poisoned.
+    pub fn cancel(&self) -> Result<bool, String> {
+        let pending = self
+            .pending
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?
+            .take();
+        let Some(pending) = pending else {
+            return Ok(false);
+        };
+        *pending
+            .cancelled
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))? = true;
+        Ok(true)
+    }
+}
+
+/// Sleep in short steps until `start_at`. Returns `false` if `cancelled`
+/// is raised (or poisoned) first.
+fn wait_until(start_at: SystemTime, cancelled: &Mutex<bool>) -> bool {
+    loop {
+        if cancelled.lock().map(|c| *c).unwrap_or(true) {
+            return false;
+        }
+        // The wall clock, not a monotonic one: the user asked for a time
+        // of day, and the machine may sleep or have its clock set meanwhile.
+        match start_at.duration_since(SystemTime::now()) {
+            Ok(left) if !left.is_zero() => std::thread::sleep(left.min(POLL_INTERVAL)),
+            _ => return true,
+        }
+    }
+}
+
+/// Clear the schedule if it is still the one `cancelled` belongs to and has
+/// not been cancelled. Returns whether it was.
+fn take_if_current(pending: &Mutex<Option<Pending>>, cancelled: &Arc<Mutex<bool>>) -> bool {
+    let Ok(mut pending) = pending.lock() else {
+        return false;
+    };
+    let current = pending
+        .as_ref()
+        .is_some_and(|p| Arc::ptr_eq(&p.cancelled, cancelled));
+    if !current || cancelled.lock().map(|c| *c).unwrap_or(true) {
+        return false;
+    }
+    pending.take();
+    true
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn soon() -> SystemTime {
+        SystemTime::now() + Duration::from_millis(100)
+    }
+
+    #[test]
+    fn test_scheduled_start_runs_once_at_the_time() {
+        let scheduler = RecordingScheduler::new();
+        let (tx, rx) = mpsc::channel();
+        let start_at = soon();
+        scheduler
+            .schedule(start_at, move || tx.send(SystemTime::now()).unwrap())
+            .expect("schedule");
+        assert_eq!(scheduler.scheduled_at(), Ok(Some(unix_secs(start_at))));
+
+        let fired_at = rx.recv_timeout(Duration::from_secs(2)).expect("fired");
+        assert!(fired_at >= start_at);
+        assert_eq!(scheduler.scheduled_at(), Ok(None));
+        // The callback was consumed; nothing else arrives.
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn test_cancelled_schedule_never_starts() {
+        let scheduler = RecordingScheduler::new();
+        let (tx, rx) = mpsc::channel();
+        scheduler
+            .schedule(soon(), move || tx.send(()).unwrap())
+            .expect("schedule");
+
+        assert_eq!(scheduler.cancel(), Ok(true));
+        assert_eq!(scheduler.scheduled_at(), Ok(None));
+        assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+        assert_eq!(scheduler.cancel(), Ok(false));
+    }
+
+    #[test]
+    fn test_only_one_recording_can_be_scheduled() {
+        let scheduler = RecordingScheduler::new();
+        let far = SystemTime::now() + Duration::from_secs(3600);
+        scheduler.schedule(far, || {}).expect("schedule");
+        assert_eq!(
+            scheduler.schedule(soon(), || {}),
+            Err(ERR_ALREADY_SCHEDULED.to_string())
+        );
+
+        // Cancelling frees the slot, and the replacement is the one that
+        // fires.
+        scheduler.cancel().expect("cancel");
+        let (tx, rx) = mpsc::channel();
+        scheduler
+            .schedule(soon(), move || tx.send(()).unwrap())
+            .expect("reschedule");
+        assert!(rx.recv_timeout(Duration::from_secs(2)).is_ok());
+    }
+
+    #[test]
+    fn test_start_time_in_the_past_is_rejected() {
+        let scheduler = RecordingScheduler::new();
+        let past = SystemTime::now() - Duration::from_secs(1);
+        let err = scheduler.schedule(past, || {}).unwrap_err();
+        assert!(err.starts_with(ERR_START_IN_PAST), "got: {err}");
+        assert_eq!(scheduler.scheduled_at(), Ok(None));
+    }
+}
@@ -0,0 +1,517 @@
+//! Destinations for captured audio.
+//!
+//! The capture loop converts device buffers to i16 samples and hands them to
+//! an [`AudioSink`]. The default sink writes WAV files via hound, optionally
+//! rolling over to a new numbered file every N frames; [`BufferSink`] keeps
+//! the samples in memory instead.
+
+use std::fs::File;
+use std::io::{BufWriter, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::audio::wav::{append_riff_chunk, bext_chunk};
+use crate::recordings::write_checksum;
+use crate::storage::describe_io_error;
+
+/// Something that accepts interleaved i16 samples from the capture loop.
+pub trait AudioSink: Send {
+    /// Write a buffer of interleaved samples.
+    ///
+    /// # Errors
+    /// Returns an error if the samples cannot be written.
+    fn write_samples(&mut self, samples: &[i16]) -> Result<(), String>;
+
+    /// Flush and close the sink, returning the files it produced.
+    ///
+    /// # Errors
+    /// Returns an error if the underlying file cannot be finalized.
+    fn finalize(self: Box<Self>) -> Result<Vec<PathBuf>, String>;
+}
+
+/// Writes samples to a WAV file, or to a series of `_partNNN` WAV files when
+/// segmentation is enabled.
+pub struct WavSink {
+    base_path: PathBuf,
+    spec: hound::WavSpec,
+    /// Frames per segment, or `None` to write a single file.
+    segment_frames: Option<u64>,
+    writer: Option<hound::WavWriter<BufWriter<File>>>,
+    /// Frames written to the current file.
+    frames_in_segment: u64,
+    /// Every file opened so far, in order.
+    paths: Vec<PathBuf>,
+    /// `sync_all` each file after it is finalized.
+    fsync_on_finalize: bool,
+    /// Append a Broadcast Wave `bext` chunk to each file when finalizing it.
+    broadcast_wave: bool,
+    /// When the current file was opened, i.e. its first sample's time.
+    opened_at: SystemTime,
+}
+
+impl WavSink {
+    /// Create the sink and open its first file.
+    ///
+    /// With `segment_frames` set, files are named
+    /// `<stem>_part001.<ext>`, `<stem>_part002.<ext>`, … next to `base_path`.
+    ///
+    /// # Errors
+    /// Returns an error if the first WAV file cannot be created.
+    pub fn create(
+        base_path: &Path,
+        spec: hound::WavSpec,
+        segment_frames: Option<u64>,
+    ) -> Result<Self, String> {
+        let mut sink = Self {
+            base_path: base_path.to_path_buf(),
+            spec,
+            segment_frames: segment_frames.filter(|&n| n > 0),
+            writer: None,
+            frames_in_segment: 0,
+            paths: Vec::new(),
+            fsync_on_finalize: false,
+            broadcast_wave: false,
+            opened_at: SystemTime::now(),
+        };
+        sink.open_next()?;
+        Ok(sink)
+    }
+
+    /// Path of the first file this sink writes for `base_path`.
+    pub fn first_path(base_path: &Path, segmented: bool) -> PathBuf {
+        if segmented {
+            segment_path(base_path, 1)
+        } else {
+            base_path.to_path_buf()
+        }
+    }
+
+    /// Force each file to disk with `sync_all` after it is finalized, so a
+    /// power loss right after stopping cannot lose it.
+    pub fn with_fsync_on_finalize(mut self, enabled: bool) -> Self {
+        self.fsync_on_finalize = enabled;
+        self
+    }
+
+    /// Give each file a Broadcast Wave `bext` chunk with its origination
+    /// date, time and time reference, so DAWs can place it on a timeline.
+    pub fn with_broadcast_wave(mut self, enabled: bool) -> Self {
+        self.broadcast_wave = enabled;
+        self
+    }
+
+    /// Finalize the current file (if any), store the checksum of its audio
+    /// next to it, and sync it to disk if requested.
+    fn finalize_current(&mut self) -> Result<(), String> {
+        let Some(writer) = self.writer.take() else {
+            return Ok(());
+        };
+        writer
+            .finalize()
+            .map_err(|e| format!("Failed to finalize WAV file: {e}"))?;
+
+        if self.broadcast_wave {
+            if let Some(path) = self.paths.last() {
+                append_riff_chunk(
+                    path,
+                    b"bext",
+                    &bext_chunk(self.opened_at, self.spec.sample_rate),
+                )?;
+            }
+        }
+        if let Some(path) = self.paths.last() {
+            write_checksum(path)?;
+        }
+        if self.fsync_on_finalize {
+            if let Some(path) = self.paths.last() {
+                File::open(path)
+                    .and_then(|f| f.sync_all())
+                    .map_err(|e| format!("Failed to sync '{}' to disk: {e}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finalize the current file (if any) and open the next one.
+    fn open_next(&mut self) -> Result<(), String> {
+        self.finalize_current()?;
+
+        let path = match self.segment_frames {
+            Some(_) => segment_path(&self.base_path, self.paths.len() + 1),
+            None => self.base_path.clone(),
+        };
+
+        let writer = hound::WavWriter::create(&path, self.spec)
+            .map_err(|e| wav_error(&path, "create WAV file", e))?;
+        self.writer = Some(writer);
+        self.opened_at = SystemTime::now();
+        self.frames_in_segment = 0;
+        self.paths.push(path);
+        Ok(())
+    }
+
+    /// Write whole frames to the current file.
+    fn write_run(&mut self, samples: &[i16]) -> Result<(), String> {
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| "WAV writer already finalized".to_string())?;
+        let path = self
+            .paths
+            .last()
+            .map(PathBuf::as_path)
+            .unwrap_or(&self.base_path);
+        for &sample in samples {
+            writer
+                .write_sample(sample)
+                .map_err(|e| wav_error(path, "write WAV file", e))?;
+        }
+        self.frames_in_segment += (samples.len() / self.spec.channels.max(1) as usize) as u64;
+        Ok(())
+    }
+}
+
+impl AudioSink for WavSink {
+    fn write_samples(&mut self, samples: &[i16]) -> Result<(), String> {
+        let channels = self.spec.channels.max(1) as usize;
+        let frames = samples.len() / channels;
+
+        let splits = match self.segment_frames {
+            Some(segment) => segment_split_points(self.frames_in_segment, frames as u64, segment),
+            None => Vec::new(),
+        };
+
+        let mut start = 0;
+        for split in splits {
+            let split = split as usize;
+            self.write_run(&samples[start * channels..split * channels])?;
+            self.open_next()?;
+            start = split;
+        }
+        self.write_run(&samples[start * channels..frames * channels])
+    }
+
+    fn finalize(mut self: Box<Self>) -> Result<Vec<PathBuf>, String> {
+        self.finalize_current()?;
+        Ok(self.paths)
+    }
+}
+
+/// Appends samples to a shared in-memory buffer, for captures that are
+/// never written to disk.
+pub struct BufferSink(Arc<Mutex<Vec<i16>>>);
+
+impl BufferSink {
+    /// A sink appending to `buffer`, which the caller keeps to read the
+    /// samples back.
+    pub fn new(buffer: Arc<Mutex<Vec<i16>>>) -> Self {
+        Self(buffer)
+    }
+}
+
+impl AudioSink for BufferSink {
+    fn write_samples(&mut self, samples: &[i16]) -> Result<(), String> {
+        self.0
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?
+            .extend_from_slice(samples);
+        Ok(())
+    }
+
+    fn finalize(self: Box<Self>) -> Result<Vec<PathBuf>, String> {
+        Ok(Vec::new())
+    }
+}
+
+/// A complete WAV file holding interleaved `samples` in `spec`, built in
+/// memory.
+///
+/// # Errors
+/// Returns an error if hound rejects `spec`.
+pub fn wav_bytes(samples: &[i16], spec: hound::WavSpec) -> Result<Vec<u8>, String> {
+    let mut cursor = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut cursor, spec)
+        .map_err(|e| format!("Failed to start in-memory WAV: {e}"))?;
+    for &sample in samples {
+        writer
+            .write_sample(sample)
+            .map_err(|e| format!("Failed to write in-memory WAV: {e}"))?;
+    }
+    writer
+        .finalize()
+        .map_err(|e| format!("Failed to finalize in-memory WAV: {e}"))?;
+    Ok(cursor.into_inner())
+}
+
+/// Describe a hound error, classifying I/O failures that mean the recordings
+/// location is read-only or full.
+fn wav_error(path: &Path, action: &str, err: hound::Error) -> String {
+    match err {
+        hound::Error::IoError(io) => describe_io_error(path, action, &io),
+        e => format!("Failed to {action}: {e}"),
+    }
+}
+
+/// Path of segment `part` (1-based) for a recording based at `base_path`,
+/// e.g. `recording_123.wav` -> `recording_123_part002.wav`.
+pub fn segment_path(base_path: &Path, part: usize) -> PathBuf {
+    let stem = base_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match base_path.extension() {
+        Some(ext) => format!("{stem}_part{part:03}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_part{part:03}"),
+    };
+    base_path.with_file_name(name)
+}
+
+/// Frame offsets within an incoming buffer at which a new segment must start.
+///
+/// `already_written` is the number of frames in the current segment before
+/// this buffer and must be at most `segment_frames`. A boundary that falls
+/// exactly at the end of the buffer is not reported; the roll-over happens
+/// lazily when the next buffer arrives so no empty segment is ever created.
+pub fn segment_split_points(already_written: u64, incoming: u64, segment_frames: u64) -> Vec<u64> {
+    if segment_frames == 0 {
+        return Vec::new();
+    }
+    let mut points = Vec::new();
+    let mut next = segment_frames.saturating_sub(already_written);
+    while next < incoming {
+        points.push(next);
+        next += segment_frames;
+    }
+    points
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_dir;
+
+    fn spec() -> hound::WavSpec {
+        hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        }
+    }
+
+    #[test]
+    fn test_split_points_within_single_buffer() {
+        // 8 frames already in a 10-frame segment; 25 more arrive.
+        assert_eq!(segment_split_points(8, 25, 10), vec![2, 12, 22]);
+    }
+
+    #[test]
+    fn test_split_points_none_when_buffer_fits() {
+        assert!(segment_split_points(0, 10, 10).is_empty());
+        assert!(segment_split_points(3, 5, 10).is_empty());
+    }
+
+    #[test]
+    fn test_split_point_at_zero_when_segment_already_full() {
+        // The boundary was reached exactly at the end of the previous buffer,
+        // so the roll-over happens before writing anything new.
+        assert_eq!(segment_split_points(10, 4, 10), vec![0]);
+    }
+
+    #[test]
+    fn test_split_points_zero_segment_is_disabled() {
+        assert!(segment_split_points(0, 100, 0).is_empty());
+    }
+
+    #[test]
+    fn test_segment_path_numbering() {
+        let base = Path::new("/tmp/recording_123.wav");
+        assert_eq!(
+            segment_path(base, 1),
+            PathBuf::from("/tmp/recording_123_part001.wav")
+        );
+        assert_eq!(
+            segment_path(base, 12),
+            PathBuf::from("/tmp/recording_123_part012.wav")
+        );
+    }
+
+    #[test]
+    fn test_wav_sink_splits_into_segments() {
+        let dir = test_dir("sink", "segments");
+        let base = dir.join("recording_1.wav");
+        let mut sink: Box<dyn AudioSink> =
+            Box::new(WavSink::create(&base, spec(), Some(10)).expect("create"));
+
+        // 7 + 7 + 6 = 20 frames -> exactly two full segments.
+        sink.write_samples(&[1; 7]).expect("write");
+        sink.write_samples(&[2; 7]).expect("write");
+        sink.write_samples(&[3; 6]).expect("write");
+        // One more frame opens a third segment.
+        sink.write_samples(&[4; 1]).expect("write");
+        let paths = sink.finalize().expect("finalize");
+
+        assert_eq!(
+            paths,
+            vec![
+                dir.join("recording_1_part001.wav"),
+                dir.join("recording_1_part002.wav"),
+                dir.join("recording_1_part003.wav"),
+            ]
+        );
+        let lengths: Vec<u32> = paths
+            .iter()
+            .map(|p| hound::WavReader::open(p).expect("open").duration())
+            .collect();
+        assert_eq!(lengths, vec![10, 10, 1]);
+        // Each segment gets its own checksum as it is finalized.
+        for path in &paths {
+            assert!(
+                crate::recordings::verify_recording(path)
+                    .expect("verify")
+                    .matches
+            );
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_wav_sink_without_segments_writes_single_file() {
+        let dir = test_dir("sink", "single");
+        let base = dir.join("recording_2.wav");
+        let mut sink: Box<dyn AudioSink> =
+            Box::new(WavSink::create(&base, spec(), None).expect("create"));
+        sink.write_samples(&[5; 100]).expect("write");
+        let paths = sink.finalize().expect("finalize");
+
+        assert_eq!(paths, vec![base.clone()]);
+        assert_eq!(hound::WavReader::open(&base).expect("open").duration(), 100);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_wav_bytes_parses_back_to_the_same_samples() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            ..spec()
+        };
+        let samples: Vec<i16> = vec![0, -1, i16::MAX, i16::MIN, 1234, -1234];
+        let bytes = wav_bytes(&samples, spec).expect("wav bytes");
+        assert_eq!(&bytes[..4], b"RIFF");
+
+        let reader = hound::WavReader::new(Cursor::new(bytes)).expect("parse");
+        assert_eq!(reader.spec(), spec);
+        assert_eq!(reader.duration(), 3);
+        let read: Vec<i16> = reader
+            .into_samples::<i16>()
+            .collect::<Result<_, _>>()
+            .expect("samples");
+        assert_eq!(read, samples);
+    }
+
+    #[test]
+    fn test_buffer_sink_appends_and_produces_no_files() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let mut sink: Box<dyn AudioSink> = Box::new(BufferSink::new(Arc::clone(&buffer)));
+        sink.write_samples(&[1, 2]).expect("write");
+        sink.write_samples(&[3]).expect("write");
+        assert!(sink.finalize().expect("finalize").is_empty());
+        assert_eq!(*buffer.lock().unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_wav_sink_fsync_on_finalize_succeeds() {
+        let dir = test_dir("sink", "fsync");
+        let base = dir.join("recording_3.wav");
+        let mut sink: Box<dyn AudioSink> = Box::new(
+            WavSink::create(&base, spec(), Some(10))
+                .expect("create")
+                .with_fsync_on_finalize(true),
+        );
+        // Crosses a segment boundary so both the roll-over and the final
+        // finalize take the sync path.
+        sink.write_samples(&[6; 15]).expect("write");
+        let paths = sink.finalize().expect("finalize with fsync");
+
+        assert_eq!(paths.len(), 2);
+        assert_eq!(
+            hound::WavReader::open(&paths[1]).expect("open").duration(),
+            5
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_wav_sink_broadcast_wave_appends_bext_chunk() {
+        let dir = test_dir("sink", "bext");
+        let base = dir.join("recording_4.wav");
+        let mut sink: Box<dyn AudioSink> = Box::new(
+            WavSink::create(&base, spec(), None)
+                .expect("create")
+                .with_broadcast_wave(true),
+        );
+        sink.write_samples(&[7; 160]).expect("write");
+        let paths = sink.finalize().expect("finalize");
+
+        let bytes = std::fs::read(&paths[0]).expect("read file");
+        let riff_size = u32::from_le_bytes(bytes[4..8].try_into().expect("4 bytes"));
+        assert_eq!(riff_size as usize, bytes.len() - 8);
+
+        let chunks = crate::audio::wav::read_riff_chunks(&paths[0]).expect("chunks");
+        let ids: Vec<_> = chunks.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![*b"fmt ", *b"data", *b"bext"]);
+        let bext = &chunks[2];
+        assert_eq!(bext.size as usize, crate::audio::wav::BEXT_SIZE);
+
+        let body = &bytes[bext.offset as usize..][..bext.size as usize];
+        assert!(body[256..].starts_with(b"Second\0"));
+        let date = std::str::from_utf8(&body[320..330]).expect("date");
+        let time = std::str::from_utf8(&body[330..338]).expect("time");
+        assert_eq!(date.as_bytes()[4], b'-');
+        assert_eq!(date.as_bytes()[7], b'-');
+        let hms: Vec<u64> = time
+            .split(':')
+            .map(|part| part.parse().expect("numeric time field"))
+            .collect();
+        assert_eq!(hms.len(), 3);
+        // The time reference counts samples since midnight, so it must agree
+        // with the origination time to the second.
+        let time_reference = u64::from_le_bytes(body[338..346].try_into().expect("8 bytes"));
+        assert_eq!(
+            time_reference / 16_000,
+            hms[0] * 3_600 + hms[1] * 60 + hms[2]
+        );
+        // Version 0.
+        assert_eq!(&body[346..348], &[0, 0]);
+
+        // The extra chunk does not disturb reading the samples back.
+        let mut reader = hound::WavReader::open(&paths[0]).expect("open");
+        assert_eq!(reader.duration(), 160);
+        assert!(reader.samples::<i16>().all(|s| s.expect("sample") == 7));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_wav_sink_without_broadcast_wave_has_no_bext() {
+        let dir = test_dir("sink", "no_bext");
+        let base = dir.join("recording_5.wav");
+        let mut sink: Box<dyn AudioSink> =
+            Box::new(WavSink::create(&base, spec(), None).expect("create"));
+        sink.write_samples(&[1; 16]).expect("write");
+        let paths = sink.finalize().expect("finalize");
+
+        let chunks = crate::audio::wav::read_riff_chunks(&paths[0]).expect("chunks");
+        assert!(chunks.iter().all(|c| &c.id != b"bext"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
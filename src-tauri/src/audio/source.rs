@@ -0,0 +1,395 @@
+//! Sources of captured audio.
+//!
+//! The capture loop reads its input from an [`AudioSource`]: in the app a
+//! CPAL input device, in tests a scripted source, so the loop's write,
+//! conversion, and stop handling can run without audio hardware.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::StreamConfig;
+
+use crate::audio::config::AudioCaptureConfig;
+use crate::audio::devices::{
+    classify_build_error, classify_play_error, classify_stream_error, find_input_device,
+    ConfigRange, DeviceAllowList, DeviceAvailability,
+};
+
+/// Called with each buffer of interleaved f32 samples a stream delivers,
+/// and the input latency the stream reports for it, if it reports one.
+pub type DataCallback = Box<dyn FnMut(&[f32], Option<Duration>) + Send>;
+
+/// Called with a description of each error a stream reports.
+pub type ErrorCallback = Box<dyn FnMut(String) + Send>;
+
+/// Stream config chosen for a source, and whether its samples need
+/// converting to the target format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegotiatedConfig {
+    pub stream: StreamConfig,
+    pub need_conversion: bool,
+    /// Why this config was chosen.
+    pub reason: ConversionReason,
+}
+
+/// Why a source's stream config was chosen (see [`choose_stream_config`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionReason {
+    /// The device captures the target format natively.
+    TargetSupported,
+    /// Native passthrough: the device's default format is written as is.
+    NativePassthrough,
+    /// One channel is picked out of the device's default format (see
+    /// `source_channel`) and converted.
+    SourceChannel,
+    /// The device lacks the target format, so its default format is
+    /// captured and converted.
+    Fallback,
+    /// The device could not list its formats, so the target format was
+    /// requested blind.
+    Unqueried,
+}
+
+/// How the audio of a recording gets from its device into the file, for
+/// explaining a recording that sounds off.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ConversionInfo {
+    pub device: String,
+    /// Format the device delivers.
+    pub source_sample_rate: u32,
+    pub source_channels: u16,
+    /// Format written to the file.
+    pub output_sample_rate: u32,
+    pub output_channels: u16,
+    /// The device's samples are resampled and/or remixed.
+    pub converted: bool,
+    pub reason: ConversionReason,
+    /// `native`, or `converted from <rate> Hz / <channels> channels`.
+    pub summary: String,
+}
+
+impl ConversionInfo {
+    /// The conversion path of `negotiated` on `device` into a file of
+    /// format `spec`.
+    pub fn new(device: &str, negotiated: &NegotiatedConfig, spec: &hound::WavSpec) -> Self {
+        let source_sample_rate = negotiated.stream.sample_rate.0;
+        let source_channels = negotiated.stream.channels;
+        let summary = if negotiated.need_conversion {
+            format!("converted from {source_sample_rate} Hz / {source_channels} channels")
+        } else {
+            "native".to_string()
+        };
+        Self {
+            device: device.to_string(),
+            source_sample_rate,
+            source_channels,
+            output_sample_rate: spec.sample_rate,
+            output_channels: spec.channels,
+            converted: negotiated.need_conversion,
+            reason: negotiated.reason,
+            summary,
+        }
+    }
+}
+
+/// Choose the stream config for capturing with `capture_config` from a
+/// device's native `ranges` (`None` if it cannot list them) and its
+/// default config, which is only asked for when needed.
+///
+/// With native passthrough, record whatever the device delivers by default
+/// and write it unconverted. To pick out a `source_channel`, capture all of
+/// the device's default channels and convert. Otherwise use the target
+/// format if the device supports it, falling back to the device's default
+/// config and resampling/converting later.
+///
+/// # Errors
+/// Returns an error if the default config is needed but unavailable.
+pub fn choose_stream_config(
+    capture_config: &AudioCaptureConfig,
+    ranges: Option<&[ConfigRange]>,
+    default_config: impl FnOnce() -> Result<StreamConfig, String>,
+) -> Result<NegotiatedConfig, String> {
+    let target_rate = capture_config.sample_rate;
+    let target_channels = capture_config.channels;
+    let desired_config = StreamConfig {
+        channels: target_channels,
+        sample_rate: cpal::SampleRate(target_rate),
+        buffer_size: cpal::BufferSize::Default,
+    };
+    let negotiated = |stream, need_conversion, reason| NegotiatedConfig {
+        stream,
+        need_conversion,
+        reason,
+    };
+
+    if capture_config.native_passthrough {
+        return Ok(negotiated(
+            default_config()?,
+            false,
+            ConversionReason::NativePassthrough,
+        ));
+    }
+    if capture_config.source_channel.is_some() {
+        return Ok(negotiated(
+            default_config()?,
+            true,
+            ConversionReason::SourceChannel,
+        ));
+    }
+    Ok(match ranges {
+        Some(ranges)
+            if ranges
+                .iter()
+                .any(|r| r.covers(target_rate, target_channels)) =>
+        {
+            negotiated(desired_config, false, ConversionReason::TargetSupported)
+        }
+        Some(_) => negotiated(default_config()?, true, ConversionReason::Fallback),
+        // If we can't query supported configs, try the desired config
+        // directly and hope for the best.
+        None => negotiated(desired_config, false, ConversionReason::Unqueried),
+    })
+}
+
+/// Something the capture loop can open input streams on.
+pub trait AudioSource: Sized {
+    /// A running input stream; stops delivering buffers once dropped.
+    type Stream: StreamTrait;
+
+    /// The source named `name`, for switching a running capture to it.
+    ///
+    /// # Errors
+    /// Returns an error if the source is not allowed or cannot be found.
+    fn find(name: &str, allowed: &DeviceAllowList) -> Result<Self, String>;
+
+    /// Name reported for the source, and keyed on by the config cache.
+    fn name(&self) -> String;
+
+    /// Choose the stream config for capturing with `capture_config`.
+    ///
+    /// # Errors
+    /// Returns an error if the source cannot report a usable config.
+    fn negotiate(&self, capture_config: &AudioCaptureConfig) -> Result<NegotiatedConfig, String>;
+
+    /// Build (but do not start) a stream with `config` that hands each
+    /// buffer to `on_data` and each stream error to `on_error`.
+    ///
+    /// # Errors
+    /// Returns an error if the stream cannot be built.
+    fn build_stream(
+        &self,
+        config: &StreamConfig,
+        on_data: DataCallback,
+        on_error: ErrorCallback,
+    ) -> Result<Self::Stream, String>;
+}
+
+/// A CPAL input device.
+pub struct CpalSource(cpal::Device);
+
+impl CpalSource {
+    pub fn new(device: cpal::Device) -> Self {
+        Self(device)
+    }
+
+    /// Run a test stream with `config` for `duration` and classify how it
+    /// went. A stream that opens but delivers no buffers counts as in use.
+    pub fn probe(&self, config: &StreamConfig, duration: Duration) -> DeviceAvailability {
+        let buffers = Arc::new(Mutex::new(0usize));
+        let stream_error = Arc::new(Mutex::new(None));
+        let stream = self.0.build_input_stream(
+            config,
+            {
+                let buffers = Arc::clone(&buffers);
+                move |_: &[f32], _: &cpal::InputCallbackInfo| {
+                    if let Ok(mut count) = buffers.lock() {
+                        *count += 1;
+                    }
+                }
+            },
+            {
+                let stream_error = Arc::clone(&stream_error);
+                move |err: cpal::StreamError| {
+                    if let Ok(mut slot) = stream_error.lock() {
+                        slot.get_or_insert(classify_stream_error(&err));
+                    }
+                }
+            },
+            None,
+        );
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => return classify_build_error(&e),
+        };
+        if let Err(e) = stream.play() {
+            return classify_play_error(&e);
+        }
+        std::thread::sleep(duration);
+        drop(stream);
+
+        if let Some(reported) = stream_error.lock().ok().and_then(|mut slot| slot.take()) {
+            return reported;
+        }
+        let delivered = buffers.lock().map(|count| *count).unwrap_or(0);
+        if delivered == 0 {
+            DeviceAvailability::InUse
+        } else {
+            DeviceAvailability::Available
+        }
+    }
+}
+
+impl AudioSource for CpalSource {
+    type Stream = cpal::Stream;
+
+    fn find(name: &str, allowed: &DeviceAllowList) -> Result<Self, String> {
+        find_input_device(Some(name), allowed).map(Self)
+    }
+
+    fn name(&self) -> String {
+        self.0.name().unwrap_or_default()
+    }
+
+    /// See [`choose_stream_config`].
+    fn negotiate(&self, capture_config: &AudioCaptureConfig) -> Result<NegotiatedConfig, String> {
+        let device = &self.0;
+        // Passthrough and channel picking never look at the ranges.
+        let ranges: Option<Vec<ConfigRange>> =
+            if capture_config.native_passthrough || capture_config.source_channel.is_some() {
+                None
+            } else {
+                device
+                    .supported_input_configs()
+                    .ok()
+                    .map(|configs| configs.map(|range| ConfigRange::from(&range)).collect())
+            };
+        choose_stream_config(capture_config, ranges.as_deref(), || {
+            device
+                .default_input_config()
+                .map(|config| config.config())
+                .map_err(|e| format!("Failed to get default input config: {e}"))
+        })
+    }
+
+    fn build_stream(
+        &self,
+        config: &StreamConfig,
+        mut on_data: DataCallback,
+        mut on_error: ErrorCallback,
+    ) -> Result<cpal::Stream, String> {
+        self.0
+            .build_input_stream(
+                config,
+                move |data: &[f32], info: &cpal::InputCallbackInfo| {
+                    on_data(data, reported_latency(info))
+                },
+                move |err: cpal::StreamError| on_error(format!("Audio stream error: {err}")),
+                None,
+            )
+            .map_err(|e| format!("Failed to build input stream: {e}"))
+    }
+}
+
+/// Time from the device capturing a buffer to the callback receiving it.
+/// Backends that don't track capture time report the two as equal (or the
+/// capture as later), which is taken as no figure at all.
+fn reported_latency(info: &cpal::InputCallbackInfo) -> Option<Duration> {
+    let timestamp = info.timestamp();
+    timestamp
+        .callback
+        .duration_since(&timestamp.capture)
+        .filter(|latency| !latency.is_zero())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpal::SampleFormat;
+
+    fn range(channels: u16, min: u32, max: u32) -> ConfigRange {
+        ConfigRange {
+            channels,
+            min_sample_rate: min,
+            max_sample_rate: max,
+            sample_format: SampleFormat::I16,
+        }
+    }
+
+    fn default_48k_stereo() -> Result<StreamConfig, String> {
+        Ok(StreamConfig {
+            channels: 2,
+            sample_rate: cpal::SampleRate(48_000),
+            buffer_size: cpal::BufferSize::Default,
+        })
+    }
+
+    fn conversion(config: &AudioCaptureConfig, ranges: Option<&[ConfigRange]>) -> ConversionInfo {
+        let negotiated = choose_stream_config(config, ranges, default_48k_stereo).expect("choose");
+        let spec = crate::audio::capture::output_spec(config, &negotiated.stream);
+        ConversionInfo::new("USB Mic", &negotiated, &spec)
+    }
+
+    #[test]
+    fn test_conversion_info_native_when_device_supports_target() {
+        let config = AudioCaptureConfig::default();
+        let info = conversion(&config, Some(&[range(1, 8_000, 48_000)]));
+        assert!(!info.converted);
+        assert_eq!(info.reason, ConversionReason::TargetSupported);
+        assert_eq!(info.summary, "native");
+        assert_eq!((info.source_sample_rate, info.source_channels), (16_000, 1));
+        assert_eq!((info.output_sample_rate, info.output_channels), (16_000, 1));
+    }
+
+    #[test]
+    fn test_conversion_info_reports_fallback_source_format() {
+        // Only 44.1-48 kHz stereo: the default config is captured and
+        // converted down.
+        let config = AudioCaptureConfig::default();
+        let info = conversion(&config, Some(&[range(2, 44_100, 48_000)]));
+        assert!(info.converted);
+        assert_eq!(info.reason, ConversionReason::Fallback);
+        assert_eq!(info.summary, "converted from 48000 Hz / 2 channels");
+        assert_eq!((info.output_sample_rate, info.output_channels), (16_000, 1));
+
+        // Without a list of formats the target is tried directly.
+        let info = conversion(&config, None);
+        assert_eq!(info.reason, ConversionReason::Unqueried);
+        assert!(!info.converted);
+    }
+
+    #[test]
+    fn test_conversion_info_passthrough_writes_device_format() {
+        let config = AudioCaptureConfig {
+            native_passthrough: true,
+            ..Default::default()
+        };
+        let info = conversion(&config, Some(&[range(1, 8_000, 48_000)]));
+        assert_eq!(info.reason, ConversionReason::NativePassthrough);
+        assert_eq!(info.summary, "native");
+        assert_eq!((info.output_sample_rate, info.output_channels), (48_000, 2));
+
+        let err = choose_stream_config(&config, None, || Err("no default".into()));
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_source_channel_captures_every_device_channel() {
+        let config = AudioCaptureConfig {
+            source_channel: Some(1),
+            ..Default::default()
+        };
+        // Even a device offering the target format is opened with its
+        // default channels, so the channel can be picked out.
+        let info = conversion(&config, Some(&[range(1, 8_000, 48_000)]));
+        assert_eq!(info.reason, ConversionReason::SourceChannel);
+        assert!(info.converted);
+        assert_eq!((info.source_sample_rate, info.source_channels), (48_000, 2));
+        assert_eq!((info.output_sample_rate, info.output_channels), (16_000, 1));
+    }
+}
@@ -0,0 +1,1677 @@
+//! WAV file inspection helpers.
+//!
+//! Reads the format spec of a recording via hound and walks the raw RIFF
+//! chunk list for metadata hound does not expose (e.g. the `LIST/INFO` chunk).
+//! Also computes downsampled peak arrays for waveform thumbnails and overall
+//! levels for spotting silent recordings, trims silence off their ends, and
+//! finds the regions holding speech.
+//! Downmixes multi-channel recordings to mono, resamples them, and splits
+//! them into clips.
+//! Writes the Broadcast Wave `bext` chunk, which hound cannot, repairs the
+//! size fields of files left unfinalized by a crash, and checksums the audio
+//! itself for integrity checks.
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::audio::capture::mix_frame;
+use crate::audio::config::{MAX_SAMPLE_RATE, MIN_SAMPLE_RATE};
+use crate::recordings::civil_from_days;
+
+/// Format and length of a WAV file.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct WavInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    /// Number of frames (samples per channel).
+    pub frames: u32,
+    pub duration_seconds: f64,
+}
+
+/// Read the format spec and length of a WAV file.
+///
+/// # Errors
+/// Returns an error if the file cannot be opened or is not a valid WAV.
+pub fn read_wav_info(path: &Path) -> Result<WavInfo, String> {
+    let reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?;
+    let spec = reader.spec();
+    let frames = reader.duration();
+    let duration_seconds = if spec.sample_rate == 0 {
+        0.0
+    } else {
+        frames as f64 / spec.sample_rate as f64
+    };
+
+    Ok(WavInfo {
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        bits_per_sample: spec.bits_per_sample,
+        frames,
+        duration_seconds,
+    })
+}
+
+/// A top-level chunk inside a RIFF/WAVE file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiffChunk {
+    pub id: [u8; 4],
+    /// Byte offset of the chunk body (just past the 8-byte chunk header).
+    pub offset: u64,
+    /// Declared size of the chunk body in bytes.
+    pub size: u32,
+}
+
+/// List the top-level chunks of a RIFF/WAVE file without reading their
+/// bodies.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or lacks a `RIFF....WAVE`
+/// header.
+pub fn read_riff_chunks(path: &Path) -> Result<Vec<RiffChunk>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open '{}': {e}", path.display()))?;
+    let file_len = file
+        .metadata()
+        .map_err(|e| format!("Failed to stat '{}': {e}", path.display()))?
+        .len();
+    let mut reader = BufReader::new(file);
+
+    let mut header = [0u8; 12];
+    reader
+        .read_exact(&mut header)
+        .map_err(|e| format!("Failed to read RIFF header of '{}': {e}", path.display()))?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err(format!("'{}' is not a RIFF/WAVE file", path.display()));
+    }
+
+    let mut chunks = Vec::new();
+    let mut pos: u64 = 12;
+    while pos + 8 <= file_len {
+        reader
+            .seek(SeekFrom::Start(pos))
+            .map_err(|e| format!("Failed to seek in '{}': {e}", path.display()))?;
+        let mut chunk_header = [0u8; 8];
+        reader
+            .read_exact(&mut chunk_header)
+            .map_err(|e| format!("Failed to read chunk header in '{}': {e}", path.display()))?;
+
+        let mut id = [0u8; 4];
+        id.copy_from_slice(&chunk_header[0..4]);
+        let size = u32::from_le_bytes([
+            chunk_header[4],
+            chunk_header[5],
+            chunk_header[6],
+            chunk_header[7],
+        ]);
+        chunks.push(RiffChunk {
+            id,
+            offset: pos + 8,
+            size,
+        });
+
+        // Chunk bodies are padded to an even length.
+        pos += 8 + u64::from(size) + u64::from(size % 2);
+    }
+
+    Ok(chunks)
+}
+
+/// Fix the RIFF and `data` chunk sizes of a WAV whose recording was cut off
+/// (e.g. by a crash) before they were finalized, so players will open it.
+/// Returns whether a repair was needed.
+///
+/// The declared sizes are trusted if they account for the file exactly.
+/// Otherwise `data` is taken to run to the end of the file, rounded down to
+/// whole frames, and any bytes of a partial last frame are left outside the
+/// RIFF. Only the two size fields are ever written.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or written, is not a
+/// RIFF/WAVE file, or has no `fmt ` or `data` chunk.
+pub fn repair_wav(path: &Path) -> Result<bool, String> {
+    let chunks = read_riff_chunks(path)?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open '{}': {e}", path.display()))?;
+    let file_len = file
+        .metadata()
+        .map_err(|e| format!("Failed to stat '{}': {e}", path.display()))?
+        .len();
+
+    let fmt = chunks
+        .iter()
+        .find(|c| &c.id == b"fmt ")
+        .ok_or_else(|| format!("'{}' has no fmt chunk", path.display()))?;
+    let data_index = chunks
+        .iter()
+        .position(|c| &c.id == b"data")
+        .ok_or_else(|| format!("'{}' has no data chunk", path.display()))?;
+    let data = &chunks[data_index];
+
+    let chunk_end = |c: &RiffChunk| c.offset + u64::from(c.size) + u64::from(c.size % 2);
+    let (data_size, riff_end) = match chunks.last() {
+        Some(last) if chunk_end(last) == file_len => (data.size, file_len),
+        _ => {
+            let mut block_align = [0u8; 2];
+            file.seek(SeekFrom::Start(fmt.offset + 12))
+                .and_then(|_| file.read_exact(&mut block_align))
+                .map_err(|e| format!("Failed to read fmt chunk of '{}': {e}", path.display()))?;
+            let block_align = u64::from(u16::from_le_bytes(block_align).max(1));
+            let available = file_len.saturating_sub(data.offset);
+            let size = u32::try_from(available - available % block_align)
+                .map_err(|_| format!("'{}' is too large for a RIFF file", path.display()))?;
+            (size, data.offset + u64::from(size))
+        }
+    };
+    let riff_size = u32::try_from(riff_end - 8)
+        .map_err(|_| format!("'{}' is too large for a RIFF file", path.display()))?;
+
+    let mut declared_riff = [0u8; 4];
+    file.seek(SeekFrom::Start(4))
+        .and_then(|_| file.read_exact(&mut declared_riff))
+        .map_err(|e| format!("Failed to read RIFF header of '{}': {e}", path.display()))?;
+    let mut repaired = false;
+    for (offset, declared, actual) in [
+        (4, u32::from_le_bytes(declared_riff), riff_size),
+        (data.offset - 4, data.size, data_size),
+    ] {
+        if declared != actual {
+            file.seek(SeekFrom::Start(offset))
+                .and_then(|_| file.write_all(&actual.to_le_bytes()))
+                .map_err(|e| format!("Failed to repair '{}': {e}", path.display()))?;
+            repaired = true;
+        }
+    }
+    Ok(repaired)
+}
+
+/// Hex SHA-256 of the body of the `data` chunk, i.e. of the audio alone.
+///
+/// Headers and other chunks are left out, so the checksum survives
+/// [`repair_wav`], an appended `bext` chunk or edited metadata. A body
+/// declared past the end of the file is hashed up to the end.
+///
+/// # Errors
+/// Returns an error if the file cannot be read, is not a RIFF/WAVE file, or
+/// has no `data` chunk.
+pub fn data_checksum(path: &Path) -> Result<String, String> {
+    let data = read_riff_chunks(path)?
+        .into_iter()
+        .find(|chunk| &chunk.id == b"data")
+        .ok_or_else(|| format!("'{}' has no data chunk", path.display()))?;
+
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open '{}': {e}", path.display()))?;
+    file.seek(SeekFrom::Start(data.offset))
+        .map_err(|e| format!("Failed to seek in '{}': {e}", path.display()))?;
+    let mut body = BufReader::new(file).take(u64::from(data.size));
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = body
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read '{}': {e}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Append a chunk to the end of a finalized RIFF/WAVE file, padding an odd
+/// body to an even length, and update the RIFF size to cover it.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or written or is not a
+/// RIFF/WAVE file.
+pub fn append_riff_chunk(path: &Path, id: &[u8; 4], body: &[u8]) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open '{}': {e}", path.display()))?;
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header)
+        .map_err(|e| format!("Failed to read RIFF header of '{}': {e}", path.display()))?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err(format!("'{}' is not a RIFF/WAVE file", path.display()));
+    }
+    let body_len = u32::try_from(body.len())
+        .map_err(|_| format!("Chunk too large for '{}'", path.display()))?;
+
+    let mut chunk = Vec::with_capacity(body.len() + 9);
+    chunk.extend_from_slice(id);
+    chunk.extend_from_slice(&body_len.to_le_bytes());
+    chunk.extend_from_slice(body);
+    if body.len() % 2 == 1 {
+        chunk.push(0);
+    }
+    let end = file
+        .seek(SeekFrom::End(0))
+        .and_then(|end| file.write_all(&chunk).map(|()| end))
+        .map_err(|e| format!("Failed to append chunk to '{}': {e}", path.display()))?;
+    let riff_size = u32::try_from(end + chunk.len() as u64 - 8)
+        .map_err(|_| format!("'{}' is too large for a RIFF file", path.display()))?;
+    file.seek(SeekFrom::Start(4))
+        .and_then(|_| file.write_all(&riff_size.to_le_bytes()))
+        .map_err(|e| format!("Failed to update RIFF size of '{}': {e}", path.display()))
+}
+
+/// Length of a `bext` chunk body without coding history (EBU Tech 3285).
+pub const BEXT_SIZE: usize = 602;
+
+/// Written to the `bext` originator field.
+const BEXT_ORIGINATOR: &str = "Second";
+
+/// Body of a minimal version 0 Broadcast Wave `bext` chunk for a file whose
+/// first sample was captured at `origin`.
+///
+/// The origination date (`yyyy-mm-dd`) and time (`hh:mm:ss`) are in UTC,
+/// like the dated recording folders, and the time reference is the number
+/// of samples at `sample_rate` from that midnight to `origin`, which DAWs
+/// use to place the file on a timeline. Description, UMID and coding
+/// history are left empty.
+pub fn bext_chunk(origin: SystemTime, sample_rate: u32) -> Vec<u8> {
+    let since_epoch = origin.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let of_day = secs % 86_400;
+    let since_midnight = of_day as f64 + f64::from(since_epoch.subsec_nanos()) / 1e9;
+    let time_reference = (since_midnight * f64::from(sample_rate)) as u64;
+
+    let mut body = vec![0u8; BEXT_SIZE];
+    // Description (256) precedes the originator (32) and its reference (32).
+    body[256..256 + BEXT_ORIGINATOR.len()].copy_from_slice(BEXT_ORIGINATOR.as_bytes());
+    body[320..330].copy_from_slice(format!("{year:04}-{month:02}-{day:02}").as_bytes());
+    body[330..338].copy_from_slice(
+        format!(
+            "{:02}:{:02}:{:02}",
+            of_day / 3_600,
+            of_day / 60 % 60,
+            of_day % 60
+        )
+        .as_bytes(),
+    );
+    body[338..346].copy_from_slice(&time_reference.to_le_bytes());
+    // Version (0), UMID and the reserved block stay zeroed.
+    body
+}
+
+/// Read the `LIST/INFO` metadata chunk, if present, as a map from the
+/// four-character field id (e.g. `ICRD`, `ICMT`) to its text value.
+///
+/// Files without an INFO chunk yield an empty map.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is not a RIFF/WAVE file.
+pub fn read_info_metadata(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let mut metadata = BTreeMap::new();
+
+    let chunks = read_riff_chunks(path)?;
+    let Some(list) = chunks.iter().find(|c| &c.id == b"LIST") else {
+        return Ok(metadata);
+    };
+
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open '{}': {e}", path.display()))?;
+    file.seek(SeekFrom::Start(list.offset))
+        .map_err(|e| format!("Failed to seek in '{}': {e}", path.display()))?;
+    let mut body = Vec::new();
+    file.take(u64::from(list.size))
+        .read_to_end(&mut body)
+        .map_err(|e| format!("Failed to read LIST chunk in '{}': {e}", path.display()))?;
+
+    if body.len() < 4 || &body[0..4] != b"INFO" {
+        return Ok(metadata);
+    }
+
+    let mut pos = 4;
+    while pos + 8 <= body.len() {
+        let id = String::from_utf8_lossy(&body[pos..pos + 4]).into_owned();
+        let size = u32::from_le_bytes([body[pos + 4], body[pos + 5], body[pos + 6], body[pos + 7]])
+            as usize;
+        let start = pos + 8;
+        let end = (start + size).min(body.len());
+        let value = String::from_utf8_lossy(&body[start..end])
+            .trim_end_matches('\0')
+            .to_string();
+        metadata.insert(id, value);
+        pos = start + size + size % 2;
+    }
+
+    Ok(metadata)
+}
+
+/// Summarize a WAV as `buckets` peak amplitudes for drawing a waveform.
+///
+/// The frames are split into `buckets` equal windows and each yields the
+/// sample with the largest magnitude in it (across all channels), keeping
+/// its sign and normalized to [-1, 1]. A file with fewer frames than
+/// `buckets` yields one value per frame; an empty file yields none.
+///
+/// # Errors
+/// Returns an error if `buckets` is zero or the file cannot be read as a
+/// WAV.
+pub fn compute_waveform(path: &Path, buckets: usize) -> Result<Vec<f32>, String> {
+    if buckets == 0 {
+        return Err("Waveform needs at least one bucket".into());
+    }
+
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?;
+    let spec = reader.spec();
+    let channels = usize::from(spec.channels.max(1));
+    let frames = reader.duration() as usize;
+    let buckets = buckets.min(frames);
+    let mut peaks = vec![0.0_f32; buckets];
+    if buckets == 0 {
+        return Ok(peaks);
+    }
+
+    for (i, sample) in normalized_samples(&mut reader).enumerate() {
+        let sample =
+            sample.map_err(|e| format!("Failed to read samples from '{}': {e}", path.display()))?;
+        let bucket = ((i / channels) as u64 * buckets as u64 / frames as u64) as usize;
+        let peak = &mut peaks[bucket.min(buckets - 1)];
+        if sample.abs() > peak.abs() {
+            *peak = sample.clamp(-1.0, 1.0);
+        }
+    }
+
+    Ok(peaks)
+}
+
+/// Copy samples of `reader` to `writer` unchanged, in the sample type that
+/// fits its spec: all that are left, or at most `limit`.
+///
+/// # Errors
+/// Returns the first read or write error.
+pub fn copy_samples<R: Read, W: Write + Seek>(
+    reader: &mut hound::WavReader<R>,
+    writer: &mut hound::WavWriter<W>,
+    limit: Option<usize>,
+) -> Result<(), hound::Error> {
+    fn copy<S: hound::Sample, R: Read, W: Write + Seek>(
+        reader: &mut hound::WavReader<R>,
+        writer: &mut hound::WavWriter<W>,
+        limit: usize,
+    ) -> Result<(), hound::Error> {
+        for sample in reader.samples::<S>().take(limit) {
+            writer.write_sample(sample?)?;
+        }
+        Ok(())
+    }
+
+    let spec = reader.spec();
+    let limit = limit.unwrap_or(usize::MAX);
+    match spec.sample_format {
+        hound::SampleFormat::Float => copy::<f32, _, _>(reader, writer, limit),
+        hound::SampleFormat::Int if spec.bits_per_sample <= 16 => {
+            copy::<i16, _, _>(reader, writer, limit)
+        }
+        hound::SampleFormat::Int => copy::<i32, _, _>(reader, writer, limit),
+    }
+}
+
+/// Every sample of `reader`, interleaved, scaled to [-1, 1].
+fn normalized_samples<R: Read>(
+    reader: &mut hound::WavReader<R>,
+) -> Box<dyn Iterator<Item = Result<f32, hound::Error>> + '_> {
+    let spec = reader.spec();
+    match spec.sample_format {
+        hound::SampleFormat::Float => Box::new(reader.samples::<f32>()),
+        hound::SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            Box::new(
+                reader
+                    .samples::<i32>()
+                    .map(move |s| s.map(|v| v as f32 / scale)),
+            )
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Levels
+// ---------------------------------------------------------------------------
+
+/// Floor reported for digital silence, which has no finite dBFS value.
+pub const MIN_DBFS: f32 = -120.0;
+
+/// Overall peak and RMS level of a recording, in dBFS (0 = full scale).
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct AudioLevels {
+    pub peak_dbfs: f32,
+    pub rms_dbfs: f32,
+}
+
+impl AudioLevels {
+    /// Levels of interleaved `samples` in [-1, 1]. No samples at all
+    /// measures as [`MIN_DBFS`].
+    pub fn of(samples: impl IntoIterator<Item = f32>) -> Self {
+        let (mut peak, mut sum_squares, mut count) = (0.0_f32, 0.0_f64, 0_u64);
+        for sample in samples {
+            peak = peak.max(sample.abs());
+            sum_squares += f64::from(sample) * f64::from(sample);
+            count += 1;
+        }
+        let rms = if count == 0 {
+            0.0
+        } else {
+            (sum_squares / count as f64).sqrt() as f32
+        };
+        Self {
+            peak_dbfs: amplitude_to_dbfs(peak),
+            rms_dbfs: amplitude_to_dbfs(rms),
+        }
+    }
+
+    /// Effectively silent: not even the peak reaches `threshold_dbfs`.
+    pub fn is_silent(&self, threshold_dbfs: f32) -> bool {
+        self.peak_dbfs < threshold_dbfs
+    }
+}
+
+/// Whether a recording is effectively silent, with the levels measured.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct SilenceReport {
+    pub silent: bool,
+    #[serde(flatten)]
+    pub levels: AudioLevels,
+}
+
+/// Measure the WAV at `path` (see [`measure_levels`]) and classify it
+/// against `threshold_dbfs` (see [`AudioLevels::is_silent`]).
+///
+/// # Errors
+/// Returns an error if the file cannot be read as a WAV.
+pub fn check_silence(path: &Path, threshold_dbfs: f32) -> Result<SilenceReport, String> {
+    let levels = measure_levels(path)?;
+    Ok(SilenceReport {
+        silent: levels.is_silent(threshold_dbfs),
+        levels,
+    })
+}
+
+/// `amplitude` (1.0 = full scale) in dBFS, floored at [`MIN_DBFS`].
+pub fn amplitude_to_dbfs(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        return MIN_DBFS;
+    }
+    (20.0 * amplitude.log10()).max(MIN_DBFS)
+}
+
+/// Measure the overall levels of the WAV at `path`. A zero-byte file or one
+/// with a header but no samples measures as silence.
+///
+/// # Errors
+/// Returns an error if the file cannot be read as a WAV.
+pub fn measure_levels(path: &Path) -> Result<AudioLevels, String> {
+    let empty = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?
+        .len()
+        == 0;
+    if empty {
+        return Ok(AudioLevels::of([]));
+    }
+
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?;
+    let samples = normalized_samples(&mut reader)
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|e| format!("Failed to read samples from '{}': {e}", path.display()))?;
+    Ok(AudioLevels::of(samples))
+}
+
+// ---------------------------------------------------------------------------
+// Trimming
+// ---------------------------------------------------------------------------
+
+/// Audio kept either side of the audible range by [`trim_silence`], so the
+/// soft start of the first word and the decay of the last are not cut.
+pub const TRIM_PADDING: Duration = Duration::from_millis(200);
+
+/// Prefix of the error returned by [`trim_silence`] when no sample reaches
+/// the threshold, so there is nothing to keep.
+pub const ERR_ALL_SILENT: &str = "Recording is silent throughout";
+
+/// Where [`trim_silence`] wrote the trimmed copy, and how much it cut.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct TrimResult {
+    pub path: String,
+    pub trimmed_start_seconds: f64,
+    pub trimmed_end_seconds: f64,
+}
+
+/// The frames `[start, end)` of interleaved `samples` from the first frame
+/// with a sample at or above `threshold_dbfs` to the last, widened by
+/// `padding_frames` each side (within the buffer). `None` if no frame is
+/// that loud.
+pub fn audible_range(
+    samples: &[f32],
+    channels: usize,
+    threshold_dbfs: f32,
+    padding_frames: usize,
+) -> Option<(usize, usize)> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let audible = |frame: &usize| {
+        let peak = samples[frame * channels..(frame + 1) * channels]
+            .iter()
+            .fold(0.0_f32, |peak, s| peak.max(s.abs()));
+        !AudioLevels::of([peak]).is_silent(threshold_dbfs)
+    };
+    let first = (0..frames).find(audible)?;
+    let last = (first..frames).rev().find(audible)?;
+    Some((
+        first.saturating_sub(padding_frames),
+        (last + 1 + padding_frames).min(frames),
+    ))
+}
+
+/// Write a copy of the WAV at `path` without the silence before the first
+/// sample at or above `threshold_dbfs` and after the last, keeping
+/// [`TRIM_PADDING`] either side. Written to `output`, or next to `path` as
+/// `<stem>_trimmed.wav`; an existing file is not overwritten.
+///
+/// A recording with no sample that loud is an [`ERR_ALL_SILENT`] error
+/// rather than an empty file.
+///
+/// # Errors
+/// Returns an error if the recording is entirely silent, the output
+/// exists, or reading or writing fails.
+pub fn trim_silence(
+    path: &Path,
+    threshold_dbfs: f32,
+    output: Option<&Path>,
+) -> Result<TrimResult, String> {
+    let read_err =
+        |e: hound::Error| format!("Failed to read samples from '{}': {e}", path.display());
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?;
+    let spec = reader.spec();
+    let channels = usize::from(spec.channels.max(1));
+    let samples = normalized_samples(&mut reader)
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(read_err)?;
+    let padding = (TRIM_PADDING.as_secs_f64() * f64::from(spec.sample_rate)) as usize;
+    let (start, end) = audible_range(&samples, channels, threshold_dbfs, padding)
+        .ok_or_else(|| format!("{ERR_ALL_SILENT}: '{}'", path.display()))?;
+    let frames = samples.len() / channels;
+    drop(samples);
+
+    let output = match output {
+        Some(output) => output.to_path_buf(),
+        None => suffixed_path(path, "trimmed"),
+    };
+    if output.exists() {
+        return Err(format!("'{}' already exists", output.display()));
+    }
+    reader
+        .seek(start as u32)
+        .map_err(|e| read_err(hound::Error::IoError(e)))?;
+    let written = hound::WavWriter::create(&output, spec)
+        .map_err(|e| format!("Failed to create '{}': {e}", output.display()))
+        .and_then(|mut writer| {
+            copy_samples(&mut reader, &mut writer, Some((end - start) * channels))
+                .map_err(read_err)?;
+            writer
+                .finalize()
+                .map_err(|e| format!("Failed to finalize '{}': {e}", output.display()))
+        });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&output);
+        return Err(e);
+    }
+
+    let seconds = |frames: usize| frames as f64 / f64::from(spec.sample_rate.max(1));
+    Ok(TrimResult {
+        path: output
+            .to_str()
+            .ok_or("Trimmed path is not valid UTF-8")?
+            .to_string(),
+        trimmed_start_seconds: seconds(start),
+        trimmed_end_seconds: seconds(frames - end),
+    })
+}
+
+/// `dir/name.wav` -> `dir/name_<suffix>.wav`.
+fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    path.with_file_name(format!("{stem}_{suffix}.wav"))
+}
+
+// ---------------------------------------------------------------------------
+// Speech regions
+// ---------------------------------------------------------------------------
+
+/// Length of the windows [`detect_speech_regions`] classifies one at a time.
+pub const SPEECH_WINDOW: Duration = Duration::from_millis(30);
+
+/// A stretch of a recording holding speech, in seconds from its start.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct SpeechRegion {
+    pub start_s: f64,
+    pub end_s: f64,
+}
+
+/// Whether each window of `window_frames` frames of interleaved `samples`
+/// holds speech: its RMS level reaches `threshold_dbfs`. A trailing partial
+/// window is classified too.
+pub fn speech_windows(
+    samples: &[f32],
+    channels: usize,
+    window_frames: usize,
+    threshold_dbfs: f32,
+) -> Vec<bool> {
+    let window = window_frames.max(1) * channels.max(1);
+    samples
+        .chunks(window)
+        .map(|chunk| AudioLevels::of(chunk.iter().copied()).rms_dbfs >= threshold_dbfs)
+        .collect()
+}
+
+/// Turn per-window speech flags into regions, each window `window_s`
+/// seconds long. Regions separated by less than `min_gap_s` of non-speech
+/// are merged into one.
+pub fn merge_speech_windows(speech: &[bool], window_s: f64, min_gap_s: f64) -> Vec<SpeechRegion> {
+    let mut regions: Vec<SpeechRegion> = Vec::new();
+    let mut start = None;
+    for (i, &is_speech) in speech.iter().chain([&false]).enumerate() {
+        match (is_speech, start) {
+            (true, None) => start = Some(i),
+            (false, Some(first)) => {
+                let region = SpeechRegion {
+                    start_s: first as f64 * window_s,
+                    end_s: i as f64 * window_s,
+                };
+                match regions.last_mut() {
+                    Some(last) if region.start_s - last.end_s < min_gap_s => {
+                        last.end_s = region.end_s;
+                    }
+                    _ => regions.push(region),
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    regions
+}
+
+/// The speech regions of the WAV at `path`: [`SPEECH_WINDOW`]s whose RMS
+/// level reaches `threshold_dbfs`, with regions less than `min_gap` apart
+/// merged. A silent recording has none.
+///
+/// # Errors
+/// Returns an error if the file cannot be read as a WAV.
+pub fn detect_speech_regions(
+    path: &Path,
+    threshold_dbfs: f32,
+    min_gap: Duration,
+) -> Result<Vec<SpeechRegion>, String> {
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?;
+    let spec = reader.spec();
+    let samples = normalized_samples(&mut reader)
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|e| format!("Failed to read samples from '{}': {e}", path.display()))?;
+    let window_frames =
+        ((SPEECH_WINDOW.as_secs_f64() * f64::from(spec.sample_rate)) as usize).max(1);
+    let speech = speech_windows(
+        &samples,
+        usize::from(spec.channels),
+        window_frames,
+        threshold_dbfs,
+    );
+    let window_s = window_frames as f64 / f64::from(spec.sample_rate.max(1));
+    let mut regions = merge_speech_windows(&speech, window_s, min_gap.as_secs_f64());
+    // The last window may be partial; don't run past the end.
+    let duration = (samples.len() / usize::from(spec.channels.max(1))) as f64
+        / f64::from(spec.sample_rate.max(1));
+    if let Some(last) = regions.last_mut() {
+        last.end_s = last.end_s.min(duration);
+    }
+    Ok(regions)
+}
+
+// ---------------------------------------------------------------------------
+// Downmix
+// ---------------------------------------------------------------------------
+
+/// Prefix of the error returned by [`downmix_to_mono`] for a recording that
+/// has only one channel.
+pub const ERR_ALREADY_MONO: &str = "Recording is already mono";
+
+/// Write a mono copy of the multi-channel WAV at `path`, each sample the
+/// mix of its frame with one weight per channel (see [`mix_frame`]; equal
+/// weights averaging the channels by default). Written to `output`, or
+/// next to `path` as `<stem>_mono.wav`, in the same sample format; an
+/// existing file is not overwritten. Returns the path written.
+///
+/// # Errors
+/// Returns an [`ERR_ALREADY_MONO`] error for a mono recording, an error if
+/// `weights` does not have one finite weight per channel or the output
+/// exists, or an error if reading or writing fails.
+pub fn downmix_to_mono(
+    path: &Path,
+    output: Option<&Path>,
+    weights: Option<&[f32]>,
+) -> Result<String, String> {
+    let read_err =
+        |e: hound::Error| format!("Failed to read samples from '{}': {e}", path.display());
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?;
+    let spec = reader.spec();
+    let channels = usize::from(spec.channels);
+    if channels < 2 {
+        return Err(format!("{ERR_ALREADY_MONO}: '{}'", path.display()));
+    }
+    let equal = vec![1.0 / channels as f32; channels];
+    let weights = weights.unwrap_or(&equal);
+    if weights.len() != channels || !weights.iter().all(|w| w.is_finite()) {
+        return Err(format!(
+            "Expected {channels} finite channel weights, got {weights:?}"
+        ));
+    }
+
+    let output = match output {
+        Some(output) => output.to_path_buf(),
+        None => suffixed_path(path, "mono"),
+    };
+    if output.exists() {
+        return Err(format!("'{}' already exists", output.display()));
+    }
+    let mono = hound::WavSpec {
+        channels: 1,
+        ..spec
+    };
+    let written = hound::WavWriter::create(&output, mono)
+        .map_err(|e| format!("Failed to create '{}': {e}", output.display()))
+        .and_then(|mut writer| {
+            let write_err =
+                |e: hound::Error| format!("Failed to write '{}': {e}", output.display());
+            let mut frame = Vec::with_capacity(channels);
+            for sample in normalized_samples(&mut reader) {
+                frame.push(sample.map_err(read_err)?);
+                if frame.len() == channels {
+                    write_normalized(&mut writer, mix_frame(&frame, Some(weights)))
+                        .map_err(write_err)?;
+                    frame.clear();
+                }
+            }
+            writer
+                .finalize()
+                .map_err(|e| format!("Failed to finalize '{}': {e}", output.display()))
+        });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&output);
+        return Err(e);
+    }
+
+    output
+        .to_str()
+        .map(str::to_string)
+        .ok_or_else(|| "Downmixed path is not valid UTF-8".to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Resampling
+// ---------------------------------------------------------------------------
+
+/// Resample interleaved `channels`-channel `samples` from `source_rate` to
+/// `target_rate` by linear interpolation between neighbouring frames.
+///
+/// The output has `ceil(frames * target_rate / source_rate)` frames, so it
+/// lasts as long as the input, whichever way the rate changes.
+pub fn resample_linear(
+    samples: &[f32],
+    channels: usize,
+    source_rate: u32,
+    target_rate: u32,
+) -> Vec<f32> {
+    if channels == 0 || source_rate == 0 || target_rate == 0 {
+        return Vec::new();
+    }
+    let frames = samples.len() / channels;
+    if frames == 0 {
+        return Vec::new();
+    }
+    let (source, target) = (u64::from(source_rate), u64::from(target_rate));
+    let output_frames = (frames as u64 * target).div_ceil(source) as usize;
+    let step = source as f64 / target as f64;
+
+    let mut output = Vec::with_capacity(output_frames * channels);
+    for i in 0..output_frames {
+        let position = i as f64 * step;
+        let before = (position as usize).min(frames - 1);
+        let after = (before + 1).min(frames - 1);
+        let fraction = (position - before as f64).clamp(0.0, 1.0) as f32;
+        for ch in 0..channels {
+            let a = samples[before * channels + ch];
+            let b = samples[after * channels + ch];
+            output.push(a + (b - a) * fraction);
+        }
+    }
+    output
+}
+
+/// Write a copy of the WAV at `path` resampled to `target_rate` (see
+/// [`resample_linear`]), keeping its channel count and sample format.
+/// Written to `output`, or next to `path` as `<stem>_<rate>hz.wav`; an
+/// existing file is not overwritten. Returns the path written.
+///
+/// # Errors
+/// Returns an error if `target_rate` is out of range or already the
+/// recording's rate, if the output exists, or if reading or writing fails.
+pub fn resample_recording(
+    path: &Path,
+    target_rate: u32,
+    output: Option<&Path>,
+) -> Result<String, String> {
+    if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&target_rate) {
+        return Err(format!(
+            "Invalid target rate {target_rate}: must be between {MIN_SAMPLE_RATE} and {MAX_SAMPLE_RATE} Hz"
+        ));
+    }
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?;
+    let spec = reader.spec();
+    if spec.sample_rate == target_rate {
+        return Err(format!(
+            "'{}' is already at {target_rate} Hz",
+            path.display()
+        ));
+    }
+    let output = match output {
+        Some(output) => output.to_path_buf(),
+        None => suffixed_path(path, &format!("{target_rate}hz")),
+    };
+    if output.exists() {
+        return Err(format!("'{}' already exists", output.display()));
+    }
+
+    let samples = normalized_samples(&mut reader)
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|e| format!("Failed to read samples from '{}': {e}", path.display()))?;
+    let resampled = resample_linear(
+        &samples,
+        usize::from(spec.channels),
+        spec.sample_rate,
+        target_rate,
+    );
+
+    let written = hound::WavWriter::create(
+        &output,
+        hound::WavSpec {
+            sample_rate: target_rate,
+            ..spec
+        },
+    )
+    .map_err(|e| format!("Failed to create '{}': {e}", output.display()))
+    .and_then(|mut writer| {
+        for &sample in &resampled {
+            write_normalized(&mut writer, sample)
+                .map_err(|e| format!("Failed to write '{}': {e}", output.display()))?;
+        }
+        writer
+            .finalize()
+            .map_err(|e| format!("Failed to finalize '{}': {e}", output.display()))
+    });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&output);
+        return Err(e);
+    }
+
+    output
+        .to_str()
+        .map(str::to_string)
+        .ok_or_else(|| "Resampled path is not valid UTF-8".to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Splitting
+// ---------------------------------------------------------------------------
+
+/// The frame ranges `[start, end)` of the clips a recording of `frames`
+/// frames at `sample_rate` is cut into at `split_points_seconds`.
+///
+/// A point at exactly 0 or at the end of the recording cuts nothing off,
+/// so it yields no empty clip.
+///
+/// # Errors
+/// Returns an error if a point is not finite, not after the one before
+/// it, or outside the recording.
+pub fn split_ranges(
+    split_points_seconds: &[f32],
+    sample_rate: u32,
+    frames: u32,
+) -> Result<Vec<(u32, u32)>, String> {
+    let duration = f64::from(frames) / f64::from(sample_rate.max(1));
+    let mut bounds = vec![0];
+    let mut previous: Option<f32> = None;
+    for &point in split_points_seconds {
+        if !point.is_finite() || point < 0.0 || f64::from(point) > duration {
+            return Err(format!(
+                "Split point {point} s is outside the recording (0 to {duration:.3} s)"
+            ));
+        }
+        if previous.is_some_and(|previous| point <= previous) {
+            return Err(format!(
+                "Split points must be ascending, but {point} s follows {} s",
+                previous.unwrap_or_default()
+            ));
+        }
+        previous = Some(point);
+        let frame = (f64::from(point) * f64::from(sample_rate)).round() as u32;
+        bounds.push(frame.min(frames));
+    }
+    bounds.push(frames);
+
+    Ok(bounds
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .filter(|(start, end)| end > start)
+        .collect())
+}
+
+/// Cut the WAV at `path` at `split_points_seconds` (see [`split_ranges`])
+/// and write each clip to `output_dir`, created if missing, as
+/// `<stem>_clip<n>.wav` in the same format. Returns the paths written, in
+/// order. An existing file is not overwritten, and on failure no clip is
+/// left behind.
+///
+/// # Errors
+/// Returns an error if the split points are invalid, a clip already
+/// exists, or reading or writing fails.
+pub fn split_recording(
+    path: &Path,
+    split_points_seconds: &[f32],
+    output_dir: &Path,
+) -> Result<Vec<String>, String> {
+    let read_err =
+        |e: hound::Error| format!("Failed to read samples from '{}': {e}", path.display());
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?;
+    let spec = reader.spec();
+    let channels = usize::from(spec.channels.max(1));
+    let ranges = split_ranges(split_points_seconds, spec.sample_rate, reader.duration())?;
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let outputs: Vec<PathBuf> = (1..=ranges.len())
+        .map(|n| output_dir.join(format!("{stem}_clip{n}.wav")))
+        .collect();
+    if let Some(existing) = outputs.iter().find(|output| output.exists()) {
+        return Err(format!("'{}' already exists", existing.display()));
+    }
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create '{}': {e}", output_dir.display()))?;
+
+    let written = ranges
+        .iter()
+        .zip(&outputs)
+        .try_for_each(|(&(start, end), output)| {
+            reader
+                .seek(start)
+                .map_err(|e| read_err(hound::Error::IoError(e)))?;
+            let mut writer = hound::WavWriter::create(output, spec)
+                .map_err(|e| format!("Failed to create '{}': {e}", output.display()))?;
+            copy_samples(
+                &mut reader,
+                &mut writer,
+                Some((end - start) as usize * channels),
+            )
+            .map_err(read_err)?;
+            writer
+                .finalize()
+                .map_err(|e| format!("Failed to finalize '{}': {e}", output.display()))
+        });
+    if let Err(e) = written {
+        for output in &outputs {
+            let _ = std::fs::remove_file(output);
+        }
+        return Err(e);
+    }
+
+    outputs
+        .iter()
+        .map(|output| {
+            output
+                .to_str()
+                .map(str::to_string)
+                .ok_or_else(|| "Clip path is not valid UTF-8".to_string())
+        })
+        .collect()
+}
+
+/// Write a sample in [-1, 1] in `writer`'s sample format, clipping
+/// anything beyond.
+fn write_normalized<W: Write + Seek>(
+    writer: &mut hound::WavWriter<W>,
+    sample: f32,
+) -> Result<(), hound::Error> {
+    let spec = writer.spec();
+    let sample = sample.clamp(-1.0, 1.0);
+    match spec.sample_format {
+        hound::SampleFormat::Float => writer.write_sample(sample),
+        hound::SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            let max = scale - 1.0;
+            writer.write_sample((sample * scale).round().clamp(-scale, max) as i32)
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{test_dir, write_test_wav};
+    use std::io::Write;
+
+    #[test]
+    fn test_compute_waveform_over_ramp() {
+        let dir = test_dir("wav", "waveform_ramp");
+        let path = dir.join("ramp.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create wav");
+        // Ramp from -32768 to 32767 over 16 000 frames.
+        for i in 0..16_000_i32 {
+            writer
+                .write_sample((i * 65_535 / 15_999 - 32_768) as i16)
+                .expect("write sample");
+        }
+        writer.finalize().expect("finalize");
+
+        let peaks = compute_waveform(&path, 100).expect("waveform");
+        assert_eq!(peaks.len(), 100);
+        assert!(peaks.windows(2).all(|w| w[0] < w[1]), "{peaks:?}");
+        assert_eq!(peaks[0], -1.0);
+        assert!(peaks[99] > 0.99);
+        assert!(peaks.iter().all(|p| (-1.0..=1.0).contains(p)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_compute_waveform_short_and_empty_files() {
+        let dir = test_dir("wav", "waveform_short");
+        let short = dir.join("short.wav");
+        write_test_wav(&short, 16_000, 10);
+        let peaks = compute_waveform(&short, 500).expect("waveform");
+        assert_eq!(peaks.len(), 10);
+        assert_eq!(peaks[3], 3.0 / 32_768.0);
+
+        let empty = dir.join("empty.wav");
+        write_test_wav(&empty, 16_000, 0);
+        assert!(compute_waveform(&empty, 500).expect("waveform").is_empty());
+        assert!(compute_waveform(&empty, 0).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_read_wav_info_reports_spec_and_duration() {
+        let dir = test_dir("wav", "info");
+        let path = dir.join("a.wav");
+        write_test_wav(&path, 16_000, 8_000);
+
+        let info = read_wav_info(&path).expect("wav info");
+        assert_eq!(info.sample_rate, 16_000);
+        assert_eq!(info.channels, 1);
+        assert_eq!(info.bits_per_sample, 16);
+        assert_eq!(info.frames, 8_000);
+        assert!((info.duration_seconds - 0.5).abs() < 1e-9);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_read_wav_info_rejects_non_wav() {
+        let dir = test_dir("wav", "not_wav");
+        let path = dir.join("junk.wav");
+        std::fs::write(&path, b"definitely not audio").expect("write junk");
+        assert!(read_wav_info(&path).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_read_riff_chunks_finds_fmt_and_data() {
+        let dir = test_dir("wav", "chunks");
+        let path = dir.join("a.wav");
+        write_test_wav(&path, 16_000, 10);
+
+        let chunks = read_riff_chunks(&path).expect("chunks");
+        let ids: Vec<_> = chunks.iter().map(|c| c.id).collect();
+        assert!(ids.contains(b"fmt "));
+        let data = chunks
+            .iter()
+            .find(|c| &c.id == b"data")
+            .expect("data chunk");
+        assert_eq!(data.size, 20);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_repair_wav_restores_crashed_header() {
+        let dir = test_dir("wav", "repair");
+        let path = dir.join("crashed.wav");
+        write_test_wav(&path, 16_000, 1_000);
+        let original = std::fs::read(&path).expect("read");
+
+        // What a crash before finalizing leaves: sizes still zero, plus
+        // half a frame that never got its partner.
+        let mut bytes = original.clone();
+        bytes[4..8].copy_from_slice(&36u32.to_le_bytes());
+        bytes[40..44].copy_from_slice(&0u32.to_le_bytes());
+        bytes.push(0x7f);
+        std::fs::write(&path, &bytes).expect("corrupt");
+
+        assert!(repair_wav(&path).expect("repair"));
+        let repaired = std::fs::read(&path).expect("read");
+        assert_eq!(&repaired[..original.len()], &original[..]);
+        let mut reader = hound::WavReader::open(&path).expect("readable again");
+        assert_eq!(reader.duration(), 1_000);
+        assert_eq!(reader.samples::<i16>().count(), 1_000);
+
+        // Nothing left to fix the second time.
+        assert!(!repair_wav(&path).expect("repair again"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_repair_wav_leaves_valid_files_alone() {
+        let dir = test_dir("wav", "repair_valid");
+        let path = dir.join("ok.wav");
+        write_test_wav(&path, 16_000, 100);
+        append_riff_chunk(&path, b"bext", &bext_chunk(UNIX_EPOCH, 16_000)).expect("bext");
+        let before = std::fs::read(&path).expect("read");
+
+        assert!(!repair_wav(&path).expect("repair"));
+        assert_eq!(std::fs::read(&path).expect("read"), before);
+
+        // An oversized RIFF size alone is fixed too.
+        let mut bytes = before.clone();
+        bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).expect("corrupt");
+        assert!(repair_wav(&path).expect("repair"));
+        assert_eq!(std::fs::read(&path).expect("read"), before);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_data_checksum_covers_only_the_audio() {
+        let dir = test_dir("wav", "checksum");
+        let path = dir.join("abcd.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create");
+        // Little-endian, these two samples are the bytes "abcd".
+        writer.write_sample(0x6261i16).expect("write");
+        writer.write_sample(0x6463i16).expect("write");
+        writer.finalize().expect("finalize");
+
+        let abcd = "88d4266fd4e6338d13b845fcf289579d209c897823b9217da3e161936f031589";
+        assert_eq!(data_checksum(&path).expect("checksum"), abcd);
+
+        // Neither a new chunk nor a wrong RIFF size changes it.
+        append_riff_chunk(&path, b"bext", &bext_chunk(UNIX_EPOCH, 16_000)).expect("bext");
+        let mut bytes = std::fs::read(&path).expect("read");
+        bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).expect("edit header");
+        assert_eq!(data_checksum(&path).expect("checksum"), abcd);
+
+        // Changing the audio does.
+        bytes[44] = b'z';
+        std::fs::write(&path, &bytes).expect("edit audio");
+        assert_ne!(data_checksum(&path).expect("checksum"), abcd);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bext_chunk_fields_at_fixed_time() {
+        // 2024-06-15 12:00:00.5 UTC.
+        let origin = UNIX_EPOCH + Duration::from_millis(1_718_452_800_500);
+        let body = bext_chunk(origin, 48_000);
+        assert_eq!(body.len(), BEXT_SIZE);
+        assert!(body[..256].iter().all(|&b| b == 0));
+        assert_eq!(&body[256..263], b"Second\0");
+        assert_eq!(&body[320..330], b"2024-06-15");
+        assert_eq!(&body[330..338], b"12:00:00");
+        let time_reference = u64::from_le_bytes(body[338..346].try_into().expect("8 bytes"));
+        assert_eq!(time_reference, 43_200 * 48_000 + 24_000);
+        assert!(body[346..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_append_riff_chunk_pads_and_updates_size() {
+        let dir = test_dir("wav", "append_chunk");
+        let path = dir.join("a.wav");
+        write_test_wav(&path, 16_000, 10);
+
+        append_riff_chunk(&path, b"junk", b"abc").expect("append");
+        let bytes = std::fs::read(&path).expect("read");
+        assert_eq!(bytes.len() % 2, 0);
+        let riff_size = u32::from_le_bytes(bytes[4..8].try_into().expect("4 bytes"));
+        assert_eq!(riff_size as usize, bytes.len() - 8);
+        let chunks = read_riff_chunks(&path).expect("chunks");
+        let junk = chunks.last().expect("appended chunk");
+        assert_eq!(&junk.id, b"junk");
+        assert_eq!(junk.size, 3);
+        assert_eq!(read_wav_info(&path).expect("info").frames, 10);
+
+        let err = append_riff_chunk(&dir.join("missing.wav"), b"junk", b"").unwrap_err();
+        assert!(err.contains("missing.wav"), "{err}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_read_info_metadata_parses_list_chunk() {
+        let dir = test_dir("wav", "info_chunk");
+        let path = dir.join("a.wav");
+        write_test_wav(&path, 16_000, 10);
+
+        // Append a LIST/INFO chunk with an odd-length (padded) field.
+        let mut list = b"INFO".to_vec();
+        list.extend_from_slice(b"ICMT");
+        list.extend_from_slice(&4u32.to_le_bytes());
+        list.extend_from_slice(b"mic\0");
+        list.extend_from_slice(b"INAM");
+        list.extend_from_slice(&3u32.to_le_bytes());
+        list.extend_from_slice(b"abc\0");
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .expect("open for append");
+        file.write_all(b"LIST").expect("write id");
+        file.write_all(&(list.len() as u32).to_le_bytes())
+            .expect("write size");
+        file.write_all(&list).expect("write body");
+        drop(file);
+
+        let metadata = read_info_metadata(&path).expect("metadata");
+        assert_eq!(metadata.get("ICMT").map(String::as_str), Some("mic"));
+        assert_eq!(metadata.get("INAM").map(String::as_str), Some("abc"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_read_info_metadata_without_list_is_empty() {
+        let dir = test_dir("wav", "no_info");
+        let path = dir.join("a.wav");
+        write_test_wav(&path, 16_000, 10);
+        assert!(read_info_metadata(&path).expect("metadata").is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_levels_classify_silence_and_tone() {
+        let silence = vec![0.0_f32; 16_000];
+        // A -20 dBFS 440 Hz tone.
+        let tone: Vec<f32> = (0..16_000)
+            .map(|i| 0.1 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16_000.0).sin())
+            .collect();
+
+        let levels = AudioLevels::of(silence);
+        assert_eq!(levels.peak_dbfs, MIN_DBFS);
+        assert_eq!(levels.rms_dbfs, MIN_DBFS);
+        assert!(levels.is_silent(-60.0));
+
+        let levels = AudioLevels::of(tone);
+        assert!((levels.peak_dbfs + 20.0).abs() < 0.1, "{levels:?}");
+        // A sine's RMS is 3 dB below its peak.
+        assert!((levels.rms_dbfs + 23.0).abs() < 0.1, "{levels:?}");
+        assert!(!levels.is_silent(-60.0));
+        assert!(levels.is_silent(-10.0));
+    }
+
+    #[test]
+    fn test_measure_levels_treats_empty_files_as_silent() {
+        let dir = test_dir("wav", "levels_empty");
+        let zero_bytes = dir.join("zero.wav");
+        std::fs::write(&zero_bytes, b"").expect("write file");
+        assert!(measure_levels(&zero_bytes)
+            .expect("levels")
+            .is_silent(-60.0));
+
+        let header_only = dir.join("header.wav");
+        write_test_wav(&header_only, 16_000, 0);
+        assert!(measure_levels(&header_only)
+            .expect("levels")
+            .is_silent(-60.0));
+
+        // `write_test_wav` writes a ramp up to 99/32768, about -50 dBFS.
+        let quiet = dir.join("quiet.wav");
+        write_test_wav(&quiet, 16_000, 1_000);
+        let levels = measure_levels(&quiet).expect("levels");
+        assert!(
+            levels.is_silent(-40.0) && !levels.is_silent(-60.0),
+            "{levels:?}"
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_audible_range_finds_trim_boundaries() {
+        // Stereo: 10 silent frames, 5 loud (on one channel only), 3 quiet
+        // but not silent, then 7 silent.
+        let mut samples = vec![0.0_f32; 2 * 10];
+        samples.extend([0.5, 0.0].repeat(5));
+        samples.extend([0.0, 0.001].repeat(3));
+        samples.extend(vec![0.0; 2 * 7]);
+
+        assert_eq!(audible_range(&samples, 2, -40.0, 0), Some((10, 15)));
+        // A lower threshold counts the quiet frames too.
+        assert_eq!(audible_range(&samples, 2, -70.0, 0), Some((10, 18)));
+        // Padding widens the range but stays inside the buffer.
+        assert_eq!(audible_range(&samples, 2, -40.0, 4), Some((6, 19)));
+        assert_eq!(audible_range(&samples, 2, -40.0, 100), Some((0, 25)));
+
+        assert_eq!(audible_range(&samples, 2, -3.0, 0), None);
+        assert_eq!(audible_range(&[], 1, -40.0, 0), None);
+    }
+
+    #[test]
+    fn test_resample_linear_interpolates_between_frames() {
+        // Doubling the rate puts a midpoint between each pair of frames.
+        let up = resample_linear(&[0.0, 10.0, 1.0, 20.0], 2, 8_000, 16_000);
+        assert_eq!(up, vec![0.0, 10.0, 0.5, 15.0, 1.0, 20.0, 1.0, 20.0]);
+        // Halving it keeps every other frame.
+        let down = resample_linear(&[0.0, 0.25, 0.5, 0.75], 1, 16_000, 8_000);
+        assert_eq!(down, vec![0.0, 0.5]);
+        assert!(resample_linear(&[], 1, 16_000, 8_000).is_empty());
+    }
+
+    #[test]
+    fn test_resample_recording_up_and_down_keeps_duration() {
+        let dir = test_dir("wav", "resample");
+        let path = dir.join("rec.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        // One second of stereo.
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create wav");
+        for i in 0..16_000 {
+            writer.write_sample((i % 1_000) as i16).expect("write left");
+            writer
+                .write_sample(-((i % 1_000) as i16))
+                .expect("write right");
+        }
+        writer.finalize().expect("finalize");
+
+        let cd = resample_recording(&path, 44_100, None).expect("upsample");
+        assert_eq!(cd, dir.join("rec_44100hz.wav").to_str().unwrap());
+        let info = read_wav_info(Path::new(&cd)).expect("read");
+        assert_eq!((info.sample_rate, info.channels), (44_100, 2));
+        assert_eq!(info.frames, 44_100);
+
+        let phone = dir.join("phone.wav");
+        resample_recording(&path, 8_000, Some(&phone)).expect("downsample");
+        let info = read_wav_info(&phone).expect("read");
+        assert_eq!((info.sample_rate, info.channels), (8_000, 2));
+        assert_eq!(info.frames, 8_000);
+
+        // The same rate, a rate out of range and an existing output are
+        // refused.
+        assert!(resample_recording(&path, 16_000, Some(&dir.join("x.wav"))).is_err());
+        assert!(resample_recording(&path, 1_000, Some(&dir.join("x.wav"))).is_err());
+        assert!(resample_recording(&path, 8_000, Some(&phone)).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_downmix_to_mono_applies_weights() {
+        let dir = test_dir("wav", "downmix");
+        let path = dir.join("stereo.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        // A ramp on the left against a constant on the right.
+        let frames: Vec<(i16, i16)> = (0..100).map(|i| (i * 100, -4_000)).collect();
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create wav");
+        for &(left, right) in &frames {
+            writer.write_sample(left).expect("write left");
+            writer.write_sample(right).expect("write right");
+        }
+        writer.finalize().expect("finalize");
+        let mono_samples = |path: &Path| -> Vec<i16> {
+            let mut reader = hound::WavReader::open(path).expect("open mono");
+            assert_eq!(reader.spec().channels, 1);
+            reader
+                .samples::<i16>()
+                .map(|s| s.expect("sample"))
+                .collect()
+        };
+
+        let equal = downmix_to_mono(&path, None, None).expect("downmix");
+        assert_eq!(equal, dir.join("stereo_mono.wav").to_str().unwrap());
+        let expected: Vec<i16> = frames.iter().map(|&(l, r)| (l + r) / 2).collect();
+        assert_eq!(mono_samples(Path::new(&equal)), expected);
+
+        let weighted = dir.join("weighted.wav");
+        downmix_to_mono(&path, Some(&weighted), Some(&[0.75, 0.25])).expect("downmix");
+        let expected: Vec<i16> = frames
+            .iter()
+            .map(|&(l, r)| (f32::from(l) * 0.75 + f32::from(r) * 0.25).round() as i16)
+            .collect();
+        assert_eq!(mono_samples(&weighted), expected);
+
+        // Bad weights, an existing output and a mono input are refused.
+        assert!(downmix_to_mono(&path, Some(&dir.join("x.wav")), Some(&[1.0])).is_err());
+        assert!(downmix_to_mono(&path, Some(&weighted), None).is_err());
+        let err = downmix_to_mono(&weighted, None, None).unwrap_err();
+        assert!(err.starts_with(ERR_ALREADY_MONO), "got: {err}");
+        assert!(!dir.join("x.wav").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_split_ranges_validates_and_skips_empty_edges() {
+        assert_eq!(
+            split_ranges(&[0.25, 0.5], 16_000, 16_000),
+            Ok(vec![(0, 4_000), (4_000, 8_000), (8_000, 16_000)])
+        );
+        // Points at the very start and end cut nothing off.
+        assert_eq!(
+            split_ranges(&[0.0, 0.5, 1.0], 16_000, 16_000),
+            Ok(vec![(0, 8_000), (8_000, 16_000)])
+        );
+        assert_eq!(split_ranges(&[], 16_000, 16_000), Ok(vec![(0, 16_000)]));
+
+        assert!(split_ranges(&[0.5, 0.25], 16_000, 16_000).is_err());
+        assert!(split_ranges(&[0.5, 0.5], 16_000, 16_000).is_err());
+        assert!(split_ranges(&[1.5], 16_000, 16_000).is_err());
+        assert!(split_ranges(&[-0.1], 16_000, 16_000).is_err());
+        assert!(split_ranges(&[f32::NAN], 16_000, 16_000).is_err());
+    }
+
+    #[test]
+    fn test_split_recording_writes_one_clip_per_segment() {
+        let dir = test_dir("wav", "split");
+        let path = dir.join("talk.wav");
+        write_test_wav(&path, 16_000, 16_000);
+        let clips_dir = dir.join("clips");
+
+        let clips = split_recording(&path, &[0.0, 0.25, 0.75], &clips_dir).expect("split");
+        assert_eq!(
+            clips,
+            (1..=3)
+                .map(|n| clips_dir.join(format!("talk_clip{n}.wav")))
+                .map(|p| p.to_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        );
+        let mut start = 0;
+        for (clip, expected_frames) in clips.iter().zip([4_000, 8_000, 4_000]) {
+            let mut reader = hound::WavReader::open(clip).expect("open clip");
+            assert_eq!(reader.spec(), hound::WavReader::open(&path).unwrap().spec());
+            let samples: Vec<i16> = reader.samples().map(|s| s.expect("sample")).collect();
+            assert_eq!(samples.len(), expected_frames);
+            // Each clip continues the source where the previous one ended.
+            assert_eq!(samples[0], (start % 100) as i16);
+            start += expected_frames;
+        }
+
+        // Existing clips are not overwritten; bad points write nothing.
+        assert!(split_recording(&path, &[0.5], &clips_dir).is_err());
+        assert!(split_recording(&path, &[2.0], &dir.join("none")).is_err());
+        assert!(!dir.join("none").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_trim_silence_writes_trimmed_copy() {
+        let dir = test_dir("wav", "trim");
+        let path = dir.join("rec.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create wav");
+        // 0.5 s silence, 0.1 s speech, 0.25 s silence.
+        for i in 0..13_600 {
+            let loud = (8_000..9_600).contains(&i);
+            writer
+                .write_sample(if loud { 8_000_i16 } else { 0 })
+                .expect("write sample");
+        }
+        writer.finalize().expect("finalize");
+
+        let result = trim_silence(&path, -40.0, None).expect("trim");
+        let trimmed = dir.join("rec_trimmed.wav");
+        assert_eq!(result.path, trimmed.to_str().unwrap());
+        // TRIM_PADDING (0.2 s) is kept either side.
+        assert!((result.trimmed_start_seconds - 0.3).abs() < 1e-9);
+        assert!((result.trimmed_end_seconds - 0.05).abs() < 1e-9);
+        let info = read_wav_info(&trimmed).expect("read trimmed");
+        assert_eq!(info.frames, 1_600 + 2 * 3_200);
+        assert!(!measure_levels(&trimmed).expect("levels").is_silent(-40.0));
+
+        // Not overwritten on a second run.
+        assert!(trim_silence(&path, -40.0, None).is_err());
+
+        let silent = dir.join("silent.wav");
+        write_test_wav(&silent, 16_000, 1_000);
+        let err = trim_silence(&silent, -40.0, None).unwrap_err();
+        assert!(err.starts_with(ERR_ALL_SILENT), "got: {err}");
+        assert!(!dir.join("silent_trimmed.wav").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_merge_speech_windows_bridges_short_gaps() {
+        let region = |start_s, end_s| SpeechRegion { start_s, end_s };
+        // Speech in windows 1-2, 4, and 8-9 of 0.5 s each: gaps of 0.5 s
+        // and 1.5 s.
+        let speech = [
+            false, true, true, false, true, false, false, false, true, true,
+        ];
+        assert_eq!(
+            merge_speech_windows(&speech, 0.5, 0.0),
+            vec![region(0.5, 1.5), region(2.0, 2.5), region(4.0, 5.0)]
+        );
+        assert_eq!(
+            merge_speech_windows(&speech, 0.5, 1.0),
+            vec![region(0.5, 2.5), region(4.0, 5.0)]
+        );
+        // A gap exactly `min_gap_s` long is kept.
+        assert_eq!(
+            merge_speech_windows(&speech, 0.5, 1.5),
+            vec![region(0.5, 2.5), region(4.0, 5.0)]
+        );
+        assert_eq!(
+            merge_speech_windows(&speech, 0.5, 2.0),
+            vec![region(0.5, 5.0)]
+        );
+        assert!(merge_speech_windows(&[false; 4], 0.5, 1.0).is_empty());
+        assert!(merge_speech_windows(&[], 0.5, 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_detect_speech_regions() {
+        let dir = test_dir("wav", "speech_regions");
+        let path = dir.join("rec.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create wav");
+        // Speech at 0.24-0.48 s and 0.72-0.96 s of a 1.2 s recording.
+        for i in 0..19_200 {
+            let loud = (3_840..7_680).contains(&i) || (11_520..15_360).contains(&i);
+            writer
+                .write_sample(if loud { 8_000_i16 } else { 0 })
+                .expect("write sample");
+        }
+        writer.finalize().expect("finalize");
+
+        let regions = detect_speech_regions(&path, -40.0, Duration::ZERO).expect("regions");
+        assert_eq!(regions.len(), 2);
+        assert!((regions[0].start_s - 0.24).abs() < 1e-9);
+        assert!((regions[0].end_s - 0.48).abs() < 1e-9);
+        assert!((regions[1].start_s - 0.72).abs() < 1e-9);
+        let merged = detect_speech_regions(&path, -40.0, Duration::from_millis(300))
+            .expect("merged regions");
+        assert_eq!(merged.len(), 1);
+        assert!((merged[0].end_s - 0.96).abs() < 1e-9);
+
+        let silent = dir.join("silent.wav");
+        write_test_wav(&silent, 16_000, 1_000);
+        assert!(detect_speech_regions(&silent, -40.0, Duration::ZERO)
+            .expect("silent regions")
+            .is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
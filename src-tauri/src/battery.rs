@@ -0,0 +1,298 @@
+//! Battery-aware recording budget.
+//!
+//! Estimates how long a recording can go on before a laptop running on
+//! battery gets down to a safety reserve, from the charge level and drain
+//! rate the OS reports: `/sys/class/power_supply` on Linux, `pmset` on macOS.
+//! Machines that report no battery (desktops, other platforms) are treated
+//! as having no limit.
+
+use std::path::Path;
+#[cfg(target_os = "macos")]
+use std::process::{Command, Stdio};
+
+/// Charge left in reserve: the budget runs out when the battery would drop
+/// below this level, not when it is empty.
+pub const BATTERY_RESERVE_PERCENT: f64 = 10.0;
+
+/// Drain assumed when the OS reports none, e.g. just after unplugging:
+/// roughly a five-hour battery.
+pub const DEFAULT_DRAIN_PERCENT_PER_HOUR: f64 = 20.0;
+
+/// Where Linux exposes batteries and power supplies.
+#[cfg(target_os = "linux")]
+const SYSFS_POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+/// Battery state as reported by the OS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryReading {
+    /// Charge level, 0–100.
+    pub percent: f64,
+    /// Running on the battery rather than on mains power.
+    pub discharging: bool,
+    /// Current drain in percent of a full charge per hour, if reported.
+    pub drain_percent_per_hour: Option<f64>,
+}
+
+/// How long recording can safely continue.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RecordingBudget {
+    /// No battery limit: on mains power, or no battery was found.
+    pub unlimited: bool,
+    /// Minutes until the battery reaches [`BATTERY_RESERVE_PERCENT`];
+    /// `None` when `unlimited`.
+    pub minutes: Option<f64>,
+    /// The estimate rests on a reported battery state and drain rate rather
+    /// than on assumptions.
+    pub confident: bool,
+    pub battery_percent: Option<f64>,
+}
+
+/// Estimate the recording budget for the machine's battery right now.
+pub fn recording_budget() -> RecordingBudget {
+    estimate_budget(read_battery().as_ref())
+}
+
+/// The recording budget for `reading`, or an unlimited one without a
+/// battery.
+///
+/// While discharging, the charge above the reserve is divided by the drain
+/// rate, falling back to [`DEFAULT_DRAIN_PERCENT_PER_HOUR`] (and marking
+/// the estimate not confident) when there is none.
+pub fn estimate_budget(reading: Option<&BatteryReading>) -> RecordingBudget {
+    let Some(reading) = reading else {
+        return RecordingBudget {
+            unlimited: true,
+            minutes: None,
+            confident: false,
+            battery_percent: None,
+        };
+    };
+    if !reading.discharging {
+        return RecordingBudget {
+            unlimited: true,
+            minutes: None,
+            confident: true,
+            battery_percent: Some(reading.percent),
+        };
+    }
+
+    let reported = reading.drain_percent_per_hour.filter(|rate| *rate > 0.0);
+    let drain = reported.unwrap_or(DEFAULT_DRAIN_PERCENT_PER_HOUR);
+    let usable = (reading.percent - BATTERY_RESERVE_PERCENT).max(0.0);
+    RecordingBudget {
+        unlimited: false,
+        minutes: Some(usable / drain * 60.0),
+        confident: reported.is_some(),
+        battery_percent: Some(reading.percent),
+    }
+}
+
+/// The OS's battery state, or `None` if it reports no battery.
+#[cfg(target_os = "linux")]
+fn read_battery() -> Option<BatteryReading> {
+    read_sysfs_battery(Path::new(SYSFS_POWER_SUPPLY))
+}
+
+/// The OS's battery state, or `None` if it reports no battery.
+#[cfg(target_os = "macos")]
+fn read_battery() -> Option<BatteryReading> {
+    let output = Command::new("pmset")
+        .args(["-g", "batt"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_pmset_batt(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The OS's battery state, or `None` if it reports no battery.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read_battery() -> Option<BatteryReading> {
+    None
+}
+
+/// Read the first battery under a sysfs `power_supply` directory.
+///
+/// The drain rate comes from `power_now` over `energy_full` (µW, µWh) or,
+/// on batteries that count charge instead, `current_now` over
+/// `charge_full` (µA, µAh).
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn read_sysfs_battery(power_supply: &Path) -> Option<BatteryReading> {
+    let mut supplies: Vec<_> = std::fs::read_dir(power_supply)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    supplies.sort();
+
+    let battery = supplies
+        .into_iter()
+        .find(|dir| read_field(dir, "type").as_deref() == Some("Battery"))?;
+    let number = |name: &str| read_field(&battery, name)?.parse::<f64>().ok();
+
+    let percent = number("capacity")?;
+    let discharging = read_field(&battery, "status").as_deref() == Some("Discharging");
+    let rate = |now: &str, full: &str| match (number(now), number(full)) {
+        (Some(now), Some(full)) if full > 0.0 => Some(now.abs() / full * 100.0),
+        _ => None,
+    };
+    let drain_percent_per_hour =
+        rate("power_now", "energy_full").or_else(|| rate("current_now", "charge_full"));
+
+    Some(BatteryReading {
+        percent,
+        discharging,
+        drain_percent_per_hour,
+    })
+}
+
+/// Trimmed contents of the sysfs attribute `name` in `dir`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn read_field(dir: &Path, name: &str) -> Option<String> {
+    std::fs::read_to_string(dir.join(name))
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+/// Parse the battery line of `pmset -g batt`, e.g.
+/// `-InternalBattery-0 (id=1234) 85%; discharging; 3:12 remaining present: true`.
+///
+/// The drain rate follows from the level and macOS's time-to-empty, which
+/// is missing (`(no estimate)`) for a while after unplugging.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_pmset_batt(output: &str) -> Option<BatteryReading> {
+    let line = output
+        .lines()
+        .find(|line| line.contains("InternalBattery"))?;
+    let mut fields = line.split(';').map(str::trim);
+
+    let percent = fields
+        .next()?
+        .rsplit(char::is_whitespace)
+        .next()?
+        .strip_suffix('%')?
+        .parse::<f64>()
+        .ok()?;
+    let discharging = fields.next() == Some("discharging");
+    let hours_left = fields
+        .next()
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|time| time.split_once(':'))
+        .and_then(|(h, m)| Some(h.parse::<f64>().ok()? + m.parse::<f64>().ok()? / 60.0))
+        .filter(|hours| *hours > 0.0);
+
+    Some(BatteryReading {
+        percent,
+        discharging,
+        drain_percent_per_hour: hours_left
+            .filter(|_| discharging)
+            .map(|hours| percent / hours),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn discharging(percent: f64, drain: Option<f64>) -> BatteryReading {
+        BatteryReading {
+            percent,
+            discharging: true,
+            drain_percent_per_hour: drain,
+        }
+    }
+
+    #[test]
+    fn test_budget_divides_charge_above_reserve_by_drain() {
+        // 70% left, 10% reserve, 30%/h: two hours.
+        let budget = estimate_budget(Some(&discharging(70.0, Some(30.0))));
+        assert!(!budget.unlimited);
+        assert!(budget.confident);
+        assert!((budget.minutes.expect("minutes") - 120.0).abs() < 1e-9);
+        assert_eq!(budget.battery_percent, Some(70.0));
+
+        // Already in the reserve: nothing left to spend.
+        let budget = estimate_budget(Some(&discharging(8.0, Some(30.0))));
+        assert_eq!(budget.minutes, Some(0.0));
+    }
+
+    #[test]
+    fn test_budget_without_drain_rate_assumes_default_and_is_not_confident() {
+        let budget = estimate_budget(Some(&discharging(50.0, None)));
+        assert!(!budget.confident);
+        let expected = 40.0 / DEFAULT_DRAIN_PERCENT_PER_HOUR * 60.0;
+        assert!((budget.minutes.expect("minutes") - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_budget_unlimited_on_mains_or_without_battery() {
+        let charging = BatteryReading {
+            percent: 40.0,
+            discharging: false,
+            drain_percent_per_hour: None,
+        };
+        let budget = estimate_budget(Some(&charging));
+        assert!(budget.unlimited && budget.confident);
+        assert_eq!(budget.minutes, None);
+
+        let budget = estimate_budget(None);
+        assert!(budget.unlimited && !budget.confident);
+        assert_eq!(budget.battery_percent, None);
+    }
+
+    #[test]
+    fn test_read_sysfs_battery_skips_mains_and_reads_energy_rate() {
+        let root = std::env::temp_dir().join("second_test_battery_sysfs");
+        let _ = std::fs::remove_dir_all(&root);
+        let write = |dir: &str, fields: &[(&str, &str)]| {
+            std::fs::create_dir_all(root.join(dir)).expect("create supply");
+            for (name, value) in fields {
+                std::fs::write(root.join(dir).join(name), format!("{value}\n")).expect("write");
+            }
+        };
+        write("AC", &[("type", "Mains"), ("online", "0")]);
+        write(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("capacity", "64"),
+                ("energy_full", "50000000"),
+                ("power_now", "10000000"),
+            ],
+        );
+
+        let reading = read_sysfs_battery(&root).expect("battery");
+        assert_eq!(reading, discharging(64.0, Some(20.0)));
+        assert!(read_sysfs_battery(&root.join("AC")).is_none());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_parse_pmset_batt() {
+        let output = "Now drawing from 'Battery Power'\n \
+            -InternalBattery-0 (id=4653155)\t60%; discharging; 2:00 remaining present: true\n";
+        assert_eq!(
+            parse_pmset_batt(output),
+            Some(discharging(60.0, Some(30.0)))
+        );
+
+        let output = "Now drawing from 'Battery Power'\n \
+            -InternalBattery-0 (id=4653155)\t97%; discharging; (no estimate) present: true\n";
+        assert_eq!(parse_pmset_batt(output), Some(discharging(97.0, None)));
+
+        let output = "Now drawing from 'AC Power'\n \
+            -InternalBattery-0 (id=4653155)\t80%; charging; 0:45 remaining present: true\n";
+        let reading = parse_pmset_batt(output).expect("reading");
+        assert!(!reading.discharging);
+        assert_eq!(reading.drain_percent_per_hour, None);
+
+        assert_eq!(parse_pmset_batt("Now drawing from 'AC Power'\n"), None);
+    }
+}
@@ -0,0 +1,249 @@
+//! The app's cache of derived files, such as transcoded playback copies.
+//!
+//! Everything lives under [`CACHE_DIR_NAME`] in the app data dir, one
+//! subfolder per feature (e.g. `playback`), so it can be listed and cleared
+//! in one place. A cached file may have a `.source` companion recording the
+//! path of the file it was made from.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::storage;
+
+/// Subfolder of the app data dir holding the cache.
+pub const CACHE_DIR_NAME: &str = "cache";
+
+/// Extension of the companion file naming a cached file's source.
+const SOURCE_EXTENSION: &str = "source";
+
+/// Prefix of the error returned when asked to delete a path that is not a
+/// file in the cache.
+pub const ERR_NOT_IN_CACHE: &str = "Not a cached file";
+
+/// One cached file.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CacheEntry {
+    pub path: String,
+    /// Subfolder of the cache it is in, e.g. `playback`.
+    pub kind: Option<String>,
+    pub size_bytes: u64,
+    /// File it was made from, if recorded.
+    pub source: Option<String>,
+}
+
+/// Everything in the cache, and the room left on its volume.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CacheListing {
+    pub entries: Vec<CacheEntry>,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+/// `<path>.source`, where [`record_source`] keeps the source of `path`.
+fn source_record_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(SOURCE_EXTENSION);
+    PathBuf::from(name)
+}
+
+fn is_source_record(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == SOURCE_EXTENSION)
+}
+
+/// Note that the cached file at `cached` was made from `source`, for
+/// [`list_cache`]. Best effort: a cached file without a note still works.
+pub fn record_source(cached: &Path, source: &Path) {
+    if let Err(e) = fs::write(
+        source_record_path(cached),
+        source.to_string_lossy().as_bytes(),
+    ) {
+        eprintln!("Failed to record source of '{}': {e}", cached.display());
+    }
+}
+
+/// List the files in the cache at `dir`, largest first. A missing cache is
+/// empty.
+///
+/// # Errors
+/// Returns an error if the cache or one of its subfolders cannot be read,
+/// or the free space on its volume cannot be queried.
+pub fn list_cache(dir: &Path) -> Result<CacheListing, String> {
+    let mut files = Vec::new();
+    if dir.exists() {
+        collect_files(dir, &mut files)?;
+    }
+
+    let mut entries: Vec<CacheEntry> = files
+        .iter()
+        .filter(|path| !is_source_record(path))
+        .map(|path| CacheEntry {
+            path: path.to_string_lossy().into_owned(),
+            kind: path
+                .strip_prefix(dir)
+                .ok()
+                .and_then(|relative| relative.parent())
+                .and_then(|parent| parent.components().next())
+                .map(|kind| kind.as_os_str().to_string_lossy().into_owned()),
+            size_bytes: fs::metadata(path).map_or(0, |m| m.len()),
+            source: fs::read_to_string(source_record_path(path)).ok(),
+        })
+        .collect();
+    entries.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes).then(a.path.cmp(&b.path)));
+
+    Ok(CacheListing {
+        total_bytes: entries.iter().map(|entry| entry.size_bytes).sum(),
+        available_bytes: storage::disk_space(dir, 0)?.available_bytes,
+        entries,
+    })
+}
+
+/// Delete everything in the cache at `dir`, keeping the folder itself.
+/// Returns the bytes freed.
+///
+/// # Errors
+/// Returns an error if the cache cannot be read or a file cannot be
+/// deleted; files deleted before that stay deleted.
+pub fn clear_cache(dir: &Path) -> Result<u64, String> {
+    let mut files = Vec::new();
+    if dir.exists() {
+        collect_files(dir, &mut files)?;
+    }
+    let mut freed = 0;
+    for path in files {
+        freed += remove_file(&path)?;
+    }
+    // Only the now-empty subfolders are left.
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+    Ok(freed)
+}
+
+/// Delete the cached file at `path`, and its source note, from the cache at
+/// `dir`. Returns the bytes freed.
+///
+/// # Errors
+/// Returns an [`ERR_NOT_IN_CACHE`] error if `path` is not a file inside
+/// `dir`, or an error if it cannot be deleted.
+pub fn clear_cache_entry(dir: &Path, path: &Path) -> Result<u64, String> {
+    let not_in_cache = || format!("{ERR_NOT_IN_CACHE}: '{}'", path.display());
+    let dir = dir.canonicalize().map_err(|_| not_in_cache())?;
+    let resolved = storage::file_within(&dir, path).ok_or_else(not_in_cache)?;
+
+    let mut freed = remove_file(&resolved)?;
+    let record = source_record_path(&resolved);
+    if record.is_file() {
+        freed += remove_file(&record)?;
+    }
+    Ok(freed)
+}
+
+/// Delete the file at `path`, returning its size.
+fn remove_file(path: &Path) -> Result<u64, String> {
+    let size = fs::metadata(path).map_or(0, |m| m.len());
+    fs::remove_file(path).map_err(|e| format!("Failed to delete '{}': {e}", path.display()))?;
+    Ok(size)
+}
+
+/// Append the files under `dir` to `files`, descending into subfolders.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read cache directory '{}': {e}", dir.display()))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read cache directory entry: {e}"))?;
+        let path = entry.path();
+        // `file_type` does not follow symlinks, so a link cycle cannot
+        // recurse forever.
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            collect_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_dir;
+
+    #[test]
+    fn test_list_and_clear_cache_entries() {
+        let root = test_dir("cache", "entries");
+        let cache = root.join(CACHE_DIR_NAME);
+        fs::create_dir_all(cache.join("playback")).expect("create cache");
+        let big = cache.join("playback/a-0123.m4a");
+        let small = cache.join("playback/b-4567.m4a");
+        fs::write(&big, [0_u8; 300]).expect("write big");
+        fs::write(&small, [0_u8; 100]).expect("write small");
+        record_source(&big, Path::new("/recordings/a.wav"));
+
+        let listing = list_cache(&cache).expect("list");
+        assert_eq!(listing.total_bytes, 400);
+        assert!(listing.available_bytes > 0);
+        assert_eq!(
+            listing.entries,
+            vec![
+                CacheEntry {
+                    path: big.to_string_lossy().into_owned(),
+                    kind: Some("playback".into()),
+                    size_bytes: 300,
+                    source: Some("/recordings/a.wav".into()),
+                },
+                CacheEntry {
+                    path: small.to_string_lossy().into_owned(),
+                    kind: Some("playback".into()),
+                    size_bytes: 100,
+                    source: None,
+                },
+            ]
+        );
+
+        // One entry, with its source note.
+        let freed = clear_cache_entry(&cache, &big).expect("clear entry");
+        assert_eq!(freed, 300 + "/recordings/a.wav".len() as u64);
+        assert!(!big.exists() && !source_record_path(&big).exists());
+        assert_eq!(list_cache(&cache).expect("list").entries.len(), 1);
+
+        assert_eq!(clear_cache(&cache).expect("clear"), 100);
+        assert!(cache.is_dir());
+        assert!(list_cache(&cache).expect("list").entries.is_empty());
+        // A cache that was never created is simply empty.
+        assert!(list_cache(&root.join("missing"))
+            .expect("list missing")
+            .entries
+            .is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_clear_cache_entry_rejects_paths_outside_the_cache() {
+        let root = test_dir("cache", "outside");
+        let cache = root.join(CACHE_DIR_NAME);
+        fs::create_dir_all(cache.join("playback")).expect("create cache");
+        let outside = root.join("recording.wav");
+        fs::write(&outside, b"RIFF").expect("write outside");
+
+        for path in [
+            outside.clone(),
+            cache.join("playback/../../recording.wav"),
+            cache.join("playback"),
+            cache.join("playback/missing.m4a"),
+        ] {
+            let err = clear_cache_entry(&cache, &path).unwrap_err();
+            assert!(err.starts_with(ERR_NOT_IN_CACHE), "got: {err}");
+        }
+        assert!(outside.exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,414 @@
+//! User settings file stored in the app config directory.
+//!
+//! `second.json` holds overrides that power users set by hand, such as the
+//! exact Python interpreter to run the backend with, and the settings saved
+//! from the app: sidecar profiles, the default transcription prompt and the
+//! transcription language. Every field is optional and a missing file is the
+//! same as an empty one.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// Name of the settings file inside the app config directory.
+pub const CONFIG_FILE_NAME: &str = "second.json";
+
+/// Name of the profile that auto-detects everything, as `start_sidecar`
+/// does. It cannot be registered over.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Contents of `second.json`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Python interpreter to run the backend with. Takes priority over the
+    /// backend virtualenv and `$PATH`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub python_path: Option<String>,
+    /// How many times to look for the backend directory before giving up,
+    /// for a backend on a network share that may still be mounting at
+    /// launch. Unset means a single attempt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend_dir_attempts: Option<u32>,
+    /// Delay between those attempts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend_dir_retry_ms: Option<u64>,
+    /// Named ways of launching the sidecar, besides [`DEFAULT_PROFILE`].
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub sidecar_profiles: BTreeMap<String, SidecarProfile>,
+    /// `initial_prompt` for transcriptions that do not pass their own, e.g.
+    /// domain jargon. Written as `null` when cleared, so saving drops an
+    /// old value rather than keeping it as an unknown key.
+    pub default_initial_prompt: Option<String>,
+    /// Language hint (ISO 639-1, e.g. `en`) sent with every transcription
+    /// that does not pass its own; unset means the backend auto-detects.
+    /// Written as `null` when cleared, like `default_initial_prompt`.
+    pub transcription_language: Option<String>,
+}
+
+/// One way of launching the sidecar, e.g. a local CPU model or a wrapper
+/// script that runs the backend on a remote GPU box. Whatever is left unset
+/// is auto-detected as for the default profile.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SidecarProfile {
+    /// Program run as `<python_path> main.py <args>...`; an interpreter or
+    /// a wrapper script.
+    pub python_path: Option<String>,
+    /// Working directory containing `main.py`.
+    pub backend_dir: Option<String>,
+    /// Extra arguments after `main.py`.
+    pub args: Vec<String>,
+    /// Environment variables set for the sidecar on top of the app's own.
+    pub env: BTreeMap<String, String>,
+}
+
+impl AppConfig {
+    /// The profile called `name`; [`DEFAULT_PROFILE`] is always there.
+    ///
+    /// # Errors
+    /// Returns an error if no profile of that name is registered.
+    pub fn sidecar_profile(&self, name: &str) -> Result<SidecarProfile, String> {
+        if name == DEFAULT_PROFILE {
+            return Ok(SidecarProfile::default());
+        }
+        self.sidecar_profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("No sidecar profile named '{name}'"))
+    }
+
+    /// The prompt a transcription should use: `per_call` if given, else
+    /// the [default](Self::default_initial_prompt), else none. A blank
+    /// `per_call` prompt turns the default off for that call.
+    pub fn initial_prompt(&self, per_call: Option<String>) -> Option<String> {
+        per_call
+            .or_else(|| self.default_initial_prompt.clone())
+            .filter(|prompt| !prompt.trim().is_empty())
+    }
+
+    /// Every profile by name, [`DEFAULT_PROFILE`] included.
+    pub fn list_sidecar_profiles(&self) -> BTreeMap<String, SidecarProfile> {
+        let mut profiles = self.sidecar_profiles.clone();
+        profiles.insert(DEFAULT_PROFILE.to_string(), SidecarProfile::default());
+        profiles
+    }
+}
+
+/// Path of the settings file inside `config_dir`.
+pub fn config_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(CONFIG_FILE_NAME)
+}
+
+/// Load `second.json` from `config_dir`, returning defaults if it does not
+/// exist.
+///
+/// # Errors
+/// Returns an error if the file exists but cannot be read or parsed.
+pub fn load_app_config(config_dir: &Path) -> Result<AppConfig, String> {
+    let path = config_path(config_dir);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(AppConfig::default()),
+        Err(e) => return Err(format!("Failed to read '{}': {e}", path.display())),
+    };
+    serde_json::from_str(&contents).map_err(|e| format!("Invalid '{}': {e}", path.display()))
+}
+
+/// Add `profile` to `second.json` in `config_dir` under `name`, replacing
+/// any profile of that name. Keys the app does not know about are kept.
+///
+/// # Errors
+/// Returns an error if `name` is empty or [`DEFAULT_PROFILE`], or if the
+/// file cannot be read, parsed or written.
+pub fn register_sidecar_profile(
+    config_dir: &Path,
+    name: &str,
+    profile: SidecarProfile,
+) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Sidecar profile name must not be empty".into());
+    }
+    if name == DEFAULT_PROFILE {
+        return Err(format!(
+            "The '{DEFAULT_PROFILE}' sidecar profile is built in and cannot be replaced"
+        ));
+    }
+
+    let mut config = load_app_config(config_dir)?;
+    config.sidecar_profiles.insert(name.to_string(), profile);
+    save_app_config(config_dir, &config)
+}
+
+/// Save `prompt` as the default `initial_prompt` in `second.json` in
+/// `config_dir`, or clear it with `None` (or a blank prompt).
+///
+/// # Errors
+/// Returns an error if the file cannot be read, parsed or written.
+pub fn set_default_initial_prompt(config_dir: &Path, prompt: Option<String>) -> Result<(), String> {
+    let mut config = load_app_config(config_dir)?;
+    config.default_initial_prompt = prompt.filter(|prompt| !prompt.trim().is_empty());
+    save_app_config(config_dir, &config)
+}
+
+/// Check that `language` is a lowercase two-letter ISO 639-1 code such as
+/// `en` or `de`.
+///
+/// # Errors
+/// Returns an error naming `language` otherwise.
+pub fn validate_language(language: &str) -> Result<(), String> {
+    if language.len() == 2 && language.bytes().all(|b| b.is_ascii_lowercase()) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid language '{language}': expected a lowercase two-letter ISO 639-1 code such as 'en'"
+        ))
+    }
+}
+
+/// Save `language` as the transcription language in `second.json` in
+/// `config_dir`, or go back to auto-detection with `None`.
+///
+/// # Errors
+/// Returns an error if `language` is not a valid code (see
+/// [`validate_language`]) or the file cannot be read, parsed or written.
+pub fn set_transcription_language(
+    config_dir: &Path,
+    language: Option<String>,
+) -> Result<(), String> {
+    if let Some(language) = &language {
+        validate_language(language)?;
+    }
+    let mut config = load_app_config(config_dir)?;
+    config.transcription_language = language;
+    save_app_config(config_dir, &config)
+}
+
+/// Write `config` to `second.json` in `config_dir`, keeping any keys in the
+/// existing file that [`AppConfig`] does not know about.
+///
+/// # Errors
+/// Returns an error if the existing file cannot be read or parsed, or the
+/// new one cannot be written.
+pub fn save_app_config(config_dir: &Path, config: &AppConfig) -> Result<(), String> {
+    let path = config_path(config_dir);
+    let mut merged = match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str::<serde_json::Map<String, Value>>(&contents)
+            .map_err(|e| format!("Invalid '{}': {e}", path.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => serde_json::Map::new(),
+        Err(e) => return Err(format!("Failed to read '{}': {e}", path.display())),
+    };
+    let Value::Object(known) =
+        serde_json::to_value(config).map_err(|e| format!("Failed to serialize app config: {e}"))?
+    else {
+        return Err("App config did not serialize to an object".into());
+    };
+    merged.extend(known);
+
+    fs::create_dir_all(config_dir)
+        .map_err(|e| format!("Failed to create '{}': {e}", config_dir.display()))?;
+    let json = serde_json::to_string_pretty(&merged)
+        .map_err(|e| format!("Failed to serialize app config: {e}"))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write '{}': {e}", path.display()))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_dir;
+
+    #[test]
+    fn test_missing_file_yields_defaults() {
+        let dir = test_dir("config", "missing");
+        assert_eq!(load_app_config(&dir).expect("load"), AppConfig::default());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reads_python_path_and_ignores_unknown_keys() {
+        let dir = test_dir("config", "python_path");
+        fs::write(
+            config_path(&dir),
+            r#"{"python_path": "/opt/py/bin/python3", "theme": "dark"}"#,
+        )
+        .expect("write config");
+
+        let config = load_app_config(&dir).expect("load");
+        assert_eq!(config.python_path.as_deref(), Some("/opt/py/bin/python3"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reads_backend_dir_retry() {
+        let dir = test_dir("config", "backend_dir_retry");
+        fs::write(
+            config_path(&dir),
+            r#"{"backend_dir_attempts": 5, "backend_dir_retry_ms": 2000}"#,
+        )
+        .expect("write config");
+
+        let config = load_app_config(&dir).expect("load");
+        assert_eq!(config.backend_dir_attempts, Some(5));
+        assert_eq!(config.backend_dir_retry_ms, Some(2000));
+        assert_eq!(config.python_path, None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_register_sidecar_profile_persists_and_keeps_other_keys() {
+        let dir = test_dir("config", "profiles");
+        fs::write(
+            config_path(&dir),
+            r#"{"python_path": "/opt/py/bin/python3", "theme": "dark"}"#,
+        )
+        .expect("write config");
+
+        let gpu = SidecarProfile {
+            python_path: Some("/usr/local/bin/gpu-box".into()),
+            backend_dir: Some("/srv/second/backend".into()),
+            args: vec!["--device".into(), "cuda".into()],
+            env: BTreeMap::from([("CUDA_VISIBLE_DEVICES".into(), "0".into())]),
+        };
+        register_sidecar_profile(&dir, "gpu", gpu.clone()).expect("register gpu");
+        register_sidecar_profile(&dir, " cpu ", SidecarProfile::default()).expect("register cpu");
+
+        let config = load_app_config(&dir).expect("load");
+        assert_eq!(
+            config.list_sidecar_profiles().keys().collect::<Vec<_>>(),
+            vec!["cpu", "default", "gpu"]
+        );
+        assert_eq!(config.sidecar_profile("gpu"), Ok(gpu));
+        assert_eq!(
+            config.sidecar_profile(DEFAULT_PROFILE),
+            Ok(SidecarProfile::default())
+        );
+        assert!(config.sidecar_profile("tpu").is_err());
+        assert_eq!(config.python_path.as_deref(), Some("/opt/py/bin/python3"));
+        let raw = fs::read_to_string(config_path(&dir)).expect("read config");
+        assert!(raw.contains("\"theme\""), "got: {raw}");
+
+        // Registering again replaces the profile.
+        register_sidecar_profile(&dir, "gpu", SidecarProfile::default()).expect("replace");
+        let config = load_app_config(&dir).expect("load");
+        assert_eq!(config.sidecar_profiles["gpu"], SidecarProfile::default());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_initial_prompt_precedence() {
+        let none = AppConfig::default();
+        let with_default = AppConfig {
+            default_initial_prompt: Some("Cardiology notes.".into()),
+            ..AppConfig::default()
+        };
+
+        // Per-call beats the default, which beats nothing.
+        assert_eq!(
+            with_default.initial_prompt(Some("Legal brief.".into())),
+            Some("Legal brief.".into())
+        );
+        assert_eq!(
+            with_default.initial_prompt(None),
+            Some("Cardiology notes.".into())
+        );
+        assert_eq!(none.initial_prompt(None), None);
+        assert_eq!(
+            none.initial_prompt(Some("Legal brief.".into())),
+            Some("Legal brief.".into())
+        );
+        // A blank per-call prompt opts out of the default.
+        assert_eq!(with_default.initial_prompt(Some(" ".into())), None);
+    }
+
+    #[test]
+    fn test_default_initial_prompt_persists_and_clears() {
+        let dir = test_dir("config", "initial_prompt");
+        fs::write(config_path(&dir), r#"{"theme": "dark"}"#).expect("write config");
+
+        set_default_initial_prompt(&dir, Some("Kubernetes, kubectl".into())).expect("set");
+        let config = load_app_config(&dir).expect("load");
+        assert_eq!(
+            config.default_initial_prompt.as_deref(),
+            Some("Kubernetes, kubectl")
+        );
+
+        set_default_initial_prompt(&dir, None).expect("clear");
+        assert_eq!(
+            load_app_config(&dir).expect("load").default_initial_prompt,
+            None
+        );
+        let raw = fs::read_to_string(config_path(&dir)).expect("read config");
+        assert!(raw.contains("\"theme\""), "got: {raw}");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_validate_language() {
+        for good in ["en", "de", "zh"] {
+            assert!(validate_language(good).is_ok(), "{good}");
+        }
+        for bad in ["", "EN", "eng", "e1", "en-US", " en", "zz "] {
+            let err = validate_language(bad).unwrap_err();
+            assert!(err.contains("ISO 639-1"), "got: {err}");
+        }
+    }
+
+    #[test]
+    fn test_transcription_language_persists_and_rejects_garbage() {
+        let dir = test_dir("config", "language");
+
+        set_transcription_language(&dir, Some("fr".into())).expect("set");
+        assert_eq!(
+            load_app_config(&dir)
+                .expect("load")
+                .transcription_language
+                .as_deref(),
+            Some("fr")
+        );
+        assert!(set_transcription_language(&dir, Some("French".into())).is_err());
+        assert_eq!(
+            load_app_config(&dir)
+                .expect("load")
+                .transcription_language
+                .as_deref(),
+            Some("fr")
+        );
+
+        set_transcription_language(&dir, None).expect("clear");
+        assert_eq!(
+            load_app_config(&dir).expect("load").transcription_language,
+            None
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_register_sidecar_profile_rejects_reserved_and_empty_names() {
+        let dir = test_dir("config", "profiles_reserved");
+        assert!(
+            register_sidecar_profile(&dir, DEFAULT_PROFILE, SidecarProfile::default()).is_err()
+        );
+        assert!(register_sidecar_profile(&dir, "  ", SidecarProfile::default()).is_err());
+        assert!(!config_path(&dir).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_malformed_file_is_error() {
+        let dir = test_dir("config", "malformed");
+        fs::write(config_path(&dir), "{not json").expect("write config");
+        let err = load_app_config(&dir).unwrap_err();
+        assert!(err.contains(CONFIG_FILE_NAME), "got: {err}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,360 @@
+//! One-shot validation of the Python backend setup.
+//!
+//! Walks the same path as `start_sidecar` — backend directory, interpreter,
+//! interpreter version, `main.py`, then a spawn / health / shutdown cycle —
+//! and reports every step separately, so "it doesn't start" can be narrowed
+//! down to the step that actually failed. A step whose inputs are missing
+//! because an earlier step failed is reported as skipped.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::protocol::{SidecarRequest, SidecarResponse};
+use crate::sidecar::{find_backend_dir, find_python, SidecarManager};
+
+/// Oldest Python the backend supports (`requires-python` in
+/// `backend/pyproject.toml`).
+pub const MIN_PYTHON_VERSION: (u32, u32) = (3, 11);
+
+/// Step names, in the order they appear in a report.
+pub const STEP_BACKEND_DIR: &str = "backend_dir";
+pub const STEP_PYTHON: &str = "python";
+pub const STEP_PYTHON_VERSION: &str = "python_version";
+pub const STEP_MAIN_PY: &str = "main_py";
+pub const STEP_HEALTH_CHECK: &str = "health_check";
+
+/// Outcome of one diagnostic step.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DiagnosticStep {
+    pub step: String,
+    pub ok: bool,
+    /// What was found on success, or why the step failed or was skipped.
+    pub detail: String,
+}
+
+/// The individual checks behind [`run_diagnostics`].
+///
+/// Each returns a human-readable detail on success. [`SystemPreflight`]
+/// runs them for real; tests substitute canned outcomes.
+pub trait Preflight {
+    /// Resolve the backend directory.
+    fn backend_dir(&self) -> Result<String, String>;
+    /// Find the interpreter to run the backend with.
+    fn python(&self, backend_dir: Option<&str>) -> Result<String, String>;
+    /// Check that `python` is new enough.
+    fn python_version(&self, python: &str) -> Result<String, String>;
+    /// Check that `backend_dir` contains `main.py`.
+    fn main_script(&self, backend_dir: &str) -> Result<String, String>;
+    /// Start the backend, send a health check, and shut it down again.
+    fn health_cycle(&self, python: &str, backend_dir: &str) -> Result<String, String>;
+}
+
+/// Run every check in `checks` and assemble the report.
+///
+/// Independent steps always run; a step that needs the result of a failed
+/// step is reported as failed with a "skipped" detail naming it.
+pub fn run_diagnostics(checks: &dyn Preflight) -> Vec<DiagnosticStep> {
+    let mut report = Vec::new();
+
+    let backend_dir = record(&mut report, STEP_BACKEND_DIR, checks.backend_dir());
+    let python = record(
+        &mut report,
+        STEP_PYTHON,
+        checks.python(backend_dir.as_deref()),
+    );
+
+    match &python {
+        Some(python) => {
+            record(
+                &mut report,
+                STEP_PYTHON_VERSION,
+                checks.python_version(python),
+            );
+        }
+        None => report.push(skipped(STEP_PYTHON_VERSION, STEP_PYTHON)),
+    }
+
+    match &backend_dir {
+        Some(dir) => {
+            record(&mut report, STEP_MAIN_PY, checks.main_script(dir));
+        }
+        None => report.push(skipped(STEP_MAIN_PY, STEP_BACKEND_DIR)),
+    }
+
+    // The health check needs every earlier step; name the first failure.
+    match (report.iter().find(|s| !s.ok), python, backend_dir) {
+        (None, Some(python), Some(dir)) => {
+            record(
+                &mut report,
+                STEP_HEALTH_CHECK,
+                checks.health_cycle(&python, &dir),
+            );
+        }
+        (failed, _, _) => {
+            let failed = failed.map_or(STEP_PYTHON, |s| s.step.as_str()).to_string();
+            report.push(skipped(STEP_HEALTH_CHECK, &failed));
+        }
+    }
+
+    report
+}
+
+/// Append the outcome of `step` to `report`, returning the detail on
+/// success.
+fn record(
+    report: &mut Vec<DiagnosticStep>,
+    step: &str,
+    result: Result<String, String>,
+) -> Option<String> {
+    let (ok, detail) = match &result {
+        Ok(detail) => (true, detail.clone()),
+        Err(e) => (false, e.clone()),
+    };
+    report.push(DiagnosticStep {
+        step: step.to_string(),
+        ok,
+        detail,
+    });
+    result.ok()
+}
+
+fn skipped(step: &str, because: &str) -> DiagnosticStep {
+    DiagnosticStep {
+        step: step.to_string(),
+        ok: false,
+        detail: format!("Skipped because '{because}' failed"),
+    }
+}
+
+/// [`Preflight`] that inspects the real system.
+pub struct SystemPreflight {
+    /// App config dir holding `second.json`, passed to [`find_python`].
+    pub config_dir: Option<PathBuf>,
+}
+
+impl Preflight for SystemPreflight {
+    fn backend_dir(&self) -> Result<String, String> {
+        find_backend_dir()
+    }
+
+    fn python(&self, backend_dir: Option<&str>) -> Result<String, String> {
+        find_python(backend_dir, self.config_dir.as_deref())
+    }
+
+    fn python_version(&self, python: &str) -> Result<String, String> {
+        let output = Command::new(python)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format!("Failed to run '{python} --version': {e}"))?;
+        // Python 2 prints its version to stderr.
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let text = text.trim();
+        let (major, minor) = parse_python_version(text)
+            .ok_or_else(|| format!("Could not parse Python version from '{text}'"))?;
+        if (major, minor) < MIN_PYTHON_VERSION {
+            return Err(format!(
+                "{text} is too old; the backend requires Python {}.{} or newer",
+                MIN_PYTHON_VERSION.0, MIN_PYTHON_VERSION.1
+            ));
+        }
+        Ok(text.to_string())
+    }
+
+    fn main_script(&self, backend_dir: &str) -> Result<String, String> {
+        let main = Path::new(backend_dir).join("main.py");
+        if !main.is_file() {
+            return Err(format!("'{}' does not exist", main.display()));
+        }
+        Ok(main.display().to_string())
+    }
+
+    fn health_cycle(&self, python: &str, backend_dir: &str) -> Result<String, String> {
+        let mut mgr = SidecarManager::new();
+        mgr.start(python, backend_dir)?;
+        let health = mgr.send_typed::<_, SidecarResponse>(&SidecarRequest::Health);
+        // Always shut down, even when the health check failed.
+        let stopped = mgr.stop();
+
+        let health = health?;
+        stopped?;
+        if !health.is_healthy() {
+            return Err(format!("Health check failed: {health:?}"));
+        }
+        Ok("Backend started, answered a health check, and shut down".into())
+    }
+}
+
+/// Extract `(major, minor)` from `python --version` output such as
+/// `"Python 3.11.7"`.
+fn parse_python_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.split_whitespace().nth(1)?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts
+        .next()?
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .ok()?;
+    Some((major, minor))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Preflight with canned outcomes.
+    struct MockPreflight {
+        backend_dir: Result<String, String>,
+        python: Result<String, String>,
+        version: Result<String, String>,
+        main_script: Result<String, String>,
+        health: Result<String, String>,
+    }
+
+    impl MockPreflight {
+        fn healthy() -> Self {
+            Self {
+                backend_dir: Ok("/app/backend".into()),
+                python: Ok("python3".into()),
+                version: Ok("Python 3.11.7".into()),
+                main_script: Ok("/app/backend/main.py".into()),
+                health: Ok("healthy".into()),
+            }
+        }
+    }
+
+    impl Preflight for MockPreflight {
+        fn backend_dir(&self) -> Result<String, String> {
+            self.backend_dir.clone()
+        }
+
+        fn python(&self, _backend_dir: Option<&str>) -> Result<String, String> {
+            self.python.clone()
+        }
+
+        fn python_version(&self, _python: &str) -> Result<String, String> {
+            self.version.clone()
+        }
+
+        fn main_script(&self, _backend_dir: &str) -> Result<String, String> {
+            self.main_script.clone()
+        }
+
+        fn health_cycle(&self, _python: &str, _backend_dir: &str) -> Result<String, String> {
+            self.health.clone()
+        }
+    }
+
+    fn steps(report: &[DiagnosticStep]) -> Vec<(&str, bool)> {
+        report.iter().map(|s| (s.step.as_str(), s.ok)).collect()
+    }
+
+    #[test]
+    fn test_all_steps_pass() {
+        let report = run_diagnostics(&MockPreflight::healthy());
+        assert_eq!(
+            steps(&report),
+            vec![
+                (STEP_BACKEND_DIR, true),
+                (STEP_PYTHON, true),
+                (STEP_PYTHON_VERSION, true),
+                (STEP_MAIN_PY, true),
+                (STEP_HEALTH_CHECK, true),
+            ]
+        );
+        assert_eq!(report[1].detail, "python3");
+    }
+
+    #[test]
+    fn test_missing_backend_dir_skips_dependent_steps() {
+        let checks = MockPreflight {
+            backend_dir: Err("no backend".into()),
+            ..MockPreflight::healthy()
+        };
+        let report = run_diagnostics(&checks);
+        assert_eq!(
+            steps(&report),
+            vec![
+                (STEP_BACKEND_DIR, false),
+                (STEP_PYTHON, true),
+                (STEP_PYTHON_VERSION, true),
+                (STEP_MAIN_PY, false),
+                (STEP_HEALTH_CHECK, false),
+            ]
+        );
+        assert_eq!(report[0].detail, "no backend");
+        assert!(report[3].detail.contains("'backend_dir'"));
+        assert!(report[4].detail.contains("'backend_dir'"));
+    }
+
+    #[test]
+    fn test_old_python_blocks_health_check() {
+        let checks = MockPreflight {
+            version: Err("Python 3.8.10 is too old".into()),
+            ..MockPreflight::healthy()
+        };
+        let report = run_diagnostics(&checks);
+        assert_eq!(report[2].detail, "Python 3.8.10 is too old");
+        assert!(report[3].ok);
+        assert!(!report[4].ok);
+        assert!(report[4].detail.contains("'python_version'"));
+    }
+
+    #[test]
+    fn test_failed_health_check_is_reported() {
+        let checks = MockPreflight {
+            health: Err("Sidecar process closed stdout (possible crash)".into()),
+            ..MockPreflight::healthy()
+        };
+        let report = run_diagnostics(&checks);
+        assert_eq!(report.len(), 5);
+        assert!(report[..4].iter().all(|s| s.ok));
+        assert!(report[4].detail.contains("possible crash"));
+    }
+
+    #[test]
+    fn test_parse_python_version() {
+        assert_eq!(parse_python_version("Python 3.11.7"), Some((3, 11)));
+        assert_eq!(parse_python_version("Python 3.13.0rc1"), Some((3, 13)));
+        assert_eq!(parse_python_version("Python 3.12"), Some((3, 12)));
+        assert_eq!(parse_python_version("Python 2.7.18"), Some((2, 7)));
+        assert_eq!(parse_python_version("not python"), None);
+        assert_eq!(parse_python_version(""), None);
+    }
+
+    #[test]
+    fn test_health_cycle_leaves_no_process_running() {
+        let Ok(python) = find_python(None, None) else {
+            eprintln!("Skipping health cycle test: python not found");
+            return;
+        };
+        let dir = std::env::temp_dir().join("second_test_diagnose_health");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        std::fs::write(
+            dir.join("main.py"),
+            "import sys\nfor line in sys.stdin:\n    print('{\"type\": \"health\", \"status\": \"ok\"}', flush=True)\n",
+        )
+        .expect("write main.py");
+
+        let checks = SystemPreflight { config_dir: None };
+        let dir_str = dir.to_str().expect("utf-8");
+        assert!(checks.main_script(dir_str).is_ok());
+        checks.health_cycle(&python, dir_str).expect("health cycle");
+
+        std::fs::remove_file(dir.join("main.py")).expect("remove main.py");
+        assert!(checks.main_script(dir_str).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
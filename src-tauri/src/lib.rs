@@ -1,23 +1,94 @@
+mod about;
 mod audio;
+mod battery;
+mod cache;
+mod config;
+mod diagnose;
+mod log_file;
+mod media;
+mod models;
+mod playback;
+mod protocol;
+mod recordings;
 mod sidecar;
+mod storage;
+mod subtitles;
+#[cfg(test)]
+mod test_util;
+mod transcribe;
+use std::collections::BTreeMap;
 
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
 
+use base64::Engine as _;
 use serde_json::Value;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
-use crate::audio::capture::AudioCaptureManager;
-use crate::audio::devices;
-use crate::sidecar::{find_backend_dir, find_python, SidecarManager};
+use crate::about::AboutInfo;
+use crate::audio::bridge::{AudioBridge, AudioBridgeInfo};
+use crate::audio::capture::{
+    AudioCaptureManager, AudioStatus, CaptureState, InputLatency, LeadIn, Meters, Reconfigured,
+    RecordingResult, RecordingStatus, StartOptions,
+};
+use crate::audio::config::AudioCaptureConfig;
+use crate::audio::devices::{self, DeviceAllowList, DeviceAvailability};
+use crate::audio::monitor::{PlaybackManager, PlaybackStatus};
+use crate::audio::permission::{microphone_permission, MicrophonePermission};
+use crate::audio::schedule::RecordingScheduler;
+use crate::audio::wav::{SilenceReport, SpeechRegion, TrimResult};
+use crate::battery::RecordingBudget;
+use crate::cache::CacheListing;
+use crate::config::{load_app_config, AppConfig, SidecarProfile, DEFAULT_PROFILE};
+use crate::diagnose::{run_diagnostics, DiagnosticStep, SystemPreflight};
+use crate::models::ModelCatalog;
+use crate::playback::Encoder;
+use crate::protocol::{
+    ComputeDevice, ModelInfo, SidecarCapabilities, SidecarRequest, SidecarResponse, Transcript,
+};
+use crate::recordings::{ChecksumVerification, Manifest};
+use crate::sidecar::{
+    resolve_profile, BackendDirRetry, ProcessInfo, ReconnectPolicy, ReconnectStats, SidecarLaunch,
+    SidecarManager, TraceEntry, DEFAULT_TRACE_CAPACITY,
+};
+use crate::storage::{DiskSpace, StorageProbe};
+use crate::subtitles::SubtitleFormat;
+use crate::transcribe::{
+    BenchmarkResult, Compression, PipelineTiming, TranscriptCache, TranscriptionError,
+    TranscriptionErrorKind, TranscriptionErrorLog, TranscriptionSession,
+};
+
+/// Name of the sidecar log file inside the app log dir.
+const SIDECAR_LOG_FILE_NAME: &str = "sidecar.log";
 
 /// Tauri-managed state wrapping the sidecar process manager.
-struct SidecarState(Mutex<SidecarManager>);
+struct SidecarState(Arc<Mutex<SidecarManager>>);
+
+/// Tauri-managed state holding transcripts cached by `retranscribe`.
+struct TranscriptCacheState(Mutex<TranscriptCache>);
+
+/// Tauri-managed state holding recent transcription failures.
+struct TranscriptionErrorState(Mutex<TranscriptionErrorLog>);
+
+/// Tauri-managed state caching the sidecar's model list.
+struct ModelCatalogState(Mutex<ModelCatalog>);
+
+/// Tauri-managed state holding the current streaming transcription session.
+struct TranscriptionSessionState(Mutex<Option<TranscriptionSession>>);
+
+/// Tauri-managed state wrapping monitoring playback.
+struct PlaybackState(PlaybackManager);
+
+/// Tauri-managed state holding the live audio bridge, while it runs.
+struct AudioBridgeState(Mutex<Option<AudioBridge>>);
 
 /// Tauri-managed state wrapping the audio capture manager.
 struct AudioState {
     manager: AudioCaptureManager,
     recordings_dir: Mutex<PathBuf>,
+    config: Mutex<AudioCaptureConfig>,
+    scheduler: RecordingScheduler,
 }
 
 // ---------------------------------------------------------------------------
@@ -26,23 +97,216 @@ struct AudioState {
 
 /// Start the Python sidecar, auto-detecting the Python interpreter and backend
 /// directory. Sends a health check after startup and returns `"ok"` on success.
+///
+/// Safe to call again while the sidecar runs: a running sidecar that passes
+/// a quick health check is left alone and `"ok"` returned straight away.
+///
+/// A `python_path` in `second.json` in the app config dir overrides
+/// interpreter discovery, and `backend_dir_attempts` /
+/// `backend_dir_retry_ms` keep looking for a backend directory on a share
+/// that is still mounting.
+///
+/// `memory_limit_bytes` caps the sidecar's address space on Unix (it starts
+/// uncapped, with a warning, elsewhere); `sidecar_process_info` reports it.
 #[tauri::command]
-fn start_sidecar(state: tauri::State<'_, SidecarState>) -> Result<String, String> {
+fn start_sidecar(
+    memory_limit_bytes: Option<u64>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+    catalog: tauri::State<'_, ModelCatalogState>,
+) -> Result<String, String> {
+    start_sidecar_profile(
+        DEFAULT_PROFILE.to_string(),
+        memory_limit_bytes,
+        app,
+        state,
+        catalog,
+    )
+}
+
+/// Start the Python sidecar as the profile `name` describes (see
+/// `register_sidecar_profile`), auto-detecting whatever it leaves unset.
+/// The `default` profile is `start_sidecar`. Health-checked and
+/// memory-capped the same way.
+#[tauri::command]
+fn start_sidecar_profile(
+    name: String,
+    memory_limit_bytes: Option<u64>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+    catalog: tauri::State<'_, ModelCatalogState>,
+) -> Result<String, String> {
+    // Resolved before taking the lock, so a retrying search doesn't hold
+    // up other sidecar commands.
+    let config_dir = app.path().app_config_dir().ok();
+    let config = match config_dir.as_deref() {
+        Some(dir) => load_app_config(dir)?,
+        None => AppConfig::default(),
+    };
+    let launch = SidecarLaunch {
+        memory_limit_bytes,
+        ..resolve_profile(
+            &config.sidecar_profile(&name)?,
+            config_dir.as_deref(),
+            BackendDirRetry::from_config(&config),
+        )?
+    };
+
     let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.set_language(config.transcription_language.clone());
+    if mgr.ensure_started(launch)? {
+        // A new process may have a different set of models.
+        catalog
+            .0
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?
+            .invalidate();
+    }
 
-    let backend_dir = find_backend_dir()?;
-    let python = find_python(Some(&backend_dir))?;
+    Ok("ok".into())
+}
 
-    mgr.start(&python, &backend_dir)?;
+/// Save a named way of launching the sidecar to `second.json`: any of
+/// `python_path` (an interpreter or wrapper script), `backend_dir`, extra
+/// `args` after `main.py`, and `env` vars. Replaces a profile of the same
+/// name; `default` is reserved.
+#[tauri::command]
+fn register_sidecar_profile(
+    name: String,
+    profile: SidecarProfile,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let config_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve app config directory: {e}"))?;
+    config::register_sidecar_profile(&config_dir, &name, profile)
+}
 
-    // Verify the sidecar is responding.
-    let health = mgr.send_message(serde_json::json!({"type": "health"}))?;
-    if health.get("status").and_then(Value::as_str) != Some("ok") {
-        mgr.stop()?;
-        return Err(format!("Health check failed: {health}"));
+/// Every sidecar profile by name, including the built-in `default`.
+#[tauri::command]
+fn list_sidecar_profiles(
+    app: tauri::AppHandle,
+) -> Result<BTreeMap<String, SidecarProfile>, String> {
+    let config = match app.path().app_config_dir().ok() {
+        Some(dir) => load_app_config(&dir)?,
+        None => AppConfig::default(),
+    };
+    Ok(config.list_sidecar_profiles())
+}
+
+/// The `initial_prompt` a transcription command should use: `per_call`,
+/// else the default saved with `set_default_initial_prompt`.
+fn resolve_initial_prompt(
+    app: &tauri::AppHandle,
+    per_call: Option<String>,
+) -> Result<Option<String>, String> {
+    let config = match app.path().app_config_dir().ok() {
+        Some(dir) => load_app_config(&dir)?,
+        None => AppConfig::default(),
+    };
+    Ok(config.initial_prompt(per_call))
+}
+
+/// Note `result` in the transcription error log if transcribing `path`
+/// failed, and pass it on.
+fn note_transcription_error<T>(
+    app: &tauri::AppHandle,
+    path: &str,
+    result: Result<T, String>,
+) -> Result<T, String> {
+    if let Err(message) = &result {
+        if let Ok(mut log) = app.state::<TranscriptionErrorState>().0.lock() {
+            log.record(path, TranscriptionErrorKind::of(message), message);
+        }
     }
+    result
+}
 
-    Ok("ok".into())
+/// [`note_transcription_error`] that also notes an error response from the
+/// sidecar.
+fn note_transcription(
+    app: &tauri::AppHandle,
+    path: &str,
+    result: Result<Value, String>,
+) -> Result<Value, String> {
+    if let Ok(response) = &result {
+        if let Ok(mut log) = app.state::<TranscriptionErrorState>().0.lock() {
+            log.record_response(path, response);
+        }
+    }
+    note_transcription_error(app, path, result)
+}
+
+/// The most recent transcription failures, oldest first: the last `limit`
+/// of them, or all that are kept (up to
+/// [`transcribe::TRANSCRIPTION_ERROR_LOG_CAPACITY`]).
+#[tauri::command]
+fn recent_transcription_errors(
+    limit: Option<usize>,
+    state: tauri::State<'_, TranscriptionErrorState>,
+) -> Result<Vec<TranscriptionError>, String> {
+    let log = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    Ok(log.recent(limit))
+}
+
+/// Check a per-call `language` override before it is sent.
+fn validate_language(language: Option<&str>) -> Result<(), String> {
+    language
+        .map(config::validate_language)
+        .transpose()
+        .map(drop)
+}
+
+/// Send `language` (a lowercase ISO 639-1 code such as `en`) as the
+/// language hint of every transcription, or let the backend auto-detect
+/// with `null`. Saved in `second.json`, so it survives restarts; the
+/// transcription commands' own `language` takes precedence.
+#[tauri::command]
+fn set_sidecar_language(
+    lang: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<(), String> {
+    let config_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve app config directory: {e}"))?;
+    config::set_transcription_language(&config_dir, lang.clone())?;
+    state
+        .0
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?
+        .set_language(lang);
+    Ok(())
+}
+
+/// The `initial_prompt` used by transcriptions that don't pass one, if set.
+#[tauri::command]
+fn get_default_initial_prompt(app: tauri::AppHandle) -> Result<Option<String>, String> {
+    resolve_initial_prompt(&app, None)
+}
+
+/// Save `prompt` as the `initial_prompt` for transcriptions that don't pass
+/// their own, or clear it with `null`. Kept in `second.json`, so it
+/// survives restarts.
+#[tauri::command]
+fn set_default_initial_prompt(prompt: Option<String>, app: tauri::AppHandle) -> Result<(), String> {
+    let config_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve app config directory: {e}"))?;
+    config::set_default_initial_prompt(&config_dir, prompt)
+}
+
+/// Run every backend preflight check — backend dir, Python, its version,
+/// `main.py`, and a throwaway spawn/health/shutdown cycle — and report each
+/// step. Independent of the managed sidecar; leaves no process running.
+#[tauri::command]
+fn diagnose_backend(app: tauri::AppHandle) -> Vec<DiagnosticStep> {
+    run_diagnostics(&SystemPreflight {
+        config_dir: app.path().app_config_dir().ok(),
+    })
 }
 
 /// Stop the Python sidecar process.
@@ -56,16 +320,48 @@ fn stop_sidecar(state: tauri::State<'_, SidecarState>) -> Result<(), String> {
 #[tauri::command]
 fn sidecar_health(state: tauri::State<'_, SidecarState>) -> Result<Value, String> {
     let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
-    mgr.send_message(serde_json::json!({"type": "health"}))
+    mgr.send_typed(&SidecarRequest::Health)
 }
 
 /// Send an arbitrary JSON message to the sidecar and return the response.
+///
+/// A message that is not an object with a non-empty string `type` fails
+/// with an `InvalidMessage` error without being sent, unless `unchecked`
+/// is set.
 #[tauri::command]
-fn send_to_sidecar(message: Value, state: tauri::State<'_, SidecarState>) -> Result<Value, String> {
+fn send_to_sidecar(
+    message: Value,
+    unchecked: Option<bool>,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<Value, String> {
+    if !unchecked.unwrap_or(false) {
+        protocol::validate_message(&message)?;
+    }
     let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
     mgr.send_message(message)
 }
 
+/// [`send_to_sidecar`], returning `{ value, round_trip_ms, request_bytes,
+/// response_bytes }` so the UI can chart latency per request type.
+#[tauri::command]
+fn send_to_sidecar_timed(
+    message: Value,
+    unchecked: Option<bool>,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<Value, String> {
+    if !unchecked.unwrap_or(false) {
+        protocol::validate_message(&message)?;
+    }
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    let timed = mgr.send_message_timed(message)?;
+    Ok(serde_json::json!({
+        "value": timed.value,
+        "round_trip_ms": timed.round_trip.as_secs_f64() * 1000.0,
+        "request_bytes": timed.request_bytes,
+        "response_bytes": timed.response_bytes,
+    }))
+}
+
 /// Check whether the sidecar process is currently running.
 #[tauri::command]
 fn sidecar_status(state: tauri::State<'_, SidecarState>) -> Result<bool, String> {
@@ -73,36 +369,1560 @@ fn sidecar_status(state: tauri::State<'_, SidecarState>) -> Result<bool, String>
     Ok(mgr.is_running())
 }
 
+/// Load the sidecar's model by transcribing a short silent clip, so the
+/// first real transcription is not slowed down by it. Waits up to
+/// [`sidecar::WARMUP_TIMEOUT`]; meant to be called right after
+/// `start_sidecar`.
+#[tauri::command]
+async fn warm_up_sidecar(app: tauri::AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SidecarState>();
+        let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+        mgr.warm_up(&transcribe::warmup_request()?, sidecar::WARMUP_TIMEOUT)
+            .map(drop)
+    })
+    .await
+    .map_err(|e| format!("Warm-up worker failed: {e}"))?
+}
+
+/// Time the sidecar transcribing a fixed 5-second tone, warming it up
+/// first if needed. The real-time factor is comparable across models and
+/// machines.
+#[tauri::command]
+async fn benchmark_transcription(app: tauri::AppHandle) -> Result<BenchmarkResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SidecarState>();
+        let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+        transcribe::benchmark_transcription(&mut mgr)
+    })
+    .await
+    .map_err(|e| format!("Benchmark worker failed: {e}"))?
+}
+
+/// Transcribe a recording once, reporting how long reading, encoding and
+/// the sidecar round trip each took rather than the transcript.
+#[tauri::command]
+async fn timed_transcribe(
+    path: String,
+    compress: Option<Compression>,
+    language: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<PipelineTiming, String> {
+    validate_language(language.as_deref())?;
+    let initial_prompt = resolve_initial_prompt(&app, None)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<SidecarState>();
+        let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+        let timing = mgr.with_language(language, |mgr| {
+            transcribe::timed_transcribe(
+                mgr,
+                &PathBuf::from(&path),
+                initial_prompt.as_deref(),
+                compress,
+            )
+        });
+        note_transcription_error(&app, &path, timing)
+    })
+    .await
+    .map_err(|e| format!("Timing worker failed: {e}"))?
+}
+
+/// List the model profiles the sidecar can switch between. Answered from
+/// the cached list unless `refresh` is set or nothing is cached yet.
+#[tauri::command]
+fn list_sidecar_models(
+    refresh: Option<bool>,
+    state: tauri::State<'_, SidecarState>,
+    catalog: tauri::State<'_, ModelCatalogState>,
+) -> Result<Vec<ModelInfo>, String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    let mut catalog = catalog
+        .0
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    catalog.list(&mut mgr, refresh.unwrap_or(false))
+}
+
+/// The last model list the sidecar reported, or `null` if it has not been
+/// asked since the list was last invalidated. Never contacts the sidecar.
+#[tauri::command]
+fn cached_sidecar_models(
+    catalog: tauri::State<'_, ModelCatalogState>,
+) -> Result<Option<Vec<ModelInfo>>, String> {
+    let catalog = catalog
+        .0
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    Ok(catalog.cached().map(<[ModelInfo]>::to_vec))
+}
+
+/// Switch the sidecar's active model to `model_id`. Fails with an
+/// "Unknown model" error for an id the sidecar does not have.
+#[tauri::command]
+fn set_sidecar_model(
+    model_id: String,
+    state: tauri::State<'_, SidecarState>,
+    catalog: tauri::State<'_, ModelCatalogState>,
+) -> Result<(), String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    let mut catalog = catalog
+        .0
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    catalog.set_model(&mut mgr, &model_id)
+}
+
+/// Move the sidecar's model to `device` (`"cpu"`, `"cuda"` or `"auto"`)
+/// without restarting it, returning the device now in use. Fails with a
+/// "Compute device unavailable" or "Compute device out of memory" error
+/// when the backend reports either.
+#[tauri::command]
+fn set_sidecar_device(
+    device: ComputeDevice,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<String, String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.set_device(device)
+}
+
+/// Optional features the running sidecar supports, asked once per process.
+/// An older backend that cannot answer gets a conservative default set.
+#[tauri::command]
+fn sidecar_capabilities(
+    state: tauri::State<'_, SidecarState>,
+) -> Result<SidecarCapabilities, String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.capabilities()
+}
+
+/// Whether the backend can transcribe audio chunk by chunk. `false` when it
+/// does not say, so the UI can fall back to whole-file transcription.
+#[tauri::command]
+fn sidecar_supports_streaming(state: tauri::State<'_, SidecarState>) -> Result<bool, String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    Ok(mgr.supports_streaming())
+}
+
+/// Versions of the app, Tauri, and the backend, plus the OS and CPU
+/// architecture, for bug reports. The backend fields are `null` when the
+/// sidecar is not running or cannot say.
+#[tauri::command]
+fn about_info(state: tauri::State<'_, SidecarState>) -> Result<AboutInfo, String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    let backend = about::query_backend_version(&mut mgr);
+    Ok(about::about_info(tauri::VERSION, backend))
+}
+
+/// The most recent failed sidecar exchange as `{ message, age_ms }`, or
+/// `null`. Stays set until an exchange succeeds or it is cleared with
+/// `clear_sidecar_error`, so the UI can show an unhealthy badge.
+#[tauri::command]
+fn sidecar_last_error(state: tauri::State<'_, SidecarState>) -> Result<Option<Value>, String> {
+    let mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    Ok(mgr.last_error().map(|(at, message)| {
+        serde_json::json!({
+            "message": message,
+            "age_ms": at.elapsed().as_millis() as u64,
+        })
+    }))
+}
+
+/// Acknowledge the sidecar's last error so `sidecar_last_error` reports
+/// `null` again.
+#[tauri::command]
+fn clear_sidecar_error(state: tauri::State<'_, SidecarState>) -> Result<(), String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.clear_error();
+    Ok(())
+}
+
+/// Send the sidecar a keepalive after every `interval_ms` of idleness to
+/// keep the model warm, or stop with `null` (the default).
+#[tauri::command]
+fn set_sidecar_keepalive(
+    interval_ms: Option<u64>,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<(), String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.set_keepalive(interval_ms.map(Duration::from_millis));
+    Ok(())
+}
+
+/// Restart the sidecar and resend the message when it dies mid-request, up
+/// to `max_retries` times, waiting `initial_backoff_ms` before the first
+/// restart and doubling up to `max_backoff_ms`, each wait moved randomly by
+/// up to `jitter` (0 to 1) of itself. `max_retries` of 0 turns this off.
+#[tauri::command]
+fn set_reconnect_policy(
+    max_retries: u32,
+    initial_backoff_ms: u64,
+    max_backoff_ms: u64,
+    jitter: f64,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<(), String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.set_reconnect_policy(ReconnectPolicy {
+        max_retries,
+        initial_backoff: Duration::from_millis(initial_backoff_ms),
+        max_backoff: Duration::from_millis(max_backoff_ms),
+        jitter,
+    })
+}
+
+/// How many times the sidecar has been restarted, automatically or not,
+/// as `{ total_restarts, last_restart_at }` (milliseconds since the Unix
+/// epoch, or `null`).
+#[tauri::command]
+fn sidecar_reconnect_stats(
+    state: tauri::State<'_, SidecarState>,
+) -> Result<ReconnectStats, String> {
+    let mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    Ok(mgr.reconnect_stats())
+}
+
+/// Whether the sidecar is running and has been warmed up.
+#[tauri::command]
+fn sidecar_ready(state: tauri::State<'_, SidecarState>) -> Result<bool, String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    Ok(mgr.is_ready())
+}
+
+/// Report the sidecar's PID and memory/CPU usage (`running: false` with null
+/// stats when it is not running), the compute device set with
+/// `set_sidecar_device`, if any, and the memory limit it was started with.
+#[tauri::command]
+fn sidecar_process_info(state: tauri::State<'_, SidecarState>) -> Result<ProcessInfo, String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    Ok(mgr.process_info())
+}
+
+/// Turn recording of sidecar request/response pairs on or off. Keeps the last
+/// `capacity` exchanges (default 50) while enabled.
+#[tauri::command]
+fn set_sidecar_trace(
+    enabled: bool,
+    capacity: Option<usize>,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<(), String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.set_trace(enabled, capacity.unwrap_or(DEFAULT_TRACE_CAPACITY));
+    Ok(())
+}
+
+/// Emit each line the sidecar writes to stderr as `sidecar://log` with
+/// `{ line }`, starting with the most recent buffered lines. Replaces any
+/// stream already running; stopping the sidecar ends it.
+#[tauri::command]
+fn start_sidecar_log_stream(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<(), String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.set_log_listener(Some(Box::new(move |line| {
+        let _ = app.emit("sidecar://log", serde_json::json!({ "line": line }));
+    })))
+}
+
+/// Stop emitting `sidecar://log` events.
+#[tauri::command]
+fn stop_sidecar_log_stream(state: tauri::State<'_, SidecarState>) -> Result<(), String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.set_log_listener(None)
+}
+
+/// Turn the sidecar log file on or off. While on, the sidecar's stderr (and
+/// any traced exchanges) is written to `sidecar.log` in the app log dir,
+/// rotating to `sidecar.log.1` past `max_bytes` (default 1 MiB). Returns the
+/// log file path when enabled.
+#[tauri::command]
+fn set_sidecar_log(
+    enabled: bool,
+    max_bytes: Option<u64>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<Option<String>, String> {
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    if !enabled {
+        mgr.set_log_file(None, 0)?;
+        return Ok(None);
+    }
+    let path = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve app log directory: {e}"))?
+        .join(SIDECAR_LOG_FILE_NAME);
+    mgr.set_log_file(
+        Some(&path),
+        max_bytes.unwrap_or(log_file::DEFAULT_LOG_MAX_BYTES),
+    )?;
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+/// Return the recorded sidecar exchanges, oldest first.
+#[tauri::command]
+fn sidecar_trace(state: tauri::State<'_, SidecarState>) -> Result<Vec<TraceEntry>, String> {
+    let mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    Ok(mgr.trace())
+}
+
+/// Transcribe a recorded WAV file by sending it to the sidecar as a single
+/// `transcribe_chunk` message.
+///
+/// When `compress` is set, the PCM payload is compressed before base64
+/// encoding and the message is flagged so the sidecar decompresses it.
+///
+/// With `skip_if_silent_dbfs`, a recording whose peak stays below that level
+/// is not sent; an empty transcription with `"silent": true` is returned
+/// instead.
+///
+/// Recordings longer than `max_chunk_seconds`, or rejected by the sidecar as
+/// too large, are sent in overlapping pieces and the texts joined.
+///
+/// Without an `initial_prompt`, the saved default prompt is used, and
+/// without a `language`, the one set with `set_sidecar_language`; this holds
+/// for every transcription command.
+#[tauri::command]
+fn transcribe_recording(
+    path: String,
+    initial_prompt: Option<String>,
+    compress: Option<Compression>,
+    skip_if_silent_dbfs: Option<f32>,
+    max_chunk_seconds: Option<f64>,
+    language: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<Value, String> {
+    validate_language(language.as_deref())?;
+    if let Some(threshold) = skip_if_silent_dbfs {
+        if audio::wav::check_silence(&PathBuf::from(&path), threshold)?.silent {
+            return Ok(serde_json::json!({
+                "type": "transcription",
+                "text": "",
+                "segments": [],
+                "silent": true,
+            }));
+        }
+    }
+    let initial_prompt = resolve_initial_prompt(&app, initial_prompt)?;
+    let state = app.state::<SidecarState>();
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    let response = mgr.with_language(language, |mgr| {
+        transcribe::transcribe_file_chunked(
+            mgr,
+            &PathBuf::from(&path),
+            initial_prompt.as_deref(),
+            compress,
+            max_chunk_seconds,
+        )
+    });
+    note_transcription(&app, &path, response)
+}
+
+/// Transcribe an audio file that may not be a WAV, e.g. an MP3 or M4A
+/// dragged into the app. Other formats are decoded to a temporary 16 kHz
+/// mono WAV first, which is deleted afterwards.
+/// Unrecognised files fail with an "Unsupported audio format" error.
+#[tauri::command]
+fn transcribe_media_file(
+    path: String,
+    initial_prompt: Option<String>,
+    compress: Option<Compression>,
+    language: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<Value, String> {
+    validate_language(language.as_deref())?;
+    let initial_prompt = resolve_initial_prompt(&app, initial_prompt)?;
+    let response =
+        media::prepare_media(&PathBuf::from(&path), &media::media_temp_dir()).and_then(|input| {
+            let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+            mgr.with_language(language, |mgr| {
+                transcribe::transcribe_file(mgr, input.path(), initial_prompt.as_deref(), compress)
+            })
+        });
+    note_transcription(&app, &path, response)
+}
+
+/// [`transcribe_recording`] for a backend that reports progress on long
+/// files: `transcribe://progress` is emitted with `{ fraction }` for each
+/// progress line before the final response is returned.
+#[tauri::command]
+fn transcribe_recording_with_progress(
+    path: String,
+    initial_prompt: Option<String>,
+    compress: Option<Compression>,
+    language: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<Value, String> {
+    validate_language(language.as_deref())?;
+    let initial_prompt = resolve_initial_prompt(&app, initial_prompt)?;
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    let response = mgr.with_language(language, |mgr| {
+        transcribe::transcribe_with_progress(
+            mgr,
+            &PathBuf::from(&path),
+            initial_prompt.as_deref(),
+            compress,
+            &mut |fraction| {
+                let _ = app.emit(
+                    "transcribe://progress",
+                    serde_json::json!({ "fraction": fraction }),
+                );
+            },
+        )
+    });
+    note_transcription(&app, &path, response)
+}
+
+/// Write `transcript`'s timestamped segments to `output` as an SRT or
+/// WebVTT subtitle file.
+#[tauri::command]
+fn export_subtitles(
+    transcript: Transcript,
+    format: SubtitleFormat,
+    output: String,
+) -> Result<(), String> {
+    subtitles::write_subtitles(&transcript, format, &PathBuf::from(output))
+}
+
+/// Re-transcribe an existing recording, usually with a different
+/// `initial_prompt`, without touching the audio.
+///
+/// Unless `use_cache` is `false`, a file (unchanged since) that was already
+/// re-transcribed with the same prompt returns the cached transcript.
+#[tauri::command]
+fn retranscribe(
+    path: String,
+    initial_prompt: Option<String>,
+    compress: Option<Compression>,
+    use_cache: Option<bool>,
+    language: Option<String>,
+    app: tauri::AppHandle,
+    cache: tauri::State<'_, TranscriptCacheState>,
+) -> Result<Value, String> {
+    validate_language(language.as_deref())?;
+    let initial_prompt = resolve_initial_prompt(&app, initial_prompt)?;
+    let state = app.state::<SidecarState>();
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    let mut cache = cache.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    let response = mgr.with_language(language, |mgr| {
+        transcribe::retranscribe(
+            mgr,
+            use_cache.unwrap_or(true).then_some(&mut *cache),
+            &PathBuf::from(&path),
+            initial_prompt.as_deref(),
+            compress,
+        )
+    });
+    note_transcription(&app, &path, response)
+}
+
+/// Transcribe a recorded WAV file incrementally, in
+/// [`transcribe::STREAM_CHUNK_DURATION`] chunks.
+///
+/// `transcribe://partial` is emitted with `{ text }` (the cumulative
+/// transcript) after each chunk, then `transcribe://final` with the full
+/// text, which is also returned.
+#[tauri::command]
+fn stream_transcribe_file(
+    path: String,
+    initial_prompt: Option<String>,
+    language: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<String, String> {
+    validate_language(language.as_deref())?;
+    let initial_prompt = resolve_initial_prompt(&app, initial_prompt)?;
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.require_streaming()?;
+    let text = mgr.with_language(language, |mgr| {
+        transcribe::stream_transcribe_file(
+            mgr,
+            &PathBuf::from(&path),
+            initial_prompt.as_deref(),
+            transcribe::STREAM_CHUNK_DURATION,
+            &mut |text| {
+                let _ = app.emit("transcribe://partial", serde_json::json!({ "text": text }));
+            },
+        )
+    });
+    let text = note_transcription_error(&app, &path, text)?;
+    let _ = app.emit("transcribe://final", serde_json::json!({ "text": text }));
+    Ok(text)
+}
+
+/// Transcribe a recorded WAV file incrementally, like
+/// `stream_transcribe_file`, writing the transcript to `transcript_out` as
+/// it goes so closing the app mid-way loses nothing already transcribed.
+///
+/// Segments are appended to `<transcript_out>.partial` as each chunk
+/// finishes; the finished transcript (`{ text, segments }`) is then moved to
+/// `transcript_out`, which is also returned. `transcribe://partial` is
+/// emitted with `{ text }` after each chunk.
+#[tauri::command]
+fn transcribe_to_file_streaming(
+    path: String,
+    transcript_out: String,
+    initial_prompt: Option<String>,
+    language: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+) -> Result<Transcript, String> {
+    validate_language(language.as_deref())?;
+    let initial_prompt = resolve_initial_prompt(&app, initial_prompt)?;
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    mgr.require_streaming()?;
+    let transcript = mgr.with_language(language, |mgr| {
+        transcribe::stream_transcribe_to_file(
+            mgr,
+            &PathBuf::from(&path),
+            &PathBuf::from(transcript_out),
+            initial_prompt.as_deref(),
+            transcribe::STREAM_CHUNK_DURATION,
+            &mut |text| {
+                let _ = app.emit("transcribe://partial", serde_json::json!({ "text": text }));
+            },
+        )
+    });
+    note_transcription_error(&app, &path, transcript)
+}
+
+/// Begin a streaming transcription session, replacing any current one.
+///
+/// Chunks sent with `transcribe_session_chunk` share `initial_prompt`, and
+/// the session survives the sidecar crashing mid-stream. Fails up front if
+/// the backend cannot stream.
+#[tauri::command]
+fn start_transcription_session(
+    initial_prompt: Option<String>,
+    compress: Option<Compression>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+    session: tauri::State<'_, TranscriptionSessionState>,
+) -> Result<(), String> {
+    let initial_prompt = resolve_initial_prompt(&app, initial_prompt)?;
+    state
+        .0
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?
+        .require_streaming()?;
+    let mut session = session
+        .0
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    *session = Some(TranscriptionSession::new(initial_prompt, compress));
+    Ok(())
+}
+
+/// Transcribe the next chunk (a WAV file) of the current session.
+///
+/// If the sidecar crashed, it is restarted and the session's prompt and
+/// recent transcript are replayed to it before the chunk is retried;
+/// `transcribe://resumed` is emitted with `{ transcript_tail }` when that
+/// happens.
+#[tauri::command]
+fn transcribe_session_chunk(
+    path: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SidecarState>,
+    session: tauri::State<'_, TranscriptionSessionState>,
+) -> Result<SidecarResponse, String> {
+    let mut session = session
+        .0
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    let session = session
+        .as_mut()
+        .ok_or_else(|| "No transcription session in progress".to_string())?;
+    let samples = note_transcription_error(
+        &app,
+        &path,
+        transcribe::read_samples_16k_mono(&PathBuf::from(&path)),
+    )?;
+    let mut mgr = state.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    let response = session.submit_chunk(&mut mgr, &samples, &mut |tail| {
+        let _ = app.emit(
+            "transcribe://resumed",
+            serde_json::json!({ "transcript_tail": tail }),
+        );
+    });
+    if let Ok(SidecarResponse::Error { message }) = &response {
+        if let Ok(mut log) = app.state::<TranscriptionErrorState>().0.lock() {
+            log.record(&path, TranscriptionErrorKind::Backend, message);
+        }
+    }
+    note_transcription_error(&app, &path, response)
+}
+
+/// End the current transcription session, if any.
+#[tauri::command]
+fn end_transcription_session(
+    session: tauri::State<'_, TranscriptionSessionState>,
+) -> Result<(), String> {
+    session
+        .0
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?
+        .take();
+    Ok(())
+}
+
 // ---------------------------------------------------------------------------
 // Audio commands
 // ---------------------------------------------------------------------------
 
-/// List all available audio input device names.
+/// List the names of the audio input devices on the allow-list.
+///
+/// Gives up after `timeout_ms` (default 3 s) so a hanging audio host cannot
+/// freeze the device picker.
+#[tauri::command]
+fn list_audio_devices(
+    timeout_ms: Option<u64>,
+    state: tauri::State<'_, AudioState>,
+) -> Result<Vec<String>, String> {
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(devices::DEFAULT_ENUMERATION_TIMEOUT);
+    let devs = devices::list_input_devices_with_timeout(timeout)?;
+    let allowed = state.manager.allowed_devices()?;
+    Ok(allowed.filter(devs).into_iter().map(|d| d.name).collect())
+}
+
+/// A capture format (`{ suggested_sample_rate, suggested_channels,
+/// default_device_needs_conversion }`) most allowed input devices record
+/// natively, preferring 16 kHz mono for speech.
+#[tauri::command]
+fn suggest_capture_config(
+    timeout_ms: Option<u64>,
+    state: tauri::State<'_, AudioState>,
+) -> Result<devices::CaptureSuggestion, String> {
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(devices::DEFAULT_ENUMERATION_TIMEOUT);
+    let allowed = state.manager.allowed_devices()?;
+    devices::suggest_host_capture_config_with_timeout(allowed, timeout)
+}
+
+/// Whether `device_name` (or the default input device) can be captured from
+/// in exclusive mode; `false` on platforms without one. Set the capture
+/// config's `exclusive` to ask for it.
+#[tauri::command]
+fn supports_exclusive_capture(
+    device_name: Option<String>,
+    state: tauri::State<'_, AudioState>,
+) -> Result<bool, String> {
+    let allowed = state.manager.allowed_devices()?;
+    let device = devices::find_input_device(device_name.as_deref(), &allowed)?;
+    Ok(devices::exclusive_support(&device).unwrap_or(false))
+}
+
+/// Return the device allow-list (empty when every device is allowed).
+#[tauri::command]
+fn get_device_allow_list(state: tauri::State<'_, AudioState>) -> Result<DeviceAllowList, String> {
+    state.manager.allowed_devices()
+}
+
+/// Restrict recording to devices matching `patterns` (exact names, or globs
+/// using `*` and `?`). An empty list allows every device. Rejected while a
+/// recording is running.
+#[tauri::command]
+fn set_device_allow_list(
+    patterns: Vec<String>,
+    state: tauri::State<'_, AudioState>,
+) -> Result<(), String> {
+    state
+        .manager
+        .set_allowed_devices(DeviceAllowList::new(patterns))
+}
+
+/// Whether the app may use the microphone: `granted`, `denied`,
+/// `undetermined`, or `unknown` where the platform has no permission API.
 #[tauri::command]
-fn list_audio_devices() -> Result<Vec<String>, String> {
-    let devs = devices::list_input_devices()?;
-    Ok(devs.into_iter().map(|d| d.name).collect())
+fn microphone_permission_status() -> MicrophonePermission {
+    microphone_permission()
 }
 
 /// Start recording audio from the specified device (or the default device).
 ///
-/// Returns the file path of the WAV file being recorded.
+/// Uses the stored capture config unless `config` overrides it for this
+/// recording. Returns the file path of the WAV file being recorded.
+///
+/// With `lead_in_ms`, an `audio://countdown` event carrying
+/// `{ remaining_secs }` is emitted each second before capture begins. The
+/// command returns immediately; the countdown can be aborted with
+/// `cancel_audio_recording`.
+///
+/// With `ephemeral`, the WAV is written to a temp directory and deleted a
+/// while after `stop_audio_recording` unless `keep_recording` is called.
+///
+/// With `max_duration_ms`, capture stops by itself after that long; call
+/// `stop_audio_recording` to collect the files. Likewise with the config's
+/// `max_file_bytes`, just before the file would outgrow it, emitting
+/// `audio://size-limit-reached` with `{ bytes }`.
+///
+/// If the first second of audio is exact silence, `audio://possibly-muted`
+/// is emitted so the UI can point at the mute switch or mic permission.
+/// Once the recording reaches the config's `soft_duration_warn_ms`,
+/// `audio://duration-warning` is emitted once with `{ elapsed_ms }`; unlike
+/// `max_duration_ms` it does not stop capture. With the first buffer,
+/// `audio://input-latency` is emitted with `{ latency_ms, estimated }` (see
+/// `audio_input_latency`). If the config asks for `exclusive` mode and the
+/// device cannot be opened that way, `audio://exclusive-unavailable` is
+/// emitted and the recording shares the device.
 #[tauri::command]
 fn start_audio_recording(
     device_name: Option<String>,
+    config: Option<AudioCaptureConfig>,
+    lead_in_ms: Option<u64>,
+    ephemeral: Option<bool>,
+    max_duration_ms: Option<u64>,
+    app: tauri::AppHandle,
     state: tauri::State<'_, AudioState>,
 ) -> Result<String, String> {
     let recordings_dir = state
         .recordings_dir
         .lock()
         .map_err(|e| format!("Lock poisoned: {e}"))?;
-    state.manager.start(device_name.as_deref(), &recordings_dir)
+    let config = match config {
+        Some(config) => config,
+        None => state
+            .config
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?
+            .clone(),
+    };
+    let events = start_options_with_events(&app);
+    let lead_in = lead_in_ms.filter(|&ms| ms > 0).map(|ms| {
+        LeadIn::new(Duration::from_millis(ms), move |remaining_secs| {
+            let _ = app.emit(
+                "audio://countdown",
+                serde_json::json!({ "remaining_secs": remaining_secs }),
+            );
+        })
+    });
+    state.manager.start(
+        device_name.as_deref(),
+        &recordings_dir,
+        &config,
+        StartOptions {
+            lead_in,
+            ephemeral: ephemeral.unwrap_or(false),
+            max_duration: max_duration_ms.map(Duration::from_millis),
+            ..events
+        },
+    )
 }
 
-/// Stop the current audio recording. Returns the path to the finalized WAV file.
+/// Record the mic (`mic_device`, or the default device) on the left channel
+/// and `loopback_device`, an input carrying system audio, on the right of
+/// one stereo WAV at the capture config's sample rate. Returns the file
+/// path; `stop_audio_recording` finalizes it like any recording.
 #[tauri::command]
-fn stop_audio_recording(state: tauri::State<'_, AudioState>) -> Result<String, String> {
-    state.manager.stop()
+fn start_dual_capture(
+    mic_device: Option<String>,
+    loopback_device: String,
+    config: Option<AudioCaptureConfig>,
+    max_duration_ms: Option<u64>,
+    state: tauri::State<'_, AudioState>,
+) -> Result<String, String> {
+    let recordings_dir = state
+        .recordings_dir
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    let config = match config {
+        Some(config) => config,
+        None => state
+            .config
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?
+            .clone(),
+    };
+    state.manager.start_dual(
+        mic_device.as_deref(),
+        &loopback_device,
+        &recordings_dir,
+        &config,
+        StartOptions {
+            max_duration: max_duration_ms.map(Duration::from_millis),
+            ..Default::default()
+        },
+    )
+}
+
+/// [`StartOptions`] whose callbacks emit `audio://possibly-muted`,
+/// `audio://duration-warning`, `audio://input-latency`,
+/// `audio://exclusive-unavailable` and `audio://size-limit-reached`.
+fn start_options_with_events(app: &tauri::AppHandle) -> StartOptions {
+    let muted_app = app.clone();
+    let warning_app = app.clone();
+    let latency_app = app.clone();
+    let exclusive_app = app.clone();
+    let size_app = app.clone();
+    StartOptions {
+        on_possibly_muted: Some(Box::new(move || {
+            let _ = muted_app.emit("audio://possibly-muted", ());
+        })),
+        on_duration_warning: Some(Box::new(move |elapsed| {
+            let _ = warning_app.emit(
+                "audio://duration-warning",
+                serde_json::json!({ "elapsed_ms": elapsed.as_millis() as u64 }),
+            );
+        })),
+        on_input_latency: Some(Box::new(move |latency| {
+            let _ = latency_app.emit("audio://input-latency", latency);
+        })),
+        on_exclusive_fallback: Some(Box::new(move || {
+            let _ = exclusive_app.emit("audio://exclusive-unavailable", ());
+        })),
+        on_size_limit_reached: Some(Box::new(move |bytes| {
+            let _ = size_app.emit(
+                "audio://size-limit-reached",
+                serde_json::json!({ "bytes": bytes }),
+            );
+        })),
+        ..Default::default()
+    }
+}
+
+/// Arm a recording from `device_name` (or the default device) to start at
+/// `start_at_unix` (seconds since the Unix epoch) with the stored capture
+/// config, stopping by itself after `max_duration_ms` if given.
+///
+/// When the time comes, `audio://scheduled-started` is emitted with
+/// `{ path }`, or `audio://scheduled-failed` with `{ error }` if the
+/// recording cannot start (e.g. one was started by hand meanwhile). A start
+/// time that has already passed is rejected rather than started late. Fails
+/// if a recording is in progress or already scheduled.
+#[tauri::command]
+fn schedule_recording(
+    start_at_unix: u64,
+    device_name: Option<String>,
+    max_duration_ms: Option<u32>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AudioState>,
+) -> Result<(), String> {
+    state.manager.ensure_idle("schedule a recording")?;
+    let start_at = UNIX_EPOCH + Duration::from_secs(start_at_unix);
+    let max_duration = max_duration_ms.map(|ms| Duration::from_millis(u64::from(ms)));
+    state.scheduler.schedule(start_at, move || {
+        let _ = match start_scheduled(&app, device_name.as_deref(), max_duration) {
+            Ok(path) => app.emit(
+                "audio://scheduled-started",
+                serde_json::json!({ "path": path }),
+            ),
+            Err(error) => app.emit(
+                "audio://scheduled-failed",
+                serde_json::json!({ "error": error }),
+            ),
+        };
+    })
+}
+
+/// Start the recording armed by `schedule_recording` with the stored
+/// recordings directory and capture config.
+fn start_scheduled(
+    app: &tauri::AppHandle,
+    device_name: Option<&str>,
+    max_duration: Option<Duration>,
+) -> Result<String, String> {
+    let state = app.state::<AudioState>();
+    let recordings_dir = state
+        .recordings_dir
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?
+        .clone();
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?
+        .clone();
+    state.manager.start(
+        device_name,
+        &recordings_dir,
+        &config,
+        StartOptions {
+            max_duration,
+            ..start_options_with_events(app)
+        },
+    )
+}
+
+/// Start time (seconds since the Unix epoch) of the recording armed by
+/// `schedule_recording`, or `null` if none is waiting.
+#[tauri::command]
+fn scheduled_recording(state: tauri::State<'_, AudioState>) -> Result<Option<u64>, String> {
+    state.scheduler.scheduled_at()
+}
+
+/// Cancel the recording armed by `schedule_recording`. Returns whether one
+/// was scheduled.
+#[tauri::command]
+fn cancel_scheduled_recording(state: tauri::State<'_, AudioState>) -> Result<bool, String> {
+    state.scheduler.cancel()
+}
+
+/// Record exactly `duration_ms` from the specified device (or the default
+/// device) with the stored capture config, and return the finalized WAV
+/// path(s) — one unless the clip spans several segments.
+///
+/// Runs on a worker thread; `audio://clip-progress` events carrying
+/// `{ elapsed_ms, duration_ms }` are emitted while recording. Fails if a
+/// recording is already in progress. The `max_total_bytes` cap is applied
+/// afterwards as for `stop_audio_recording`.
+#[tauri::command]
+async fn record_clip(
+    device_name: Option<String>,
+    duration_ms: u32,
+    app: tauri::AppHandle,
+) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AudioState>();
+        let recordings_dir = state
+            .recordings_dir
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?
+            .clone();
+        let config = state
+            .config
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?
+            .clone();
+        let paths = state.manager.record_clip(
+            device_name.as_deref(),
+            &recordings_dir,
+            &config,
+            Duration::from_millis(u64::from(duration_ms)),
+            |elapsed| {
+                let _ = app.emit(
+                    "audio://clip-progress",
+                    serde_json::json!({
+                        "elapsed_ms": elapsed.as_millis() as u64,
+                        "duration_ms": duration_ms,
+                    }),
+                );
+            },
+        )?;
+        enforce_recordings_cap(&app, &state, &paths);
+        Ok(paths)
+    })
+    .await
+    .map_err(|e| format!("Clip worker failed: {e}"))?
+}
+
+/// Record `duration_ms` from a device into memory and return it as a
+/// base64-encoded WAV file for the frontend to play back, so the device can
+/// be tested before recording with it. Nothing is written to disk.
+#[tauri::command]
+async fn capture_preview(
+    device_name: Option<String>,
+    duration_ms: u32,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AudioState>();
+        let config = state
+            .config
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?
+            .clone();
+        let wav = state.manager.capture_preview(
+            device_name.as_deref(),
+            &config,
+            Duration::from_millis(u64::from(duration_ms)),
+        )?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(wav))
+    })
+    .await
+    .map_err(|e| format!("Preview worker failed: {e}"))?
+}
+
+/// Briefly open a device (or the default one) to tell whether another app
+/// holds it, so the UI can warn before recording. Returns `{ status }`,
+/// one of `available`, `in_use` or `error` (with a `message`).
+#[tauri::command]
+async fn check_device_availability(
+    device_name: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<DeviceAvailability, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AudioState>();
+        let config = state
+            .config
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?
+            .clone();
+        state
+            .manager
+            .check_device_availability(device_name.as_deref(), &config)
+    })
+    .await
+    .map_err(|e| format!("Availability check failed: {e}"))?
+}
+
+/// Move a stopped ephemeral recording into the recordings directory so it is
+/// not deleted. Returns the new path.
+#[tauri::command]
+fn keep_recording(path: String, state: tauri::State<'_, AudioState>) -> Result<String, String> {
+    let recordings_dir = state
+        .recordings_dir
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    state
+        .manager
+        .keep_recording(&PathBuf::from(path), &recordings_dir)
+}
+
+/// Rename the recording at `old_path` inside the recordings directory to
+/// `new_name`, keeping its extension and renaming its `.txt`/`.json`
+/// transcripts with it. Returns the new path.
+#[tauri::command]
+fn rename_recording(
+    old_path: String,
+    new_name: String,
+    state: tauri::State<'_, AudioState>,
+) -> Result<String, String> {
+    let recordings_dir = state
+        .recordings_dir
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    recordings::rename_recording(&recordings_dir, &PathBuf::from(old_path), &new_name)?
+        .to_str()
+        .map(String::from)
+        .ok_or_else(|| "Recording path is not valid UTF-8".into())
+}
+
+/// Join the recordings at `paths`, in order, into one new WAV at `output`
+/// (relative to the recordings directory unless absolute). Every input
+/// must be in the recordings directory and share one WAV format. Returns
+/// the path written.
+#[tauri::command]
+fn concatenate_recordings(
+    paths: Vec<String>,
+    output: String,
+    state: tauri::State<'_, AudioState>,
+) -> Result<String, String> {
+    let recordings_dir = state
+        .recordings_dir
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    recordings::concatenate_recordings(&recordings_dir, &paths, &PathBuf::from(output))?
+        .to_str()
+        .map(String::from)
+        .ok_or_else(|| "Recording path is not valid UTF-8".into())
+}
+
+/// Report whether the capture engine is idle, counting down, recording, or
+/// paused.
+#[tauri::command]
+fn audio_recording_status(state: tauri::State<'_, AudioState>) -> Result<RecordingStatus, String> {
+    state.manager.status()
+}
+
+/// Everything the UI shows about the capture engine in one poll: whether
+/// it is idle, armed (counting down, or a recording is scheduled),
+/// recording, paused, or finalizing a stopped recording, with the device,
+/// time recorded, and file of the current recording.
+#[tauri::command]
+fn audio_status(state: tauri::State<'_, AudioState>) -> Result<AudioStatus, String> {
+    let mut status = state.manager.audio_status()?;
+    if status.state == CaptureState::Idle && state.scheduler.scheduled_at()?.is_some() {
+        status.state = CaptureState::Armed;
+    }
+    Ok(status)
+}
+
+/// Play a recording through `output_device` (or the default output
+/// device) to check it without leaving the app, converting it to the
+/// device's rate and channel count. Returns once playback has started.
+#[tauri::command]
+fn play_recording(
+    path: String,
+    output_device: Option<String>,
+    playback: tauri::State<'_, PlaybackState>,
+) -> Result<(), String> {
+    playback.0.play(&PathBuf::from(path), output_device)
+}
+
+/// Stop the recording started with `play_recording`.
+#[tauri::command]
+fn stop_playback(playback: tauri::State<'_, PlaybackState>) -> Result<(), String> {
+    playback.0.stop()
+}
+
+/// Report whether a recording is playing; back to `idle` once it ends.
+#[tauri::command]
+fn playback_status(playback: tauri::State<'_, PlaybackState>) -> Result<PlaybackStatus, String> {
+    playback.0.status()
+}
+
+/// Peak level (0.0 to 1.0) and clipped-sample count of the current
+/// recording since it started or since `reset_recording_meters`.
+#[tauri::command]
+fn recording_meters(state: tauri::State<'_, AudioState>) -> Result<Meters, String> {
+    state.manager.meters()
+}
+
+/// Zero the current recording's peak-hold and clip meters so they show
+/// recent activity. Recording carries on, and the totals reported on stop
+/// still cover the whole recording. Fails when no recording is running.
+#[tauri::command]
+fn reset_recording_meters(state: tauri::State<'_, AudioState>) -> Result<(), String> {
+    state.manager.reset_meters()
+}
+
+/// Start streaming the audio being recorded to `{ url }` on loopback as
+/// Server-Sent Events, each a JSON array of mono 16-bit samples at 8 kHz,
+/// for drawing a live waveform. Returns the running bridge's URL if it is
+/// already started.
+#[tauri::command]
+fn start_audio_bridge(
+    state: tauri::State<'_, AudioState>,
+    bridge: tauri::State<'_, AudioBridgeState>,
+) -> Result<AudioBridgeInfo, String> {
+    let mut bridge = bridge.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    if let Some(running) = bridge.as_ref() {
+        return Ok(running.info());
+    }
+    let started = AudioBridge::start()?;
+    state.manager.set_frame_tap(Some(started.broadcast()))?;
+    Ok(bridge.insert(started).info())
+}
+
+/// Stop the audio bridge, disconnecting its clients. A no-op if it is not
+/// running.
+#[tauri::command]
+fn stop_audio_bridge(
+    state: tauri::State<'_, AudioState>,
+    bridge: tauri::State<'_, AudioBridgeState>,
+) -> Result<(), String> {
+    let mut bridge = bridge.0.lock().map_err(|e| format!("Lock poisoned: {e}"))?;
+    state.manager.set_frame_tap(None)?;
+    if let Some(running) = bridge.take() {
+        running.stop();
+    }
+    Ok(())
+}
+
+/// Input latency of the current recording as `{ latency_ms, estimated }`,
+/// or `null` when idle or before audio arrives. `estimated` is set when the
+/// audio backend gave no figure and it was derived from the buffer size and
+/// sample rate instead.
+#[tauri::command]
+fn audio_input_latency(
+    state: tauri::State<'_, AudioState>,
+) -> Result<Option<InputLatency>, String> {
+    state.manager.current_input_latency_ms()
+}
+
+/// Abort a lead-in countdown started by `start_audio_recording` before any
+/// file is created.
+#[tauri::command]
+fn cancel_audio_recording(state: tauri::State<'_, AudioState>) -> Result<(), String> {
+    state.manager.cancel()
+}
+
+/// Pause the current audio recording.
+#[tauri::command]
+fn pause_audio_recording(state: tauri::State<'_, AudioState>) -> Result<(), String> {
+    state.manager.pause()
+}
+
+/// Resume a paused audio recording. The capture config's `pause_behavior`
+/// decides whether the pause is spliced out or written as silence.
+#[tauri::command]
+fn resume_audio_recording(state: tauri::State<'_, AudioState>) -> Result<(), String> {
+    state.manager.resume()
+}
+
+/// Move the current recording to another input device without closing the
+/// file. The old device keeps recording if the new one fails to open.
+#[tauri::command]
+fn switch_device(new_device: String, state: tauri::State<'_, AudioState>) -> Result<(), String> {
+    state.manager.switch_device(&new_device)
+}
+
+/// Stop the current audio recording. Returns a `RecordingResult`: the
+/// finalized WAV path(s) — one, or one per segment when segmentation is
+/// enabled — with the duration, peak, clip count, device, and format.
+///
+/// Gives up waiting after `timeout_ms` (default 5 s) so a stuck finalize
+/// cannot freeze the UI; the recording then finishes in the background.
+/// With `post_roll_ms` in the capture config, capture carries on that much
+/// longer first, and the wait is extended by as much.
+///
+/// With `max_total_bytes` set in the stored capture config, the oldest
+/// other recordings are then deleted until the directory fits, and
+/// `audio://evicted` is emitted with `{ paths }` of those removed.
+#[tauri::command]
+fn stop_audio_recording(
+    timeout_ms: Option<u64>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AudioState>,
+) -> Result<RecordingResult, String> {
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(audio::capture::DEFAULT_STOP_TIMEOUT);
+    let result = state.manager.stop_with_timeout(timeout)?;
+    enforce_recordings_cap(&app, &state, &result.paths);
+    Ok(result)
+}
+
+/// [`stop_audio_recording`] returning only the finalized WAV paths, for
+/// callers written against its earlier return shape.
+#[tauri::command]
+fn stop_audio_recording_paths(
+    timeout_ms: Option<u64>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AudioState>,
+) -> Result<Vec<String>, String> {
+    stop_audio_recording(timeout_ms, app, state).map(|result| result.paths)
+}
+
+/// Apply the stored config's `max_total_bytes` cap to the recordings
+/// directory, sparing the just-finished `recording` files, and emit
+/// `audio://evicted` if anything was deleted. Failures are logged rather
+/// than failing the stop, which has already succeeded.
+fn enforce_recordings_cap(app: &tauri::AppHandle, state: &AudioState, recording: &[String]) {
+    let Some(max_total_bytes) = state.config.lock().ok().and_then(|c| c.max_total_bytes) else {
+        return;
+    };
+    let Ok(recordings_dir) = state.recordings_dir.lock().map(|d| d.clone()) else {
+        return;
+    };
+    let exempt: Vec<PathBuf> = recording.iter().map(PathBuf::from).collect();
+    match recordings::enforce_size_limit(&recordings_dir, max_total_bytes, &exempt) {
+        Ok(evicted) if !evicted.is_empty() => {
+            let paths: Vec<String> = evicted
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect();
+            let _ = app.emit("audio://evicted", serde_json::json!({ "paths": paths }));
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to enforce recordings size cap: {e}"),
+    }
+}
+
+/// How the current or last recording's audio got from the device into the
+/// file: `{ device, source_sample_rate, source_channels,
+/// output_sample_rate, output_channels, converted, reason, summary }`,
+/// where `summary` reads `native` or `converted from 48000 Hz / 2
+/// channels`. `null` before the first recording.
+#[tauri::command]
+fn last_conversion_info(
+    state: tauri::State<'_, AudioState>,
+) -> Result<Option<audio::source::ConversionInfo>, String> {
+    state.manager.last_conversion_info()
+}
+
+/// Downsample a recording into `buckets` signed peak amplitudes in [-1, 1]
+/// for drawing a waveform. Shorter files yield one value per frame.
+#[tauri::command]
+fn compute_waveform(path: String, buckets: usize) -> Result<Vec<f32>, String> {
+    audio::wav::compute_waveform(&PathBuf::from(path), buckets)
+}
+
+/// Report whether the recording at `path` is effectively silent — its peak
+/// below `threshold_dbfs` — with its measured peak and RMS levels. Empty
+/// and header-only files count as silent.
+#[tauri::command]
+fn is_recording_silent(path: String, threshold_dbfs: f32) -> Result<SilenceReport, String> {
+    audio::wav::check_silence(&PathBuf::from(path), threshold_dbfs)
+}
+
+/// Write a copy of the recording at `path` with the silence (below
+/// `threshold_dbfs`) cut from both ends, keeping a short margin. Saved to
+/// `output`, or as `<name>_trimmed.wav` next to it. Returns the new path
+/// and the seconds cut from the start and end. An entirely silent
+/// recording is an error rather than an empty file.
+#[tauri::command]
+fn trim_silence(
+    path: String,
+    threshold_dbfs: f32,
+    output: Option<String>,
+) -> Result<TrimResult, String> {
+    audio::wav::trim_silence(
+        &PathBuf::from(path),
+        threshold_dbfs,
+        output.map(PathBuf::from).as_deref(),
+    )
+}
+
+/// Find the stretches of the recording at `path` that hold speech — 30 ms
+/// windows whose RMS level reaches `threshold_dbfs` — merging those less
+/// than `min_gap_ms` apart. A silent recording has none.
+#[tauri::command]
+fn detect_speech_regions(
+    path: String,
+    threshold_dbfs: f32,
+    min_gap_ms: u64,
+) -> Result<Vec<SpeechRegion>, String> {
+    audio::wav::detect_speech_regions(
+        &PathBuf::from(path),
+        threshold_dbfs,
+        Duration::from_millis(min_gap_ms),
+    )
+}
+
+/// Cut a recording into clips at `split_points_seconds` (ascending, within
+/// the recording) and write them to `output_dir` as `<name>_clip<n>.wav`.
+/// Points at the very start or end cut nothing off. Returns the clip paths
+/// in order.
+#[tauri::command]
+fn split_recording(
+    path: String,
+    split_points_seconds: Vec<f32>,
+    output_dir: String,
+) -> Result<Vec<String>, String> {
+    audio::wav::split_recording(
+        &PathBuf::from(path),
+        &split_points_seconds,
+        &PathBuf::from(output_dir),
+    )
+}
+
+/// Write a mono copy of a stereo (or multi-channel) recording, mixing the
+/// channels with `weights`, one per channel, or equally by default. Written
+/// to `output`, or next to it as `<name>_mono.wav`. Returns the path written.
+#[tauri::command]
+fn downmix_to_mono(
+    path: String,
+    output: Option<String>,
+    weights: Option<Vec<f32>>,
+) -> Result<String, String> {
+    audio::wav::downmix_to_mono(
+        &PathBuf::from(path),
+        output.map(PathBuf::from).as_deref(),
+        weights.as_deref(),
+    )
+}
+
+/// Write a copy of a recording at `target_rate` Hz (e.g. 44100 for CD,
+/// 8000 for telephony), keeping its channels, to `output`, or next to it as
+/// `<name>_<rate>hz.wav`. Returns the path written.
+#[tauri::command]
+fn resample_recording(
+    path: String,
+    target_rate: u32,
+    output: Option<String>,
+) -> Result<String, String> {
+    audio::wav::resample_recording(
+        &PathBuf::from(path),
+        target_rate,
+        output.map(PathBuf::from).as_deref(),
+    )
+}
+
+/// Fix the size fields of a WAV left unfinalized by a crash so players can
+/// open it; the audio itself is not touched. Returns whether it needed
+/// repairing.
+#[tauri::command]
+fn repair_wav(path: String) -> Result<bool, String> {
+    audio::wav::repair_wav(&PathBuf::from(path))
+}
+
+/// Recompute the SHA-256 of a recording's audio and compare it with the
+/// one stored in its `.sha256` file when it was finalized. Only the `data`
+/// chunk is hashed, so header repairs and metadata edits still match.
+#[tauri::command]
+fn verify_recording(path: String) -> Result<ChecksumVerification, String> {
+    recordings::verify_recording(&PathBuf::from(path))
+}
+
+/// List the recording formats compiled into this build (always includes
+/// `"wav"`).
+#[tauri::command]
+fn supported_recording_formats() -> Vec<String> {
+    audio::config::supported_recording_formats()
+}
+
+/// Return the stored capture config used by `start_audio_recording`.
+#[tauri::command]
+fn get_capture_config(state: tauri::State<'_, AudioState>) -> Result<AudioCaptureConfig, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    Ok(config.clone())
+}
+
+/// Replace the stored capture config. Rejected while a recording is running.
+#[tauri::command]
+fn set_capture_config(
+    config: AudioCaptureConfig,
+    state: tauri::State<'_, AudioState>,
+) -> Result<(), String> {
+    config.validate()?;
+    let mut current = state
+        .config
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    state.manager.ensure_idle("change capture config")?;
+    *current = config;
+    Ok(())
+}
+
+/// Replace the stored capture config, applying it to the running recording
+/// if there is one: the current file is finalized and capture continues
+/// into a new file with the new config. `audio://reconfigured` is then
+/// emitted with `{ finished, next }` (the files written under the old
+/// config and the first new one), which is also returned; `null` when idle.
+#[tauri::command]
+fn reconfigure_capture(
+    new_config: AudioCaptureConfig,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AudioState>,
+) -> Result<Option<Reconfigured>, String> {
+    new_config.validate()?;
+    let mut current = state
+        .config
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    let reconfigured = state.manager.reconfigure(&new_config)?;
+    if let Some(reconfigured) = &reconfigured {
+        let _ = app.emit("audio://reconfigured", reconfigured);
+    }
+    *current = new_config;
+    Ok(reconfigured)
+}
+
+/// Report free space on the volume holding the recordings directory, plus an
+/// estimate of how many minutes of audio fit at the current capture format.
+#[tauri::command]
+fn recordings_disk_space(state: tauri::State<'_, AudioState>) -> Result<DiskSpace, String> {
+    let bytes_per_second = state
+        .config
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?
+        .bytes_per_second();
+    let recordings_dir = state
+        .recordings_dir
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    storage::disk_space(&recordings_dir, bytes_per_second)
+}
+
+/// Time a small write and fsync in the recordings directory, so the UI can
+/// warn that a folder (e.g. a network mount) may be too slow to record to.
+#[tauri::command]
+fn probe_recordings_storage(state: tauri::State<'_, AudioState>) -> Result<StorageProbe, String> {
+    let recordings_dir = state
+        .recordings_dir
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?;
+    Ok(storage::probe_storage(&recordings_dir))
+}
+
+/// Estimate how many minutes recording can continue on battery before it
+/// drops to a 10% reserve, from the level and drain rate the OS reports.
+/// `unlimited` on mains power or when no battery is found (desktops);
+/// `confident` is false when the drain rate had to be assumed.
+#[tauri::command]
+fn estimate_recording_budget() -> RecordingBudget {
+    battery::recording_budget()
+}
+
+/// Build a manifest of every recording (spec, duration, size, INFO metadata).
+///
+/// When `output` is given the manifest is also written there as JSON.
+/// Unreadable files are reported under `errors` instead of failing the export.
+#[tauri::command]
+fn export_recordings_manifest(
+    output: Option<String>,
+    state: tauri::State<'_, AudioState>,
+) -> Result<Manifest, String> {
+    let recordings_dir = state
+        .recordings_dir
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?
+        .clone();
+    let manifest = recordings::build_manifest(&recordings_dir)?;
+    if let Some(output) = output {
+        recordings::write_manifest(&manifest, &PathBuf::from(output))?;
+    }
+    Ok(manifest)
+}
+
+/// Recordings without a `.txt`/`.json` transcript next to them, searching
+/// dated subfolders too, as `{ path, size_bytes, duration_seconds,
+/// started_at_unix }` sorted by path. `duration_seconds` is `null` for a
+/// WAV that cannot be read.
+#[tauri::command]
+fn list_untranscribed_recordings(
+    state: tauri::State<'_, AudioState>,
+) -> Result<Vec<recordings::UntranscribedRecording>, String> {
+    let recordings_dir = state
+        .recordings_dir
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?
+        .clone();
+    recordings::list_untranscribed_recordings(&recordings_dir)
+}
+
+/// Delete the `.txt`/`.json` transcripts and `.sha256` checksums whose
+/// recording was deleted outside the app, searching dated subfolders too.
+/// With `dry_run` they are only listed. Returns `{ paths, reclaimed_bytes,
+/// dry_run }`.
+#[tauri::command]
+fn purge_orphan_transcripts(
+    dry_run: bool,
+    state: tauri::State<'_, AudioState>,
+) -> Result<recordings::OrphanPurge, String> {
+    let recordings_dir = state
+        .recordings_dir
+        .lock()
+        .map_err(|e| format!("Lock poisoned: {e}"))?
+        .clone();
+    recordings::purge_orphan_companions(&recordings_dir, dry_run)
+}
+
+/// The app's cache of derived files (see [`cache`]), inside its data dir.
+fn cache_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {e}"))?
+        .join(cache::CACHE_DIR_NAME))
+}
+
+/// List the files the app has cached, such as playback transcodes, largest
+/// first, with their sizes and the files they were made from, plus the
+/// total and the free space left.
+#[tauri::command]
+fn list_cache(app: tauri::AppHandle) -> Result<CacheListing, String> {
+    cache::list_cache(&cache_dir(&app)?)
+}
+
+/// Delete every cached file. They are recreated as needed. Returns the
+/// bytes freed.
+#[tauri::command]
+fn clear_cache(app: tauri::AppHandle) -> Result<u64, String> {
+    cache::clear_cache(&cache_dir(&app)?)
+}
+
+/// Delete one cached file listed by `list_cache`. Paths outside the cache
+/// are refused. Returns the bytes freed.
+#[tauri::command]
+fn clear_cache_entry(path: String, app: tauri::AppHandle) -> Result<u64, String> {
+    cache::clear_cache_entry(&cache_dir(&app)?, &PathBuf::from(path))
+}
+
+/// Return a path the webview can play for the recording at `path`.
+///
+/// Transcodes to AAC in the app's cache (reusing a previous transcode if
+/// the recording is unchanged). Returns the original WAV path if no encoder
+/// is available.
+#[tauri::command]
+fn prepare_for_playback(path: String, app: tauri::AppHandle) -> Result<String, String> {
+    let cache_dir = cache_dir(&app)?.join("playback");
+    let encoder = Encoder::detect();
+    let playable =
+        playback::prepare_for_playback(&PathBuf::from(path), &cache_dir, encoder.as_ref())?;
+    playable
+        .to_str()
+        .map(String::from)
+        .ok_or_else(|| "Playback path is not valid UTF-8".into())
 }
 
 // ---------------------------------------------------------------------------
@@ -111,9 +1931,22 @@ fn stop_audio_recording(state: tauri::State<'_, AudioState>) -> Result<String, S
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let sidecar = Arc::new(Mutex::new(SidecarManager::new()));
+    if let Err(e) = sidecar::spawn_keepalive(Arc::downgrade(&sidecar)) {
+        eprintln!("{e}");
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .manage(SidecarState(Mutex::new(SidecarManager::new())))
+        .manage(SidecarState(sidecar))
+        .manage(TranscriptCacheState(Mutex::new(TranscriptCache::new())))
+        .manage(TranscriptionErrorState(Mutex::new(
+            TranscriptionErrorLog::new(),
+        )))
+        .manage(ModelCatalogState(Mutex::new(ModelCatalog::new())))
+        .manage(TranscriptionSessionState(Mutex::new(None)))
+        .manage(PlaybackState(PlaybackManager::new()))
+        .manage(AudioBridgeState(Mutex::new(None)))
         .setup(|app| {
             // Resolve the recordings directory inside the app's data dir.
             let app_data_dir = app
@@ -124,22 +1957,123 @@ pub fn run() {
 
             let recordings_dir = app_data_dir.join("recordings");
 
+            let manager = AudioCaptureManager::new()
+                .with_lock_file(app_data_dir.join(audio::lock::LOCK_FILE_NAME));
+            manager.purge_ephemeral_leftovers(&audio::capture::ephemeral_dir());
+
             app.manage(AudioState {
-                manager: AudioCaptureManager::new(),
+                manager,
                 recordings_dir: Mutex::new(recordings_dir),
+                config: Mutex::new(AudioCaptureConfig::default()),
+                scheduler: RecordingScheduler::new(),
             });
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             start_sidecar,
+            start_sidecar_profile,
+            register_sidecar_profile,
+            list_sidecar_profiles,
+            get_default_initial_prompt,
+            set_default_initial_prompt,
+            set_sidecar_language,
             stop_sidecar,
+            diagnose_backend,
             sidecar_health,
             send_to_sidecar,
+            send_to_sidecar_timed,
             sidecar_status,
+            warm_up_sidecar,
+            sidecar_ready,
+            sidecar_capabilities,
+            sidecar_supports_streaming,
+            sidecar_last_error,
+            about_info,
+            clear_sidecar_error,
+            set_sidecar_keepalive,
+            set_reconnect_policy,
+            sidecar_reconnect_stats,
+            benchmark_transcription,
+            timed_transcribe,
+            list_sidecar_models,
+            cached_sidecar_models,
+            set_sidecar_model,
+            set_sidecar_device,
+            sidecar_process_info,
+            set_sidecar_trace,
+            sidecar_trace,
+            set_sidecar_log,
+            start_sidecar_log_stream,
+            stop_sidecar_log_stream,
+            transcribe_recording,
+            transcribe_media_file,
+            transcribe_recording_with_progress,
+            export_subtitles,
+            retranscribe,
+            stream_transcribe_file,
+            transcribe_to_file_streaming,
+            start_transcription_session,
+            transcribe_session_chunk,
+            end_transcription_session,
+            recent_transcription_errors,
             list_audio_devices,
+            suggest_capture_config,
+            supports_exclusive_capture,
+            get_device_allow_list,
+            set_device_allow_list,
+            microphone_permission_status,
             start_audio_recording,
+            start_dual_capture,
+            schedule_recording,
+            scheduled_recording,
+            cancel_scheduled_recording,
+            record_clip,
+            capture_preview,
+            check_device_availability,
             stop_audio_recording,
+            stop_audio_recording_paths,
+            last_conversion_info,
+            pause_audio_recording,
+            resume_audio_recording,
+            switch_device,
+            cancel_audio_recording,
+            keep_recording,
+            rename_recording,
+            concatenate_recordings,
+            audio_recording_status,
+            audio_status,
+            play_recording,
+            stop_playback,
+            playback_status,
+            audio_input_latency,
+            recording_meters,
+            reset_recording_meters,
+            start_audio_bridge,
+            stop_audio_bridge,
+            get_capture_config,
+            set_capture_config,
+            reconfigure_capture,
+            supported_recording_formats,
+            compute_waveform,
+            is_recording_silent,
+            trim_silence,
+            detect_speech_regions,
+            downmix_to_mono,
+            resample_recording,
+            split_recording,
+            repair_wav,
+            verify_recording,
+            recordings_disk_space,
+            probe_recordings_storage,
+            estimate_recording_budget,
+            export_recordings_manifest,
+            list_untranscribed_recordings,
+            purge_orphan_transcripts,
+            prepare_for_playback,
+            list_cache,
+            clear_cache,
+            clear_cache_entry,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,153 @@
+//! Size-capped log file with a single rotated backup.
+//!
+//! Lines are appended to `<path>` until the next line would push it past
+//! `max_bytes`; the file is then renamed to `<path>.1` (replacing any older
+//! backup) and a fresh `<path>` is started. At most about twice `max_bytes`
+//! is ever kept on disk.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Default size cap for log files.
+pub const DEFAULT_LOG_MAX_BYTES: u64 = 1024 * 1024;
+
+/// An open, rotating log file.
+#[derive(Debug)]
+pub struct RotatingLog {
+    path: PathBuf,
+    max_bytes: u64,
+    file: File,
+    /// Current size of `file`.
+    written: u64,
+}
+
+impl RotatingLog {
+    /// Open (or create) `path` for appending, creating its parent directory
+    /// if needed. `max_bytes` is raised to at least 1.
+    ///
+    /// # Errors
+    /// Returns an error if the directory or file cannot be created.
+    pub fn open(path: &Path, max_bytes: u64) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create log directory: {e}"))?;
+        }
+        let file = open_append(path)?;
+        let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes: max_bytes.max(1),
+            file,
+            written,
+        })
+    }
+
+    /// Path of the rotated backup: `<path>.1`.
+    pub fn backup_path(&self) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(".1");
+        PathBuf::from(name)
+    }
+
+    /// Append `line` plus a newline, rotating first if it would not fit.
+    ///
+    /// A single line longer than `max_bytes` is still written whole, into a
+    /// freshly rotated file.
+    ///
+    /// # Errors
+    /// Returns an error if rotation or the write fails.
+    pub fn write_line(&mut self, line: &str) -> Result<(), String> {
+        let len = line.len() as u64 + 1;
+        if self.written > 0 && self.written + len > self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{line}")
+            .map_err(|e| format!("Failed to write '{}': {e}", self.path.display()))?;
+        self.written += len;
+        Ok(())
+    }
+
+    /// Move the current file to the backup slot and start an empty one.
+    fn rotate(&mut self) -> Result<(), String> {
+        let backup = self.backup_path();
+        fs::rename(&self.path, &backup)
+            .map_err(|e| format!("Failed to rotate '{}': {e}", self.path.display()))?;
+        self.file = open_append(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open log file '{}': {e}", path.display()))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_dir;
+
+    #[test]
+    fn test_rotates_when_cap_is_exceeded() {
+        let dir = test_dir("log_file", "rotate");
+        let path = dir.join("sidecar.log");
+        let mut log = RotatingLog::open(&path, 33).expect("open");
+
+        // Three 11-byte lines fill 33 bytes exactly; the fourth rotates.
+        for i in 0..3 {
+            log.write_line(&format!("line {i:05}")).expect("write");
+        }
+        assert!(!log.backup_path().exists());
+        log.write_line("line 00003").expect("write");
+
+        let backup = fs::read_to_string(log.backup_path()).expect("backup");
+        assert_eq!(backup, "line 00000\nline 00001\nline 00002\n");
+        assert_eq!(fs::read_to_string(&path).expect("log"), "line 00003\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_second_rotation_replaces_backup() {
+        let dir = test_dir("log_file", "rotate_twice");
+        let path = dir.join("sidecar.log");
+        let mut log = RotatingLog::open(&path, 8).expect("open");
+
+        for line in ["aaaaa", "bbbbb", "ccccc"] {
+            log.write_line(line).expect("write");
+        }
+        assert_eq!(fs::read_to_string(log.backup_path()).unwrap(), "bbbbb\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "ccccc\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reopen_appends_and_counts_existing_size() {
+        let dir = test_dir("log_file", "reopen");
+        let path = dir.join("logs").join("sidecar.log");
+        RotatingLog::open(&path, 16)
+            .expect("open")
+            .write_line("first line")
+            .expect("write");
+
+        let mut log = RotatingLog::open(&path, 16).expect("reopen");
+        log.write_line("second").expect("write");
+        assert_eq!(
+            fs::read_to_string(log.backup_path()).unwrap(),
+            "first line\n"
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
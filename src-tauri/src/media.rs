@@ -0,0 +1,362 @@
+//! Audio files from outside the app, such as MP3s dragged into the window.
+//!
+//! The transcription pipeline reads WAV only. [`prepare_media`] detects the
+//! format of a file from its magic bytes (falling back to its extension)
+//! and decodes anything that is not WAV in-process with `symphonia`,
+//! resampling it to a temporary 16 kHz mono WAV. The temporary file is
+//! deleted when the returned [`MediaInput`] is dropped.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::audio::capture::{convert_to_mono_16k, CHANNELS, SAMPLE_RATE};
+use crate::audio::sink::wav_bytes;
+
+/// Error prefix when a file is not in a format the app can decode.
+pub const ERR_UNSUPPORTED_MEDIA: &str = "Unsupported audio format";
+
+/// Bytes read from the start of a file to recognise its format.
+const MAGIC_LEN: usize = 12;
+
+/// Audio container formats recognised by [`detect_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaFormat {
+    Wav,
+    Mp3,
+    /// MPEG-4 audio (`.m4a`, `.mp4`, `.aac` in an MP4 container).
+    M4a,
+    Ogg,
+    Flac,
+}
+
+impl MediaFormat {
+    /// The format whose signature starts `header`, if any.
+    pub fn from_magic(header: &[u8]) -> Option<Self> {
+        match header {
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some(Self::Wav),
+            [b'I', b'D', b'3', ..] => Some(Self::Mp3),
+            // MPEG audio frame sync with a non-reserved layer (AAC ADTS
+            // streams share the sync word but have layer 0).
+            [0xFF, second, ..] if second & 0xE0 == 0xE0 && second & 0x06 != 0 => Some(Self::Mp3),
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some(Self::M4a),
+            [b'O', b'g', b'g', b'S', ..] => Some(Self::Ogg),
+            [b'f', b'L', b'a', b'C', ..] => Some(Self::Flac),
+            _ => None,
+        }
+    }
+
+    /// The format usually stored under `path`'s extension, if any.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "wav" | "wave" => Some(Self::Wav),
+            "mp3" => Some(Self::Mp3),
+            "m4a" | "mp4" | "aac" => Some(Self::M4a),
+            "ogg" | "oga" | "opus" => Some(Self::Ogg),
+            "flac" => Some(Self::Flac),
+            _ => None,
+        }
+    }
+}
+
+/// Detect the format of the file at `path`, trusting its contents over its
+/// extension.
+///
+/// # Errors
+/// Returns an error if the file cannot be read, or an
+/// [`ERR_UNSUPPORTED_MEDIA`] error if neither its contents nor its
+/// extension are recognised.
+pub fn detect_format(path: &Path) -> Result<MediaFormat, String> {
+    let mut header = Vec::with_capacity(MAGIC_LEN);
+    fs::File::open(path)
+        .and_then(|f| f.take(MAGIC_LEN as u64).read_to_end(&mut header))
+        .map_err(|e| format!("Failed to read '{}': {e}", path.display()))?;
+
+    MediaFormat::from_magic(&header)
+        .or_else(|| MediaFormat::from_extension(path))
+        .ok_or_else(|| format!("{ERR_UNSUPPORTED_MEDIA}: '{}'", path.display()))
+}
+
+/// Directory decoded media is written to while it is transcribed.
+pub fn media_temp_dir() -> PathBuf {
+    std::env::temp_dir().join("second-media")
+}
+
+/// A WAV file ready for transcription: either the original file, or a
+/// decoded copy that is deleted when this is dropped.
+#[derive(Debug)]
+pub struct MediaInput {
+    path: PathBuf,
+    temporary: bool,
+}
+
+impl MediaInput {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for MediaInput {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Make the audio file at `source` readable by the transcription pipeline.
+///
+/// WAV files are used as they are. Other formats are decoded and resampled
+/// into a 16 kHz mono WAV inside `temp_dir`.
+///
+/// # Errors
+/// Returns an [`ERR_UNSUPPORTED_MEDIA`] error for unrecognised files or
+/// codecs, or an error if decoding or writing the temporary file fails.
+pub fn prepare_media(source: &Path, temp_dir: &Path) -> Result<MediaInput, String> {
+    let format = detect_format(source)?;
+    if format == MediaFormat::Wav {
+        return Ok(MediaInput {
+            path: source.to_path_buf(),
+            temporary: false,
+        });
+    }
+    let samples = decode_16k_mono(source)?;
+
+    fs::create_dir_all(temp_dir)
+        .map_err(|e| format!("Failed to create media temp directory: {e}"))?;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    // Owned from here on, so every failure below cleans up.
+    let input = MediaInput {
+        path: temp_dir.join(format!("media_{}_{nanos}.wav", std::process::id())),
+        temporary: true,
+    };
+
+    let target = hound::WavSpec {
+        channels: CHANNELS,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    fs::write(input.path(), wav_bytes(&samples, target)?)
+        .map_err(|e| format!("Failed to write decoded audio: {e}"))?;
+    Ok(input)
+}
+
+/// Decode the first audio track of `source` to 16 kHz mono samples.
+///
+/// Corrupt packets are skipped, as players do. If the stream changes rate
+/// or channel count part way, each stretch is converted on its own.
+///
+/// # Errors
+/// Returns an [`ERR_UNSUPPORTED_MEDIA`] error if the container or codec is
+/// not supported or holds no audio, or an error if reading fails.
+fn decode_16k_mono(source: &Path) -> Result<Vec<i16>, String> {
+    let unsupported = |detail: &dyn std::fmt::Display| {
+        format!("{ERR_UNSUPPORTED_MEDIA}: '{}': {detail}", source.display())
+    };
+    let file = fs::File::open(source)
+        .map_err(|e| format!("Failed to read '{}': {e}", source.display()))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = source.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut reader = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| unsupported(&e))?
+        .format;
+    let track = reader
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| unsupported(&"no audio track"))?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| unsupported(&e))?;
+
+    let mut samples = Vec::new();
+    // Interleaved audio not yet converted, and its rate and channel count.
+    let mut pending: Vec<f32> = Vec::new();
+    let mut layout = (0, 0);
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(DecodeError::ResetRequired) => break,
+            Err(e) => return Err(format!("Failed to read '{}': {e}", source.display())),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(DecodeError::DecodeError(_)) => continue,
+            Err(e) => return Err(format!("Failed to decode '{}': {e}", source.display())),
+        };
+        let spec = *decoded.spec();
+        let channels = u16::try_from(spec.channels.count()).unwrap_or(u16::MAX);
+        if (spec.rate, channels) != layout {
+            samples.extend(convert_to_mono_16k(&pending, layout.0, layout.1));
+            pending.clear();
+            layout = (spec.rate, channels);
+        }
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        pending.extend_from_slice(buffer.samples());
+    }
+    samples.extend(convert_to_mono_16k(&pending, layout.0, layout.1));
+
+    if samples.is_empty() {
+        return Err(unsupported(&"no decodable audio"));
+    }
+    Ok(samples)
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_dir;
+
+    fn write_wav(path: &Path, sample_rate: u32, channels: u16, frames: usize) {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).expect("create wav");
+        for i in 0..frames * usize::from(channels) {
+            writer.write_sample((i % 100) as i16).expect("sample");
+        }
+        writer.finalize().expect("finalize");
+    }
+
+    #[test]
+    fn test_format_from_magic_bytes() {
+        assert_eq!(
+            MediaFormat::from_magic(b"RIFF\x24\0\0\0WAVEfmt "),
+            Some(MediaFormat::Wav)
+        );
+        assert_eq!(
+            MediaFormat::from_magic(b"ID3\x04\0\0"),
+            Some(MediaFormat::Mp3)
+        );
+        assert_eq!(
+            MediaFormat::from_magic(&[0xFF, 0xFB, 0x90, 0x64]),
+            Some(MediaFormat::Mp3)
+        );
+        assert_eq!(MediaFormat::from_magic(&[0xFF, 0xF1, 0x50, 0x80]), None);
+        assert_eq!(
+            MediaFormat::from_magic(b"\0\0\0\x20ftypM4A "),
+            Some(MediaFormat::M4a)
+        );
+        assert_eq!(
+            MediaFormat::from_magic(b"OggS\0\x02"),
+            Some(MediaFormat::Ogg)
+        );
+        assert_eq!(
+            MediaFormat::from_magic(b"fLaC\0\0\0\x22"),
+            Some(MediaFormat::Flac)
+        );
+        assert_eq!(MediaFormat::from_magic(b"RIFF\x24\0\0\0AVI "), None);
+        assert_eq!(MediaFormat::from_magic(b""), None);
+    }
+
+    #[test]
+    fn test_detect_format_prefers_contents_then_extension() {
+        let dir = test_dir("media", "detect");
+        // A WAV misnamed as .mp3 is still a WAV.
+        let misnamed = dir.join("clip.mp3");
+        write_wav(&misnamed, 16_000, 1, 10);
+        assert_eq!(detect_format(&misnamed), Ok(MediaFormat::Wav));
+
+        // Unknown contents fall back to the extension.
+        let by_ext = dir.join("voice.M4A");
+        fs::write(&by_ext, b"????").expect("write");
+        assert_eq!(detect_format(&by_ext), Ok(MediaFormat::M4a));
+
+        let notes = dir.join("notes.txt");
+        fs::write(&notes, b"hello").expect("write");
+        let err = detect_format(&notes).unwrap_err();
+        assert!(err.starts_with(ERR_UNSUPPORTED_MEDIA), "got: {err}");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_wav_is_used_in_place() {
+        let dir = test_dir("media", "wav_in_place");
+        let wav = dir.join("recording.wav");
+        write_wav(&wav, 16_000, 1, 10);
+
+        let input = prepare_media(&wav, &dir.join("tmp")).expect("prepare");
+        assert_eq!(input.path(), wav);
+        drop(input);
+        assert!(wav.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bundled_mp3_decodes_to_16k_mono_and_is_removed_on_drop() {
+        let dir = test_dir("media", "decode");
+        // 0.6 s of 48 kHz mono MPEG-1 Layer III silence.
+        let mp3 = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/silence_48k_mono.mp3");
+
+        let temp_dir = dir.join("tmp");
+        let input = prepare_media(&mp3, &temp_dir).expect("prepare");
+        assert!(input.path().starts_with(&temp_dir));
+        let reader = hound::WavReader::open(input.path()).expect("open decoded");
+        assert_eq!(reader.spec().sample_rate, SAMPLE_RATE);
+        assert_eq!(reader.spec().channels, 1);
+        assert_eq!(reader.spec().bits_per_sample, 16);
+        // 25 frames of 1152 samples, a third of them at 16 kHz.
+        assert_eq!(reader.duration(), 9_600);
+
+        let temp = input.path().to_path_buf();
+        drop(input);
+        assert!(!temp.exists());
+        assert!(mp3.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_undecodable_media_is_unsupported_and_leaves_no_temp_file() {
+        let dir = test_dir("media", "decode_fails");
+        // An MP3 by its signature, with no frames after the tag header.
+        let mp3 = dir.join("clip.mp3");
+        fs::write(&mp3, b"ID3\x04\0\0\0\0\0\0").expect("write");
+
+        let temp_dir = dir.join("tmp");
+        let err = prepare_media(&mp3, &temp_dir).unwrap_err();
+        assert!(err.starts_with(ERR_UNSUPPORTED_MEDIA), "got: {err}");
+        assert!(!temp_dir.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
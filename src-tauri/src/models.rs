@@ -0,0 +1,168 @@
+//! Model profiles held by the sidecar.
+//!
+//! A backend can keep several transcription models in memory and switch
+//! between them instantly. The last model list it reported is cached so the
+//! UI can show it without a round trip each time; switching models
+//! invalidates the cache, since it changes what the backend reports.
+
+use crate::protocol::{ModelInfo, SidecarRequest, SidecarResponse};
+use crate::sidecar::SidecarManager;
+
+/// Prefix of the error returned when the sidecar does not know a model id.
+pub const ERR_UNKNOWN_MODEL: &str = "Unknown model";
+
+/// The sidecar's last reported model list.
+#[derive(Debug, Default)]
+pub struct ModelCatalog {
+    models: Option<Vec<ModelInfo>>,
+}
+
+impl ModelCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cached list, if the sidecar has been asked since the cache was
+    /// last invalidated.
+    pub fn cached(&self) -> Option<&[ModelInfo]> {
+        self.models.as_deref()
+    }
+
+    /// Forget the cached list, e.g. because the sidecar was restarted.
+    pub fn invalidate(&mut self) {
+        self.models = None;
+    }
+
+    /// The sidecar's models: the cached list unless `refresh` is set or
+    /// there is none, otherwise a fresh `list_models` query.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or the sidecar replies with an
+    /// error or an unexpected response.
+    pub fn list(
+        &mut self,
+        mgr: &mut SidecarManager,
+        refresh: bool,
+    ) -> Result<Vec<ModelInfo>, String> {
+        if let (false, Some(models)) = (refresh, &self.models) {
+            return Ok(models.clone());
+        }
+
+        match mgr.send_typed(&SidecarRequest::ListModels)? {
+            SidecarResponse::ModelsList { models } => {
+                self.models = Some(models.clone());
+                Ok(models)
+            }
+            SidecarResponse::Error { message } => {
+                Err(format!("Sidecar failed to list models: {message}"))
+            }
+            other => Err(format!("Unexpected response to list_models: {other:?}")),
+        }
+    }
+
+    /// Make `model_id` the sidecar's active model. The cached list is
+    /// invalidated whether or not the switch succeeds.
+    ///
+    /// # Errors
+    /// Returns an [`ERR_UNKNOWN_MODEL`] error if the sidecar does not
+    /// recognise `model_id`, or an error if the request fails or the
+    /// sidecar reports another error.
+    pub fn set_model(&mut self, mgr: &mut SidecarManager, model_id: &str) -> Result<(), String> {
+        self.invalidate();
+        let response = mgr.send_typed(&SidecarRequest::SetModel {
+            model_id: model_id.to_string(),
+        })?;
+        match response {
+            SidecarResponse::Error { message } => Err(set_model_error(model_id, &message)),
+            // The backend acknowledges with a reply of its own type.
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Map the sidecar's error `message` for `set_model` to our error, typed as
+/// [`ERR_UNKNOWN_MODEL`] when the backend says it does not know the id.
+fn set_model_error(model_id: &str, message: &str) -> String {
+    let lower = message.to_lowercase();
+    if lower.contains("unknown model") || lower.contains("not found") {
+        format!("{ERR_UNKNOWN_MODEL} '{model_id}': {message}")
+    } else {
+        format!("Sidecar failed to switch to model '{model_id}': {message}")
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reports two models, naming each after how many times the list was
+    /// queried so a test can tell a fresh list from a cached one.
+    const MODELS_BACKEND: &str = r#"import json, sys
+queries = 0
+for line in sys.stdin:
+    msg = json.loads(line)
+    if msg["type"] == "list_models":
+        queries += 1
+        models = [{"id": m, "name": f"{m} #{queries}", "loaded": True} for m in ("small", "large")]
+        reply = {"type": "models_list", "models": models}
+    elif msg["model_id"] in ("small", "large"):
+        reply = {"type": "model_set", "model_id": msg["model_id"]}
+    else:
+        reply = {"type": "error", "message": f"Unknown model id: {msg['model_id']}"}
+    print(json.dumps(reply), flush=True)
+"#;
+
+    #[test]
+    fn test_set_model_invalidates_cached_list() {
+        let Ok(python) = crate::sidecar::find_python(None, None) else {
+            eprintln!("Skipping model catalog test: python not found");
+            return;
+        };
+        let dir = std::env::temp_dir().join("second_test_models_catalog");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        std::fs::write(dir.join("main.py"), MODELS_BACKEND).expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        let mut catalog = ModelCatalog::new();
+        assert!(catalog.cached().is_none());
+
+        let first = catalog.list(&mut mgr, false).expect("list");
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].name, "small #1");
+        // Served from the cache: the backend is not asked again.
+        assert_eq!(catalog.list(&mut mgr, false).expect("list"), first);
+
+        catalog.set_model(&mut mgr, "large").expect("set model");
+        assert!(catalog.cached().is_none());
+        let after = catalog.list(&mut mgr, false).expect("list");
+        assert_eq!(after[0].name, "small #2");
+
+        // `refresh` bypasses the cache.
+        let refreshed = catalog.list(&mut mgr, true).expect("list");
+        assert_eq!(refreshed[0].name, "small #3");
+
+        let err = catalog.set_model(&mut mgr, "huge").unwrap_err();
+        assert!(err.starts_with(ERR_UNKNOWN_MODEL), "got: {err}");
+        assert!(catalog.cached().is_none());
+
+        mgr.stop().expect("stop");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_set_model_error_is_typed_only_for_unknown_ids() {
+        assert!(set_model_error("x", "Unknown model id: x").starts_with(ERR_UNKNOWN_MODEL));
+        assert!(set_model_error("x", "model 'x' not found").starts_with(ERR_UNKNOWN_MODEL));
+
+        let err = set_model_error("x", "CUDA out of memory");
+        assert!(!err.starts_with(ERR_UNKNOWN_MODEL), "got: {err}");
+        assert!(err.contains("CUDA out of memory"), "got: {err}");
+    }
+}
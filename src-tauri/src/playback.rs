@@ -0,0 +1,278 @@
+//! Playback copies of recordings for the webview's `<audio>` element.
+//!
+//! Raw 16 kHz mono WAV does not play reliably in every webview and large
+//! files are slow to load, so recordings are transcoded to AAC (`.m4a`) with
+//! an external encoder — `afconvert` on macOS, otherwise `ffmpeg` — and
+//! cached. Cache entries are keyed by source path and modification time, so
+//! re-recording or editing a file invalidates its entry, and each records
+//! its source (see [`crate::cache`]). When no encoder is available the
+//! original WAV path is used instead.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cache;
+
+/// Extension of transcoded playback files.
+const PLAYBACK_EXTENSION: &str = "m4a";
+
+/// AAC bitrate for playback copies. Speech at 16 kHz needs very little.
+const PLAYBACK_BITRATE: &str = "48000";
+
+/// External tool used to transcode recordings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Encoder {
+    /// macOS `afconvert`.
+    Afconvert(PathBuf),
+    Ffmpeg(PathBuf),
+}
+
+impl Encoder {
+    /// Find an available encoder, preferring `afconvert` (always present on
+    /// macOS) over `ffmpeg`.
+    pub fn detect() -> Option<Self> {
+        if let Some(path) = find_executable("afconvert") {
+            return Some(Encoder::Afconvert(path));
+        }
+        find_executable("ffmpeg").map(Encoder::Ffmpeg)
+    }
+
+    /// Transcode `source` into an AAC `.m4a` at `output`.
+    ///
+    /// # Errors
+    /// Returns an error if the encoder cannot be run or exits unsuccessfully.
+    pub fn transcode(&self, source: &Path, output: &Path) -> Result<(), String> {
+        let mut command = match self {
+            Encoder::Afconvert(exe) => {
+                let mut c = Command::new(exe);
+                c.args(["-f", "m4af", "-d", "aac", "-b", PLAYBACK_BITRATE])
+                    .arg(source)
+                    .arg(output);
+                c
+            }
+            Encoder::Ffmpeg(exe) => {
+                let mut c = Command::new(exe);
+                c.args(["-y", "-loglevel", "error", "-i"])
+                    .arg(source)
+                    .args(["-c:a", "aac", "-b:a", PLAYBACK_BITRATE, "-f", "ipod"])
+                    .arg(output);
+                c
+            }
+        };
+
+        let result = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| format!("Failed to run encoder: {e}"))?;
+
+        if !result.status.success() {
+            return Err(format!(
+                "Encoder exited with {}: {}",
+                result.status,
+                String::from_utf8_lossy(&result.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Cache key for a source file: a hash of its path and modification time.
+///
+/// Any change to either yields a different key, so a stale entry is simply
+/// never looked up again.
+pub fn cache_key(source: &Path, modified: SystemTime) -> String {
+    let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let mut hash = Fnv1a::new();
+    hash.write(source.to_string_lossy().as_bytes());
+    hash.write(&since_epoch.as_secs().to_le_bytes());
+    hash.write(&since_epoch.subsec_nanos().to_le_bytes());
+    format!("{:016x}", hash.finish())
+}
+
+/// Path of the cached playback copy of `source` inside `cache_dir`.
+pub fn cached_path(cache_dir: &Path, source: &Path, modified: SystemTime) -> PathBuf {
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "recording".into());
+    cache_dir.join(format!(
+        "{stem}-{}.{PLAYBACK_EXTENSION}",
+        cache_key(source, modified)
+    ))
+}
+
+/// Return a path the frontend can play for the recording at `source`.
+///
+/// Uses the cached transcode if one exists for the file's current mtime,
+/// otherwise transcodes into `cache_dir` with `encoder`. Falls back to the
+/// original path when no encoder is available or transcoding fails.
+///
+/// # Errors
+/// Returns an error if `source` does not exist or the cache directory cannot
+/// be created.
+pub fn prepare_for_playback(
+    source: &Path,
+    cache_dir: &Path,
+    encoder: Option<&Encoder>,
+) -> Result<PathBuf, String> {
+    let modified = fs::metadata(source)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to stat '{}': {e}", source.display()))?;
+
+    let cached = cached_path(cache_dir, source, modified);
+    if cached.is_file() {
+        return Ok(cached);
+    }
+
+    let Some(encoder) = encoder else {
+        return Ok(source.to_path_buf());
+    };
+
+    fs::create_dir_all(cache_dir)
+        .map_err(|e| format!("Failed to create playback cache directory: {e}"))?;
+
+    // Encode to a temporary name so an interrupted run never leaves a
+    // truncated file that later looks like a cache hit.
+    let partial = cached.with_extension(format!("partial.{PLAYBACK_EXTENSION}"));
+    match encoder.transcode(source, &partial) {
+        Ok(()) => {
+            fs::rename(&partial, &cached)
+                .map_err(|e| format!("Failed to store playback file: {e}"))?;
+            cache::record_source(&cached, source);
+            Ok(cached)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            eprintln!(
+                "prepare_for_playback: falling back to '{}': {e}",
+                source.display()
+            );
+            Ok(source.to_path_buf())
+        }
+    }
+}
+
+/// Search `$PATH` for an executable named `name`.
+fn find_executable(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// 64-bit FNV-1a. Used instead of `DefaultHasher`, whose output may change
+/// between Rust releases and would silently invalidate the cache.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_dir;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cache_key_is_stable_for_same_path_and_mtime() {
+        let t = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let path = Path::new("/rec/recording_1.wav");
+        assert_eq!(cache_key(path, t), cache_key(path, t));
+        assert_eq!(cache_key(path, t).len(), 16);
+    }
+
+    #[test]
+    fn test_cache_key_changes_with_mtime_or_path() {
+        let t = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let path = Path::new("/rec/recording_1.wav");
+        let base = cache_key(path, t);
+
+        assert_ne!(base, cache_key(path, t + Duration::from_secs(1)));
+        assert_ne!(base, cache_key(path, t + Duration::from_nanos(1)));
+        assert_ne!(base, cache_key(Path::new("/rec/recording_2.wav"), t));
+    }
+
+    #[test]
+    fn test_cached_path_is_fresh_until_source_changes() {
+        let dir = test_dir("playback", "staleness");
+        let source = dir.join("recording_1.wav");
+        let cache = dir.join("cache");
+        fs::write(&source, b"RIFF").expect("write source");
+        let t1 = fs::metadata(&source).unwrap().modified().unwrap();
+
+        // Simulate a previous transcode for the current mtime.
+        let cached = cached_path(&cache, &source, t1);
+        assert!(cached
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("recording_1-"));
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(&cached, b"m4a").unwrap();
+        assert_eq!(
+            prepare_for_playback(&source, &cache, None).expect("prepare"),
+            cached
+        );
+
+        // Touching the source makes the entry stale; with no encoder the
+        // original path is returned rather than the outdated copy.
+        let t2 = t1 + Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(t2)
+            .unwrap();
+        assert_ne!(cached_path(&cache, &source, t2), cached);
+        assert_eq!(
+            prepare_for_playback(&source, &cache, None).expect("prepare"),
+            source
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_transcode_falls_back_to_source() {
+        let dir = test_dir("playback", "fallback");
+        let source = dir.join("recording_1.wav");
+        fs::write(&source, b"RIFF").expect("write source");
+
+        let broken = Encoder::Ffmpeg(PathBuf::from("/no/such/ffmpeg"));
+        let result = prepare_for_playback(&source, &dir.join("cache"), Some(&broken));
+        assert_eq!(result.expect("prepare"), source);
+        assert_eq!(fs::read_dir(dir.join("cache")).unwrap().count(), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_prepare_missing_source_is_error() {
+        let dir = test_dir("playback", "missing");
+        let result = prepare_for_playback(&dir.join("nope.wav"), &dir, None);
+        assert!(result.unwrap_err().contains("Failed to stat"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,539 @@
+//! Typed messages for the sidecar's JSON-lines protocol.
+//!
+//! Mirrors `MessageType` / `ResponseType` in `backend/ipc/protocol.py` for
+//! the messages the app sends itself, so a misspelt `type` or field is a
+//! compile error rather than a runtime "unknown message". Anything not
+//! covered here can still be sent as raw JSON with
+//! [`SidecarManager::send_message`](crate::sidecar::SidecarManager::send_message).
+
+use serde_json::Value;
+
+use crate::transcribe::Compression;
+
+/// Prefix of the error returned by [`validate_message`] for a raw message
+/// the sidecar could not make sense of.
+pub const ERR_INVALID_MESSAGE: &str = "InvalidMessage";
+
+/// A request to the sidecar. Serializes to `{"type": "<snake_case>", ...}`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SidecarRequest {
+    Health,
+    TranscribeChunk {
+        /// Base64 of 16 kHz mono 16-bit PCM, compressed with `compression`
+        /// if set.
+        audio_base64: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        initial_prompt: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        compression: Option<Compression>,
+    },
+    Diarize {
+        audio_path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        num_speakers: Option<u32>,
+    },
+    CreateMeeting {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        audio_path: Option<String>,
+    },
+    /// Restore streaming context on a freshly restarted sidecar.
+    ResumeContext {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        initial_prompt: Option<String>,
+        /// Most recent transcript text, for continuity across the restart.
+        transcript_tail: String,
+    },
+    /// Ask for the model profiles the sidecar can switch between.
+    ListModels,
+    /// Make the model `model_id` the active one.
+    SetModel {
+        model_id: String,
+    },
+    /// Ask which optional features the backend supports.
+    Capabilities,
+    /// Ask for the backend's version and the protocol version it speaks.
+    Version,
+    /// Sent while idle to keep the model warm. The reply is ignored.
+    Keepalive,
+    /// Move the model to another compute device without restarting.
+    SetDevice {
+        device: ComputeDevice,
+    },
+}
+
+/// Compute device the sidecar can run its model on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComputeDevice {
+    Cpu,
+    Cuda,
+    /// Let the backend pick, usually CUDA when it is available.
+    Auto,
+}
+
+/// Reply to `set_device`: `{"status": "ok", "device": ...}` naming the
+/// device actually in use (what `auto` resolved to), or a failure with a
+/// `message`. Read without regard to `type`, so an `error` response parses
+/// too, as a failure.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DeviceSetResponse {
+    #[serde(default)]
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// One segment of a `transcription` response.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TranscriptSegment {
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+    #[serde(default)]
+    pub is_partial: bool,
+}
+
+/// A finished transcript with segment timestamps: the `text` and `segments`
+/// of a `transcription` response, which deserializes into it directly.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Transcript {
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub segments: Vec<TranscriptSegment>,
+}
+
+/// One model profile reported in a `models_list` response.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub name: String,
+    /// Held in memory, so switching to it is instant.
+    #[serde(default)]
+    pub loaded: bool,
+}
+
+/// Optional features reported in a `capabilities` response.
+///
+/// Fields the backend leaves out take their [`Default`] value, which is also
+/// what is assumed for a backend too old to answer `capabilities` at all:
+/// only what every backend version has supported.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SidecarCapabilities {
+    /// Transcription segments carry start/end times.
+    pub timestamps: bool,
+    pub diarization: bool,
+    pub translation: bool,
+    /// Can hold several models and switch with `set_model`.
+    pub model_switching: bool,
+    /// Accepts gzip-compressed audio in `transcribe_chunk`.
+    pub compression: bool,
+    /// Transcribes audio chunk by chunk as it is recorded.
+    pub streaming: bool,
+    pub backend_version: Option<String>,
+    /// Name of the transcription engine, e.g. `"faster-whisper"`.
+    pub engine: Option<String>,
+    /// `false` when the backend did not answer and these are the defaults.
+    #[serde(skip_deserializing)]
+    pub reported: bool,
+}
+
+impl Default for SidecarCapabilities {
+    fn default() -> Self {
+        Self {
+            timestamps: true,
+            diarization: true,
+            translation: false,
+            model_switching: false,
+            compression: false,
+            streaming: false,
+            backend_version: None,
+            engine: None,
+            reported: false,
+        }
+    }
+}
+
+/// A response from the sidecar, keyed on its `type` field.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SidecarResponse {
+    Health {
+        status: String,
+    },
+    Transcription {
+        text: String,
+        #[serde(default)]
+        segments: Vec<TranscriptSegment>,
+        #[serde(default)]
+        is_partial: bool,
+    },
+    DiarizationComplete {
+        segments: Vec<Value>,
+        #[serde(default)]
+        embeddings: Value,
+    },
+    MeetingCreated {
+        meeting_id: i64,
+    },
+    ModelsList {
+        models: Vec<ModelInfo>,
+    },
+    Error {
+        message: String,
+    },
+    /// Any response type not modelled above.
+    #[serde(other)]
+    Other,
+}
+
+impl SidecarResponse {
+    /// `true` for a `health` response reporting `"ok"`.
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, SidecarResponse::Health { status } if status == "ok")
+    }
+}
+
+/// Check that a raw `message` has the shape every request shares: a JSON
+/// object with a non-empty string `type`.
+///
+/// # Errors
+/// Returns an [`ERR_INVALID_MESSAGE`] error saying what is wrong otherwise.
+pub fn validate_message(message: &Value) -> Result<(), String> {
+    let Some(fields) = message.as_object() else {
+        return Err(format!(
+            "{ERR_INVALID_MESSAGE}: expected a JSON object, got {message}"
+        ));
+    };
+    match fields.get("type") {
+        Some(Value::String(kind)) if !kind.trim().is_empty() => Ok(()),
+        Some(Value::String(_)) => Err(format!("{ERR_INVALID_MESSAGE}: `type` is empty")),
+        Some(other) => Err(format!(
+            "{ERR_INVALID_MESSAGE}: `type` must be a string, got {other}"
+        )),
+        None => Err(format!("{ERR_INVALID_MESSAGE}: missing `type` field")),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip_request(request: SidecarRequest, wire: Value) {
+        assert_eq!(serde_json::to_value(&request).expect("serialize"), wire);
+        let back: SidecarRequest = serde_json::from_value(wire).expect("parse");
+        assert_eq!(back, request);
+    }
+
+    fn round_trip_response(wire: Value, response: SidecarResponse) {
+        let parsed: SidecarResponse = serde_json::from_value(wire.clone()).expect("parse");
+        assert_eq!(parsed, response);
+        assert_eq!(serde_json::to_value(&response).expect("serialize"), wire);
+    }
+
+    #[test]
+    fn test_health_request() {
+        round_trip_request(SidecarRequest::Health, json!({"type": "health"}));
+    }
+
+    #[test]
+    fn test_transcribe_chunk_request() {
+        round_trip_request(
+            SidecarRequest::TranscribeChunk {
+                audio_base64: "AAAA".into(),
+                initial_prompt: Some("Alice Bob".into()),
+                language: None,
+                compression: Some(Compression::Gzip),
+            },
+            json!({
+                "type": "transcribe_chunk",
+                "audio_base64": "AAAA",
+                "initial_prompt": "Alice Bob",
+                "compression": "gzip",
+            }),
+        );
+        round_trip_request(
+            SidecarRequest::TranscribeChunk {
+                audio_base64: "AAAA".into(),
+                initial_prompt: None,
+                language: Some("en".into()),
+                compression: None,
+            },
+            json!({"type": "transcribe_chunk", "audio_base64": "AAAA", "language": "en"}),
+        );
+    }
+
+    #[test]
+    fn test_diarize_and_create_meeting_requests() {
+        round_trip_request(
+            SidecarRequest::Diarize {
+                audio_path: "/rec/a.wav".into(),
+                num_speakers: Some(2),
+            },
+            json!({"type": "diarize", "audio_path": "/rec/a.wav", "num_speakers": 2}),
+        );
+        round_trip_request(
+            SidecarRequest::CreateMeeting {
+                title: Some("Standup".into()),
+                audio_path: None,
+            },
+            json!({"type": "create_meeting", "title": "Standup"}),
+        );
+    }
+
+    #[test]
+    fn test_resume_context_request() {
+        round_trip_request(
+            SidecarRequest::ResumeContext {
+                initial_prompt: Some("Alice Bob".into()),
+                transcript_tail: "and that's the plan".into(),
+            },
+            json!({
+                "type": "resume_context",
+                "initial_prompt": "Alice Bob",
+                "transcript_tail": "and that's the plan",
+            }),
+        );
+    }
+
+    #[test]
+    fn test_model_requests() {
+        round_trip_request(SidecarRequest::ListModels, json!({"type": "list_models"}));
+        round_trip_request(
+            SidecarRequest::SetModel {
+                model_id: "large-v3".into(),
+            },
+            json!({"type": "set_model", "model_id": "large-v3"}),
+        );
+    }
+
+    #[test]
+    fn test_models_list_response() {
+        round_trip_response(
+            json!({
+                "type": "models_list",
+                "models": [
+                    {"id": "small", "name": "Whisper small", "loaded": true},
+                    {"id": "large-v3", "name": "Whisper large v3", "loaded": false},
+                ],
+            }),
+            SidecarResponse::ModelsList {
+                models: vec![
+                    ModelInfo {
+                        id: "small".into(),
+                        name: "Whisper small".into(),
+                        loaded: true,
+                    },
+                    ModelInfo {
+                        id: "large-v3".into(),
+                        name: "Whisper large v3".into(),
+                        loaded: false,
+                    },
+                ],
+            },
+        );
+
+        // `loaded` is optional on the wire.
+        let parsed: SidecarResponse = serde_json::from_value(json!({
+            "type": "models_list",
+            "models": [{"id": "tiny", "name": "Whisper tiny"}],
+        }))
+        .expect("parse");
+        let SidecarResponse::ModelsList { models } = parsed else {
+            panic!("expected models_list, got {parsed:?}");
+        };
+        assert!(!models[0].loaded);
+    }
+
+    #[test]
+    fn test_health_and_error_responses() {
+        let health = SidecarResponse::Health {
+            status: "ok".into(),
+        };
+        assert!(health.is_healthy());
+        round_trip_response(json!({"type": "health", "status": "ok"}), health);
+        round_trip_response(
+            json!({"type": "error", "message": "boom"}),
+            SidecarResponse::Error {
+                message: "boom".into(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_transcription_response() {
+        round_trip_response(
+            json!({
+                "type": "transcription",
+                "text": "hello",
+                "segments": [{"text": "hello", "start": 0.0, "end": 0.5, "is_partial": false}],
+                "is_partial": false,
+            }),
+            SidecarResponse::Transcription {
+                text: "hello".into(),
+                segments: vec![TranscriptSegment {
+                    text: "hello".into(),
+                    start: 0.0,
+                    end: 0.5,
+                    is_partial: false,
+                }],
+                is_partial: false,
+            },
+        );
+    }
+
+    #[test]
+    fn test_diarization_and_meeting_responses() {
+        round_trip_response(
+            json!({
+                "type": "diarization_complete",
+                "segments": [{"speaker": "SPEAKER_00", "start": 0.0, "end": 1.0}],
+                "embeddings": {"SPEAKER_00": [0.1, 0.2]},
+            }),
+            SidecarResponse::DiarizationComplete {
+                segments: vec![json!({"speaker": "SPEAKER_00", "start": 0.0, "end": 1.0})],
+                embeddings: json!({"SPEAKER_00": [0.1, 0.2]}),
+            },
+        );
+        round_trip_response(
+            json!({"type": "meeting_created", "meeting_id": 7}),
+            SidecarResponse::MeetingCreated { meeting_id: 7 },
+        );
+    }
+
+    #[test]
+    fn test_unknown_response_type_is_other() {
+        let parsed: SidecarResponse =
+            serde_json::from_value(json!({"type": "speakers_list", "speakers": []}))
+                .expect("parse");
+        assert_eq!(parsed, SidecarResponse::Other);
+        assert!(!parsed.is_healthy());
+    }
+
+    #[test]
+    fn test_capabilities_request() {
+        round_trip_request(
+            SidecarRequest::Capabilities,
+            json!({"type": "capabilities"}),
+        );
+    }
+
+    #[test]
+    fn test_version_request() {
+        round_trip_request(SidecarRequest::Version, json!({"type": "version"}));
+    }
+
+    #[test]
+    fn test_keepalive_request() {
+        round_trip_request(SidecarRequest::Keepalive, json!({"type": "keepalive"}));
+    }
+
+    #[test]
+    fn test_set_device_request() {
+        round_trip_request(
+            SidecarRequest::SetDevice {
+                device: ComputeDevice::Cuda,
+            },
+            json!({"type": "set_device", "device": "cuda"}),
+        );
+        round_trip_request(
+            SidecarRequest::SetDevice {
+                device: ComputeDevice::Auto,
+            },
+            json!({"type": "set_device", "device": "auto"}),
+        );
+    }
+
+    #[test]
+    fn test_device_set_responses() {
+        let ok: DeviceSetResponse =
+            serde_json::from_value(json!({"status": "ok", "device": "cuda"})).expect("parse");
+        assert_eq!(
+            ok,
+            DeviceSetResponse {
+                status: "ok".into(),
+                device: Some("cuda".into()),
+                message: None,
+            }
+        );
+
+        let failed: DeviceSetResponse = serde_json::from_value(json!({
+            "status": "error",
+            "message": "CUDA is not available",
+        }))
+        .expect("parse");
+        assert_eq!(failed.status, "error");
+        assert_eq!(failed.message.as_deref(), Some("CUDA is not available"));
+
+        // A plain `error` response reads as a failure without a status.
+        let error: DeviceSetResponse = serde_json::from_value(json!({
+            "type": "error",
+            "message": "Unknown message type: set_device",
+        }))
+        .expect("parse");
+        assert_eq!(error.status, "");
+        assert_eq!(error.device, None);
+    }
+
+    #[test]
+    fn test_capabilities_response_parses_with_defaults_for_missing_fields() {
+        let caps: SidecarCapabilities = serde_json::from_value(json!({
+            "type": "capabilities",
+            "diarization": false,
+            "translation": true,
+            "backend_version": "0.4.1",
+            "some_future_flag": true,
+        }))
+        .expect("parse");
+        assert_eq!(
+            caps,
+            SidecarCapabilities {
+                diarization: false,
+                translation: true,
+                backend_version: Some("0.4.1".into()),
+                ..SidecarCapabilities::default()
+            }
+        );
+        // Only the manager sets `reported`, never the wire.
+        let caps: SidecarCapabilities =
+            serde_json::from_value(json!({"reported": true})).expect("parse");
+        assert!(!caps.reported);
+    }
+
+    #[test]
+    fn test_validate_message_accepts_typed_object() {
+        assert!(validate_message(&json!({"type": "health"})).is_ok());
+        assert!(validate_message(&json!({"type": "transcribe_chunk", "audio_base64": ""})).is_ok());
+    }
+
+    #[test]
+    fn test_validate_message_rejects_missing_or_bad_type() {
+        for bad in [
+            json!({"audio_base64": "AAAA"}),
+            json!({"type": ""}),
+            json!({"type": 3}),
+            json!({"type": null}),
+            json!(["health"]),
+            json!("health"),
+        ] {
+            let err = validate_message(&bad).unwrap_err();
+            assert!(err.starts_with(ERR_INVALID_MESSAGE), "{bad}: {err}");
+        }
+        assert!(validate_message(&json!({}))
+            .unwrap_err()
+            .contains("missing `type`"));
+    }
+}
@@ -0,0 +1,1286 @@
+//! Operations over the recordings directory as a whole.
+//!
+//! Lists the WAV files the capture engine has written, renames them along
+//! with their transcripts, evicts the oldest to keep the directory under a
+//! size cap, purges transcripts left behind by recordings deleted outside
+//! the app, joins several into one file, builds a JSON manifest of them
+//! for backup tooling, and checksums their audio for integrity checks.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::audio::wav::{copy_samples, data_checksum, read_info_metadata, read_wav_info};
+use crate::storage;
+
+/// List the `.wav` files inside `dir` and its subfolders, sorted by path.
+///
+/// Subfolders are searched so recordings kept in the
+/// [`DateHierarchy`](crate::audio::config::DirectoryLayout::DateHierarchy)
+/// layout are found.
+///
+/// A missing directory yields an empty list, since it is only created when
+/// the first recording starts.
+///
+/// # Errors
+/// Returns an error if the directory or one of its subfolders exists but
+/// cannot be read.
+pub fn list_wav_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    collect_files(dir, &mut files, &has_wav_extension)?;
+    files.sort();
+
+    Ok(files)
+}
+
+/// Append the files under `dir` for which `keep` holds to `files`,
+/// descending into subfolders.
+fn collect_files(
+    dir: &Path,
+    files: &mut Vec<PathBuf>,
+    keep: &dyn Fn(&Path) -> bool,
+) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read recordings directory: {e}"))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read recordings directory entry: {e}"))?;
+        let path = entry.path();
+        // `file_type` does not follow symlinks, so a link cycle cannot
+        // recurse forever.
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        if is_dir {
+            collect_files(&path, files, keep)?;
+        } else if path.is_file() && keep(&path) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Subfolder of the recordings directory for a recording started at
+/// `unix_secs`, as `YYYY/MM/` in UTC.
+pub fn dated_subdir(unix_secs: u64) -> PathBuf {
+    let (year, month, _) = civil_from_days(unix_secs / 86_400);
+    PathBuf::from(format!("{year:04}")).join(format!("{month:02}"))
+}
+
+/// Gregorian year, month and day of the day `days` after 1970-01-01.
+///
+/// Howard Hinnant's `civil_from_days`, restricted to dates after the epoch.
+pub fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// File name prefix of recordings, followed by the unix start time.
+pub const RECORDING_FILE_PREFIX: &str = "recording_";
+
+/// Start time encoded in a recording's file name, as in
+/// `recording_1718452800.wav`.
+///
+/// Suffixes after the timestamp are allowed if each is `_<digits>` (a copy
+/// given a unique name) or `_part<digits>` (a segment), in any order. Any
+/// other name returns `None`.
+pub fn parse_recording_timestamp(filename: &str) -> Option<SystemTime> {
+    let stem = Path::new(filename).file_stem()?.to_str()?;
+    let rest = stem.strip_prefix(RECORDING_FILE_PREFIX)?;
+    let mut parts = rest.split('_');
+    let secs = parts.next().filter(|s| is_digits(s))?.parse().ok()?;
+    let suffixes_ok = parts.all(|part| is_digits(part.strip_prefix("part").unwrap_or(part)));
+    if !suffixes_ok {
+        return None;
+    }
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+/// When the recording at `path` started: the time in its file name (see
+/// [`parse_recording_timestamp`]), or else its modification time, which is
+/// less reliable because copying a file can change it. `None` if neither
+/// is available.
+pub fn recording_started_at(path: &Path) -> Option<SystemTime> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(parse_recording_timestamp)
+        .or_else(|| fs::metadata(path).and_then(|m| m.modified()).ok())
+}
+
+/// Non-empty and ASCII digits only.
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Returns `true` if the path ends in `.wav` (case-insensitive).
+fn has_wav_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("wav"))
+}
+
+// ---------------------------------------------------------------------------
+// Renaming
+// ---------------------------------------------------------------------------
+
+/// Prefix of the error returned when a new recording name is empty or could
+/// escape the recording's folder.
+pub const ERR_INVALID_RECORDING_NAME: &str = "Invalid recording name";
+
+/// Prefix of the error returned when the renamed recording or one of its
+/// companions would overwrite an existing file.
+pub const ERR_RECORDING_EXISTS: &str = "Recording already exists";
+
+/// Prefix of the error returned when a path to act on is not a file inside
+/// the recordings directory.
+pub const ERR_NOT_A_RECORDING: &str = "Not a recording";
+
+/// Prefix of the error returned by [`concatenate_recordings`] when the
+/// inputs do not all share one WAV format.
+pub const ERR_SPEC_MISMATCH: &str = "Recordings have different formats";
+
+/// Extensions of transcript files kept next to a recording under the same
+/// stem, renamed with it.
+pub const COMPANION_EXTENSIONS: &[&str] = &["txt", "json"];
+
+/// Extension of the file next to a recording, under the same stem, holding
+/// the hex SHA-256 of its audio (see [`write_checksum`]).
+pub const CHECKSUM_EXTENSION: &str = "sha256";
+
+/// Prefix of the error returned by [`verify_recording`] when the recording
+/// has no checksum file.
+pub const ERR_NO_CHECKSUM: &str = "No checksum stored for recording";
+
+/// Turn the user-supplied `new_name` into a file name for a recording with
+/// extension `extension`.
+///
+/// Surrounding whitespace is trimmed and `.{extension}` is appended unless
+/// the name already ends with it (case-insensitive).
+///
+/// # Errors
+/// Returns an [`ERR_INVALID_RECORDING_NAME`] error if the name is empty,
+/// `.` or `..`, or contains a path separator or control character.
+pub fn sanitize_recording_name(new_name: &str, extension: &str) -> Result<String, String> {
+    let name = new_name.trim();
+    let invalid = |reason: &str| format!("{ERR_INVALID_RECORDING_NAME} '{new_name}': {reason}");
+    if name.is_empty() {
+        return Err(invalid("name is empty"));
+    }
+    if name.contains(['/', '\\']) {
+        return Err(invalid("name contains a path separator"));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(invalid("name contains a control character"));
+    }
+    if name == "." || name == ".." {
+        return Err(invalid("name refers to a directory"));
+    }
+
+    let suffix = format!(".{extension}");
+    let has_extension = name.len() > suffix.len()
+        && name
+            .get(name.len() - suffix.len()..)
+            .is_some_and(|end| end.eq_ignore_ascii_case(&suffix));
+    Ok(if has_extension || extension.is_empty() {
+        name.to_string()
+    } else {
+        format!("{name}{suffix}")
+    })
+}
+
+/// Rename the recording at `old_path`, which must be inside
+/// `recordings_dir`, to `new_name` (see [`sanitize_recording_name`]) in the
+/// same folder. Transcript companions with the same stem and one of the
+/// [`COMPANION_EXTENSIONS`], and its checksum file, are renamed with it.
+/// Returns the new path.
+///
+/// # Errors
+/// Returns an [`ERR_NOT_A_RECORDING`] error if `old_path` is not a file
+/// inside `recordings_dir`, an [`ERR_INVALID_RECORDING_NAME`] error for a bad
+/// name, an [`ERR_RECORDING_EXISTS`] error if the recording or a companion
+/// would overwrite a file, or an error if a rename fails (renames already
+/// done are undone).
+pub fn rename_recording(
+    recordings_dir: &Path,
+    old_path: &Path,
+    new_name: &str,
+) -> Result<PathBuf, String> {
+    let dir = canonical_dir(recordings_dir)?;
+    let old = resolve_recording(&dir, old_path)?;
+
+    let extension = old.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let file_name = sanitize_recording_name(new_name, extension)?;
+    let new = old.with_file_name(file_name);
+    if new == old {
+        return Ok(new);
+    }
+
+    let mut renames = vec![(old.clone(), new.clone())];
+    for ext in COMPANION_EXTENSIONS.iter().chain([&CHECKSUM_EXTENSION]) {
+        let companion = old.with_extension(ext);
+        if companion.is_file() {
+            renames.push((companion, new.with_extension(ext)));
+        }
+    }
+    for (from, to) in &renames {
+        // A case-only rename on a case-insensitive volume finds the file
+        // itself.
+        let is_self = to.canonicalize().is_ok_and(|t| &t == from);
+        if to.exists() && !is_self {
+            return Err(format!("{ERR_RECORDING_EXISTS}: '{}'", to.display()));
+        }
+    }
+
+    for (done, (from, to)) in renames.iter().enumerate() {
+        if let Err(e) = fs::rename(from, to) {
+            for (from, to) in renames[..done].iter().rev() {
+                let _ = fs::rename(to, from);
+            }
+            return Err(format!(
+                "Failed to rename '{}' to '{}': {e}",
+                from.display(),
+                to.display()
+            ));
+        }
+    }
+    Ok(new)
+}
+
+fn canonical_dir(recordings_dir: &Path) -> Result<PathBuf, String> {
+    recordings_dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve '{}': {e}", recordings_dir.display()))
+}
+
+/// Resolve `path` (following `..` and symlinks) and check it is a file
+/// inside `dir`, which must already be canonical.
+fn resolve_recording(dir: &Path, path: &Path) -> Result<PathBuf, String> {
+    let not_a_recording = || {
+        format!(
+            "{ERR_NOT_A_RECORDING}: '{}' is not a file in the recordings directory",
+            path.display()
+        )
+    };
+    storage::file_within(dir, path).ok_or_else(not_a_recording)
+}
+
+// ---------------------------------------------------------------------------
+// Size limit
+// ---------------------------------------------------------------------------
+
+/// A recording considered for eviction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredRecording {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// From [`recording_started_at`].
+    pub started_at: SystemTime,
+}
+
+/// The recordings to delete, oldest first, to bring the total size of
+/// `recordings` down to `max_total_bytes`.
+///
+/// Recordings in `exempt` still count towards the total but are never
+/// selected, so the result may leave the total above the cap. Ties in age
+/// are broken by path.
+pub fn select_evictions(
+    recordings: &[StoredRecording],
+    max_total_bytes: u64,
+    exempt: &[PathBuf],
+) -> Vec<PathBuf> {
+    let mut total: u64 = recordings.iter().map(|r| r.size_bytes).sum();
+    let mut candidates: Vec<&StoredRecording> = recordings
+        .iter()
+        .filter(|r| !exempt.contains(&r.path))
+        .collect();
+    candidates.sort_by(|a, b| (a.started_at, &a.path).cmp(&(b.started_at, &b.path)));
+
+    let mut evicted = Vec::new();
+    for recording in candidates {
+        if total <= max_total_bytes {
+            break;
+        }
+        total = total.saturating_sub(recording.size_bytes);
+        evicted.push(recording.path.clone());
+    }
+    evicted
+}
+
+/// Delete the oldest recordings in `dir` (see [`select_evictions`]) until
+/// their total size is at most `max_total_bytes`, never touching `exempt`.
+/// Transcript companions (see [`COMPANION_EXTENSIONS`]) and checksum files
+/// are deleted with their recording. Returns the recordings deleted.
+///
+/// A recording that cannot be stat'ed or deleted is skipped, so the total
+/// may stay above the cap.
+///
+/// # Errors
+/// Returns an error if the directory cannot be listed.
+pub fn enforce_size_limit(
+    dir: &Path,
+    max_total_bytes: u64,
+    exempt: &[PathBuf],
+) -> Result<Vec<PathBuf>, String> {
+    let recordings: Vec<StoredRecording> = list_wav_files(dir)?
+        .into_iter()
+        .filter_map(|path| {
+            let size_bytes = fs::metadata(&path).ok()?.len();
+            let started_at = recording_started_at(&path).unwrap_or(UNIX_EPOCH);
+            Some(StoredRecording {
+                path,
+                size_bytes,
+                started_at,
+            })
+        })
+        .collect();
+
+    let mut deleted = Vec::new();
+    for path in select_evictions(&recordings, max_total_bytes, exempt) {
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Failed to evict '{}': {e}", path.display());
+            continue;
+        }
+        for ext in COMPANION_EXTENSIONS.iter().chain([&CHECKSUM_EXTENSION]) {
+            let _ = fs::remove_file(path.with_extension(ext));
+        }
+        deleted.push(path);
+    }
+    Ok(deleted)
+}
+
+// ---------------------------------------------------------------------------
+// Orphaned companions
+// ---------------------------------------------------------------------------
+
+/// Companion files whose recording is gone, as found by
+/// [`purge_orphan_companions`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct OrphanPurge {
+    /// Orphans deleted or, in a dry run, that would be.
+    pub paths: Vec<String>,
+    /// Total size of `paths`.
+    pub reclaimed_bytes: u64,
+    pub dry_run: bool,
+}
+
+/// Whether `path` has the extension of a transcript companion (see
+/// [`COMPANION_EXTENSIONS`]) or checksum file.
+fn has_companion_extension(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| {
+        COMPANION_EXTENSIONS
+            .iter()
+            .chain([&CHECKSUM_EXTENSION])
+            .any(|ext| e.eq_ignore_ascii_case(ext))
+    })
+}
+
+/// The companion and checksum files in `files` with no `.wav` of the same
+/// stem next to them in `files`, in listing order.
+pub fn find_orphan_companions(files: &[PathBuf]) -> Vec<PathBuf> {
+    let recordings: BTreeSet<PathBuf> = files
+        .iter()
+        .filter(|path| has_wav_extension(path))
+        .map(|path| path.with_extension(""))
+        .collect();
+    files
+        .iter()
+        .filter(|path| has_companion_extension(path))
+        .filter(|path| !recordings.contains(&path.with_extension("")))
+        .cloned()
+        .collect()
+}
+
+/// Delete the transcript companions and checksum files in `dir` and its
+/// subfolders whose recording no longer exists (see
+/// [`find_orphan_companions`]), e.g. after it was deleted outside the app.
+/// With `dry_run` nothing is deleted and the orphans are only listed.
+///
+/// An orphan that cannot be stat'ed or deleted is skipped.
+///
+/// # Errors
+/// Returns an error if the directory or one of its subfolders cannot be
+/// listed.
+pub fn purge_orphan_companions(dir: &Path, dry_run: bool) -> Result<OrphanPurge, String> {
+    let mut files = Vec::new();
+    if dir.exists() {
+        collect_files(dir, &mut files, &|path| {
+            has_wav_extension(path) || has_companion_extension(path)
+        })?;
+        files.sort();
+    }
+
+    let mut purge = OrphanPurge {
+        paths: Vec::new(),
+        reclaimed_bytes: 0,
+        dry_run,
+    };
+    for path in find_orphan_companions(&files) {
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        if !dry_run {
+            if let Err(e) = fs::remove_file(&path) {
+                eprintln!("Failed to delete orphan '{}': {e}", path.display());
+                continue;
+            }
+        }
+        purge.reclaimed_bytes += metadata.len();
+        purge.paths.push(path.to_string_lossy().into_owned());
+    }
+    Ok(purge)
+}
+
+// ---------------------------------------------------------------------------
+// Checksums
+// ---------------------------------------------------------------------------
+
+/// Result of checking a recording against its stored checksum.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ChecksumVerification {
+    pub path: String,
+    /// Hex SHA-256 from the checksum file.
+    pub expected: String,
+    /// Hex SHA-256 of the audio now.
+    pub actual: String,
+    pub matches: bool,
+}
+
+/// Compute the checksum of the recording at `path` (see
+/// [`data_checksum`]) and store it in the [`CHECKSUM_EXTENSION`] file next
+/// to it, replacing any earlier one. Returns the checksum.
+///
+/// # Errors
+/// Returns an error if the recording cannot be read or the checksum file
+/// cannot be written.
+pub fn write_checksum(path: &Path) -> Result<String, String> {
+    let checksum = data_checksum(path)?;
+    let checksum_path = path.with_extension(CHECKSUM_EXTENSION);
+    fs::write(&checksum_path, format!("{checksum}\n"))
+        .map_err(|e| format!("Failed to write '{}': {e}", checksum_path.display()))?;
+    Ok(checksum)
+}
+
+/// The checksum stored next to the recording at `path`, if there is one.
+///
+/// # Errors
+/// Returns an error if the checksum file exists but cannot be read.
+pub fn stored_checksum(path: &Path) -> Result<Option<String>, String> {
+    let checksum_path = path.with_extension(CHECKSUM_EXTENSION);
+    match fs::read_to_string(&checksum_path) {
+        Ok(contents) => Ok(Some(contents.trim().to_ascii_lowercase())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read '{}': {e}", checksum_path.display())),
+    }
+}
+
+/// Recompute the checksum of the recording at `path` and compare it with
+/// the stored one.
+///
+/// # Errors
+/// Returns an [`ERR_NO_CHECKSUM`] error if no checksum was stored, or an
+/// error if either file cannot be read.
+pub fn verify_recording(path: &Path) -> Result<ChecksumVerification, String> {
+    let expected =
+        stored_checksum(path)?.ok_or_else(|| format!("{ERR_NO_CHECKSUM}: '{}'", path.display()))?;
+    let actual = data_checksum(path)?;
+    Ok(ChecksumVerification {
+        path: path.to_string_lossy().into_owned(),
+        matches: actual == expected,
+        expected,
+        actual,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Manifest
+// ---------------------------------------------------------------------------
+
+/// One readable recording in the manifest.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size_bytes: u64,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    pub frames: u32,
+    pub duration_seconds: f64,
+    /// Start time in unix seconds, from [`recording_started_at`].
+    pub started_at_unix: Option<u64>,
+    /// Fields from the WAV's `LIST/INFO` chunk, keyed by four-character id.
+    pub metadata: BTreeMap<String, String>,
+    /// Stored checksum of the audio (see [`write_checksum`]), if any.
+    pub checksum: Option<String>,
+}
+
+/// A recording that could not be read, recorded instead of failing the
+/// whole export.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ManifestError {
+    pub path: String,
+    pub error: String,
+}
+
+/// Manifest of every recording in the recordings directory.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Manifest {
+    pub recordings: Vec<ManifestEntry>,
+    pub errors: Vec<ManifestError>,
+}
+
+/// Build a manifest of every WAV in `dir`.
+///
+/// Files that cannot be read are listed under `errors` rather than aborting.
+///
+/// # Errors
+/// Returns an error only if the directory itself cannot be listed.
+pub fn build_manifest(dir: &Path) -> Result<Manifest, String> {
+    let mut manifest = Manifest {
+        recordings: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    for path in list_wav_files(dir)? {
+        let path_str = path.to_string_lossy().into_owned();
+        match manifest_entry(&path) {
+            Ok(entry) => manifest.recordings.push(entry),
+            Err(error) => manifest.errors.push(ManifestError {
+                path: path_str,
+                error,
+            }),
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Read the spec, size, and INFO metadata for a single recording.
+fn manifest_entry(path: &Path) -> Result<ManifestEntry, String> {
+    let size_bytes = fs::metadata(path)
+        .map_err(|e| format!("Failed to stat '{}': {e}", path.display()))?
+        .len();
+    let info = read_wav_info(path)?;
+    let metadata = read_info_metadata(path)?;
+    let checksum = stored_checksum(path)?;
+
+    Ok(ManifestEntry {
+        path: path.to_string_lossy().into_owned(),
+        size_bytes,
+        sample_rate: info.sample_rate,
+        channels: info.channels,
+        bits_per_sample: info.bits_per_sample,
+        frames: info.frames,
+        duration_seconds: info.duration_seconds,
+        started_at_unix: recording_started_at(path)
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+        metadata,
+        checksum,
+    })
+}
+
+/// A recording with no transcript companion yet.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct UntranscribedRecording {
+    pub path: String,
+    pub size_bytes: u64,
+    /// `None` if the WAV cannot be read, e.g. after a crash.
+    pub duration_seconds: Option<f64>,
+    /// Start time in unix seconds, from [`recording_started_at`].
+    pub started_at_unix: Option<u64>,
+}
+
+/// Whether a transcript companion (see [`COMPANION_EXTENSIONS`]) sits next
+/// to the recording at `path`.
+pub fn has_transcript(path: &Path) -> bool {
+    COMPANION_EXTENSIONS
+        .iter()
+        .any(|ext| path.with_extension(ext).is_file())
+}
+
+/// Every WAV in `dir` and its subfolders without a transcript companion,
+/// sorted by path: the transcription backlog.
+///
+/// # Errors
+/// Returns an error if the directory or one of its subfolders cannot be
+/// listed, or a recording cannot be stat'ed.
+pub fn list_untranscribed_recordings(dir: &Path) -> Result<Vec<UntranscribedRecording>, String> {
+    list_wav_files(dir)?
+        .into_iter()
+        .filter(|path| !has_transcript(path))
+        .map(|path| {
+            let size_bytes = fs::metadata(&path)
+                .map_err(|e| format!("Failed to stat '{}': {e}", path.display()))?
+                .len();
+            Ok(UntranscribedRecording {
+                path: path.to_string_lossy().into_owned(),
+                size_bytes,
+                duration_seconds: read_wav_info(&path).ok().map(|info| info.duration_seconds),
+                started_at_unix: recording_started_at(&path)
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs()),
+            })
+        })
+        .collect()
+}
+
+/// Write a manifest to `output` as pretty-printed JSON.
+///
+/// # Errors
+/// Returns an error if serialization or the file write fails.
+pub fn write_manifest(manifest: &Manifest, output: &Path) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {e}"))?;
+    fs::write(output, json)
+        .map_err(|e| format!("Failed to write manifest to '{}': {e}", output.display()))
+}
+
+// ---------------------------------------------------------------------------
+// Concatenation
+// ---------------------------------------------------------------------------
+
+/// Join the recordings at `paths`, in order, into a new WAV at `output`.
+/// All of them must be inside `recordings_dir`; a relative `output` is
+/// taken relative to it. Returns the path written.
+///
+/// Samples are streamed from one file to the next rather than loaded, and
+/// the output's RIFF and data lengths are written when it is finalized. A
+/// failed write leaves no partial output behind.
+///
+/// # Errors
+/// Returns an [`ERR_NOT_A_RECORDING`] error if an input is not a file inside
+/// `recordings_dir`, an [`ERR_SPEC_MISMATCH`] error naming the first input
+/// whose format differs from the first one's, an
+/// [`ERR_INVALID_RECORDING_NAME`] error if `output` is not a `.wav` path
+/// inside `recordings_dir`, an [`ERR_RECORDING_EXISTS`] error if it already
+/// exists, or an error if reading or writing fails.
+pub fn concatenate_recordings(
+    recordings_dir: &Path,
+    paths: &[PathBuf],
+    output: &Path,
+) -> Result<PathBuf, String> {
+    let dir = canonical_dir(recordings_dir)?;
+    let inputs = paths
+        .iter()
+        .map(|path| resolve_recording(&dir, path))
+        .collect::<Result<Vec<_>, _>>()?;
+    if inputs.is_empty() {
+        return Err("No recordings to concatenate".into());
+    }
+
+    let mut readers: Vec<hound::WavReader<std::io::BufReader<fs::File>>> =
+        Vec::with_capacity(inputs.len());
+    for input in &inputs {
+        let reader = hound::WavReader::open(input)
+            .map_err(|e| format!("Failed to read WAV '{}': {e}", input.display()))?;
+        if let Some(first) = readers.first() {
+            let (expected, found) = (first.spec(), reader.spec());
+            if found != expected {
+                return Err(format!(
+                    "{ERR_SPEC_MISMATCH}: '{}' is {} but '{}' is {}",
+                    input.display(),
+                    describe_spec(&found),
+                    inputs[0].display(),
+                    describe_spec(&expected),
+                ));
+            }
+        }
+        readers.push(reader);
+    }
+
+    let output = resolve_output(&dir, &recordings_dir.join(output))?;
+    let spec = readers[0].spec();
+    let written = hound::WavWriter::create(&output, spec)
+        .map_err(|e| format!("Failed to create '{}': {e}", output.display()))
+        .and_then(|mut writer| {
+            for (reader, input) in readers.iter_mut().zip(&inputs) {
+                copy_samples(reader, &mut writer, None)
+                    .map_err(|e| format!("Failed to copy '{}': {e}", input.display()))?;
+            }
+            writer
+                .finalize()
+                .map_err(|e| format!("Failed to finalize '{}': {e}", output.display()))
+        });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&output);
+        return Err(e);
+    }
+    Ok(output)
+}
+
+/// Check `output` names a new `.wav` file directly inside an existing
+/// folder of `dir` (which must be canonical), and resolve its folder.
+fn resolve_output(dir: &Path, output: &Path) -> Result<PathBuf, String> {
+    let invalid = || {
+        format!(
+            "{ERR_INVALID_RECORDING_NAME}: '{}' is not a WAV path in the recordings directory",
+            output.display()
+        )
+    };
+    let file_name = output.file_name().ok_or_else(invalid)?;
+    if !has_wav_extension(output) {
+        return Err(invalid());
+    }
+    let parent = output
+        .parent()
+        .and_then(|p| p.canonicalize().ok())
+        .filter(|p| p.starts_with(dir))
+        .ok_or_else(invalid)?;
+    let output = parent.join(file_name);
+    if output.exists() {
+        return Err(format!("{ERR_RECORDING_EXISTS}: '{}'", output.display()));
+    }
+    Ok(output)
+}
+
+/// E.g. `"16000 Hz, 1 channel(s), 16-bit int"`.
+fn describe_spec(spec: &hound::WavSpec) -> String {
+    let format = match spec.sample_format {
+        hound::SampleFormat::Float => "float",
+        hound::SampleFormat::Int => "int",
+    };
+    format!(
+        "{} Hz, {} channel(s), {}-bit {format}",
+        spec.sample_rate, spec.channels, spec.bits_per_sample
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{test_dir, write_test_wav};
+
+    #[test]
+    fn test_list_wav_files_missing_dir_is_empty() {
+        let dir = std::env::temp_dir().join("second_test_recordings_missing_xyz");
+        let _ = fs::remove_dir_all(&dir);
+        assert!(list_wav_files(&dir).expect("list").is_empty());
+    }
+
+    #[test]
+    fn test_list_wav_files_skips_other_extensions() {
+        let dir = test_dir("recordings", "list");
+        write_test_wav(&dir.join("b.wav"), 16_000, 1);
+        write_test_wav(&dir.join("a.WAV"), 16_000, 1);
+        fs::write(dir.join("notes.txt"), "hi").expect("write txt");
+
+        let files = list_wav_files(&dir).expect("list");
+        let names: Vec<_> = files
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["a.WAV", "b.wav"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_list_wav_files_recurses_into_subfolders() {
+        let dir = test_dir("recordings", "recurse");
+        fs::create_dir_all(dir.join("2024/06")).expect("create subdirs");
+        fs::create_dir_all(dir.join("2024/07")).expect("create subdirs");
+        write_test_wav(&dir.join("flat.wav"), 16_000, 1);
+        write_test_wav(&dir.join("2024/06/a.wav"), 16_000, 1);
+        write_test_wav(&dir.join("2024/07/b.wav"), 16_000, 1);
+        fs::write(dir.join("2024/07/notes.txt"), "hi").expect("write txt");
+
+        let files = list_wav_files(&dir).expect("list");
+        let relative: Vec<_> = files
+            .iter()
+            .map(|p| p.strip_prefix(&dir).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            relative,
+            vec![
+                PathBuf::from("2024/06/a.wav"),
+                PathBuf::from("2024/07/b.wav"),
+                PathBuf::from("flat.wav"),
+            ]
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_dated_subdir_is_utc_year_and_month() {
+        // 2024-06-15T12:00:00Z
+        assert_eq!(dated_subdir(1_718_452_800), PathBuf::from("2024/06"));
+        assert_eq!(dated_subdir(0), PathBuf::from("1970/01"));
+        // Month and leap-year boundaries: 2024-02-29T23:59:59Z and the
+        // following second.
+        assert_eq!(dated_subdir(1_709_251_199), PathBuf::from("2024/02"));
+        assert_eq!(dated_subdir(1_709_251_200), PathBuf::from("2024/03"));
+        // 2023-12-31T23:59:59Z and the new year.
+        assert_eq!(dated_subdir(1_704_067_199), PathBuf::from("2023/12"));
+        assert_eq!(dated_subdir(1_704_067_200), PathBuf::from("2024/01"));
+    }
+
+    #[test]
+    fn test_parse_recording_timestamp_from_default_names() {
+        let at = |secs| Some(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(
+            parse_recording_timestamp("recording_1718452800.wav"),
+            at(1_718_452_800)
+        );
+        // Segments and collision suffixes keep the timestamp.
+        assert_eq!(
+            parse_recording_timestamp("recording_1718452800_part002.wav"),
+            at(1_718_452_800)
+        );
+        assert_eq!(
+            parse_recording_timestamp("recording_1718452800_1.wav"),
+            at(1_718_452_800)
+        );
+        assert_eq!(
+            parse_recording_timestamp("recording_1718452800_part001_2.WAV"),
+            at(1_718_452_800)
+        );
+    }
+
+    #[test]
+    fn test_parse_recording_timestamp_rejects_other_names() {
+        for name in [
+            "meeting.wav",
+            "recording_.wav",
+            "recording_abc.wav",
+            "recording_-5.wav",
+            "recording_1718452800_copy.wav",
+            "recording_1718452800_.wav",
+            "Recording_1718452800.wav",
+        ] {
+            assert_eq!(parse_recording_timestamp(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_recording_started_at_falls_back_to_mtime() {
+        let dir = test_dir("recordings", "started_at");
+        let named = dir.join("recording_1718452800_1.wav");
+        let other = dir.join("interview.wav");
+        write_test_wav(&named, 16_000, 1);
+        write_test_wav(&other, 16_000, 1);
+
+        assert_eq!(
+            recording_started_at(&named),
+            Some(UNIX_EPOCH + Duration::from_secs(1_718_452_800))
+        );
+        assert_eq!(
+            recording_started_at(&other),
+            fs::metadata(&other).unwrap().modified().ok()
+        );
+        assert_eq!(recording_started_at(&dir.join("missing.wav")), None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_build_manifest_shape() {
+        let dir = test_dir("recordings", "manifest");
+        write_test_wav(&dir.join("recording_1.wav"), 16_000, 16_000);
+        write_test_wav(&dir.join("recording_2.wav"), 48_000, 24_000);
+        fs::write(dir.join("broken.wav"), b"garbage").expect("write broken");
+
+        let manifest = build_manifest(&dir).expect("manifest");
+        assert_eq!(manifest.recordings.len(), 2);
+        assert_eq!(manifest.errors.len(), 1);
+        assert!(manifest.errors[0].path.ends_with("broken.wav"));
+
+        let json = serde_json::to_value(&manifest).expect("serialize");
+        let first = &json["recordings"][0];
+        assert!(first["path"].as_str().unwrap().ends_with("recording_1.wav"));
+        assert_eq!(first["sample_rate"], 16_000);
+        assert_eq!(first["frames"], 16_000);
+        assert_eq!(first["duration_seconds"], 1.0);
+        assert_eq!(first["size_bytes"], 44 + 32_000);
+        assert_eq!(first["started_at_unix"], 1);
+        assert!(first["metadata"].is_object());
+        assert_eq!(json["recordings"][1]["duration_seconds"], 0.5);
+
+        let output = dir.join("manifest.json");
+        write_manifest(&manifest, &output).expect("write manifest");
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).expect("read")).expect("parse");
+        assert_eq!(written, json);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_list_untranscribed_recordings_skips_ones_with_companions() {
+        let dir = test_dir("recordings", "untranscribed");
+        fs::create_dir_all(dir.join("2024/06")).expect("create subdirs");
+        write_test_wav(&dir.join("recording_1.wav"), 16_000, 16_000);
+        fs::write(dir.join("recording_1.txt"), "hello").expect("write txt");
+        write_test_wav(&dir.join("recording_2.wav"), 16_000, 8_000);
+        write_test_wav(&dir.join("2024/06/recording_3.wav"), 16_000, 1);
+        fs::write(dir.join("2024/06/recording_3.json"), "{}").expect("write json");
+        write_test_wav(&dir.join("2024/06/recording_4.wav"), 16_000, 1);
+        // A companion with another stem does not count.
+        fs::write(dir.join("2024/06/recording_40.txt"), "x").expect("write txt");
+        fs::write(dir.join("broken.wav"), b"garbage").expect("write broken");
+
+        let backlog = list_untranscribed_recordings(&dir).expect("list");
+        let relative: Vec<_> = backlog
+            .iter()
+            .map(|r| Path::new(&r.path).strip_prefix(&dir).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            relative,
+            vec![
+                PathBuf::from("2024/06/recording_4.wav"),
+                PathBuf::from("broken.wav"),
+                PathBuf::from("recording_2.wav"),
+            ]
+        );
+        assert_eq!(backlog[1].duration_seconds, None);
+        assert_eq!(backlog[2].duration_seconds, Some(0.5));
+        assert_eq!(backlog[2].size_bytes, 44 + 16_000);
+        assert_eq!(backlog[2].started_at_unix, Some(2));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sanitize_recording_name_rejects_paths_and_traversal() {
+        for name in [
+            "../evil",
+            "a/b",
+            "..\\evil",
+            "sub\\name",
+            "..",
+            ".",
+            "  ",
+            "",
+            "a\0b",
+        ] {
+            let err = sanitize_recording_name(name, "wav").unwrap_err();
+            assert!(
+                err.starts_with(ERR_INVALID_RECORDING_NAME),
+                "{name:?}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sanitize_recording_name_keeps_or_adds_extension() {
+        assert_eq!(
+            sanitize_recording_name(" Standup ", "wav").unwrap(),
+            "Standup.wav"
+        );
+        assert_eq!(
+            sanitize_recording_name("Standup.WAV", "wav").unwrap(),
+            "Standup.WAV"
+        );
+        assert_eq!(
+            sanitize_recording_name("notes.mp3", "wav").unwrap(),
+            "notes.mp3.wav"
+        );
+        assert_eq!(sanitize_recording_name(".wav", "wav").unwrap(), ".wav.wav");
+        assert_eq!(
+            sanitize_recording_name("..hidden", "wav").unwrap(),
+            "..hidden.wav"
+        );
+    }
+
+    #[test]
+    fn test_rename_recording_moves_transcript_companions() {
+        let dir = test_dir("recordings", "rename");
+        fs::create_dir_all(dir.join("2024/06")).expect("create subdirs");
+        let old = dir.join("2024/06/recording_1718452800.wav");
+        write_test_wav(&old, 16_000, 1);
+        fs::write(old.with_extension("txt"), "hello").expect("write txt");
+        fs::write(old.with_extension("json"), "{}").expect("write json");
+        write_checksum(&old).expect("write checksum");
+        // Same stem but not a companion extension: left alone.
+        fs::write(old.with_extension("log"), "").expect("write log");
+
+        let new = rename_recording(&dir, &old, "Standup").expect("rename");
+        let folder = dir.join("2024/06").canonicalize().expect("canonicalize");
+        assert_eq!(new, folder.join("Standup.wav"));
+        assert!(new.is_file() && !old.exists());
+        assert_eq!(
+            fs::read_to_string(folder.join("Standup.txt")).expect("read txt"),
+            "hello"
+        );
+        assert!(folder.join("Standup.json").is_file());
+        assert!(verify_recording(&new).expect("verify").matches);
+        assert!(!old.with_extension("txt").exists());
+        assert!(old.with_extension("log").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_recording_rejects_collisions_without_moving_anything() {
+        let dir = test_dir("recordings", "rename_collision");
+        let old = dir.join("a.wav");
+        write_test_wav(&old, 16_000, 1);
+        fs::write(dir.join("a.txt"), "a").expect("write txt");
+        // Only the companion collides.
+        fs::write(dir.join("b.txt"), "b").expect("write txt");
+
+        let err = rename_recording(&dir, &old, "b").unwrap_err();
+        assert!(err.starts_with(ERR_RECORDING_EXISTS), "got: {err}");
+        assert!(old.is_file() && !dir.join("b.wav").exists());
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a");
+
+        write_test_wav(&dir.join("c.wav"), 16_000, 1);
+        let err = rename_recording(&dir, &old, "c.wav").unwrap_err();
+        assert!(err.starts_with(ERR_RECORDING_EXISTS), "got: {err}");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_recording_rejects_files_outside_recordings_dir() {
+        let dir = test_dir("recordings", "rename_outside");
+        let recordings = dir.join("recordings");
+        fs::create_dir_all(&recordings).expect("create dir");
+        let outside = dir.join("other.wav");
+        write_test_wav(&outside, 16_000, 1);
+
+        let err = rename_recording(&recordings, &outside, "x").unwrap_err();
+        assert!(err.starts_with(ERR_NOT_A_RECORDING), "got: {err}");
+        // `..` in the path is resolved before the check.
+        let sneaky = recordings.join("../other.wav");
+        let err = rename_recording(&recordings, &sneaky, "x").unwrap_err();
+        assert!(err.starts_with(ERR_NOT_A_RECORDING), "got: {err}");
+        let err = rename_recording(&recordings, &recordings, "x").unwrap_err();
+        assert!(err.starts_with(ERR_NOT_A_RECORDING), "got: {err}");
+        assert!(outside.is_file());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_concatenate_recordings_joins_samples_in_order() {
+        let dir = test_dir("recordings", "concat");
+        let first = dir.join("recording_part001.wav");
+        let second = dir.join("recording_part002.wav");
+        write_test_wav(&first, 16_000, 1_600);
+        write_test_wav(&second, 16_000, 400);
+
+        let inputs = [first, second];
+        let output =
+            concatenate_recordings(&dir, &inputs, Path::new("joined.wav")).expect("concatenate");
+        assert_eq!(output, dir.canonicalize().unwrap().join("joined.wav"));
+        let info = read_wav_info(&output).expect("read output");
+        assert_eq!(info.frames, 2_000);
+        assert_eq!((info.sample_rate, info.channels), (16_000, 1));
+
+        // The output is not overwritten by a second run.
+        let err = concatenate_recordings(&dir, &inputs[..1], &output).unwrap_err();
+        assert!(err.starts_with(ERR_RECORDING_EXISTS), "got: {err}");
+        // Inputs and output must stay inside the recordings directory.
+        let outside = std::env::temp_dir().join("second_test_recordings_concat.wav");
+        let err = concatenate_recordings(&dir, &inputs[..1], &outside).unwrap_err();
+        assert!(err.starts_with(ERR_INVALID_RECORDING_NAME), "got: {err}");
+        let err =
+            concatenate_recordings(&dir, &[dir.join("../x.wav")], Path::new("y.wav")).unwrap_err();
+        assert!(err.starts_with(ERR_NOT_A_RECORDING), "got: {err}");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_concatenate_recordings_rejects_spec_mismatch() {
+        let dir = test_dir("recordings", "concat_mismatch");
+        let first = dir.join("a.wav");
+        let odd = dir.join("b.wav");
+        write_test_wav(&first, 16_000, 100);
+        write_test_wav(&odd, 44_100, 100);
+
+        let err = concatenate_recordings(&dir, &[first, odd], Path::new("joined.wav")).unwrap_err();
+        assert!(err.starts_with(ERR_SPEC_MISMATCH), "got: {err}");
+        assert!(err.contains("b.wav"), "got: {err}");
+        assert!(err.contains("44100 Hz"), "got: {err}");
+        assert!(!dir.join("joined.wav").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn stored(name: &str, size_bytes: u64, age_secs: u64) -> StoredRecording {
+        StoredRecording {
+            path: PathBuf::from(name),
+            size_bytes,
+            started_at: UNIX_EPOCH + Duration::from_secs(1_000_000 - age_secs),
+        }
+    }
+
+    #[test]
+    fn test_select_evictions_removes_oldest_until_under_cap() {
+        let recordings = vec![
+            stored("newest.wav", 100, 10),
+            stored("oldest.wav", 100, 300),
+            stored("middle.wav", 100, 200),
+            stored("older.wav", 50, 250),
+        ];
+        // 350 bytes in total.
+        assert!(select_evictions(&recordings, 350, &[]).is_empty());
+        assert_eq!(
+            select_evictions(&recordings, 300, &[]),
+            vec![PathBuf::from("oldest.wav")]
+        );
+        assert_eq!(
+            select_evictions(&recordings, 200, &[]),
+            vec![PathBuf::from("oldest.wav"), PathBuf::from("older.wav")]
+        );
+        assert_eq!(select_evictions(&recordings, 0, &[]).len(), 4);
+    }
+
+    #[test]
+    fn test_select_evictions_skips_exempt_recordings() {
+        let recordings = vec![
+            stored("active.wav", 500, 400),
+            stored("old.wav", 100, 300),
+            stored("new.wav", 100, 10),
+        ];
+        let exempt = [PathBuf::from("active.wav")];
+        // The exempt recording alone is over the cap: everything else goes.
+        assert_eq!(
+            select_evictions(&recordings, 400, &exempt),
+            vec![PathBuf::from("old.wav"), PathBuf::from("new.wav")]
+        );
+        assert_eq!(
+            select_evictions(&recordings, 600, &exempt),
+            vec![PathBuf::from("old.wav")]
+        );
+    }
+
+    #[test]
+    fn test_verify_recording_detects_changed_audio() {
+        let dir = test_dir("recordings", "verify");
+        let path = dir.join("recording_1718452800.wav");
+        write_test_wav(&path, 16_000, 100);
+
+        assert!(verify_recording(&path)
+            .unwrap_err()
+            .starts_with(ERR_NO_CHECKSUM));
+        let checksum = write_checksum(&path).expect("write checksum");
+        let verification = verify_recording(&path).expect("verify");
+        assert!(verification.matches);
+        assert_eq!(verification.expected, checksum);
+
+        let manifest = build_manifest(&dir).expect("manifest");
+        assert_eq!(manifest.recordings[0].checksum, Some(checksum.clone()));
+
+        // Flip one sample: the audio no longer matches.
+        let mut bytes = fs::read(&path).expect("read");
+        bytes[50] ^= 0xff;
+        fs::write(&path, &bytes).expect("write");
+        let verification = verify_recording(&path).expect("verify");
+        assert!(!verification.matches);
+        assert_eq!(verification.expected, checksum);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_find_orphan_companions_over_listing() {
+        let files: Vec<PathBuf> = [
+            "a/recording_1.wav",
+            "a/recording_1.txt",
+            "a/recording_1.sha256",
+            "a/recording_2.json",
+            "a/recording_2.sha256",
+            "a/Loud.WAV",
+            "a/Loud.json",
+            "b/recording_1.txt",
+            "b/notes.md",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+
+        assert_eq!(
+            find_orphan_companions(&files),
+            [
+                "a/recording_2.json",
+                "a/recording_2.sha256",
+                "b/recording_1.txt"
+            ]
+            .iter()
+            .map(PathBuf::from)
+            .collect::<Vec<_>>()
+        );
+        assert!(find_orphan_companions(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_purge_orphan_companions_recurses_and_honours_dry_run() {
+        let dir = test_dir("recordings", "orphans");
+        let month = dir.join("2024/06");
+        fs::create_dir_all(&month).expect("create subdirs");
+        write_test_wav(&dir.join("recording_1.wav"), 16_000, 1_000);
+        fs::write(dir.join("recording_1.txt"), "kept").expect("write");
+        fs::write(dir.join("recording_2.txt"), "orphan").expect("write");
+        fs::write(month.join("recording_3.json"), "{}").expect("write");
+
+        let listed = purge_orphan_companions(&dir, true).expect("dry run");
+        assert!(listed.dry_run);
+        assert_eq!(listed.paths.len(), 2);
+        assert_eq!(listed.reclaimed_bytes, 8);
+        assert!(dir.join("recording_2.txt").exists());
+
+        let purged = purge_orphan_companions(&dir, false).expect("purge");
+        assert_eq!(purged.paths, listed.paths);
+        assert_eq!(purged.reclaimed_bytes, 8);
+        assert!(!dir.join("recording_2.txt").exists());
+        assert!(!month.join("recording_3.json").exists());
+        assert!(dir.join("recording_1.txt").exists());
+
+        assert!(purge_orphan_companions(&dir, false)
+            .expect("purge")
+            .paths
+            .is_empty());
+        assert!(purge_orphan_companions(&dir.join("missing"), false)
+            .expect("missing dir")
+            .paths
+            .is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_enforce_size_limit_deletes_oldest_by_file_name_timestamp() {
+        let dir = test_dir("recordings", "evict");
+        for ts in [1_718_452_800u64, 1_718_452_900, 1_718_453_000] {
+            write_test_wav(&dir.join(format!("recording_{ts}.wav")), 16_000, 1_000);
+        }
+        fs::write(dir.join("recording_1718452800.txt"), "old").expect("write txt");
+        let size = fs::metadata(dir.join("recording_1718452800.wav"))
+            .expect("stat")
+            .len();
+        let newest = dir.join("recording_1718453000.wav");
+
+        let deleted =
+            enforce_size_limit(&dir, size, std::slice::from_ref(&newest)).expect("enforce");
+        assert_eq!(
+            deleted,
+            vec![
+                dir.join("recording_1718452800.wav"),
+                dir.join("recording_1718452900.wav"),
+            ]
+        );
+        assert!(!dir.join("recording_1718452800.txt").exists());
+        assert_eq!(list_wav_files(&dir).expect("list"), vec![newest]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -3,13 +3,308 @@
 //! Manages the lifecycle of a child Python process that communicates via
 //! JSON-over-stdin/stdout. Each request is a single JSON line written to the
 //! child's stdin; each response is a single JSON line read from its stdout.
+//!
+//! The child's stderr is read line by line on a background thread, echoed to
+//! our own stderr, optionally copied to a rotating log file, and kept in a
+//! short buffer that a [listener](SidecarManager::set_log_listener) can
+//! follow live.
+//!
+//! A sidecar that dies mid-exchange can be restarted and the message resent
+//! automatically, as set by a [`ReconnectPolicy`].
 
-use std::io::{BufRead, BufReader, Write};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, VecDeque};
+use std::hash::BuildHasher;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::Path;
 use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use serde_json::Value;
 
+use crate::config::{load_app_config, AppConfig, SidecarProfile};
+use crate::log_file::RotatingLog;
+use crate::protocol::{
+    ComputeDevice, DeviceSetResponse, SidecarCapabilities, SidecarRequest, SidecarResponse,
+};
+
+/// Prefix of the error returned when the sidecar closes stdout mid-exchange,
+/// usually because it crashed.
+pub const ERR_SIDECAR_CLOSED: &str = "Sidecar process closed stdout";
+
+/// Prefix of the error returned when the sidecar does not answer within the
+/// timeout passed to [`SidecarManager::send_message_with_timeout`].
+pub const ERR_SIDECAR_TIMEOUT: &str = "Sidecar did not respond in time";
+
+/// Prefix of the error returned by [`SidecarManager::require_streaming`] when
+/// the backend cannot transcribe chunk by chunk.
+pub const ERR_STREAMING_UNSUPPORTED: &str = "Sidecar does not support streaming transcription";
+
+/// Prefix of the error returned when the sidecar cannot use the requested
+/// compute device, e.g. CUDA on a machine without a GPU.
+pub const ERR_DEVICE_UNAVAILABLE: &str = "Compute device unavailable";
+
+/// Prefix of the error returned when the model does not fit in the
+/// requested device's memory.
+pub const ERR_DEVICE_OUT_OF_MEMORY: &str = "Compute device out of memory";
+
+/// How long [`SidecarManager::warm_up`] callers should allow for the model to
+/// load.
+pub const WARMUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// `type` of the interim lines a sidecar may send while working on a long
+/// request, before its actual response.
+pub const PROGRESS_RESPONSE_TYPE: &str = "progress";
+
+/// How a [`SidecarManager`] recovers when the sidecar dies mid-exchange:
+/// it is restarted up to `max_retries` times, waiting [`backoff_delay`]
+/// before each attempt, and the message resent. The default, with no
+/// retries, leaves the error to the caller.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    pub max_retries: u32,
+    /// Wait before the first restart, doubled for each one after.
+    pub initial_backoff: Duration,
+    /// Longest wait between restarts, jitter included.
+    pub max_backoff: Duration,
+    /// Fraction (0.0 to 1.0) of each wait randomly added or taken away, so
+    /// several clients don't retry in lockstep.
+    pub jitter: f64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.0,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// # Errors
+    /// Returns an error if `jitter` is outside 0.0 to 1.0 or
+    /// `initial_backoff` exceeds `max_backoff`.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(format!(
+                "Reconnect jitter must be between 0 and 1, got {}",
+                self.jitter
+            ));
+        }
+        if self.initial_backoff > self.max_backoff {
+            return Err(format!(
+                "Initial backoff ({} ms) exceeds the maximum ({} ms)",
+                self.initial_backoff.as_millis(),
+                self.max_backoff.as_millis()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// How long `policy` waits before restart number `attempt` (from 1):
+/// `initial_backoff` doubled for each earlier attempt, moved by up to
+/// `jitter` of itself either way according to `random` (0.0 to 1.0, where
+/// 0.5 moves it not at all), and capped at `max_backoff`.
+pub fn backoff_delay(policy: &ReconnectPolicy, attempt: u32, random: f64) -> Duration {
+    let doubled = 2_u32
+        .checked_pow(attempt.saturating_sub(1))
+        .map_or(policy.max_backoff, |factor| {
+            policy.initial_backoff.saturating_mul(factor)
+        })
+        .min(policy.max_backoff);
+    let spread = policy.jitter.clamp(0.0, 1.0) * (2.0 * random.clamp(0.0, 1.0) - 1.0);
+    doubled.mul_f64(1.0 + spread).min(policy.max_backoff)
+}
+
+/// A number from 0.0 to 1.0 for [`backoff_delay`]'s jitter, different on
+/// every call.
+fn jitter_sample() -> f64 {
+    let bits = RandomState::new().hash_one(SystemTime::now());
+    (bits >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+/// Restarts a [`SidecarManager`] has made, by [`restart`](SidecarManager::restart)
+/// or under its [`ReconnectPolicy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize)]
+pub struct ReconnectStats {
+    pub total_restarts: u64,
+    /// Milliseconds since the Unix epoch.
+    pub last_restart_at: Option<u64>,
+}
+
+/// Default number of exchanges kept by the trace buffer.
+pub const DEFAULT_TRACE_CAPACITY: usize = 50;
+
+/// Upper bound on the trace buffer so a bad argument can't grow it without
+/// limit.
+pub const MAX_TRACE_CAPACITY: usize = 1_000;
+
+/// Number of recent stderr lines kept for a
+/// [log listener](SidecarManager::set_log_listener) to catch up on.
+pub const LOG_BUFFER_LINES: usize = 200;
+
+/// Called with each line the sidecar writes to stderr.
+pub type LogListener = Box<dyn FnMut(&str) + Send>;
+
+/// Recent stderr lines and whoever is following them, shared with the
+/// stderr reader thread.
+#[derive(Default)]
+struct LogTap {
+    recent: VecDeque<String>,
+    listener: Option<LogListener>,
+}
+
+impl LogTap {
+    fn push(&mut self, line: &str) {
+        if self.recent.len() == LOG_BUFFER_LINES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(line.to_string());
+        if let Some(listener) = self.listener.as_mut() {
+            listener(line);
+        }
+    }
+}
+
+/// How long the sidecar is given to answer a keepalive before the exchange
+/// counts as failed.
+pub const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long [`SidecarManager::ensure_started`] waits for an already running
+/// sidecar to answer its health probe before replacing it.
+pub const HEALTH_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One request/response exchange recorded while tracing is enabled.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct TraceEntry {
+    /// When the request was sent, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub request: Value,
+    /// The parsed response, or `None` if the exchange failed.
+    pub response: Option<Value>,
+    /// The error returned to the caller, if the exchange failed.
+    pub error: Option<String>,
+}
+
+/// A sidecar response with timing measured around the exchange, for
+/// latency analysis without help from the backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedResponse {
+    pub value: Value,
+    /// From just before the request was written to the response being read,
+    /// including any progress lines in between.
+    pub round_trip: Duration,
+    /// Length of the request line, without its newline.
+    pub request_bytes: usize,
+    /// Length of the response line, without its newline.
+    pub response_bytes: usize,
+}
+
+/// PID and rough resource usage of the sidecar process.
+///
+/// Stats are `None` when the sidecar is not running or could not be sampled.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ProcessInfo {
+    pub pid: Option<u32>,
+    pub running: bool,
+    /// Resident set size.
+    pub memory_bytes: Option<u64>,
+    /// CPU usage as reported by `ps` (100.0 = one full core).
+    pub cpu_percent: Option<f64>,
+    /// Device the model runs on, once known from a
+    /// [`set_device`](SidecarManager::set_device) switch.
+    pub compute_device: Option<String>,
+    /// Memory cap the process was last started with (see
+    /// [`SidecarLaunch::memory_limit_bytes`]).
+    pub memory_limit_bytes: Option<u64>,
+    /// Whether that cap is actually enforced (see [`MEMORY_LIMIT_SUPPORTED`]).
+    pub memory_limit_enforced: bool,
+}
+
+/// Whether [`SidecarLaunch::memory_limit_bytes`] is enforced on this
+/// platform. Elsewhere the sidecar starts without the cap, with a warning.
+/// Only Linux: macOS accepts an `RLIMIT_AS` but does not enforce it.
+pub const MEMORY_LIMIT_SUPPORTED: bool = cfg!(target_os = "linux");
+
+/// How to launch the sidecar: `<python_path> main.py <args>...` in
+/// `backend_dir`, with `env` added to the app's environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SidecarLaunch {
+    pub python_path: String,
+    pub backend_dir: String,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    /// Cap on the process's address space, so a model cannot take all of
+    /// a shared machine's memory: allocations past it fail. Only enforced
+    /// where [`MEMORY_LIMIT_SUPPORTED`].
+    pub memory_limit_bytes: Option<u64>,
+}
+
+impl SidecarLaunch {
+    /// Plain `<python_path> main.py` in `backend_dir`.
+    pub fn new(python_path: &str, backend_dir: &str) -> Self {
+        Self {
+            python_path: python_path.to_string(),
+            backend_dir: backend_dir.to_string(),
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            memory_limit_bytes: None,
+        }
+    }
+
+    /// The command to spawn, with piped stdio.
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.python_path);
+        command
+            .arg("main.py")
+            .args(&self.args)
+            .current_dir(&self.backend_dir)
+            .envs(&self.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(limit) = self.memory_limit_bytes {
+            limit_memory(&mut command, limit);
+        }
+        command
+    }
+}
+
+/// Have the process `command` spawns cap its address space at `limit`
+/// bytes (`RLIMIT_AS`) before it runs the program.
+#[cfg(target_os = "linux")]
+fn limit_memory(command: &mut Command, limit: u64) {
+    use std::os::unix::process::CommandExt;
+
+    let limit = libc::rlimit {
+        rlim_cur: limit as libc::rlim_t,
+        rlim_max: limit as libc::rlim_t,
+    };
+    // SAFETY: the hook only calls setrlimit, which is async-signal-safe,
+    // on a value copied into the closure.
+    unsafe {
+        command.pre_exec(move || {
+            if libc::setrlimit(libc::RLIMIT_AS, &limit) == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        });
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn limit_memory(_command: &mut Command, limit: u64) {
+    eprintln!(
+        "Sidecar memory limit of {limit} bytes is not supported on this platform; starting without it"
+    );
+}
+
 /// Manages a child Python sidecar process.
 ///
 /// The manager owns the child process handle and provides methods to send
@@ -18,6 +313,45 @@ pub struct SidecarManager {
     process: Option<Child>,
     stdin: Option<std::process::ChildStdin>,
     stdout: Option<BufReader<std::process::ChildStdout>>,
+    /// Ring buffer of recent exchanges; `None` while tracing is off.
+    trace: Option<VecDeque<TraceEntry>>,
+    trace_capacity: usize,
+    /// Log file that receives the child's stderr (and traced exchanges).
+    /// Shared with the stderr reader thread.
+    log: Arc<Mutex<Option<RotatingLog>>>,
+    /// Recent stderr lines and the live listener, if any. Shared with the
+    /// stderr reader thread.
+    log_tap: Arc<Mutex<LogTap>>,
+    /// The last successful launch, reused by `restart`.
+    launch: Option<SidecarLaunch>,
+    /// Set once a warm-up request succeeds; cleared when the process goes.
+    ready: bool,
+    /// Replies still owed for requests that timed out. They are read and
+    /// discarded before the next exchange so responses stay paired with
+    /// their requests.
+    stale_responses: usize,
+    /// Capabilities reported by the running process, queried once.
+    capabilities: Option<SidecarCapabilities>,
+    /// Compute device the running process last confirmed switching to.
+    compute_device: Option<String>,
+    /// When and why the most recent exchange failed. Survives restarts;
+    /// cleared by the next successful exchange or
+    /// [`clear_error`](Self::clear_error).
+    last_error: Option<(Instant, String)>,
+    /// Idle time after which a keepalive is sent; `None` (the default)
+    /// disables keepalives.
+    keepalive: Option<Duration>,
+    /// When the last exchange of any kind finished.
+    last_exchange: Option<Instant>,
+    /// Language hint added to `transcribe_chunk` requests; `None` lets the
+    /// backend auto-detect. Survives restarts.
+    language: Option<String>,
+    /// Per-call override of `language`, set by
+    /// [`with_language`](Self::with_language).
+    call_language: Option<String>,
+    /// Restarts tried when the sidecar dies mid-exchange.
+    reconnect: ReconnectPolicy,
+    reconnect_stats: ReconnectStats,
 }
 
 impl SidecarManager {
@@ -27,7 +361,86 @@ impl SidecarManager {
             process: None,
             stdin: None,
             stdout: None,
+            trace: None,
+            trace_capacity: DEFAULT_TRACE_CAPACITY,
+            log: Arc::new(Mutex::new(None)),
+            log_tap: Arc::new(Mutex::new(LogTap::default())),
+            launch: None,
+            ready: false,
+            stale_responses: 0,
+            capabilities: None,
+            compute_device: None,
+            last_error: None,
+            keepalive: None,
+            last_exchange: None,
+            language: None,
+            call_language: None,
+            reconnect: ReconnectPolicy::default(),
+            reconnect_stats: ReconnectStats::default(),
+        }
+    }
+
+    /// Copy the sidecar's stderr to a rotating log at `path`, capped at
+    /// `max_bytes` (see [`RotatingLog`]), or stop logging with `None`.
+    ///
+    /// While tracing is also enabled, every exchange is written to the log
+    /// as well. Takes effect immediately, including for a running sidecar.
+    ///
+    /// # Errors
+    /// Returns an error if the log file cannot be opened.
+    pub fn set_log_file(&mut self, path: Option<&Path>, max_bytes: u64) -> Result<(), String> {
+        let log = path.map(|p| RotatingLog::open(p, max_bytes)).transpose()?;
+        *self.log.lock().map_err(|e| format!("Lock poisoned: {e}"))? = log;
+        Ok(())
+    }
+
+    /// Follow the sidecar's stderr live, or detach with `None`.
+    ///
+    /// The listener is first handed the buffered recent lines (up to
+    /// [`LOG_BUFFER_LINES`]), then each new line as it arrives, in order. It
+    /// stays attached across restarts and is detached by
+    /// [`stop`](Self::stop).
+    ///
+    /// # Errors
+    /// Returns an error if the log lock is poisoned.
+    pub fn set_log_listener(&mut self, listener: Option<LogListener>) -> Result<(), String> {
+        let mut tap = self
+            .log_tap
+            .lock()
+            .map_err(|e| format!("Lock poisoned: {e}"))?;
+        let tap = &mut *tap;
+        tap.listener = listener;
+        if let Some(listener) = tap.listener.as_mut() {
+            for line in &tap.recent {
+                listener(line);
+            }
+        }
+        Ok(())
+    }
+
+    /// Enable or disable recording of request/response pairs.
+    ///
+    /// While enabled, the last `capacity` exchanges (clamped to
+    /// 1..=[`MAX_TRACE_CAPACITY`]) are kept in memory. Disabling discards the
+    /// buffer. Tracing is off by default.
+    pub fn set_trace(&mut self, enabled: bool, capacity: usize) {
+        self.trace_capacity = capacity.clamp(1, MAX_TRACE_CAPACITY);
+        if !enabled {
+            self.trace = None;
+            return;
         }
+        let trace = self.trace.get_or_insert_with(VecDeque::new);
+        while trace.len() > self.trace_capacity {
+            trace.pop_front();
+        }
+    }
+
+    /// Recorded exchanges, oldest first. Empty when tracing is off.
+    pub fn trace(&self) -> Vec<TraceEntry> {
+        self.trace
+            .as_ref()
+            .map(|t| t.iter().cloned().collect())
+            .unwrap_or_default()
     }
 
     /// Spawn the Python sidecar process.
@@ -40,33 +453,451 @@ impl SidecarManager {
     /// Returns an error if the process cannot be spawned or if a sidecar is
     /// already running.
     pub fn start(&mut self, python_path: &str, backend_dir: &str) -> Result<(), String> {
+        self.start_with(SidecarLaunch::new(python_path, backend_dir))
+    }
+
+    /// [`start`](Self::start) with extra arguments and environment, e.g.
+    /// from a [`SidecarProfile`] (see [`resolve_profile`]).
+    ///
+    /// # Errors
+    /// As for `start`.
+    pub fn start_with(&mut self, launch: SidecarLaunch) -> Result<(), String> {
         if self.is_running() {
             return Err("Sidecar is already running".into());
         }
 
-        let mut child = Command::new(python_path)
-            .arg("main.py")
-            .current_dir(backend_dir)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
+        let mut child = launch
+            .command()
             .spawn()
             .map_err(|e| format!("Failed to spawn sidecar: {e}"))?;
 
+        if let Some(stderr) = child.stderr.take() {
+            spawn_stderr_reader(stderr, Arc::clone(&self.log), Arc::clone(&self.log_tap));
+        }
+
         self.stdin = child.stdin.take();
         self.stdout = child.stdout.take().map(BufReader::new);
         self.process = Some(child);
+        self.ready = false;
+        self.stale_responses = 0;
+        self.capabilities = None;
+        self.compute_device = None;
+        self.last_exchange = Some(Instant::now());
+        self.launch = Some(launch);
+
+        Ok(())
+    }
+
+    /// Start the sidecar with `launch` and health-check it, unless it is
+    /// already running from the same `launch` and answers a quick health
+    /// probe. Returns whether a process was spawned, so racing callers
+    /// serialized on the manager's lock start it only once.
+    ///
+    /// A sidecar that is running but fails the probe is stopped and
+    /// replaced.
+    ///
+    /// # Errors
+    /// Returns an error if a sidecar from a different `launch` is running,
+    /// or if spawning fails or the new process fails its health check (it
+    /// is stopped again).
+    pub fn ensure_started(&mut self, launch: SidecarLaunch) -> Result<bool, String> {
+        if self.is_running() && self.launch.as_ref() == Some(&launch) {
+            if self.probe_health(HEALTH_PROBE_TIMEOUT) {
+                return Ok(false);
+            }
+            self.stop_process()?;
+        }
+
+        self.start_with(launch)?;
+        let health: SidecarResponse = self.send_typed(&SidecarRequest::Health)?;
+        if !health.is_healthy() {
+            self.stop()?;
+            return Err(format!("Health check failed: {health:?}"));
+        }
+        Ok(true)
+    }
+
+    /// Whether the sidecar answers a health check within `timeout`.
+    fn probe_health(&mut self, timeout: Duration) -> bool {
+        let Ok(message) = serde_json::to_value(SidecarRequest::Health) else {
+            return false;
+        };
+        self.send_message_with_timeout(message, timeout)
+            .ok()
+            .and_then(|response| serde_json::from_value::<SidecarResponse>(response).ok())
+            .is_some_and(|health| health.is_healthy())
+    }
+
+    /// Stop the sidecar if it is still running and start it again the way
+    /// it was last successfully started. Counted in
+    /// [`reconnect_stats`](Self::reconnect_stats).
+    ///
+    /// # Errors
+    /// Returns an error if the sidecar was never started, or if stopping or
+    /// spawning fails.
+    pub fn restart(&mut self) -> Result<(), String> {
+        let launch = self
+            .launch
+            .clone()
+            .ok_or_else(|| "Sidecar has not been started".to_string())?;
+        self.stop_process()?;
+        self.reconnect_stats.total_restarts += 1;
+        self.reconnect_stats.last_restart_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_millis() as u64);
+        self.start_with(launch)
+    }
 
+    /// Restart the sidecar and resend the message as `policy` says when it
+    /// dies mid-exchange. Timeouts and error replies are never retried.
+    ///
+    /// # Errors
+    /// Returns an error if `policy` is invalid (see
+    /// [`ReconnectPolicy::validate`]).
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) -> Result<(), String> {
+        policy.validate()?;
+        self.reconnect = policy;
         Ok(())
     }
 
+    /// How many times the sidecar has been restarted, and when last.
+    pub fn reconnect_stats(&self) -> ReconnectStats {
+        self.reconnect_stats
+    }
+
     /// Send a JSON message to the sidecar and wait for a single-line JSON
     /// response.
     ///
+    /// [Progress](PROGRESS_RESPONSE_TYPE) lines sent before the response are
+    /// skipped.
+    ///
     /// # Errors
     /// Returns an error if the sidecar is not running, or if
     /// serialization/deserialization fails, or if the write/read fails.
     pub fn send_message(&mut self, message: Value) -> Result<Value, String> {
+        self.send_message_timed(message).map(|timed| timed.value)
+    }
+
+    /// [`send_message`](Self::send_message), also reporting how long the
+    /// exchange took and how many bytes went each way.
+    ///
+    /// # Errors
+    /// As for `send_message`.
+    pub fn send_message_timed(&mut self, message: Value) -> Result<TimedResponse, String> {
+        self.send_with_timeout(message, None, &mut |_| {})
+    }
+
+    /// [`send_message`](Self::send_message), handing each
+    /// [progress](PROGRESS_RESPONSE_TYPE) line that arrives before the
+    /// response to `on_progress`. Only the response is traced.
+    ///
+    /// # Errors
+    /// As for `send_message`, including when the sidecar closes stdout
+    /// after progress lines but before the response.
+    pub fn send_message_with_progress(
+        &mut self,
+        message: Value,
+        on_progress: &mut dyn FnMut(&Value),
+    ) -> Result<Value, String> {
+        self.send_with_timeout(message, None, on_progress)
+            .map(|timed| timed.value)
+    }
+
+    /// [`send_message`](Self::send_message), giving up if no reply arrives
+    /// within `timeout`. The late reply is discarded when it arrives.
+    ///
+    /// The timeout is only enforced on Unix; elsewhere this waits as long as
+    /// `send_message`.
+    ///
+    /// # Errors
+    /// As for `send_message`, plus an [`ERR_SIDECAR_TIMEOUT`] error.
+    pub fn send_message_with_timeout(
+        &mut self,
+        message: Value,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        self.send_with_timeout(message, Some(timeout), &mut |_| {})
+            .map(|timed| timed.value)
+    }
+
+    /// Send a model warm-up `request` (allowing `timeout` for it) and mark
+    /// the sidecar [ready](Self::is_ready) if it answers with anything other
+    /// than an `error` response.
+    ///
+    /// # Errors
+    /// Returns an error if the exchange fails or times out, or the sidecar
+    /// replies with an `error` response.
+    pub fn warm_up<T: serde::Serialize>(
+        &mut self,
+        request: &T,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let message = serde_json::to_value(request)
+            .map_err(|e| format!("Failed to serialize message: {e}"))?;
+        let response = self.send_message_with_timeout(message, timeout)?;
+        if response.get("type").and_then(Value::as_str) == Some("error") {
+            return Err(format!("Sidecar warm-up failed: {response}"));
+        }
+        self.ready = true;
+        Ok(response)
+    }
+
+    /// Whether a warm-up has succeeded since the sidecar was started.
+    pub fn is_ready(&mut self) -> bool {
+        self.is_running() && self.ready
+    }
+
+    /// The optional features the running sidecar supports.
+    ///
+    /// Asked once per process and cached until the sidecar is started
+    /// again. A backend that predates the `capabilities` request (it answers
+    /// with an error or some other response) gets
+    /// [`SidecarCapabilities::default`], with `reported` unset.
+    ///
+    /// # Errors
+    /// Returns an error if the exchange fails or a `capabilities` response
+    /// is malformed.
+    pub fn capabilities(&mut self) -> Result<SidecarCapabilities, String> {
+        if let Some(caps) = &self.capabilities {
+            return Ok(caps.clone());
+        }
+
+        let message = serde_json::to_value(SidecarRequest::Capabilities)
+            .map_err(|e| format!("Failed to serialize message: {e}"))?;
+        let response = self.send_message(message)?;
+        let caps = if response.get("type").and_then(Value::as_str) == Some("capabilities") {
+            let mut caps: SidecarCapabilities = serde_json::from_value(response.clone())
+                .map_err(|e| format!("Unexpected sidecar response {response}: {e}"))?;
+            caps.reported = true;
+            caps
+        } else {
+            SidecarCapabilities::default()
+        };
+        self.capabilities = Some(caps.clone());
+        Ok(caps)
+    }
+
+    /// Whether the backend reports [`streaming`](SidecarCapabilities::streaming)
+    /// support. `false` if it does not say, or cannot be asked.
+    pub fn supports_streaming(&mut self) -> bool {
+        self.capabilities().is_ok_and(|caps| caps.streaming)
+    }
+
+    /// Fail fast before a streaming exchange the backend would not answer.
+    ///
+    /// # Errors
+    /// Returns [`ERR_STREAMING_UNSUPPORTED`] unless
+    /// [`supports_streaming`](Self::supports_streaming).
+    pub fn require_streaming(&mut self) -> Result<(), String> {
+        if self.supports_streaming() {
+            Ok(())
+        } else {
+            Err(ERR_STREAMING_UNSUPPORTED.to_string())
+        }
+    }
+
+    /// Move the sidecar's model to `device` and return the device it
+    /// reports using (what `auto` resolved to), which is cached for
+    /// [`process_info`](Self::process_info) until the process goes.
+    ///
+    /// A failed switch forgets the cached device, since the backend may be
+    /// left on either one.
+    ///
+    /// # Errors
+    /// Returns an [`ERR_DEVICE_UNAVAILABLE`] or [`ERR_DEVICE_OUT_OF_MEMORY`]
+    /// error when the backend reports either, or an error if the exchange
+    /// fails or the sidecar reports another failure.
+    pub fn set_device(&mut self, device: ComputeDevice) -> Result<String, String> {
+        self.compute_device = None;
+        let response: DeviceSetResponse = self.send_typed(&SidecarRequest::SetDevice { device })?;
+        match response {
+            DeviceSetResponse {
+                status,
+                device: Some(active),
+                ..
+            } if status == "ok" => {
+                self.compute_device = Some(active.clone());
+                Ok(active)
+            }
+            DeviceSetResponse {
+                message: Some(message),
+                ..
+            } => Err(set_device_error(device, &message)),
+            other => Err(format!("Unexpected response to set_device: {other:?}")),
+        }
+    }
+
+    /// When and why the most recent exchange failed (a timeout, a dead
+    /// process, ...), or `None` if the last one succeeded or the error has
+    /// been [cleared](Self::clear_error). An `error` response from a
+    /// healthy sidecar does not count as a failure.
+    pub fn last_error(&self) -> Option<(Instant, String)> {
+        self.last_error.clone()
+    }
+
+    /// Forget the [last error](Self::last_error), e.g. once the user has
+    /// acknowledged it.
+    pub fn clear_error(&mut self) {
+        self.last_error = None;
+    }
+
+    /// Send a `keepalive` message whenever the sidecar has been idle for
+    /// `interval`, keeping the model warm between recordings, or stop with
+    /// `None`. Only takes effect while a [`spawn_keepalive`] thread is
+    /// watching this manager.
+    pub fn set_keepalive(&mut self, interval: Option<Duration>) {
+        self.keepalive = interval.filter(|i| !i.is_zero());
+    }
+
+    /// Send `language` (an ISO 639-1 code) as the language hint of every
+    /// `transcribe_chunk` request, or let the backend auto-detect with
+    /// `None`. Requests that carry their own `language` keep it.
+    pub fn set_language(&mut self, language: Option<String>) {
+        self.language = language;
+    }
+
+    /// The language hint `transcribe_chunk` requests get right now: the
+    /// [per-call](Self::with_language) one, else the one
+    /// [set](Self::set_language), else none.
+    pub fn language(&self) -> Option<&str> {
+        self.call_language.as_deref().or(self.language.as_deref())
+    }
+
+    /// Run `f` with `per_call` as the language hint in place of the one
+    /// [set](Self::set_language). `None` keeps the set one.
+    pub fn with_language<R>(
+        &mut self,
+        per_call: Option<String>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let previous = std::mem::replace(&mut self.call_language, per_call);
+        let result = f(self);
+        self.call_language = previous;
+        result
+    }
+
+    /// Send a keepalive if one is due: keepalives are enabled, the sidecar
+    /// is running, and nothing has been exchanged for the keepalive
+    /// interval. Returns whether one was sent. The reply is discarded.
+    ///
+    /// # Errors
+    /// Returns an error if the keepalive exchange fails.
+    pub fn keepalive_if_idle(&mut self) -> Result<bool, String> {
+        let Some(interval) = self.keepalive else {
+            return Ok(false);
+        };
+        if self.last_exchange.is_some_and(|t| t.elapsed() < interval) || !self.is_running() {
+            return Ok(false);
+        }
+        let message = serde_json::to_value(SidecarRequest::Keepalive)
+            .map_err(|e| format!("Failed to serialize message: {e}"))?;
+        self.send_message_with_timeout(message, KEEPALIVE_TIMEOUT)?;
+        Ok(true)
+    }
+
+    fn send_with_timeout(
+        &mut self,
+        message: Value,
+        timeout: Option<Duration>,
+        on_progress: &mut dyn FnMut(&Value),
+    ) -> Result<TimedResponse, String> {
+        let message = with_language_hint(message, self.language());
+        let mut result = self.traced_exchange(&message, timeout, on_progress);
+        let mut attempt = 0;
+        while let Err(e) = &result {
+            let died = e.starts_with(ERR_SIDECAR_CLOSED) || !self.is_running();
+            if !died || attempt >= self.reconnect.max_retries || self.launch.is_none() {
+                break;
+            }
+            attempt += 1;
+            eprintln!(
+                "Sidecar went away ({e}); restart {attempt} of {}",
+                self.reconnect.max_retries
+            );
+            std::thread::sleep(backoff_delay(&self.reconnect, attempt, jitter_sample()));
+            result = self
+                .restart()
+                .and_then(|()| self.traced_exchange(&message, timeout, on_progress));
+        }
+        self.last_error = result.as_ref().err().map(|e| (Instant::now(), e.clone()));
+        self.last_exchange = Some(Instant::now());
+        result
+    }
+
+    /// [`exchange`](Self::exchange), recorded in the trace buffer while
+    /// tracing is on.
+    fn traced_exchange(
+        &mut self,
+        message: &Value,
+        timeout: Option<Duration>,
+        on_progress: &mut dyn FnMut(&Value),
+    ) -> Result<TimedResponse, String> {
+        if self.trace.is_none() {
+            return self.exchange(message, timeout, on_progress);
+        }
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let result = self.exchange(message, timeout, on_progress);
+        self.record_trace(TraceEntry {
+            timestamp_ms,
+            request: message.clone(),
+            response: result.as_ref().ok().map(|timed| timed.value.clone()),
+            error: result.as_ref().err().cloned(),
+        });
+        result
+    }
+
+    /// Send a typed request and parse the reply as `R`, usually
+    /// [`SidecarRequest`](crate::protocol::SidecarRequest) /
+    /// [`SidecarResponse`](crate::protocol::SidecarResponse).
+    ///
+    /// Goes through [`send_message`](Self::send_message), so exchanges are
+    /// traced like any other.
+    ///
+    /// # Errors
+    /// Returns an error if the exchange fails or the reply does not match
+    /// `R`.
+    pub fn send_typed<T, R>(&mut self, request: &T) -> Result<R, String>
+    where
+        T: serde::Serialize,
+        R: serde::de::DeserializeOwned,
+    {
+        let message = serde_json::to_value(request)
+            .map_err(|e| format!("Failed to serialize message: {e}"))?;
+        let response = self.send_message(message)?;
+        serde_json::from_value(response.clone())
+            .map_err(|e| format!("Unexpected sidecar response {response}: {e}"))
+    }
+
+    /// Append an entry to the trace buffer, evicting the oldest if full.
+    fn record_trace(&mut self, entry: TraceEntry) {
+        if let Ok(mut log) = self.log.lock() {
+            if let (Some(log), Ok(json)) = (log.as_mut(), serde_json::to_string(&entry)) {
+                let _ = log.write_line(&format!("[trace] {json}"));
+            }
+        }
+
+        let capacity = self.trace_capacity;
+        if let Some(trace) = self.trace.as_mut() {
+            if trace.len() >= capacity {
+                trace.pop_front();
+            }
+            trace.push_back(entry);
+        }
+    }
+
+    /// Write one JSON line to the sidecar and read one JSON line back,
+    /// waiting at most `timeout` for it to start arriving. Skipping replies
+    /// still owed to earlier timed-out requests counts against `timeout`.
+    fn exchange(
+        &mut self,
+        message: &Value,
+        timeout: Option<Duration>,
+        on_progress: &mut dyn FnMut(&Value),
+    ) -> Result<TimedResponse, String> {
         let stdin = self
             .stdin
             .as_mut()
@@ -76,10 +907,30 @@ impl SidecarManager {
             .as_mut()
             .ok_or_else(|| "Sidecar stdout not available".to_string())?;
 
-        let mut serialized = serde_json::to_string(&message)
+        let timed_out = |timeout: Duration| {
+            format!("{ERR_SIDECAR_TIMEOUT} (waited {} ms)", timeout.as_millis())
+        };
+        let mut deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        // Skip replies to requests that timed out earlier, with any
+        // progress lines they were still sending.
+        while self.stale_responses > 0 {
+            if let (Some(timeout), Some(deadline)) = (timeout, deadline) {
+                if !wait_readable(stdout, deadline.saturating_duration_since(Instant::now()))? {
+                    return Err(timed_out(timeout));
+                }
+            }
+            if !is_progress(&read_response(stdout)?.0) {
+                self.stale_responses -= 1;
+            }
+        }
+
+        let mut serialized = serde_json::to_string(message)
             .map_err(|e| format!("Failed to serialize message: {e}"))?;
+        let request_bytes = serialized.len();
         serialized.push('\n');
 
+        let sent_at = Instant::now();
         stdin
             .write_all(serialized.as_bytes())
             .map_err(|e| format!("Failed to write to sidecar stdin: {e}"))?;
@@ -87,27 +938,47 @@ impl SidecarManager {
             .flush()
             .map_err(|e| format!("Failed to flush sidecar stdin: {e}"))?;
 
-        let mut line = String::new();
-        let bytes_read = stdout
-            .read_line(&mut line)
-            .map_err(|e| format!("Failed to read from sidecar stdout: {e}"))?;
+        loop {
+            if let (Some(timeout), Some(deadline)) = (timeout, deadline) {
+                if !wait_readable(stdout, deadline.saturating_duration_since(Instant::now()))? {
+                    self.stale_responses += 1;
+                    return Err(timed_out(timeout));
+                }
+            }
 
-        if bytes_read == 0 {
-            return Err("Sidecar process closed stdout (possible crash)".into());
+            let (response, response_bytes) = read_response(stdout)?;
+            if !is_progress(&response) {
+                return Ok(TimedResponse {
+                    value: response,
+                    round_trip: sent_at.elapsed(),
+                    request_bytes,
+                    response_bytes,
+                });
+            }
+            on_progress(&response);
+            // Each progress line restarts the wait for the next.
+            deadline = timeout.map(|timeout| Instant::now() + timeout);
         }
-
-        serde_json::from_str(line.trim())
-            .map_err(|e| format!("Failed to parse sidecar response: {e}"))
     }
 
-    /// Kill the sidecar process and clean up handles.
+    /// Kill the sidecar process, clean up handles, and detach any log
+    /// listener.
     ///
     /// # Errors
     /// Returns an error if the kill signal cannot be sent.
     pub fn stop(&mut self) -> Result<(), String> {
+        if let Ok(mut tap) = self.log_tap.lock() {
+            tap.listener = None;
+        }
+        self.stop_process()
+    }
+
+    /// Kill the process, leaving any log listener attached for a restart.
+    fn stop_process(&mut self) -> Result<(), String> {
         // Drop stdin/stdout first so the child isn't blocked on I/O.
         self.stdin.take();
         self.stdout.take();
+        self.ready = false;
 
         if let Some(mut child) = self.process.take() {
             child
@@ -142,6 +1013,42 @@ impl SidecarManager {
             false
         }
     }
+
+    /// Report the sidecar's PID and resource usage, or `running: false` with
+    /// no stats when it is idle.
+    pub fn process_info(&mut self) -> ProcessInfo {
+        if !self.is_running() {
+            return ProcessInfo {
+                pid: None,
+                running: false,
+                memory_bytes: None,
+                cpu_percent: None,
+                compute_device: None,
+                memory_limit_bytes: self.memory_limit_bytes(),
+                memory_limit_enforced: self.memory_limit_enforced(),
+            };
+        }
+
+        let pid = self.process.as_ref().map(Child::id);
+        let stats = pid.and_then(process_stats);
+        ProcessInfo {
+            pid,
+            running: true,
+            memory_bytes: stats.map(|(memory, _)| memory),
+            cpu_percent: stats.map(|(_, cpu)| cpu),
+            compute_device: self.compute_device.clone(),
+            memory_limit_bytes: self.memory_limit_bytes(),
+            memory_limit_enforced: self.memory_limit_enforced(),
+        }
+    }
+
+    fn memory_limit_bytes(&self) -> Option<u64> {
+        self.launch.as_ref().and_then(|l| l.memory_limit_bytes)
+    }
+
+    fn memory_limit_enforced(&self) -> bool {
+        MEMORY_LIMIT_SUPPORTED && self.memory_limit_bytes().is_some()
+    }
 }
 
 impl Drop for SidecarManager {
@@ -151,37 +1058,212 @@ impl Drop for SidecarManager {
     }
 }
 
-// ---------------------------------------------------------------------------
-// Python discovery helpers
-// ---------------------------------------------------------------------------
+/// Map the sidecar's failure `message` for `set_device` to our error, typed
+/// when it says the device is missing or out of memory.
+fn set_device_error(device: ComputeDevice, message: &str) -> String {
+    let lower = message.to_lowercase();
+    let oom = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word == "oom");
+    if lower.contains("out of memory") || oom {
+        format!("{ERR_DEVICE_OUT_OF_MEMORY} ({device:?}): {message}")
+    } else if lower.contains("not available") || lower.contains("unavailable") {
+        format!("{ERR_DEVICE_UNAVAILABLE} ({device:?}): {message}")
+    } else {
+        format!("Sidecar failed to switch to device {device:?}: {message}")
+    }
+}
 
-/// Try to locate a usable Python interpreter.
-///
-/// Search order:
-/// 1. `python3` on `$PATH`
-/// 2. `python` on `$PATH`
-/// 3. The backend virtualenv at `<backend_dir>/.venv/bin/python`
-///
-/// # Errors
-/// Returns an error if no Python interpreter can be found.
-pub fn find_python(backend_dir: Option<&str>) -> Result<String, String> {
-    // 1. .venv inside the backend directory (preferred — correct Python version + deps)
-    if let Some(dir) = backend_dir {
-        let venv_python = Path::new(dir).join(".venv/bin/python");
-        if venv_python.exists() {
-            return venv_python
-                .to_str()
-                .map(String::from)
-                .ok_or_else(|| "Virtualenv python path is not valid UTF-8".into());
+/// `message` with `language` added if it is a `transcribe_chunk` request
+/// without a language of its own.
+fn with_language_hint(mut message: Value, language: Option<&str>) -> Value {
+    let Some(language) = language else {
+        return message;
+    };
+    if message.get("type").and_then(Value::as_str) == Some("transcribe_chunk") {
+        if let Some(fields) = message.as_object_mut() {
+            fields
+                .entry("language")
+                .or_insert_with(|| Value::from(language));
         }
     }
+    message
+}
 
-    // 2. python3 on PATH
+/// Read one JSON line from the sidecar's stdout, with its length in bytes.
+fn read_response(
+    stdout: &mut BufReader<std::process::ChildStdout>,
+) -> Result<(Value, usize), String> {
+    let mut line = String::new();
+    let bytes_read = stdout
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read from sidecar stdout: {e}"))?;
+
+    if bytes_read == 0 {
+        return Err(format!("{ERR_SIDECAR_CLOSED} (possible crash)"));
+    }
+
+    let line = line.trim_end_matches(['\r', '\n']);
+    let value = serde_json::from_str(line.trim())
+        .map_err(|e| format!("Failed to parse sidecar response: {e}"))?;
+    Ok((value, line.len()))
+}
+
+/// Whether `response` is an interim [progress](PROGRESS_RESPONSE_TYPE) line.
+fn is_progress(response: &Value) -> bool {
+    response.get("type").and_then(Value::as_str) == Some(PROGRESS_RESPONSE_TYPE)
+}
+
+/// Wait up to `timeout` for `stdout` to have data (or EOF) to read.
+#[cfg(unix)]
+fn wait_readable(
+    stdout: &BufReader<std::process::ChildStdout>,
+    timeout: Duration,
+) -> Result<bool, String> {
+    use std::os::unix::io::AsRawFd;
+
+    if !stdout.buffer().is_empty() {
+        return Ok(true);
+    }
+    let mut fd = libc::pollfd {
+        fd: stdout.get_ref().as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout_ms = libc::c_int::try_from(timeout.as_millis()).unwrap_or(libc::c_int::MAX);
+    // SAFETY: `fd` is a single valid pollfd that outlives the call.
+    match unsafe { libc::poll(&mut fd, 1, timeout_ms) } {
+        -1 => Err(format!(
+            "Failed to wait for sidecar stdout: {}",
+            std::io::Error::last_os_error()
+        )),
+        0 => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+/// Without `poll`, wait as long as it takes.
+#[cfg(not(unix))]
+fn wait_readable(
+    _stdout: &BufReader<std::process::ChildStdout>,
+    _timeout: Duration,
+) -> Result<bool, String> {
+    Ok(true)
+}
+
+/// Run [`forward_stderr`] on a background thread.
+fn spawn_stderr_reader(
+    stderr: impl Read + Send + 'static,
+    log: Arc<Mutex<Option<RotatingLog>>>,
+    tap: Arc<Mutex<LogTap>>,
+) {
+    let spawned = std::thread::Builder::new()
+        .name("sidecar-stderr".into())
+        .spawn(move || forward_stderr(stderr, &log, &tap));
+    if let Err(e) = spawned {
+        eprintln!("Failed to spawn sidecar stderr reader: {e}");
+    }
+}
+
+/// Forward the child's stderr to ours, to `log` when one is set, and to
+/// `tap`, until the child closes it.
+fn forward_stderr(stderr: impl Read, log: &Mutex<Option<RotatingLog>>, tap: &Mutex<LogTap>) {
+    for line in BufReader::new(stderr).lines() {
+        let Ok(line) = line else { break };
+        eprintln!("[sidecar] {line}");
+        if let Ok(mut log) = log.lock() {
+            if let Some(log) = log.as_mut() {
+                if let Err(e) = log.write_line(&line) {
+                    eprintln!("sidecar log: {e}");
+                }
+            }
+        }
+        if let Ok(mut tap) = tap.lock() {
+            tap.push(&line);
+        }
+    }
+}
+
+/// Send [keepalives](SidecarManager::set_keepalive) from a background
+/// thread for as long as `manager` is alive.
+///
+/// The thread only uses `try_lock`: while a real request holds the manager
+/// the tick is skipped, so keepalives never queue up behind (or delay)
+/// actual work, and the finished request resets the idle time anyway.
+///
+/// # Errors
+/// Returns an error if the thread cannot be spawned.
+pub fn spawn_keepalive(manager: Weak<Mutex<SidecarManager>>) -> Result<(), String> {
+    std::thread::Builder::new()
+        .name("sidecar-keepalive".into())
+        .spawn(move || {
+            let mut interval = None;
+            loop {
+                let Some(manager) = manager.upgrade() else {
+                    return;
+                };
+                if let Ok(mut mgr) = manager.try_lock() {
+                    if let Err(e) = mgr.keepalive_if_idle() {
+                        eprintln!("Sidecar keepalive failed: {e}");
+                    }
+                    interval = mgr.keepalive;
+                }
+                drop(manager);
+                // Wake often enough to send close to on time, but don't spin.
+                let tick = interval.map_or(Duration::from_secs(1), |i: Duration| {
+                    (i / 4).clamp(Duration::from_millis(10), Duration::from_secs(1))
+                });
+                std::thread::sleep(tick);
+            }
+        })
+        .map(|_| ())
+        .map_err(|e| format!("Failed to spawn sidecar keepalive thread: {e}"))
+}
+
+// ---------------------------------------------------------------------------
+// Python discovery helpers
+// ---------------------------------------------------------------------------
+
+/// Try to locate a usable Python interpreter.
+///
+/// Search order:
+/// 1. `python_path` from `second.json` in `config_dir`, if it exists and runs
+/// 2. The backend virtualenv at `<backend_dir>/.venv/bin/python`
+/// 3. `python3` on `$PATH`
+/// 4. `python` on `$PATH`
+///
+/// An unreadable config file or an unusable configured path is logged and
+/// skipped rather than treated as fatal.
+///
+/// # Errors
+/// Returns an error if no Python interpreter can be found.
+pub fn find_python(backend_dir: Option<&str>, config_dir: Option<&Path>) -> Result<String, String> {
+    // 1. Explicit override in the app config file.
+    if let Some(dir) = config_dir {
+        match configured_python(dir) {
+            Ok(Some(python)) => return Ok(python),
+            Ok(None) => {}
+            Err(e) => eprintln!("find_python: ignoring configured python_path: {e}"),
+        }
+    }
+
+    // 2. .venv inside the backend directory (preferred — correct Python version + deps)
+    if let Some(dir) = backend_dir {
+        let venv_python = Path::new(dir).join(".venv/bin/python");
+        if venv_python.exists() {
+            return venv_python
+                .to_str()
+                .map(String::from)
+                .ok_or_else(|| "Virtualenv python path is not valid UTF-8".into());
+        }
+    }
+
+    // 3. python3 on PATH
     if command_exists("python3") {
         return Ok("python3".into());
     }
 
-    // 3. python on PATH
+    // 4. python on PATH
     if command_exists("python") {
         return Ok("python".into());
     }
@@ -189,6 +1271,120 @@ pub fn find_python(backend_dir: Option<&str>) -> Result<String, String> {
     Err("Could not find a Python interpreter. Create a virtualenv in backend/.venv or install Python 3.11+.".into())
 }
 
+/// Read `python_path` from the config file in `config_dir` and check that it
+/// exists and runs. Returns `Ok(None)` if no path is configured.
+fn configured_python(config_dir: &Path) -> Result<Option<String>, String> {
+    let Some(python) = load_app_config(config_dir)?.python_path else {
+        return Ok(None);
+    };
+    if !Path::new(&python).is_file() {
+        return Err(format!("'{python}' does not exist"));
+    }
+    if !command_exists(&python) {
+        return Err(format!("'{python}' could not be run"));
+    }
+    Ok(Some(python))
+}
+
+/// Turn `profile` into a launch, finding the backend directory (with
+/// `retry`) and the interpreter (as [`find_python`] does, with `config_dir`)
+/// where it leaves them unset. The default profile auto-detects both.
+///
+/// # Errors
+/// Returns an error if a directory or interpreter that must be detected
+/// cannot be found.
+pub fn resolve_profile(
+    profile: &SidecarProfile,
+    config_dir: Option<&Path>,
+    retry: BackendDirRetry,
+) -> Result<SidecarLaunch, String> {
+    let backend_dir = match &profile.backend_dir {
+        Some(dir) => dir.clone(),
+        None => find_backend_dir_with_retry(retry)?,
+    };
+    let python_path = match &profile.python_path {
+        Some(python) => python.clone(),
+        None => find_python(Some(&backend_dir), config_dir)?,
+    };
+    Ok(SidecarLaunch {
+        python_path,
+        backend_dir,
+        args: profile.args.clone(),
+        env: profile.env.clone(),
+        memory_limit_bytes: None,
+    })
+}
+
+/// Delay between backend directory probes when `second.json` sets
+/// `backend_dir_attempts` but not `backend_dir_retry_ms`.
+pub const DEFAULT_BACKEND_DIR_RETRY: Duration = Duration::from_secs(1);
+
+/// How [`find_backend_dir_with_retry`] re-probes for a backend directory
+/// that is not there yet. The default is a single attempt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackendDirRetry {
+    pub attempts: u32,
+    pub interval: Duration,
+}
+
+impl Default for BackendDirRetry {
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            interval: DEFAULT_BACKEND_DIR_RETRY,
+        }
+    }
+}
+
+impl BackendDirRetry {
+    /// The retry set in `second.json`, with defaults for what it leaves out.
+    pub fn from_config(config: &AppConfig) -> Self {
+        let default = Self::default();
+        Self {
+            attempts: config.backend_dir_attempts.unwrap_or(default.attempts),
+            interval: config
+                .backend_dir_retry_ms
+                .map_or(default.interval, Duration::from_millis),
+        }
+    }
+}
+
+/// [`find_backend_dir`], tried up to `retry.attempts` times with
+/// `retry.interval` between attempts, so a network share that is still
+/// mounting at launch is found once it appears.
+///
+/// # Errors
+/// Returns the last attempt's error if every attempt fails.
+pub fn find_backend_dir_with_retry(retry: BackendDirRetry) -> Result<String, String> {
+    retry_probe(retry, find_backend_dir)
+}
+
+/// Run `probe` until it succeeds or `retry.attempts` (at least one) have
+/// failed.
+fn retry_probe<T>(
+    retry: BackendDirRetry,
+    mut probe: impl FnMut() -> Result<T, String>,
+) -> Result<T, String> {
+    let attempts = retry.attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match probe() {
+            Ok(found) => return Ok(found),
+            Err(e) if attempt >= attempts => {
+                return Err(if attempts > 1 {
+                    format!("{e} (after {attempts} attempts)")
+                } else {
+                    e
+                });
+            }
+            Err(_) => {
+                std::thread::sleep(retry.interval);
+                attempt += 1;
+            }
+        }
+    }
+}
+
 /// Resolve the backend directory path.
 ///
 /// Checks, in order:
@@ -244,6 +1440,28 @@ pub fn find_backend_dir() -> Result<String, String> {
     Err("Could not find the backend directory. Set SECOND_BACKEND_DIR or ensure backend/ exists relative to the project root.".into())
 }
 
+/// Sample `(memory_bytes, cpu_percent)` for `pid` using `ps`, which reports
+/// the same columns on macOS and Linux.
+fn process_stats(pid: u32) -> Option<(u64, f64)> {
+    let output = Command::new("ps")
+        .args(["-o", "rss=,%cpu=", "-p", &pid.to_string()])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_ps_stats(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the `rss %cpu` line printed by `ps -o rss=,%cpu=`. RSS is in KiB.
+fn parse_ps_stats(output: &str) -> Option<(u64, f64)> {
+    let mut fields = output.split_whitespace();
+    let rss_kib: u64 = fields.next()?.parse().ok()?;
+    let cpu: f64 = fields.next()?.replace(',', ".").parse().ok()?;
+    Some((rss_kib * 1024, cpu))
+}
+
 /// Check whether a command is available on `$PATH` by running it with
 /// `--version`.
 fn command_exists(cmd: &str) -> bool {
@@ -264,6 +1482,7 @@ fn command_exists(cmd: &str) -> bool {
 mod tests {
     use super::*;
     use serde_json::json;
+    use std::path::PathBuf;
 
     // -- Unit tests for JSON serialization / deserialization --
 
@@ -307,7 +1526,7 @@ mod tests {
     #[test]
     fn test_find_python_returns_ok() {
         // On any system with Python installed this should succeed.
-        let result = find_python(None);
+        let result = find_python(None, None);
         // We can't guarantee Python is installed in CI, so just check the
         // function doesn't panic and returns a reasonable result.
         match result {
@@ -318,7 +1537,7 @@ mod tests {
 
     #[test]
     fn test_find_python_with_nonexistent_venv() {
-        let result = find_python(Some("/tmp/definitely_does_not_exist_12345"));
+        let result = find_python(Some("/tmp/definitely_does_not_exist_12345"), None);
         // Venv doesn't exist, so falls back to system python; only errors if none found.
         match result {
             Ok(path) => assert!(!path.is_empty()),
@@ -326,6 +1545,61 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_find_python_prefers_valid_configured_path() {
+        let Ok(system_python) = find_python(None, None) else {
+            eprintln!("Skipping config test: python not found");
+            return;
+        };
+        // The configured path must be a real file, so resolve the bare
+        // command name through $PATH.
+        let Some(absolute) = std::env::var_os("PATH").and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|d| d.join(&system_python))
+                .find(|p| p.is_file())
+        }) else {
+            eprintln!("Skipping config test: cannot resolve {system_python}");
+            return;
+        };
+        let absolute = absolute.to_str().expect("utf-8").to_string();
+
+        let dir = std::env::temp_dir().join("second_test_sidecar_python_config");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+
+        // A venv in the backend dir would normally win; the config beats it.
+        let backend = dir.join("backend");
+        std::fs::create_dir_all(backend.join(".venv/bin")).expect("create venv");
+        std::fs::write(backend.join(".venv/bin/python"), "").expect("write venv python");
+        let backend = backend.to_str().expect("utf-8");
+
+        let write_config = |python: &str| {
+            std::fs::write(
+                crate::config::config_path(&dir),
+                json!({ "python_path": python }).to_string(),
+            )
+            .expect("write config");
+        };
+
+        write_config(&absolute);
+        assert_eq!(
+            find_python(Some(backend), Some(&dir)).expect("find"),
+            absolute
+        );
+
+        // An invalid configured path falls through to the normal search.
+        write_config("/no/such/python3");
+        let fallback = find_python(Some(backend), Some(&dir)).expect("find");
+        assert!(fallback.ends_with(".venv/bin/python"), "got: {fallback}");
+
+        // So does a config dir with no file at all.
+        std::fs::remove_file(crate::config::config_path(&dir)).expect("remove config");
+        let fallback = find_python(Some(backend), Some(&dir)).expect("find");
+        assert!(fallback.ends_with(".venv/bin/python"), "got: {fallback}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     // -- SidecarManager unit tests --
 
     #[test]
@@ -371,6 +1645,835 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_restart_without_start_returns_error() {
+        let mut mgr = SidecarManager::new();
+        let result = mgr.restart();
+        assert!(result.unwrap_err().contains("not been started"));
+    }
+
+    // -- Trace buffer tests --
+
+    #[test]
+    fn test_trace_is_off_by_default() {
+        let mgr = SidecarManager::new();
+        assert!(mgr.trace().is_empty());
+    }
+
+    /// Write a `main.py` that echoes every JSON line back, standing in for
+    /// the real backend. Returns `None` if no Python interpreter is available.
+    fn echo_backend(name: &str) -> Option<(String, PathBuf)> {
+        let python = find_python(None, None).ok()?;
+        let dir = std::env::temp_dir().join(format!("second_test_sidecar_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        std::fs::write(
+            dir.join("main.py"),
+            "import sys\nfor line in sys.stdin:\n    sys.stdout.write(line)\n    sys.stdout.flush()\n",
+        )
+        .expect("write main.py");
+        Some((python, dir))
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_up_to_the_cap() {
+        let policy = ReconnectPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1_000),
+            jitter: 0.0,
+        };
+        let delays: Vec<u64> = (1..=6)
+            .map(|attempt| backoff_delay(&policy, attempt, 0.9).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1_000, 1_000]);
+        // Without jitter the random draw changes nothing.
+        assert_eq!(
+            backoff_delay(&policy, 3, 0.0),
+            backoff_delay(&policy, 3, 1.0)
+        );
+        // Far past the point where doubling would overflow.
+        assert_eq!(backoff_delay(&policy, 200, 0.5), policy.max_backoff);
+
+        let jittery = ReconnectPolicy {
+            jitter: 0.5,
+            ..policy
+        };
+        assert_eq!(backoff_delay(&jittery, 2, 0.0), Duration::from_millis(100));
+        assert_eq!(backoff_delay(&jittery, 2, 0.5), Duration::from_millis(200));
+        assert_eq!(backoff_delay(&jittery, 2, 1.0), Duration::from_millis(300));
+        // Jitter never pushes past the cap.
+        assert_eq!(backoff_delay(&jittery, 4, 1.0), policy.max_backoff);
+        for _ in 0..100 {
+            let delay = backoff_delay(&jittery, 1, jitter_sample());
+            assert!((50..=150).contains(&delay.as_millis()), "got {delay:?}");
+        }
+    }
+
+    #[test]
+    fn test_reconnect_policy_validation() {
+        assert!(ReconnectPolicy::default().validate().is_ok());
+        let bad_jitter = ReconnectPolicy {
+            jitter: 1.5,
+            ..Default::default()
+        };
+        assert!(bad_jitter.validate().unwrap_err().contains("jitter"));
+        let inverted = ReconnectPolicy {
+            initial_backoff: Duration::from_secs(60),
+            ..Default::default()
+        };
+        assert!(inverted.validate().is_err());
+        let mut mgr = SidecarManager::new();
+        assert!(mgr.set_reconnect_policy(bad_jitter).is_err());
+    }
+
+    #[test]
+    fn test_reconnect_policy_restarts_a_crashed_sidecar_and_resends() {
+        let Some((python, dir)) = echo_backend("reconnect") else {
+            eprintln!("Skipping reconnect test: python not found");
+            return;
+        };
+        // Each process exits on its first message until two crashes have
+        // been logged, then echoes.
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import os, sys
+log = os.path.join(os.path.dirname(os.path.abspath(__file__)), "crashes.log")
+crashes = len(open(log).read()) if os.path.exists(log) else 0
+for line in sys.stdin:
+    if crashes < 2:
+        open(log, "a").write("x")
+        sys.exit(1)
+    sys.stdout.write(line)
+    sys.stdout.flush()
+"#,
+        )
+        .expect("write main.py");
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start");
+        let message = serde_json::json!({ "type": "health" });
+
+        // Without a policy the crash is the caller's problem.
+        let err = mgr.send_message(message.clone()).unwrap_err();
+        assert!(err.starts_with(ERR_SIDECAR_CLOSED), "got: {err}");
+        assert_eq!(mgr.reconnect_stats(), ReconnectStats::default());
+
+        mgr.restart().expect("restart");
+        mgr.set_reconnect_policy(ReconnectPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            jitter: 0.5,
+        })
+        .expect("policy");
+        assert_eq!(mgr.send_message(message.clone()), Ok(message));
+        let stats = mgr.reconnect_stats();
+        assert_eq!(stats.total_restarts, 2);
+        assert!(stats.last_restart_at.is_some());
+
+        mgr.stop().expect("stop");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_ensure_started_twice_spawns_once() {
+        let Some((python, dir)) = echo_backend("ensure_started") else {
+            eprintln!("Skipping ensure_started test: python not found");
+            return;
+        };
+        // Answers health checks and logs each process it runs as.
+        std::fs::write(
+            dir.join("main.py"),
+            "import json, os, sys\n\
+             with open('spawns.log', 'a') as f:\n    f.write(str(os.getpid()) + '\\n')\n\
+             for line in sys.stdin:\n    \
+             print(json.dumps({'type': 'health', 'status': 'ok'}), flush=True)\n",
+        )
+        .expect("write main.py");
+        let launch = SidecarLaunch::new(&python, dir.to_str().expect("utf-8"));
+
+        let mut mgr = SidecarManager::new();
+        assert!(mgr.ensure_started(launch.clone()).expect("first start"));
+        let pid = mgr.process_info().pid;
+        assert!(!mgr.ensure_started(launch).expect("second start"));
+        assert_eq!(mgr.process_info().pid, pid);
+        let _ = mgr.stop();
+
+        let spawns = std::fs::read_to_string(dir.join("spawns.log")).expect("read spawns");
+        assert_eq!(spawns.lines().count(), 1, "spawned: {spawns}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_send_message_timed_measures_the_exchange() {
+        let Some((python, dir)) = echo_backend("timed") else {
+            eprintln!("Skipping timed send test: python not found");
+            return;
+        };
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start echo backend");
+
+        let message = json!({"type": "ping", "payload": "é"});
+        let size = serde_json::to_string(&message).expect("serialize").len();
+        let timed = mgr.send_message_timed(message.clone()).expect("echo");
+        assert_eq!(timed.value, message);
+        assert_eq!(timed.request_bytes, size);
+        // The echo backend sends back exactly the line it was given.
+        assert_eq!(timed.response_bytes, size);
+        assert!(timed.round_trip > Duration::ZERO);
+        assert!(timed.round_trip < Duration::from_secs(10));
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_trace_records_exchanges_in_order() {
+        let Some((python, dir)) = echo_backend("trace") else {
+            eprintln!("Skipping trace test: python not found");
+            return;
+        };
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start echo backend");
+        mgr.set_trace(true, 2);
+
+        for i in 0..3 {
+            let response = mgr
+                .send_message(json!({"type": "ping", "n": i}))
+                .expect("echo");
+            assert_eq!(response["n"], i);
+        }
+
+        // Capacity 2: the first exchange has been evicted.
+        let trace = mgr.trace();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0].request["n"], 1);
+        assert_eq!(trace[0].response.as_ref().expect("response")["n"], 1);
+        assert_eq!(trace[1].request["n"], 2);
+        assert!(trace[0].timestamp_ms <= trace[1].timestamp_ms);
+        assert!(trace[1].error.is_none());
+
+        mgr.set_trace(false, DEFAULT_TRACE_CAPACITY);
+        mgr.send_message(json!({"type": "ping"})).expect("echo");
+        assert!(mgr.trace().is_empty());
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_keepalive_timer_sends_at_configured_cadence() {
+        let Some((python, dir)) = echo_backend("keepalive") else {
+            eprintln!("Skipping keepalive test: python not found");
+            return;
+        };
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start echo backend");
+        mgr.set_trace(true, DEFAULT_TRACE_CAPACITY);
+        mgr.set_keepalive(Some(Duration::from_millis(100)));
+        let manager = Arc::new(Mutex::new(mgr));
+        spawn_keepalive(Arc::downgrade(&manager)).expect("spawn keepalive");
+
+        std::thread::sleep(Duration::from_millis(550));
+        let keepalives = |mgr: &SidecarManager| {
+            mgr.trace()
+                .iter()
+                .filter(|e| e.request["type"] == "keepalive")
+                .count()
+        };
+        let sent = keepalives(&manager.lock().unwrap());
+        assert!((3..=6).contains(&sent), "sent {sent} keepalives");
+
+        // Disabling stops them.
+        let mut mgr = manager.lock().unwrap();
+        mgr.set_keepalive(None);
+        let sent = keepalives(&mgr);
+        drop(mgr);
+        std::thread::sleep(Duration::from_millis(300));
+        let mut mgr = manager.lock().unwrap();
+        assert_eq!(keepalives(&mgr), sent);
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn collecting_listener() -> (LogListener, Arc<Mutex<Vec<String>>>) {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&lines);
+        let listener: LogListener = Box::new(move |line| sink.lock().unwrap().push(line.into()));
+        (listener, lines)
+    }
+
+    #[test]
+    fn test_log_listener_replays_buffer_then_follows_new_lines() {
+        let mut mgr = SidecarManager::new();
+        let log = Mutex::new(None);
+        forward_stderr(&b"one\ntwo\n"[..], &log, &mgr.log_tap);
+
+        let (listener, lines) = collecting_listener();
+        mgr.set_log_listener(Some(listener)).expect("attach");
+        assert_eq!(*lines.lock().unwrap(), ["one", "two"]);
+
+        forward_stderr(&b"three\nfour\n"[..], &log, &mgr.log_tap);
+        assert_eq!(*lines.lock().unwrap(), ["one", "two", "three", "four"]);
+
+        // Detaching stops delivery; the buffer keeps filling.
+        mgr.set_log_listener(None).expect("detach");
+        forward_stderr(&b"five\n"[..], &log, &mgr.log_tap);
+        assert_eq!(lines.lock().unwrap().len(), 4);
+        assert_eq!(mgr.log_tap.lock().unwrap().recent.len(), 5);
+    }
+
+    #[test]
+    fn test_log_buffer_keeps_only_recent_lines() {
+        let mgr = SidecarManager::new();
+        let input: String = (0..LOG_BUFFER_LINES + 5)
+            .map(|i| format!("line {i}\n"))
+            .collect();
+        forward_stderr(input.as_bytes(), &Mutex::new(None), &mgr.log_tap);
+
+        let tap = mgr.log_tap.lock().unwrap();
+        assert_eq!(tap.recent.len(), LOG_BUFFER_LINES);
+        assert_eq!(tap.recent.front().map(String::as_str), Some("line 5"));
+    }
+
+    #[test]
+    fn test_stop_detaches_log_listener() {
+        let Some((python, dir)) = echo_backend("log_stream") else {
+            eprintln!("Skipping log stream test: python not found");
+            return;
+        };
+        std::fs::write(
+            dir.join("main.py"),
+            "import sys\nprint('booting', file=sys.stderr, flush=True)\nfor line in sys.stdin:\n    print(line.strip(), file=sys.stderr, flush=True)\n    sys.stdout.write(line)\n    sys.stdout.flush()\n",
+        )
+        .expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        let (listener, lines) = collecting_listener();
+        mgr.set_log_listener(Some(listener)).expect("attach");
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        mgr.send_message(json!({"type": "ping"})).expect("echo");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while lines.lock().unwrap().len() < 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(*lines.lock().unwrap(), ["booting", r#"{"type":"ping"}"#]);
+
+        mgr.stop().expect("stop");
+        forward_stderr(&b"after stop\n"[..], &Mutex::new(None), &mgr.log_tap);
+        assert_eq!(lines.lock().unwrap().len(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_keepalive_only_sent_when_idle() {
+        let mut mgr = SidecarManager::new();
+        // Disabled by default, and never sent without a process.
+        assert_eq!(mgr.keepalive_if_idle(), Ok(false));
+        mgr.set_keepalive(Some(Duration::from_millis(1)));
+        assert_eq!(mgr.keepalive_if_idle(), Ok(false));
+
+        let Some((python, dir)) = echo_backend("keepalive_idle") else {
+            eprintln!("Skipping keepalive test: python not found");
+            return;
+        };
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start echo backend");
+        mgr.set_keepalive(Some(Duration::from_secs(60)));
+        mgr.send_message(json!({"type": "ping"})).expect("echo");
+        // A real exchange just happened, so nothing is due.
+        assert_eq!(mgr.keepalive_if_idle(), Ok(false));
+
+        mgr.set_keepalive(Some(Duration::from_millis(20)));
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(mgr.keepalive_if_idle(), Ok(true));
+        assert_eq!(mgr.keepalive_if_idle(), Ok(false));
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_log_file_captures_stderr_and_trace() {
+        let Some((python, dir)) = echo_backend("log_file") else {
+            eprintln!("Skipping log file test: python not found");
+            return;
+        };
+        std::fs::write(
+            dir.join("main.py"),
+            "import sys\nprint('backend booting', file=sys.stderr, flush=True)\nfor line in sys.stdin:\n    sys.stdout.write(line)\n    sys.stdout.flush()\n",
+        )
+        .expect("write main.py");
+        let log_path = dir.join("logs").join("sidecar.log");
+
+        let mut mgr = SidecarManager::new();
+        mgr.set_log_file(Some(&log_path), 1024 * 1024)
+            .expect("set log file");
+        mgr.set_trace(true, DEFAULT_TRACE_CAPACITY);
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        mgr.send_message(json!({"type": "ping"})).expect("echo");
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let contents = loop {
+            let contents = std::fs::read_to_string(&log_path).unwrap_or_default();
+            if contents.contains("backend booting") || std::time::Instant::now() > deadline {
+                break contents;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert!(contents.contains("backend booting"), "log: {contents}");
+        assert!(contents.contains("[trace]"), "log: {contents}");
+        assert!(contents.contains("\"ping\""), "log: {contents}");
+
+        mgr.set_log_file(None, 0).expect("disable log");
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_set_device_caches_the_active_device() {
+        let Some((python, dir)) = echo_backend("set_device") else {
+            eprintln!("Skipping set_device test: python not found");
+            return;
+        };
+        // No GPU here: `auto` falls back to the CPU and `cuda` fails.
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import json, sys
+for line in sys.stdin:
+    device = json.loads(line)["device"]
+    if device == "cuda":
+        reply = {"status": "error", "message": "CUDA is not available on this machine"}
+    else:
+        reply = {"status": "ok", "device": "cpu"}
+    print(json.dumps(reply), flush=True)
+"#,
+        )
+        .expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        assert_eq!(mgr.process_info().compute_device, None);
+
+        assert_eq!(mgr.set_device(ComputeDevice::Auto).expect("switch"), "cpu");
+        assert_eq!(mgr.process_info().compute_device.as_deref(), Some("cpu"));
+
+        let err = mgr.set_device(ComputeDevice::Cuda).unwrap_err();
+        assert!(err.starts_with(ERR_DEVICE_UNAVAILABLE), "got: {err}");
+        assert_eq!(mgr.process_info().compute_device, None);
+
+        // A new process starts on whatever device it picks itself.
+        mgr.set_device(ComputeDevice::Cpu).expect("switch");
+        mgr.restart().expect("restart");
+        assert_eq!(mgr.process_info().compute_device, None);
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_set_device_error_is_typed() {
+        let oom = set_device_error(ComputeDevice::Cuda, "CUDA out of memory. Tried to allocate");
+        assert!(oom.starts_with(ERR_DEVICE_OUT_OF_MEMORY), "got: {oom}");
+        let missing = set_device_error(ComputeDevice::Cuda, "CUDA unavailable");
+        assert!(
+            missing.starts_with(ERR_DEVICE_UNAVAILABLE),
+            "got: {missing}"
+        );
+        let other = set_device_error(ComputeDevice::Cpu, "model is busy");
+        assert!(other.contains("model is busy"), "got: {other}");
+        assert!(!other.starts_with(ERR_DEVICE_UNAVAILABLE));
+    }
+
+    #[test]
+    fn test_capabilities_default_when_backend_rejects_request() {
+        let Some((python, dir)) = echo_backend("capabilities_legacy") else {
+            eprintln!("Skipping capabilities test: python not found");
+            return;
+        };
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import json, sys
+for line in sys.stdin:
+    msg = json.loads(line)
+    print(json.dumps({"type": "error", "message": f"Unknown message type: {msg['type']}"}), flush=True)
+"#,
+        )
+        .expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        let caps = mgr.capabilities().expect("capabilities");
+        assert_eq!(caps, SidecarCapabilities::default());
+        assert!(!caps.reported);
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_capabilities_are_cached_until_restart() {
+        let Some((python, dir)) = echo_backend("capabilities_cached") else {
+            eprintln!("Skipping capabilities test: python not found");
+            return;
+        };
+        // Reports how many times it has been asked as its version.
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import json, sys
+queries = 0
+for line in sys.stdin:
+    queries += 1
+    print(json.dumps({"type": "capabilities", "translation": True, "backend_version": str(queries)}), flush=True)
+"#,
+        )
+        .expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        let caps = mgr.capabilities().expect("capabilities");
+        assert!(caps.reported);
+        assert!(caps.translation);
+        assert_eq!(caps.backend_version.as_deref(), Some("1"));
+        assert_eq!(mgr.capabilities().expect("capabilities"), caps);
+
+        // A new process is asked afresh rather than served the old answer.
+        mgr.restart().expect("restart");
+        mgr.send_message(json!({"type": "health"})).expect("send");
+        let caps = mgr.capabilities().expect("capabilities");
+        assert_eq!(caps.backend_version.as_deref(), Some("2"));
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_require_streaming_follows_capabilities() {
+        for streaming in [true, false] {
+            let Some((python, dir)) = echo_backend(&format!("streaming_{streaming}")) else {
+                eprintln!("Skipping streaming test: python not found");
+                return;
+            };
+            std::fs::write(
+                dir.join("main.py"),
+                format!(
+                    r#"import json, sys
+for line in sys.stdin:
+    print(json.dumps({{"type": "capabilities", "streaming": {}}}), flush=True)
+"#,
+                    if streaming { "True" } else { "False" }
+                ),
+            )
+            .expect("write main.py");
+
+            let mut mgr = SidecarManager::new();
+            mgr.start(&python, dir.to_str().expect("utf-8"))
+                .expect("start backend");
+            assert_eq!(mgr.supports_streaming(), streaming);
+            let guard = mgr.require_streaming();
+            if streaming {
+                assert!(guard.is_ok());
+            } else {
+                let err = guard.expect_err("streaming unsupported");
+                assert!(err.starts_with(ERR_STREAMING_UNSUPPORTED));
+            }
+
+            let _ = mgr.stop();
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[test]
+    fn test_supports_streaming_defaults_to_false() {
+        let Some((python, dir)) = echo_backend("streaming_unreported") else {
+            eprintln!("Skipping streaming test: python not found");
+            return;
+        };
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import json, sys
+for line in sys.stdin:
+    print(json.dumps({"type": "capabilities", "translation": True}), flush=True)
+"#,
+        )
+        .expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        assert!(!mgr.supports_streaming());
+        assert!(mgr.require_streaming().is_err());
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_warm_up_marks_sidecar_ready() {
+        let Some((python, dir)) = echo_backend("warm_up") else {
+            eprintln!("Skipping warm-up test: python not found");
+            return;
+        };
+        // Acknowledges transcribe_chunk like a real backend and rejects the
+        // rest, so only a proper warm-up request flips readiness.
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import json, sys
+for line in sys.stdin:
+    msg = json.loads(line)
+    if msg["type"] == "transcribe_chunk":
+        print(json.dumps({"type": "transcription", "text": ""}), flush=True)
+    else:
+        print(json.dumps({"type": "error", "message": "unsupported"}), flush=True)
+"#,
+        )
+        .expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        assert!(!mgr.is_ready());
+
+        let err = mgr
+            .warm_up(&json!({"type": "warmup"}), WARMUP_TIMEOUT)
+            .unwrap_err();
+        assert!(err.contains("unsupported"), "got: {err}");
+        assert!(!mgr.is_ready());
+
+        let request = crate::transcribe::warmup_request().expect("warm-up request");
+        mgr.warm_up(&request, WARMUP_TIMEOUT).expect("warm up");
+        assert!(mgr.is_ready());
+
+        mgr.restart().expect("restart");
+        assert!(!mgr.is_ready());
+
+        let _ = mgr.stop();
+        assert!(!mgr.is_ready());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_timed_out_reply_is_discarded() {
+        let Some((python, dir)) = echo_backend("timeout") else {
+            eprintln!("Skipping timeout test: python not found");
+            return;
+        };
+        // Echoes every request, taking its time over "slow" ones.
+        std::fs::write(
+            dir.join("main.py"),
+            "import json, sys, time\nfor line in sys.stdin:\n    if json.loads(line).get('slow'):\n        time.sleep(0.3)\n    sys.stdout.write(line)\n    sys.stdout.flush()\n",
+        )
+        .expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+
+        let err = mgr
+            .send_message_with_timeout(
+                json!({"n": 1, "slow": true}),
+                std::time::Duration::from_millis(50),
+            )
+            .unwrap_err();
+        assert!(err.starts_with(ERR_SIDECAR_TIMEOUT), "got: {err}");
+
+        // The late reply to n=1 is skipped, not returned for n=2.
+        let response = mgr.send_message(json!({"n": 2})).expect("echo");
+        assert_eq!(response["n"], 2);
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_timed_call_after_a_timeout_does_not_wait_for_the_stale_reply() {
+        let Some((python, dir)) = echo_backend("stale_hang") else {
+            eprintln!("Skipping stale reply test: python not found");
+            return;
+        };
+        // Reads every request and never replies.
+        std::fs::write(
+            dir.join("main.py"),
+            "import sys
+for line in sys.stdin:
+    pass
+",
+        )
+        .expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+
+        let timeout = std::time::Duration::from_millis(50);
+        let err = mgr
+            .send_message_with_timeout(json!({"n": 1}), timeout)
+            .unwrap_err();
+        assert!(err.starts_with(ERR_SIDECAR_TIMEOUT), "got: {err}");
+
+        // The reply to n=1 never comes; n=2 still gives up on time.
+        let started = Instant::now();
+        let err = mgr
+            .send_message_with_timeout(json!({"n": 2}), timeout)
+            .unwrap_err();
+        assert!(err.starts_with(ERR_SIDECAR_TIMEOUT), "got: {err}");
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_trace_records_failed_exchanges() {
+        let mut mgr = SidecarManager::new();
+        mgr.set_trace(true, 0);
+        assert!(mgr.send_message(json!({"type": "health"})).is_err());
+
+        let trace = mgr.trace();
+        assert_eq!(trace.len(), 1);
+        assert!(trace[0].response.is_none());
+        assert!(trace[0]
+            .error
+            .as_deref()
+            .is_some_and(|e| e.contains("stdin not available")));
+    }
+
+    #[test]
+    fn test_last_error_is_sticky_until_a_successful_exchange() {
+        let Some((python, dir)) = echo_backend("last_error") else {
+            eprintln!("Skipping last error test: python not found");
+            return;
+        };
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        mgr.send_message(json!({"n": 1})).expect("echo");
+        assert!(mgr.last_error().is_none());
+
+        // Kill the process behind the manager's back.
+        let child = mgr.process.as_mut().expect("child");
+        child.kill().expect("kill");
+        child.wait().expect("wait");
+        let before = Instant::now();
+        let err = mgr.send_message(json!({"n": 2})).unwrap_err();
+        let (at, message) = mgr.last_error().expect("last error");
+        assert_eq!(message, err);
+        assert!(at >= before);
+
+        // Still reported after restarting, until something succeeds.
+        mgr.restart().expect("restart");
+        assert!(mgr.last_error().is_some());
+        mgr.send_message(json!({"n": 3})).expect("echo");
+        assert!(mgr.last_error().is_none());
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_clear_error_forgets_last_error() {
+        let mut mgr = SidecarManager::new();
+        assert!(mgr.send_message(json!({"type": "health"})).is_err());
+        assert!(mgr
+            .last_error()
+            .is_some_and(|(_, e)| e.contains("stdin not available")));
+        mgr.clear_error();
+        assert!(mgr.last_error().is_none());
+    }
+
+    // -- Process info tests --
+
+    #[test]
+    fn test_language_hint_added_to_transcribe_chunk_only() {
+        let chunk = serde_json::json!({"type": "transcribe_chunk", "audio_base64": "AAAA"});
+        assert_eq!(
+            with_language_hint(chunk.clone(), Some("de"))["language"],
+            "de"
+        );
+        assert_eq!(with_language_hint(chunk.clone(), None), chunk);
+
+        let explicit = serde_json::json!({"type": "transcribe_chunk", "language": "fr"});
+        assert_eq!(with_language_hint(explicit, Some("de"))["language"], "fr");
+
+        let health = serde_json::json!({"type": "health"});
+        assert_eq!(with_language_hint(health.clone(), Some("de")), health);
+    }
+
+    #[test]
+    fn test_per_call_language_overrides_set_language() {
+        let mut mgr = SidecarManager::new();
+        assert_eq!(mgr.language(), None);
+        mgr.with_language(Some("es".into()), |mgr| {
+            assert_eq!(mgr.language(), Some("es"));
+        });
+
+        mgr.set_language(Some("en".into()));
+        assert_eq!(mgr.language(), Some("en"));
+        let inside = mgr.with_language(Some("ja".into()), |mgr| mgr.language().map(String::from));
+        assert_eq!(inside.as_deref(), Some("ja"));
+        mgr.with_language(None, |mgr| assert_eq!(mgr.language(), Some("en")));
+        // The override ends with the call.
+        assert_eq!(mgr.language(), Some("en"));
+    }
+
+    #[test]
+    fn test_process_info_idle_has_no_stats() {
+        let mut mgr = SidecarManager::new();
+        let info = mgr.process_info();
+        assert!(!info.running);
+        assert_eq!(info.pid, None);
+        assert_eq!(info.memory_bytes, None);
+        assert_eq!(info.cpu_percent, None);
+        assert_eq!(info.memory_limit_bytes, None);
+        assert!(!info.memory_limit_enforced);
+    }
+
+    #[test]
+    fn test_process_info_running_reports_pid() {
+        let Some((python, dir)) = echo_backend("process_info") else {
+            eprintln!("Skipping process info test: python not found");
+            return;
+        };
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start echo backend");
+        let expected_pid = mgr.process.as_ref().map(Child::id);
+
+        let info = mgr.process_info();
+        assert!(info.running);
+        assert_eq!(info.pid, expected_pid);
+        // Stats depend on `ps` being available; when sampled they must be sane.
+        if let Some(memory) = info.memory_bytes {
+            assert!(memory > 0);
+            assert!(info.cpu_percent.is_some_and(|c| c >= 0.0));
+        }
+
+        mgr.stop().expect("stop");
+        assert!(!mgr.process_info().running);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_ps_stats() {
+        assert_eq!(parse_ps_stats(" 10240  12.5\n"), Some((10_485_760, 12.5)));
+        // Some locales print a decimal comma.
+        assert_eq!(parse_ps_stats("2048 0,3"), Some((2_097_152, 0.3)));
+        assert_eq!(parse_ps_stats(""), None);
+        assert_eq!(parse_ps_stats("abc 1.0"), None);
+    }
+
     // -- Integration test with the real Python backend --
 
     #[test]
@@ -389,7 +2492,7 @@ mod tests {
         };
         let backend_dir = backend_dir.to_str().expect("backend dir not utf-8");
 
-        let python = match find_python(Some(backend_dir)) {
+        let python = match find_python(Some(backend_dir), None) {
             Ok(p) => p,
             Err(_) => {
                 eprintln!("Skipping integration test: python not found");
@@ -416,6 +2519,150 @@ mod tests {
         assert!(!mgr.is_running());
     }
 
+    #[test]
+    fn test_profile_launch_constructs_expected_command() {
+        let profile = SidecarProfile {
+            python_path: Some("/usr/local/bin/gpu-box".into()),
+            backend_dir: Some("/srv/second/backend".into()),
+            args: vec!["--device".into(), "cuda".into()],
+            env: BTreeMap::from([("CUDA_VISIBLE_DEVICES".into(), "1".into())]),
+        };
+        // Nothing is left to detect, so this works on any machine.
+        let launch = resolve_profile(&profile, None, BackendDirRetry::default()).expect("resolve");
+        let command = launch.command();
+
+        assert_eq!(command.get_program(), "/usr/local/bin/gpu-box");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec!["main.py", "--device", "cuda"]
+        );
+        assert_eq!(
+            command.get_current_dir(),
+            Some(Path::new("/srv/second/backend"))
+        );
+        assert_eq!(
+            command.get_envs().collect::<Vec<_>>(),
+            vec![(
+                std::ffi::OsStr::new("CUDA_VISIBLE_DEVICES"),
+                Some(std::ffi::OsStr::new("1"))
+            )]
+        );
+
+        // A plain start is the same command without the extras.
+        let plain = SidecarLaunch::new("python3", "/srv/second/backend").command();
+        assert_eq!(plain.get_args().collect::<Vec<_>>(), vec!["main.py"]);
+        assert_eq!(plain.get_envs().count(), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_memory_limit_is_applied_to_spawned_process() {
+        let dir = std::env::temp_dir().join("second_test_sidecar_memory_limit");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        // `sh main.py` reports the address-space limit in KiB.
+        std::fs::write(dir.join("main.py"), "ulimit -v\n").expect("write script");
+
+        let launch = SidecarLaunch {
+            memory_limit_bytes: Some(512 * 1024 * 1024),
+            ..SidecarLaunch::new("sh", dir.to_str().unwrap())
+        };
+        let output = launch.command().output().expect("run");
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "524288");
+
+        let unlimited = SidecarLaunch::new("sh", dir.to_str().unwrap());
+        let output = unlimited.command().output().expect("run");
+        assert_ne!(String::from_utf8_lossy(&output.stdout).trim(), "524288");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_restart_reuses_profile_launch() {
+        let Some((python, dir)) = echo_backend("profile_restart") else {
+            eprintln!("Skipping profile restart test: python not found");
+            return;
+        };
+        let mut launch = SidecarLaunch::new(&python, dir.to_str().expect("utf-8"));
+        launch.env.insert("SECOND_TEST_PROFILE".into(), "1".into());
+        let mut mgr = SidecarManager::new();
+        mgr.start_with(launch.clone()).expect("start");
+        mgr.restart().expect("restart");
+        assert_eq!(mgr.launch, Some(launch));
+        assert!(mgr.is_running());
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_retry_probe_finds_directory_that_appears_later() {
+        let dir = std::env::temp_dir().join("second_test_sidecar_late_mount");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut probes = 0;
+        let probe = |probes: &mut u32| {
+            *probes += 1;
+            if dir.is_dir() {
+                return Ok(dir.clone());
+            }
+            // The share finishes mounting just after the first look.
+            std::fs::create_dir_all(&dir).expect("create dir");
+            Err(format!("'{}' does not exist", dir.display()))
+        };
+
+        // The default single attempt gives up straight away.
+        let err = retry_probe(BackendDirRetry::default(), || probe(&mut probes)).unwrap_err();
+        assert!(err.contains("does not exist"), "got: {err}");
+        assert_eq!(probes, 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+        probes = 0;
+        let retry = BackendDirRetry {
+            attempts: 3,
+            interval: Duration::from_millis(10),
+        };
+        assert_eq!(retry_probe(retry, || probe(&mut probes)), Ok(dir.clone()));
+        assert_eq!(probes, 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_retry_probe_reports_attempts_when_exhausted() {
+        let retry = BackendDirRetry {
+            attempts: 3,
+            interval: Duration::from_millis(1),
+        };
+        let mut probes = 0;
+        let err = retry_probe::<()>(retry, || {
+            probes += 1;
+            Err("missing".into())
+        })
+        .unwrap_err();
+        assert_eq!(err, "missing (after 3 attempts)");
+        assert_eq!(probes, 3);
+    }
+
+    #[test]
+    fn test_backend_dir_retry_from_config_defaults_to_one_attempt() {
+        assert_eq!(
+            BackendDirRetry::from_config(&AppConfig::default()),
+            BackendDirRetry::default()
+        );
+        let config = AppConfig {
+            backend_dir_attempts: Some(4),
+            backend_dir_retry_ms: Some(250),
+            ..AppConfig::default()
+        };
+        assert_eq!(
+            BackendDirRetry::from_config(&config),
+            BackendDirRetry {
+                attempts: 4,
+                interval: Duration::from_millis(250),
+            }
+        );
+    }
+
     // -- find_backend_dir tests --
     //
     // These tests modify process-global env vars and MUST run inside
@@ -0,0 +1,392 @@
+//! Filesystem helpers for the recordings directory.
+//!
+//! Reports free space on the volume holding the recordings so the UI can warn
+//! before a long recording fills the disk, and turns read-only / full volume
+//! I/O errors into messages the UI can act on.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Prefix of errors for a recordings location that cannot be written to
+/// (read-only volume or missing permissions). The UI matches on it to prompt
+/// for a different folder.
+pub const ERR_STORAGE_UNAVAILABLE: &str = "Storage unavailable";
+
+/// Prefix of errors for a recordings location whose volume is out of space.
+pub const ERR_DISK_FULL: &str = "Disk full";
+
+/// Prefix of the error returned when the recordings directory must already
+/// exist but does not.
+pub const ERR_RECORDINGS_DIR_MISSING: &str = "Recordings directory does not exist";
+
+/// Name of the file created and removed by [`ensure_writable`] and
+/// [`probe_storage`].
+const PROBE_FILE_NAME: &str = ".second_write_probe";
+
+/// Bytes written by [`probe_storage`]: about two seconds of 16 kHz mono
+/// audio.
+const PROBE_BYTES: usize = 64 * 1024;
+
+/// How quickly the recordings directory takes writes (see [`probe_storage`]).
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct StorageProbe {
+    pub writable: bool,
+    /// Time to write the probe buffer, before it is flushed to disk.
+    pub write_latency_ms: f64,
+    /// Time to flush the probe file to disk.
+    pub fsync_latency_ms: f64,
+    /// Why the directory is not writable.
+    pub error: Option<String>,
+}
+
+/// Free and total space on the filesystem containing a directory.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DiskSpace {
+    pub available_bytes: u64,
+    pub total_bytes: u64,
+    /// How many minutes of audio fit in `available_bytes` at the current
+    /// capture byte rate.
+    pub estimated_minutes_at_current_config: f64,
+}
+
+/// Query the free space for the filesystem containing `dir` and estimate how
+/// many minutes of audio fit at `bytes_per_second`.
+///
+/// The recordings directory is created lazily on the first recording, so if
+/// `dir` does not exist yet the nearest existing ancestor is queried instead.
+///
+/// # Errors
+/// Returns an error if no ancestor of `dir` exists or the filesystem cannot
+/// be stat'd.
+pub fn disk_space(dir: &Path, bytes_per_second: u64) -> Result<DiskSpace, String> {
+    let existing = dir
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| format!("Cannot stat '{}': no such directory", dir.display()))?;
+
+    let (available_bytes, total_bytes) = filesystem_space(existing)?;
+
+    Ok(DiskSpace {
+        available_bytes,
+        total_bytes,
+        estimated_minutes_at_current_config: estimate_minutes(available_bytes, bytes_per_second),
+    })
+}
+
+/// Convert a byte budget into minutes of audio at the given byte rate.
+///
+/// Returns `0.0` for a zero byte rate rather than dividing by zero.
+pub fn estimate_minutes(available_bytes: u64, bytes_per_second: u64) -> f64 {
+    if bytes_per_second == 0 {
+        return 0.0;
+    }
+    available_bytes as f64 / bytes_per_second as f64 / 60.0
+}
+
+/// Return `(available, total)` bytes for the filesystem containing `path`.
+#[cfg(unix)]
+fn filesystem_space(path: &Path) -> Result<(u64, u64), String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| format!("Path '{}' contains a NUL byte", path.display()))?;
+
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `c_path` is a valid NUL-terminated string and `stat` is a
+    // properly sized, writable statvfs struct.
+    let rc = unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) };
+    if rc != 0 {
+        return Err(format!(
+            "Failed to stat filesystem for '{}': {}",
+            path.display(),
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    // Field widths differ between platforms (u32 on macOS, u64 on Linux).
+    #[allow(clippy::unnecessary_cast)]
+    let fragment = stat.f_frsize as u64;
+    #[allow(clippy::unnecessary_cast)]
+    let (available_blocks, total_blocks) = (stat.f_bavail as u64, stat.f_blocks as u64);
+
+    Ok((available_blocks * fragment, total_blocks * fragment))
+}
+
+/// Create `dir` if needed and verify that files can be created in it by
+/// writing and removing a small probe file.
+///
+/// # Errors
+/// Returns an [`ERR_STORAGE_UNAVAILABLE`] or [`ERR_DISK_FULL`] error naming
+/// `dir` when the volume is read-only, not writable, or full, and a generic
+/// error for anything else.
+pub fn ensure_writable(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir)
+        .map_err(|e| describe_io_error(dir, "create recordings directory", &e))?;
+
+    let probe = dir.join(PROBE_FILE_NAME);
+    fs::write(&probe, b"probe").map_err(|e| {
+        let _ = fs::remove_file(&probe);
+        describe_io_error(dir, "write to recordings directory", &e)
+    })?;
+    fs::remove_file(&probe).map_err(|e| describe_io_error(dir, "write to recordings directory", &e))
+}
+
+/// Like [`ensure_writable`], but time writing a small buffer to the probe
+/// file and flushing it to disk, so a folder too slow to record to (e.g. on
+/// a network mount) can be flagged before a recording starts.
+///
+/// A directory that cannot be written to is reported with `writable: false`
+/// and the error rather than failing.
+pub fn probe_storage(dir: &Path) -> StorageProbe {
+    match time_probe_write(dir) {
+        Ok((write_latency_ms, fsync_latency_ms)) => StorageProbe {
+            writable: true,
+            write_latency_ms,
+            fsync_latency_ms,
+            error: None,
+        },
+        Err(error) => StorageProbe {
+            writable: false,
+            write_latency_ms: 0.0,
+            fsync_latency_ms: 0.0,
+            error: Some(error),
+        },
+    }
+}
+
+/// Write, sync and remove the probe file in `dir`, returning the write and
+/// sync times in milliseconds.
+fn time_probe_write(dir: &Path) -> Result<(f64, f64), String> {
+    let action = "write to recordings directory";
+    fs::create_dir_all(dir)
+        .map_err(|e| describe_io_error(dir, "create recordings directory", &e))?;
+
+    let probe = dir.join(PROBE_FILE_NAME);
+    let timed = (|| {
+        let mut file = fs::File::create(&probe)?;
+        let started = Instant::now();
+        file.write_all(&[0u8; PROBE_BYTES])?;
+        let written = started.elapsed();
+        let started = Instant::now();
+        file.sync_all()?;
+        Ok((written, started.elapsed()))
+    })();
+    let (written, synced) = timed.map_err(|e: io::Error| {
+        let _ = fs::remove_file(&probe);
+        describe_io_error(dir, action, &e)
+    })?;
+    fs::remove_file(&probe).map_err(|e| describe_io_error(dir, action, &e))?;
+    Ok((
+        written.as_secs_f64() * 1000.0,
+        synced.as_secs_f64() * 1000.0,
+    ))
+}
+
+/// Verify that `dir` exists as a directory, without creating it.
+///
+/// # Errors
+/// Returns an [`ERR_RECORDINGS_DIR_MISSING`] error naming `dir` otherwise.
+pub fn ensure_dir_exists(dir: &Path) -> Result<(), String> {
+    if dir.is_dir() {
+        Ok(())
+    } else {
+        Err(format!("{ERR_RECORDINGS_DIR_MISSING}: '{}'", dir.display()))
+    }
+}
+
+/// Verify that the file at `path` can be created for writing, so a bad
+/// file name or a directory in the way is reported before recording starts.
+///
+/// A new file is created and removed again; an existing file is only opened
+/// for writing and left untouched.
+///
+/// # Errors
+/// Returns an error naming `path`, classified like [`describe_io_error`].
+pub fn ensure_creatable(path: &Path) -> Result<(), String> {
+    let action = "create recording file";
+    if path.exists() {
+        return fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map(drop)
+            .map_err(|e| describe_io_error(path, action, &e));
+    }
+    fs::File::create_new(path).map_err(|e| describe_io_error(path, action, &e))?;
+    fs::remove_file(path).map_err(|e| describe_io_error(path, action, &e))
+}
+
+/// Resolve `path` (following `..` and symlinks) and return it if it is a
+/// file inside `dir`, which must already be canonical.
+pub fn file_within(dir: &Path, path: &Path) -> Option<PathBuf> {
+    let resolved = path.canonicalize().ok()?;
+    (resolved.starts_with(dir) && resolved.is_file()).then_some(resolved)
+}
+
+/// Describe a failed filesystem `action` on `path`, using the
+/// [`ERR_STORAGE_UNAVAILABLE`] / [`ERR_DISK_FULL`] prefixes when the error
+/// means the location cannot be used.
+pub fn describe_io_error(path: &Path, action: &str, err: &io::Error) -> String {
+    match err.kind() {
+        io::ErrorKind::ReadOnlyFilesystem | io::ErrorKind::PermissionDenied => format!(
+            "{ERR_STORAGE_UNAVAILABLE}: cannot {action} '{}' ({err}). Choose a different recordings folder.",
+            path.display()
+        ),
+        io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => format!(
+            "{ERR_DISK_FULL}: cannot {action} '{}' ({err}). Free up space or choose a different recordings folder.",
+            path.display()
+        ),
+        _ => format!("Failed to {action} '{}': {err}", path.display()),
+    }
+}
+
+#[cfg(not(unix))]
+fn filesystem_space(path: &Path) -> Result<(u64, u64), String> {
+    Err(format!(
+        "Disk space query is not supported on this platform (path: '{}')",
+        path.display()
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_minutes_at_known_byte_rate() {
+        // 16 kHz mono 16-bit = 32,000 bytes/s = 1,920,000 bytes/min.
+        let minutes = estimate_minutes(1_920_000 * 10, 32_000);
+        assert!((minutes - 10.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_estimate_minutes_zero_rate_is_zero() {
+        assert_eq!(estimate_minutes(1_000_000, 0), 0.0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_disk_space_for_missing_subdir_uses_existing_ancestor() {
+        let dir = std::env::temp_dir().join("second_test_disk_space/not/created/yet");
+        let space = disk_space(&dir, 32_000).expect("disk space");
+        assert!(space.total_bytes > 0);
+        assert!(space.available_bytes <= space.total_bytes);
+    }
+
+    #[test]
+    fn test_describe_io_error_classifies_unusable_storage() {
+        let path = Path::new("/Volumes/Backup/recordings");
+
+        let read_only = io::Error::from(io::ErrorKind::ReadOnlyFilesystem);
+        let msg = describe_io_error(path, "create WAV file", &read_only);
+        assert!(msg.starts_with(ERR_STORAGE_UNAVAILABLE), "got: {msg}");
+        assert!(msg.contains("/Volumes/Backup/recordings"));
+
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(describe_io_error(path, "create WAV file", &denied)
+            .starts_with(ERR_STORAGE_UNAVAILABLE));
+
+        let full = io::Error::from(io::ErrorKind::StorageFull);
+        let msg = describe_io_error(path, "create WAV file", &full);
+        assert!(msg.starts_with(ERR_DISK_FULL), "got: {msg}");
+        assert!(msg.contains("/Volumes/Backup/recordings"));
+
+        let other = io::Error::from(io::ErrorKind::InvalidData);
+        assert!(describe_io_error(path, "create WAV file", &other)
+            .starts_with("Failed to create WAV file"));
+    }
+
+    #[test]
+    fn test_ensure_writable_creates_dir_and_leaves_no_probe() {
+        let dir = std::env::temp_dir().join("second_test_storage_writable/nested");
+        let _ = fs::remove_dir_all(dir.parent().unwrap());
+
+        ensure_writable(&dir).expect("writable");
+        assert!(dir.is_dir());
+        assert!(!dir.join(PROBE_FILE_NAME).exists());
+
+        let _ = fs::remove_dir_all(dir.parent().unwrap());
+    }
+
+    #[test]
+    fn test_ensure_creatable_leaves_nothing_behind() {
+        let dir = std::env::temp_dir().join("second_test_storage_creatable");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create test dir");
+
+        let path = dir.join("recording.wav");
+        ensure_creatable(&path).expect("creatable");
+        assert!(!path.exists());
+
+        fs::write(&path, b"keep").expect("write");
+        ensure_creatable(&path).expect("existing file");
+        assert_eq!(fs::read(&path).expect("read"), b"keep");
+
+        let blocked = dir.join("blocked.wav");
+        fs::create_dir(&blocked).expect("create dir in the way");
+        let err = ensure_creatable(&blocked).unwrap_err();
+        assert!(err.contains("create recording file"), "got: {err}");
+        assert!(err.contains("blocked.wav"), "got: {err}");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_probe_storage_times_local_dir() {
+        let dir = std::env::temp_dir().join("second_test_storage_probe");
+        let _ = fs::remove_dir_all(&dir);
+
+        let probe = probe_storage(&dir);
+        assert!(probe.writable, "got: {probe:?}");
+        assert_eq!(probe.error, None);
+        assert!(probe.write_latency_ms >= 0.0);
+        assert!(probe.fsync_latency_ms >= 0.0);
+        assert!(!dir.join(PROBE_FILE_NAME).exists());
+
+        // A file in the way of the directory is reported, not returned as
+        // an error.
+        let blocked = dir.join("blocked");
+        fs::write(&blocked, b"x").expect("write file");
+        let probe = probe_storage(&blocked);
+        assert!(!probe.writable);
+        assert!(probe.error.is_some());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ensure_writable_rejects_read_only_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join("second_test_storage_read_only");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create test dir");
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).expect("chmod");
+
+        // Root ignores directory permissions, so the platform can't simulate
+        // a read-only location there.
+        if fs::write(dir.join("probe_check"), b"x").is_ok() {
+            eprintln!("Skipping read-only test: permissions not enforced for this user");
+            let _ = fs::set_permissions(&dir, fs::Permissions::from_mode(0o755));
+            let _ = fs::remove_dir_all(&dir);
+            return;
+        }
+
+        let err = ensure_writable(&dir.join("recordings")).unwrap_err();
+        assert!(err.starts_with(ERR_STORAGE_UNAVAILABLE), "got: {err}");
+        assert!(err.contains("second_test_storage_read_only"));
+
+        let err = ensure_writable(&dir).unwrap_err();
+        assert!(err.starts_with(ERR_STORAGE_UNAVAILABLE), "got: {err}");
+
+        let _ = fs::set_permissions(&dir, fs::Permissions::from_mode(0o755));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
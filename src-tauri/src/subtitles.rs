@@ -0,0 +1,259 @@
+//! Subtitle export for timestamped transcripts.
+//!
+//! Formats the segments of a [`Transcript`] as SubRip (`.srt`) or WebVTT
+//! (`.vtt`) cues. Segments are cleaned up on the way: empty and partial
+//! ones are dropped, overlaps are trimmed, and zero-length ones are given a
+//! minimum duration so every cue is visible.
+
+use std::fs;
+use std::path::Path;
+
+use crate::protocol::{Transcript, TranscriptSegment};
+
+/// Shortest duration given to a cue whose segment has no length, in
+/// milliseconds. Never extends a cue past the start of the next one.
+pub const MIN_CUE_MS: u64 = 500;
+
+/// Subtitle file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+}
+
+/// One subtitle cue, in milliseconds from the start of the recording.
+#[derive(Debug, Clone, PartialEq)]
+struct Cue {
+    start_ms: u64,
+    end_ms: u64,
+    text: String,
+}
+
+/// Format `transcript` as a complete subtitle file.
+pub fn format_subtitles(transcript: &Transcript, format: SubtitleFormat) -> String {
+    let mut out = String::new();
+    if format == SubtitleFormat::Vtt {
+        out.push_str("WEBVTT\n\n");
+    }
+    for (i, cue) in cues(&transcript.segments).iter().enumerate() {
+        let (start, end) = match format {
+            SubtitleFormat::Srt => {
+                out.push_str(&format!("{}\n", i + 1));
+                (timecode(cue.start_ms, ','), timecode(cue.end_ms, ','))
+            }
+            SubtitleFormat::Vtt => (timecode(cue.start_ms, '.'), timecode(cue.end_ms, '.')),
+        };
+        out.push_str(&format!("{start} --> {end}\n{}\n\n", cue.text));
+    }
+    out
+}
+
+/// Write `transcript` to `output` as a subtitle file.
+///
+/// # Errors
+/// Returns an error if the file cannot be written.
+pub fn write_subtitles(
+    transcript: &Transcript,
+    format: SubtitleFormat,
+    output: &Path,
+) -> Result<(), String> {
+    fs::write(output, format_subtitles(transcript, format))
+        .map_err(|e| format!("Failed to write subtitles to '{}': {e}", output.display()))
+}
+
+/// The cues for `segments`, in start order.
+///
+/// A cue ends where the next one starts if the two overlap, and a
+/// zero-length (or backwards) cue is stretched to [`MIN_CUE_MS`] where
+/// there is room.
+fn cues(segments: &[TranscriptSegment]) -> Vec<Cue> {
+    let mut cues: Vec<Cue> = segments
+        .iter()
+        .filter(|s| !s.is_partial)
+        .filter_map(|s| {
+            let text = cue_text(&s.text);
+            (!text.is_empty()).then(|| Cue {
+                start_ms: seconds_to_ms(s.start),
+                end_ms: seconds_to_ms(s.end),
+                text,
+            })
+        })
+        .collect();
+    cues.sort_by_key(|c| c.start_ms);
+
+    let next_starts: Vec<Option<u64>> = cues
+        .iter()
+        .skip(1)
+        .map(|c| Some(c.start_ms))
+        .chain([None])
+        .collect();
+    for (cue, next_start) in cues.iter_mut().zip(next_starts) {
+        if cue.end_ms <= cue.start_ms {
+            cue.end_ms = cue.start_ms + MIN_CUE_MS;
+        }
+        if let Some(next_start) = next_start.filter(|&n| n > cue.start_ms) {
+            cue.end_ms = cue.end_ms.min(next_start);
+        }
+    }
+    cues
+}
+
+/// Segment text made safe for a cue: trimmed, without blank lines (which
+/// end a cue) or `-->` (which marks a timing line).
+fn cue_text(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .replace("-->", "->")
+}
+
+/// Whole milliseconds in `seconds`, rounded; negative and non-finite
+/// values become zero.
+fn seconds_to_ms(seconds: f64) -> u64 {
+    if seconds.is_finite() && seconds > 0.0 {
+        (seconds * 1000.0).round() as u64
+    } else {
+        0
+    }
+}
+
+/// `HH:MM:SS<separator>mmm`, with hours growing past two digits if needed.
+fn timecode(ms: u64, separator: char) -> String {
+    let (hours, rest) = (ms / 3_600_000, ms % 3_600_000);
+    let (minutes, rest) = (rest / 60_000, rest % 60_000);
+    let (seconds, millis) = (rest / 1000, rest % 1000);
+    format!("{hours:02}:{minutes:02}:{seconds:02}{separator}{millis:03}")
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, start: f64, end: f64) -> TranscriptSegment {
+        TranscriptSegment {
+            text: text.into(),
+            start,
+            end,
+            is_partial: false,
+        }
+    }
+
+    fn transcript(segments: Vec<TranscriptSegment>) -> Transcript {
+        Transcript {
+            text: String::new(),
+            segments,
+        }
+    }
+
+    fn sample() -> Transcript {
+        transcript(vec![
+            segment(" Hello there. ", 0.0, 2.5),
+            segment("Second line\nwraps.", 59.9996, 61.001),
+            segment("Past the hour.", 3_599.5, 3_723.004),
+        ])
+    }
+
+    #[test]
+    fn test_srt_matches_hand_written_output() {
+        let expected = "\
+1
+00:00:00,000 --> 00:00:02,500
+Hello there.
+
+2
+00:01:00,000 --> 00:01:01,001
+Second line
+wraps.
+
+3
+00:59:59,500 --> 01:02:03,004
+Past the hour.
+
+";
+        assert_eq!(format_subtitles(&sample(), SubtitleFormat::Srt), expected);
+    }
+
+    #[test]
+    fn test_vtt_matches_hand_written_output() {
+        let expected = "\
+WEBVTT
+
+00:00:00.000 --> 00:00:02.500
+Hello there.
+
+00:01:00.000 --> 00:01:01.001
+Second line
+wraps.
+
+00:59:59.500 --> 01:02:03.004
+Past the hour.
+
+";
+        assert_eq!(format_subtitles(&sample(), SubtitleFormat::Vtt), expected);
+    }
+
+    #[test]
+    fn test_timecode_edge_cases() {
+        assert_eq!(timecode(0, ','), "00:00:00,000");
+        assert_eq!(timecode(999, ','), "00:00:00,999");
+        assert_eq!(timecode(3_599_999, '.'), "00:59:59.999");
+        assert_eq!(timecode(3_600_000, '.'), "01:00:00.000");
+        assert_eq!(timecode(100 * 3_600_000 + 1, ','), "100:00:00,001");
+        // Rounds to the nearest millisecond, carrying into the next unit.
+        assert_eq!(seconds_to_ms(3_599.999_6), 3_600_000);
+        assert_eq!(seconds_to_ms(-1.0), 0);
+        assert_eq!(seconds_to_ms(f64::NAN), 0);
+    }
+
+    #[test]
+    fn test_cues_trim_overlaps_and_stretch_zero_length_segments() {
+        let cues = cues(&[
+            segment("third", 5.0, 5.0),
+            segment("first", 0.0, 3.0),
+            // Overlaps the first: the first is cut where this starts.
+            segment("second", 2.0, 4.8),
+            segment("   ", 6.0, 7.0),
+            TranscriptSegment {
+                is_partial: true,
+                ..segment("partial", 6.0, 7.0)
+            },
+            // No room for the full minimum before the next cue.
+            segment("fourth", 8.0, 7.5),
+            segment("fifth", 8.2, 9.0),
+        ]);
+        let timings: Vec<(&str, u64, u64)> = cues
+            .iter()
+            .map(|c| (c.text.as_str(), c.start_ms, c.end_ms))
+            .collect();
+        assert_eq!(
+            timings,
+            [
+                ("first", 0, 2_000),
+                ("second", 2_000, 4_800),
+                ("third", 5_000, 5_500),
+                ("fourth", 8_000, 8_200),
+                ("fifth", 8_200, 9_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cue_text_cannot_break_the_format() {
+        assert_eq!(cue_text("a\n\n b \n"), "a\nb");
+        assert_eq!(cue_text("x --> y"), "x -> y");
+    }
+
+    #[test]
+    fn test_empty_transcript() {
+        let empty = Transcript::default();
+        assert_eq!(format_subtitles(&empty, SubtitleFormat::Srt), "");
+        assert_eq!(format_subtitles(&empty, SubtitleFormat::Vtt), "WEBVTT\n\n");
+    }
+}
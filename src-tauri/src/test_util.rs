@@ -0,0 +1,27 @@
+//! Fixtures shared by the unit tests of several modules.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A fresh, empty `second_test_<module>_<name>` directory in the temp dir.
+pub fn test_dir(module: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("second_test_{module}_{name}"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create test dir");
+    dir
+}
+
+/// Write a 16-bit mono WAV of `frames` samples of a small ramp to `path`.
+pub fn write_test_wav(path: &Path, sample_rate: u32, frames: u32) {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).expect("create wav");
+    for i in 0..frames {
+        writer.write_sample((i % 100) as i16).expect("write sample");
+    }
+    writer.finalize().expect("finalize");
+}
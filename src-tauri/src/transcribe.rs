@@ -0,0 +1,1742 @@
+//! Transcription requests built from recorded WAV files.
+//!
+//! Reads a recording, converts it to the 16 kHz mono 16-bit PCM the sidecar
+//! expects, optionally compresses it, and sends it as a `transcribe_chunk`
+//! message.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use base64::Engine as _;
+use serde_json::Value;
+
+use crate::audio::capture::{convert_to_mono_16k, SAMPLE_RATE};
+use crate::media::ERR_UNSUPPORTED_MEDIA;
+use crate::protocol::{SidecarRequest, SidecarResponse, Transcript, TranscriptSegment};
+use crate::sidecar::{SidecarManager, ERR_SIDECAR_CLOSED, ERR_SIDECAR_TIMEOUT};
+
+/// Compression applied to the PCM bytes before base64 encoding.
+///
+/// The chosen codec is sent in the message's `compression` field so the
+/// sidecar knows to decompress before transcribing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Gzip,
+}
+
+impl Compression {
+    /// Value of the `compression` field in the wire protocol.
+    pub fn wire_name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+        }
+    }
+}
+
+/// Audio payload ready to embed in a `transcribe_chunk` message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedAudio {
+    pub audio_base64: String,
+    pub compression: Option<Compression>,
+    /// Size of the raw PCM before compression.
+    pub raw_bytes: usize,
+    /// Size after compression (equal to `raw_bytes` when uncompressed).
+    pub compressed_bytes: usize,
+}
+
+/// Read a WAV file as 16 kHz mono i16 samples, converting if the file is in
+/// a different format.
+///
+/// # Errors
+/// Returns an error if the file cannot be read as a WAV.
+pub fn read_samples_16k_mono(path: &Path) -> Result<Vec<i16>, String> {
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to read WAV '{}': {e}", path.display()))?;
+    let spec = reader.spec();
+
+    if spec.sample_rate == SAMPLE_RATE
+        && spec.channels == 1
+        && spec.bits_per_sample == 16
+        && spec.sample_format == hound::SampleFormat::Int
+    {
+        return reader
+            .samples::<i16>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read samples from '{}': {e}", path.display()));
+    }
+
+    // Normalise everything else to f32 and reuse the capture-path converter.
+    let floats: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read samples from '{}': {e}", path.display()))?,
+        hound::SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|v| v as f32 / scale))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read samples from '{}': {e}", path.display()))?
+        }
+    };
+
+    Ok(convert_to_mono_16k(
+        &floats,
+        spec.sample_rate,
+        spec.channels,
+    ))
+}
+
+/// Serialize i16 samples as little-endian PCM bytes.
+pub fn pcm_bytes(samples: &[i16]) -> Vec<u8> {
+    samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+}
+
+/// Compress `bytes` with the given codec.
+///
+/// # Errors
+/// Returns an error if the encoder fails.
+pub fn compress(bytes: &[u8], compression: Compression) -> Result<Vec<u8>, String> {
+    match compression {
+        Compression::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder
+                .write_all(bytes)
+                .map_err(|e| format!("Failed to gzip audio: {e}"))?;
+            encoder
+                .finish()
+                .map_err(|e| format!("Failed to gzip audio: {e}"))
+        }
+    }
+}
+
+/// Optionally compress PCM bytes and base64-encode the result.
+///
+/// # Errors
+/// Returns an error if compression fails.
+pub fn encode_audio(pcm: &[u8], compression: Option<Compression>) -> Result<EncodedAudio, String> {
+    let payload = match compression {
+        Some(codec) => compress(pcm, codec)?,
+        None => pcm.to_vec(),
+    };
+
+    Ok(EncodedAudio {
+        audio_base64: base64::engine::general_purpose::STANDARD.encode(&payload),
+        compression,
+        raw_bytes: pcm.len(),
+        compressed_bytes: payload.len(),
+    })
+}
+
+/// Build a `transcribe_chunk` request for an encoded payload.
+pub fn transcribe_chunk_message(
+    audio: &EncodedAudio,
+    initial_prompt: Option<&str>,
+) -> SidecarRequest {
+    SidecarRequest::TranscribeChunk {
+        audio_base64: audio.audio_base64.clone(),
+        initial_prompt: initial_prompt.map(String::from),
+        language: None,
+        compression: audio.compression,
+    }
+}
+
+/// Transcribe a WAV file by sending it to the sidecar as a single
+/// `transcribe_chunk` message and returning the sidecar's response.
+///
+/// If the sidecar rejects the file as too large (see [`is_size_error`]),
+/// it is transcribed again in smaller pieces as by
+/// [`transcribe_file_chunked`].
+///
+/// # Errors
+/// Returns an error if the file cannot be read, encoding fails, or the
+/// sidecar request fails.
+pub fn transcribe_file(
+    mgr: &mut SidecarManager,
+    path: &Path,
+    initial_prompt: Option<&str>,
+    compression: Option<Compression>,
+) -> Result<Value, String> {
+    transcribe_file_chunked(mgr, path, initial_prompt, compression, None)
+}
+
+/// Overlap between consecutive pieces of a split transcription, so a word
+/// cut at one boundary is heard whole in the next piece.
+pub const SPLIT_CHUNK_OVERLAP: Duration = Duration::from_secs(1);
+
+/// Piece length used when the sidecar rejects a whole file as too large
+/// and no `max_chunk_seconds` was given.
+pub const FALLBACK_CHUNK_SECONDS: f64 = 60.0;
+
+/// Shortest piece a size-related failure is retried with.
+const MIN_SPLIT_CHUNK_SECONDS: f64 = 5.0;
+
+/// Error `kind`s and message fragments backends use when a payload is too
+/// big to transcribe in one go.
+const SIZE_ERROR_KINDS: &[&str] = &["payload_too_large", "out_of_memory"];
+const SIZE_ERROR_HINTS: &[&str] = &[
+    "too large",
+    "too long",
+    "too big",
+    "payload",
+    "out of memory",
+    "memoryerror",
+];
+
+/// Whether `response` is a sidecar error caused by the audio being too
+/// large, judged by its `kind` or, for backends that only send a message,
+/// by the wording of `message`.
+pub fn is_size_error(response: &Value) -> bool {
+    if response.get("type").and_then(Value::as_str) != Some("error") {
+        return false;
+    }
+    if let Some(kind) = response.get("kind").and_then(Value::as_str) {
+        if SIZE_ERROR_KINDS.contains(&kind) {
+            return true;
+        }
+    }
+    let message = response
+        .get("message")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_lowercase();
+    SIZE_ERROR_HINTS.iter().any(|hint| message.contains(hint))
+        || message
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| word == "oom")
+}
+
+/// Transcribe a WAV file, splitting it into overlapping pieces of at most
+/// `max_chunk_seconds` when it is longer than that, or when the sidecar
+/// rejects the whole file as too large. Pieces rejected as too large are
+/// halved again, down to a few seconds.
+///
+/// The pieces' texts are joined with the words repeated across each
+/// overlap removed (see [`dedupe_overlap`]), and returned as
+/// `{"type": "transcription", "text", "chunks"}`. A sidecar error for any
+/// piece is returned as-is.
+///
+/// # Errors
+/// Returns an error if `max_chunk_seconds` is not positive, or for any
+/// reason [`transcribe_file`] fails.
+pub fn transcribe_file_chunked(
+    mgr: &mut SidecarManager,
+    path: &Path,
+    initial_prompt: Option<&str>,
+    compression: Option<Compression>,
+    max_chunk_seconds: Option<f64>,
+) -> Result<Value, String> {
+    if let Some(seconds) = max_chunk_seconds {
+        if !(seconds.is_finite() && seconds > 0.0) {
+            return Err(format!("max_chunk_seconds must be positive, got {seconds}"));
+        }
+    }
+    let samples = read_samples_16k_mono(path)?;
+    let limit = max_chunk_seconds.map(seconds_to_samples);
+
+    if let Some(limit) = limit.filter(|&limit| samples.len() > limit) {
+        return transcribe_split(mgr, &samples, limit, initial_prompt, compression);
+    }
+    let response: Value =
+        mgr.send_typed(&samples_request(&samples, initial_prompt, compression)?)?;
+    if !is_size_error(&response) || samples.len() < 2 {
+        return Ok(response);
+    }
+    eprintln!(
+        "transcribe_chunk: sidecar rejected {} samples as too large, splitting",
+        samples.len()
+    );
+    let chunk_samples = seconds_to_samples(FALLBACK_CHUNK_SECONDS).min(samples.len() / 2);
+    transcribe_split(mgr, &samples, chunk_samples, initial_prompt, compression)
+}
+
+/// Transcribe `samples` in overlapping pieces of `chunk_samples`.
+fn transcribe_split(
+    mgr: &mut SidecarManager,
+    samples: &[i16],
+    chunk_samples: usize,
+    initial_prompt: Option<&str>,
+    compression: Option<Compression>,
+) -> Result<Value, String> {
+    let chunk_samples = chunk_samples.max(1);
+    let overlap = seconds_to_samples(SPLIT_CHUNK_OVERLAP.as_secs_f64()).min(chunk_samples / 2);
+    let mut text = String::new();
+    let mut chunks = 0;
+    let mut start = 0;
+
+    loop {
+        let end = (start + chunk_samples).min(samples.len());
+        let request = samples_request(&samples[start..end], initial_prompt, compression)?;
+        let response: Value = mgr.send_typed(&request)?;
+        if is_size_error(&response) && chunk_samples > seconds_to_samples(MIN_SPLIT_CHUNK_SECONDS) {
+            // Start over rather than mix piece sizes, so every overlap is
+            // the same length.
+            eprintln!("transcribe_chunk: sidecar rejected a {chunk_samples}-sample piece, halving");
+            return transcribe_split(mgr, samples, chunk_samples / 2, initial_prompt, compression);
+        }
+        if response.get("type").and_then(Value::as_str) == Some("error") {
+            return Ok(response);
+        }
+
+        let piece = response
+            .get("text")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let piece = dedupe_overlap(&text, piece);
+        if !piece.is_empty() {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(&piece);
+        }
+        chunks += 1;
+
+        if end == samples.len() {
+            break;
+        }
+        start = end - overlap;
+    }
+
+    Ok(serde_json::json!({
+        "type": "transcription",
+        "text": text,
+        "chunks": chunks,
+    }))
+}
+
+/// `next` without its leading words that repeat the end of `previous`,
+/// as happens when two transcribed pieces of audio overlap. Words are
+/// compared ignoring case and surrounding punctuation, and the longest
+/// repeated run is dropped. The remaining words are joined with single
+/// spaces.
+pub fn dedupe_overlap(previous: &str, next: &str) -> String {
+    fn normalize(word: &str) -> String {
+        word.trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase()
+    }
+    let tail: Vec<String> = previous.split_whitespace().map(normalize).collect();
+    let words: Vec<&str> = next.split_whitespace().collect();
+    let head: Vec<String> = words.iter().map(|w| normalize(w)).collect();
+
+    let repeated = (1..=tail.len().min(head.len()))
+        .rev()
+        .find(|&n| tail[tail.len() - n..] == head[..n])
+        .unwrap_or(0);
+    words[repeated..].join(" ")
+}
+
+/// Number of 16 kHz samples in `seconds` of audio.
+fn seconds_to_samples(seconds: f64) -> usize {
+    (seconds * f64::from(SAMPLE_RATE)).round() as usize
+}
+
+/// [`transcribe_file`] for a backend that reports progress on long files:
+/// `on_progress` is called with the `fraction` (0.0 to 1.0) of each
+/// `progress` line the sidecar sends before its final response.
+///
+/// # Errors
+/// As for `transcribe_file`, including when the sidecar exits before the
+/// final response.
+pub fn transcribe_with_progress(
+    mgr: &mut SidecarManager,
+    path: &Path,
+    initial_prompt: Option<&str>,
+    compression: Option<Compression>,
+    on_progress: &mut dyn FnMut(f64),
+) -> Result<Value, String> {
+    let request = serde_json::to_value(file_request(path, initial_prompt, compression)?)
+        .map_err(|e| format!("Failed to serialize message: {e}"))?;
+    mgr.send_message_with_progress(request, &mut |line| {
+        if let Some(fraction) = line.get("fraction").and_then(Value::as_f64) {
+            on_progress(fraction.clamp(0.0, 1.0));
+        }
+    })
+}
+
+/// The `transcribe_chunk` request for the whole WAV at `path`.
+fn file_request(
+    path: &Path,
+    initial_prompt: Option<&str>,
+    compression: Option<Compression>,
+) -> Result<SidecarRequest, String> {
+    samples_request(&read_samples_16k_mono(path)?, initial_prompt, compression)
+}
+
+/// The `transcribe_chunk` request for 16 kHz mono `samples`.
+fn samples_request(
+    samples: &[i16],
+    initial_prompt: Option<&str>,
+    compression: Option<Compression>,
+) -> Result<SidecarRequest, String> {
+    let audio = encode_audio(&pcm_bytes(samples), compression)?;
+
+    if let Some(codec) = compression {
+        eprintln!(
+            "transcribe_chunk: {} compressed {} -> {} bytes ({:.1}% of original)",
+            codec.wire_name(),
+            audio.raw_bytes,
+            audio.compressed_bytes,
+            percent_of(audio.compressed_bytes, audio.raw_bytes)
+        );
+    }
+
+    Ok(transcribe_chunk_message(&audio, initial_prompt))
+}
+
+/// Length of the silent clip transcribed by [`warmup_request`].
+pub const WARMUP_CLIP_DURATION: Duration = Duration::from_millis(500);
+
+/// A `transcribe_chunk` request for a short silent clip. Transcribing it
+/// makes the sidecar load its model, so the first real chunk is fast.
+///
+/// # Errors
+/// Returns an error if encoding fails.
+pub fn warmup_request() -> Result<SidecarRequest, String> {
+    let frames = crate::audio::capture::silence_frames(WARMUP_CLIP_DURATION, SAMPLE_RATE);
+    let audio = encode_audio(&pcm_bytes(&vec![0; frames as usize]), None)?;
+    Ok(transcribe_chunk_message(&audio, None))
+}
+
+/// Number of transcripts kept by [`TranscriptCache`].
+pub const TRANSCRIPT_CACHE_CAPACITY: usize = 32;
+
+/// Recent transcripts keyed by recording path, modification time, prompt
+/// and language, so re-running an unchanged file with a prompt it has
+/// already been transcribed with skips the sidecar.
+#[derive(Debug, Default)]
+pub struct TranscriptCache {
+    entries: VecDeque<(CacheKey, Value)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct CacheKey {
+    path: PathBuf,
+    modified: SystemTime,
+    initial_prompt: Option<String>,
+    language: Option<String>,
+}
+
+impl TranscriptCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<&Value> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Store `transcript`, evicting the oldest entry when full.
+    fn insert(&mut self, key: CacheKey, transcript: Value) {
+        self.entries.retain(|(k, _)| *k != key);
+        if self.entries.len() >= TRANSCRIPT_CACHE_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, transcript));
+    }
+}
+
+/// Number of failures kept by [`TranscriptionErrorLog`].
+pub const TRANSCRIPTION_ERROR_LOG_CAPACITY: usize = 100;
+
+/// What kind of failure a [`TranscriptionError`] was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptionErrorKind {
+    /// The sidecar answered with an error.
+    Backend,
+    /// The sidecar exited or closed its output mid-request.
+    SidecarClosed,
+    /// The sidecar did not answer in time.
+    Timeout,
+    /// The file could not be decoded.
+    UnsupportedMedia,
+    /// Anything else, e.g. a missing or unreadable file.
+    Other,
+}
+
+impl TranscriptionErrorKind {
+    /// The kind of the error `message` returned by a transcription.
+    pub fn of(message: &str) -> Self {
+        if message.contains(ERR_SIDECAR_CLOSED) {
+            Self::SidecarClosed
+        } else if message.contains(ERR_SIDECAR_TIMEOUT) {
+            Self::Timeout
+        } else if message.contains(ERR_UNSUPPORTED_MEDIA) {
+            Self::UnsupportedMedia
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// One failed transcription.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct TranscriptionError {
+    pub timestamp_unix_ms: u64,
+    pub path: String,
+    pub kind: TranscriptionErrorKind,
+    pub message: String,
+}
+
+/// The most recent transcription failures, oldest first, for spotting
+/// patterns such as every long file failing. Kept apart from the sidecar,
+/// so it survives restarts.
+#[derive(Debug, Default)]
+pub struct TranscriptionErrorLog {
+    entries: VecDeque<TranscriptionError>,
+}
+
+impl TranscriptionErrorLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note a failure to transcribe `path`, evicting the oldest when full.
+    pub fn record(&mut self, path: &str, kind: TranscriptionErrorKind, message: &str) {
+        if self.entries.len() >= TRANSCRIPTION_ERROR_LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        let timestamp_unix_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        self.entries.push_back(TranscriptionError {
+            timestamp_unix_ms,
+            path: path.to_string(),
+            kind,
+            message: message.to_string(),
+        });
+    }
+
+    /// Note `response` if it is a sidecar error.
+    pub fn record_response(&mut self, path: &str, response: &Value) {
+        if response.get("type").and_then(Value::as_str) == Some("error") {
+            let message = response
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("Sidecar reported an error");
+            self.record(path, TranscriptionErrorKind::Backend, message);
+        }
+    }
+
+    /// The last `limit` failures (all of them without one), oldest first.
+    pub fn recent(&self, limit: Option<usize>) -> Vec<TranscriptionError> {
+        let limit = limit.unwrap_or(self.entries.len());
+        let skip = self.entries.len().saturating_sub(limit);
+        self.entries.iter().skip(skip).cloned().collect()
+    }
+}
+
+/// Transcribe an existing recording again, typically with a different
+/// `initial_prompt`. The audio file is only read, never modified.
+///
+/// With a `cache`, a previous transcript of the same file (same mtime) and
+/// prompt is returned without contacting the sidecar, and fresh successful
+/// transcripts are stored in it.
+///
+/// # Errors
+/// Returns an error if the recording no longer exists, or for any reason
+/// [`transcribe_file`] fails.
+pub fn retranscribe(
+    mgr: &mut SidecarManager,
+    cache: Option<&mut TranscriptCache>,
+    path: &Path,
+    initial_prompt: Option<&str>,
+    compression: Option<Compression>,
+) -> Result<Value, String> {
+    let modified = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Recording '{}' is not available: {e}", path.display()))?;
+
+    let key = CacheKey {
+        path: path.to_path_buf(),
+        modified,
+        initial_prompt: initial_prompt.map(String::from),
+        language: mgr.language().map(String::from),
+    };
+    if let Some(hit) = cache.as_ref().and_then(|c| c.get(&key)) {
+        return Ok(hit.clone());
+    }
+
+    let transcript = transcribe_file(mgr, path, initial_prompt, compression)?;
+    // Sidecar-reported failures are not worth remembering.
+    if let Some(cache) = cache {
+        if transcript.get("type").and_then(Value::as_str) != Some("error") {
+            cache.insert(key, transcript.clone());
+        }
+    }
+    Ok(transcript)
+}
+
+/// Characters of recent transcript a [`TranscriptionSession`] keeps for
+/// replay after a sidecar restart.
+pub const RESUME_TAIL_CHARS: usize = 500;
+
+/// State of a chunk-by-chunk transcription stream that must survive the
+/// sidecar crashing mid-stream.
+///
+/// The session remembers the initial prompt and the tail of the transcript
+/// so far. When a chunk fails because the sidecar died, it restarts the
+/// sidecar, replays that context as a `resume_context` message, and then
+/// retries the chunk.
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptionSession {
+    initial_prompt: Option<String>,
+    compression: Option<Compression>,
+    transcript_tail: String,
+}
+
+impl TranscriptionSession {
+    pub fn new(initial_prompt: Option<String>, compression: Option<Compression>) -> Self {
+        Self {
+            initial_prompt,
+            compression,
+            transcript_tail: String::new(),
+        }
+    }
+
+    /// The last [`RESUME_TAIL_CHARS`] characters of transcript received.
+    #[allow(dead_code)]
+    pub fn transcript_tail(&self) -> &str {
+        &self.transcript_tail
+    }
+
+    /// The `resume_context` message replayed after a restart.
+    pub fn resume_request(&self) -> SidecarRequest {
+        SidecarRequest::ResumeContext {
+            initial_prompt: self.initial_prompt.clone(),
+            transcript_tail: self.transcript_tail.clone(),
+        }
+    }
+
+    /// Transcribe one chunk of 16 kHz mono samples.
+    ///
+    /// If the sidecar has crashed, it is restarted, sent
+    /// [`resume_request`](Self::resume_request), and the chunk is retried
+    /// once; `on_resumed` is called with the replayed tail before the retry.
+    /// A sidecar that rejects `resume_context` is logged and used anyway.
+    ///
+    /// # Errors
+    /// Returns an error if encoding fails, the sidecar fails for a reason
+    /// other than a crash, or the restart or retry fails.
+    pub fn submit_chunk(
+        &mut self,
+        mgr: &mut SidecarManager,
+        samples: &[i16],
+        on_resumed: &mut dyn FnMut(&str),
+    ) -> Result<SidecarResponse, String> {
+        let audio = encode_audio(&pcm_bytes(samples), self.compression)?;
+        let request = transcribe_chunk_message(&audio, self.initial_prompt.as_deref());
+
+        let response = match mgr.send_typed::<_, SidecarResponse>(&request) {
+            Ok(response) => response,
+            Err(e) if e.starts_with(ERR_SIDECAR_CLOSED) || !mgr.is_running() => {
+                eprintln!("Sidecar died mid-stream ({e}); restarting");
+                mgr.restart()?;
+                let ack: SidecarResponse = mgr.send_typed(&self.resume_request())?;
+                if let SidecarResponse::Error { message } = ack {
+                    eprintln!("Sidecar did not accept resume_context: {message}");
+                }
+                on_resumed(&self.transcript_tail);
+                mgr.send_typed(&request)?
+            }
+            Err(e) => return Err(e),
+        };
+
+        if let SidecarResponse::Transcription { text, .. } = &response {
+            self.remember(text);
+        }
+        Ok(response)
+    }
+
+    /// Append `text` to the tail, keeping only the last
+    /// [`RESUME_TAIL_CHARS`] characters.
+    fn remember(&mut self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        if !self.transcript_tail.is_empty() {
+            self.transcript_tail.push(' ');
+        }
+        self.transcript_tail.push_str(text);
+
+        let excess = self
+            .transcript_tail
+            .chars()
+            .count()
+            .saturating_sub(RESUME_TAIL_CHARS);
+        if let Some((cut, _)) = self.transcript_tail.char_indices().nth(excess) {
+            self.transcript_tail.drain(..cut);
+        }
+    }
+}
+
+/// Audio length of each chunk sent by [`stream_transcribe_file`].
+pub const STREAM_CHUNK_DURATION: Duration = Duration::from_secs(5);
+
+/// Transcribe a WAV file incrementally: split it into `chunk_duration`
+/// chunks (the last one possibly shorter) and send them in order through a
+/// [`TranscriptionSession`].
+///
+/// `on_partial` is called after each chunk with the cumulative text so far.
+/// Returns the full text.
+///
+/// # Errors
+/// Returns an error if the file cannot be read, `chunk_duration` is shorter
+/// than one sample, a chunk fails, or the sidecar reports an error.
+pub fn stream_transcribe_file(
+    mgr: &mut SidecarManager,
+    path: &Path,
+    initial_prompt: Option<&str>,
+    chunk_duration: Duration,
+    on_partial: &mut dyn FnMut(&str),
+) -> Result<String, String> {
+    let transcript = stream_transcript(mgr, path, initial_prompt, chunk_duration, &mut |t, _| {
+        on_partial(&t.text);
+        Ok(())
+    })?;
+    Ok(transcript.text)
+}
+
+/// Called by [`stream_transcript`] after each chunk with the transcript so
+/// far and the segments that chunk added.
+type ChunkCallback<'a> = dyn FnMut(&Transcript, &[TranscriptSegment]) -> Result<(), String> + 'a;
+
+/// The chunk loop behind [`stream_transcribe_file`], building a whole
+/// [`Transcript`]. Segment times are shifted to be relative to the start of
+/// the file; a chunk with text but no segments becomes one segment spanning
+/// the chunk.
+///
+/// An error from `on_chunk` stops the stream.
+fn stream_transcript(
+    mgr: &mut SidecarManager,
+    path: &Path,
+    initial_prompt: Option<&str>,
+    chunk_duration: Duration,
+    on_chunk: &mut ChunkCallback<'_>,
+) -> Result<Transcript, String> {
+    let chunk_samples = (chunk_duration.as_secs_f64() * f64::from(SAMPLE_RATE)).round() as usize;
+    if chunk_samples == 0 {
+        return Err("Chunk duration must be at least one sample".into());
+    }
+
+    let samples = read_samples_16k_mono(path)?;
+    let mut session = TranscriptionSession::new(initial_prompt.map(String::from), None);
+    let mut transcript = Transcript::default();
+
+    for (index, chunk) in samples.chunks(chunk_samples).enumerate() {
+        let offset = (index * chunk_samples) as f64 / f64::from(SAMPLE_RATE);
+        let added = transcript.segments.len();
+        match session.submit_chunk(mgr, chunk, &mut |_| {})? {
+            SidecarResponse::Transcription {
+                text: chunk_text,
+                segments,
+                ..
+            } => {
+                let chunk_text = chunk_text.trim();
+                if !chunk_text.is_empty() {
+                    if !transcript.text.is_empty() {
+                        transcript.text.push(' ');
+                    }
+                    transcript.text.push_str(chunk_text);
+                }
+                if segments.is_empty() && !chunk_text.is_empty() {
+                    transcript.segments.push(TranscriptSegment {
+                        text: chunk_text.to_string(),
+                        start: offset,
+                        end: offset + chunk.len() as f64 / f64::from(SAMPLE_RATE),
+                        is_partial: false,
+                    });
+                }
+                transcript
+                    .segments
+                    .extend(segments.into_iter().map(|segment| TranscriptSegment {
+                        start: segment.start + offset,
+                        end: segment.end + offset,
+                        ..segment
+                    }));
+            }
+            SidecarResponse::Error { message } => {
+                return Err(format!("Sidecar failed to transcribe chunk: {message}"));
+            }
+            _ => {}
+        }
+        on_chunk(&transcript, &transcript.segments[added..])?;
+    }
+
+    Ok(transcript)
+}
+
+/// Where [`stream_transcribe_to_file`] keeps the transcript of `output`
+/// while it is being written: `<output>.partial`.
+pub fn partial_transcript_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_os_string();
+    name.push(".partial");
+    PathBuf::from(name)
+}
+
+/// [`stream_transcribe_file`], also saving the transcript to `output` as
+/// it goes, for files long enough that the app may be closed before the end.
+///
+/// Each chunk's segments are appended to [`partial_transcript_path`] as
+/// JSON lines as soon as they arrive, so a crash or failure leaves
+/// everything transcribed so far there. Once every chunk is done the file
+/// is replaced by the finished [`Transcript`] as JSON and renamed to
+/// `output`, replacing any file already there.
+///
+/// # Errors
+/// As for `stream_transcribe_file`, or if the partial file cannot be
+/// written or renamed. The partial file is left in place.
+pub fn stream_transcribe_to_file(
+    mgr: &mut SidecarManager,
+    path: &Path,
+    output: &Path,
+    initial_prompt: Option<&str>,
+    chunk_duration: Duration,
+    on_partial: &mut dyn FnMut(&str),
+) -> Result<Transcript, String> {
+    let partial = partial_transcript_path(output);
+    let write_err = |e: std::io::Error| format!("Failed to write '{}': {e}", partial.display());
+    let mut file = std::fs::File::create(&partial).map_err(write_err)?;
+    let transcript = stream_transcript(
+        mgr,
+        path,
+        initial_prompt,
+        chunk_duration,
+        &mut |so_far, added| {
+            let mut lines = String::new();
+            for segment in added {
+                let line = serde_json::to_string(segment)
+                    .map_err(|e| format!("Failed to serialize segment: {e}"))?;
+                lines.push_str(&line);
+                lines.push('\n');
+            }
+            file.write_all(lines.as_bytes())
+                .and_then(|()| file.sync_data())
+                .map_err(write_err)?;
+            on_partial(&so_far.text);
+            Ok(())
+        },
+    )?;
+    drop(file);
+
+    let json = serde_json::to_string_pretty(&transcript)
+        .map_err(|e| format!("Failed to serialize transcript: {e}"))?;
+    std::fs::write(&partial, json)
+        .and_then(|()| std::fs::File::open(&partial)?.sync_all())
+        .map_err(write_err)?;
+    std::fs::rename(&partial, output).map_err(|e| {
+        format!(
+            "Failed to rename '{}' to '{}': {e}",
+            partial.display(),
+            output.display()
+        )
+    })?;
+    Ok(transcript)
+}
+
+/// Length of the clip transcribed by [`benchmark_transcription`].
+pub const BENCHMARK_CLIP_DURATION: Duration = Duration::from_secs(5);
+
+/// Frequency of the benchmark tone, in Hz.
+const BENCHMARK_TONE_HZ: f64 = 440.0;
+
+/// Timing of one benchmark transcription, reported to the frontend.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct BenchmarkResult {
+    pub audio_duration_ms: u64,
+    /// Wall-clock time from sending the request to receiving the reply.
+    pub processing_ms: u64,
+    /// `processing_ms / audio_duration_ms`; below 1.0 is faster than
+    /// real time.
+    pub real_time_factor: f64,
+}
+
+/// Processing time as a fraction of the audio's duration, or 0 for empty
+/// audio.
+pub fn real_time_factor(audio: Duration, processing: Duration) -> f64 {
+    if audio.is_zero() {
+        return 0.0;
+    }
+    processing.as_secs_f64() / audio.as_secs_f64()
+}
+
+/// A 16 kHz mono sine tone lasting `duration`, so every benchmark sends the
+/// same audio.
+pub fn benchmark_tone(duration: Duration) -> Vec<i16> {
+    let frames = crate::audio::capture::silence_frames(duration, SAMPLE_RATE);
+    (0..frames)
+        .map(|i| {
+            let t = i as f64 / f64::from(SAMPLE_RATE);
+            let sample = 0.3 * (2.0 * std::f64::consts::PI * BENCHMARK_TONE_HZ * t).sin();
+            (sample * f64::from(i16::MAX)) as i16
+        })
+        .collect()
+}
+
+/// Time one transcription of a [`BENCHMARK_CLIP_DURATION`] tone.
+///
+/// A sidecar that has not been warmed up is warmed up first, so model
+/// loading is not counted.
+///
+/// # Errors
+/// Returns an error if the warm-up or the request fails, or the sidecar
+/// reports an error.
+pub fn benchmark_transcription(mgr: &mut SidecarManager) -> Result<BenchmarkResult, String> {
+    if !mgr.is_ready() {
+        mgr.warm_up(&warmup_request()?, crate::sidecar::WARMUP_TIMEOUT)?;
+    }
+
+    let audio = encode_audio(&pcm_bytes(&benchmark_tone(BENCHMARK_CLIP_DURATION)), None)?;
+    let request = transcribe_chunk_message(&audio, None);
+
+    let started = Instant::now();
+    let response: SidecarResponse = mgr.send_typed(&request)?;
+    let processing = started.elapsed();
+
+    if let SidecarResponse::Error { message } = response {
+        return Err(format!(
+            "Sidecar failed to transcribe benchmark clip: {message}"
+        ));
+    }
+
+    Ok(BenchmarkResult {
+        audio_duration_ms: BENCHMARK_CLIP_DURATION.as_millis() as u64,
+        processing_ms: processing.as_millis() as u64,
+        real_time_factor: real_time_factor(BENCHMARK_CLIP_DURATION, processing),
+    })
+}
+
+/// Time spent in each stage of one transcription, in milliseconds.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct PipelineTiming {
+    /// Reading the WAV and converting it to 16 kHz mono.
+    pub read_ms: u64,
+    /// Compressing (if asked) and base64-encoding the PCM.
+    pub encode_ms: u64,
+    /// From sending the request to receiving the sidecar's reply.
+    pub sidecar_round_trip_ms: u64,
+    /// The sum of the stages.
+    pub total_ms: u64,
+}
+
+impl PipelineTiming {
+    /// Timing for the given stage durations.
+    pub fn from_stages(read: Duration, encode: Duration, round_trip: Duration) -> Self {
+        let ms = |d: Duration| d.as_millis() as u64;
+        PipelineTiming {
+            read_ms: ms(read),
+            encode_ms: ms(encode),
+            sidecar_round_trip_ms: ms(round_trip),
+            total_ms: ms(read + encode + round_trip),
+        }
+    }
+}
+
+/// Transcribe the WAV at `path` in one `transcribe_chunk` request, timing
+/// the read, encode and sidecar stages separately to tell slow I/O from a
+/// slow model.
+///
+/// # Errors
+/// Returns an error if the file cannot be read, encoding or the request
+/// fails, or the sidecar reports an error.
+pub fn timed_transcribe(
+    mgr: &mut SidecarManager,
+    path: &Path,
+    initial_prompt: Option<&str>,
+    compression: Option<Compression>,
+) -> Result<PipelineTiming, String> {
+    let started = Instant::now();
+    let samples = read_samples_16k_mono(path)?;
+    let read = started.elapsed();
+
+    let started = Instant::now();
+    let audio = encode_audio(&pcm_bytes(&samples), compression)?;
+    let request = transcribe_chunk_message(&audio, initial_prompt);
+    let encode = started.elapsed();
+
+    let started = Instant::now();
+    let response: SidecarResponse = mgr.send_typed(&request)?;
+    let round_trip = started.elapsed();
+
+    if let SidecarResponse::Error { message } = response {
+        return Err(format!(
+            "Sidecar failed to transcribe '{}': {message}",
+            path.display()
+        ));
+    }
+    Ok(PipelineTiming::from_stages(read, encode, round_trip))
+}
+
+/// `part` as a percentage of `whole`, or 100% for an empty whole.
+fn percent_of(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 100.0;
+    }
+    part as f64 / whole as f64 * 100.0
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Read;
+
+    fn gunzip(bytes: &[u8]) -> Vec<u8> {
+        let mut decoder = flate2::read::GzDecoder::new(bytes);
+        let mut out = Vec::new();
+        decoder.read_to_end(&mut out).expect("gunzip");
+        out
+    }
+
+    fn sample_pcm() -> Vec<u8> {
+        // A quiet sine-ish ramp with long silent stretches, like speech.
+        let samples: Vec<i16> = (0..16_000)
+            .map(|i| if i % 4000 < 1000 { (i % 200) as i16 } else { 0 })
+            .collect();
+        pcm_bytes(&samples)
+    }
+
+    #[test]
+    fn test_gzip_round_trip_restores_pcm() {
+        let pcm = sample_pcm();
+        let compressed = compress(&pcm, Compression::Gzip).expect("compress");
+        assert!(compressed.len() < pcm.len());
+        assert_eq!(gunzip(&compressed), pcm);
+    }
+
+    #[test]
+    fn test_encode_audio_round_trip_through_base64() {
+        let pcm = sample_pcm();
+        let encoded = encode_audio(&pcm, Some(Compression::Gzip)).expect("encode");
+        assert_eq!(encoded.raw_bytes, pcm.len());
+        assert!(encoded.compressed_bytes < encoded.raw_bytes);
+
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(&encoded.audio_base64)
+            .expect("base64");
+        assert_eq!(gunzip(&decoded), pcm);
+    }
+
+    #[test]
+    fn test_encode_audio_without_compression_is_plain_base64() {
+        let pcm = vec![1u8, 2, 3, 4];
+        let encoded = encode_audio(&pcm, None).expect("encode");
+        assert_eq!(encoded.audio_base64, "AQIDBA==");
+        assert_eq!(encoded.compressed_bytes, 4);
+    }
+
+    #[test]
+    fn test_transcribe_chunk_message_flags_compression() {
+        let encoded = encode_audio(&[0, 0], Some(Compression::Gzip)).expect("encode");
+        let msg = serde_json::to_value(transcribe_chunk_message(&encoded, Some("Alice Bob")))
+            .expect("serialize");
+        assert_eq!(msg["type"], "transcribe_chunk");
+        assert_eq!(msg["compression"], "gzip");
+        assert_eq!(msg["initial_prompt"], "Alice Bob");
+
+        let plain = encode_audio(&[0, 0], None).expect("encode");
+        let msg = serde_json::to_value(transcribe_chunk_message(&plain, None)).expect("serialize");
+        assert!(msg.get("compression").is_none());
+        assert!(msg.get("initial_prompt").is_none());
+    }
+
+    #[test]
+    fn test_compression_deserializes_from_lowercase() {
+        let codec: Compression = serde_json::from_str("\"gzip\"").expect("parse");
+        assert_eq!(codec, Compression::Gzip);
+    }
+
+    /// Write a short silent 16 kHz mono WAV to `path`.
+    fn write_silent_wav(path: &Path) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).expect("create");
+        for _ in 0..160 {
+            writer.write_sample(0i16).expect("sample");
+        }
+        writer.finalize().expect("finalize");
+    }
+
+    #[test]
+    fn test_retranscribe_against_echo_sidecar_uses_cache() {
+        let Ok(python) = crate::sidecar::find_python(None, None) else {
+            eprintln!("Skipping retranscribe test: python not found");
+            return;
+        };
+        let dir = std::env::temp_dir().join("second_test_transcribe_retranscribe");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        std::fs::write(
+            dir.join("main.py"),
+            "import sys\nfor line in sys.stdin:\n    sys.stdout.write(line)\n    sys.stdout.flush()\n",
+        )
+        .expect("write main.py");
+        let wav = dir.join("recording.wav");
+        write_silent_wav(&wav);
+        let before = std::fs::read(&wav).expect("read wav");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start echo backend");
+        let mut cache = TranscriptCache::new();
+
+        let first = retranscribe(&mut mgr, Some(&mut cache), &wav, Some("Alice"), None)
+            .expect("retranscribe");
+        assert_eq!(first["type"], "transcribe_chunk");
+        assert_eq!(first["initial_prompt"], "Alice");
+
+        let second = retranscribe(&mut mgr, Some(&mut cache), &wav, Some("Bob"), None)
+            .expect("retranscribe");
+        assert_eq!(second["initial_prompt"], "Bob");
+        assert_eq!(std::fs::read(&wav).expect("read wav"), before);
+
+        // With the sidecar gone, only cached (path, mtime, prompt) pairs work.
+        mgr.stop().expect("stop");
+        let cached =
+            retranscribe(&mut mgr, Some(&mut cache), &wav, Some("Alice"), None).expect("cache hit");
+        assert_eq!(cached, first);
+        assert!(retranscribe(&mut mgr, Some(&mut cache), &wav, Some("Carol"), None).is_err());
+        assert!(retranscribe(&mut mgr, None, &wav, Some("Alice"), None).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_retranscribe_missing_file_is_error() {
+        let mut mgr = SidecarManager::new();
+        let path = std::env::temp_dir().join("second_test_transcribe_missing.wav");
+        let _ = std::fs::remove_file(&path);
+        let err = retranscribe(&mut mgr, None, &path, None, None).unwrap_err();
+        assert!(err.contains("is not available"), "got: {err}");
+    }
+
+    #[test]
+    fn test_transcription_error_log_keeps_most_recent_in_order() {
+        let mut log = TranscriptionErrorLog::new();
+        for i in 0..TRANSCRIPTION_ERROR_LOG_CAPACITY + 5 {
+            log.record(
+                &format!("/r/{i}.wav"),
+                TranscriptionErrorKind::Other,
+                "failed",
+            );
+        }
+
+        let all = log.recent(None);
+        assert_eq!(all.len(), TRANSCRIPTION_ERROR_LOG_CAPACITY);
+        assert_eq!(all[0].path, "/r/5.wav");
+
+        let paths: Vec<String> = log.recent(Some(3)).into_iter().map(|e| e.path).collect();
+        let last = TRANSCRIPTION_ERROR_LOG_CAPACITY + 4;
+        assert_eq!(
+            paths,
+            [last - 2, last - 1, last].map(|i| format!("/r/{i}.wav"))
+        );
+        assert!(log.recent(Some(0)).is_empty());
+    }
+
+    #[test]
+    fn test_transcription_error_kinds() {
+        let mut log = TranscriptionErrorLog::new();
+        log.record_response("/a.wav", &json!({"type": "transcription", "text": ""}));
+        log.record_response(
+            "/b.wav",
+            &json!({"type": "error", "message": "CUDA out of memory"}),
+        );
+        let errors = log.recent(None);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, TranscriptionErrorKind::Backend);
+        assert_eq!(errors[0].message, "CUDA out of memory");
+
+        assert_eq!(
+            TranscriptionErrorKind::of(&format!("{ERR_SIDECAR_TIMEOUT} after 30 s")),
+            TranscriptionErrorKind::Timeout
+        );
+        assert_eq!(
+            TranscriptionErrorKind::of(ERR_SIDECAR_CLOSED),
+            TranscriptionErrorKind::SidecarClosed
+        );
+        assert_eq!(
+            TranscriptionErrorKind::of("Failed to read WAV"),
+            TranscriptionErrorKind::Other
+        );
+    }
+
+    #[test]
+    fn test_transcript_cache_evicts_oldest() {
+        let mut cache = TranscriptCache::new();
+        let key = |i: usize| CacheKey {
+            path: PathBuf::from("rec.wav"),
+            modified: SystemTime::UNIX_EPOCH,
+            initial_prompt: Some(i.to_string()),
+            language: None,
+        };
+        for i in 0..=TRANSCRIPT_CACHE_CAPACITY {
+            cache.insert(key(i), json!(i));
+        }
+        assert!(cache.get(&key(0)).is_none());
+        assert_eq!(cache.get(&key(1)), Some(&json!(1)));
+        assert_eq!(cache.entries.len(), TRANSCRIPT_CACHE_CAPACITY);
+    }
+
+    #[test]
+    fn test_read_samples_converts_stereo_48k() {
+        let path = std::env::temp_dir().join("second_test_transcribe_stereo.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create");
+        for _ in 0..4800 {
+            writer.write_sample(1000i16).expect("left");
+            writer.write_sample(1000i16).expect("right");
+        }
+        writer.finalize().expect("finalize");
+
+        let samples = read_samples_16k_mono(&path).expect("read");
+        // 4800 frames at 48 kHz = 0.1 s = 1600 frames at 16 kHz.
+        assert_eq!(samples.len(), 1600);
+        assert!(samples.iter().all(|&s| (s - 1000).abs() <= 1));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Backend that logs every request to `received.jsonl`, answers chunks
+    /// with "chunk N" and `resume_context` with an ack, and crashes without
+    /// replying on its second chunk the first time it runs.
+    const RESTARTING_BACKEND: &str = r#"import json, os, sys
+chunks = 0
+for line in sys.stdin:
+    msg = json.loads(line)
+    with open("received.jsonl", "a") as log:
+        log.write(line)
+    if msg["type"] == "resume_context":
+        print(json.dumps({"type": "context_resumed"}), flush=True)
+        continue
+    chunks += 1
+    if chunks == 2 and not os.path.exists("crashed"):
+        open("crashed", "w").close()
+        sys.exit(1)
+    text = "chunk %d" % chunks
+    print(json.dumps({"type": "transcription", "text": text, "segments": []}), flush=True)
+"#;
+
+    #[test]
+    fn test_session_resumes_context_after_sidecar_restart() {
+        let Ok(python) = crate::sidecar::find_python(None, None) else {
+            eprintln!("Skipping session restart test: python not found");
+            return;
+        };
+        let dir = std::env::temp_dir().join("second_test_transcribe_session_restart");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        std::fs::write(dir.join("main.py"), RESTARTING_BACKEND).expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        let mut session = TranscriptionSession::new(Some("Alice".into()), None);
+        let mut resumed = Vec::new();
+        let mut on_resumed = |tail: &str| resumed.push(tail.to_string());
+
+        session
+            .submit_chunk(&mut mgr, &[0; 160], &mut on_resumed)
+            .expect("first chunk");
+        // The backend dies here; the session restarts it and retries.
+        let second = session
+            .submit_chunk(&mut mgr, &[0; 160], &mut on_resumed)
+            .expect("second chunk");
+        assert!(
+            matches!(&second, SidecarResponse::Transcription { text, .. } if text == "chunk 1"),
+            "got: {second:?}"
+        );
+        assert_eq!(resumed, vec!["chunk 1".to_string()]);
+        assert_eq!(session.transcript_tail(), "chunk 1 chunk 1");
+
+        let received: Vec<Value> = std::fs::read_to_string(dir.join("received.jsonl"))
+            .expect("read log")
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("json"))
+            .collect();
+        let types: Vec<&str> = received
+            .iter()
+            .map(|m| m["type"].as_str().unwrap_or_default())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "transcribe_chunk",
+                "transcribe_chunk",
+                "resume_context",
+                "transcribe_chunk"
+            ]
+        );
+        assert_eq!(received[2]["initial_prompt"], "Alice");
+        assert_eq!(received[2]["transcript_tail"], "chunk 1");
+
+        mgr.stop().expect("stop");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_session_tail_keeps_last_chars() {
+        let mut session = TranscriptionSession::new(None, None);
+        session.remember("  ");
+        assert_eq!(session.transcript_tail(), "");
+
+        let word = "é".repeat(RESUME_TAIL_CHARS - 1);
+        session.remember(&word);
+        session.remember("ab");
+        assert_eq!(session.transcript_tail().chars().count(), RESUME_TAIL_CHARS);
+        assert!(session.transcript_tail().ends_with("é ab"));
+    }
+
+    #[test]
+    fn test_stream_transcribe_file_emits_one_partial_per_chunk() {
+        let Ok(python) = crate::sidecar::find_python(None, None) else {
+            eprintln!("Skipping streaming test: python not found");
+            return;
+        };
+        let dir = std::env::temp_dir().join("second_test_transcribe_stream_file");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        // Echoes each chunk's length in samples as its transcript.
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import base64, json, sys
+for line in sys.stdin:
+    msg = json.loads(line)
+    samples = len(base64.b64decode(msg["audio_base64"])) // 2
+    print(json.dumps({"type": "transcription", "text": str(samples)}), flush=True)
+"#,
+        )
+        .expect("write main.py");
+        // 0.25 s of audio: two full 0.1 s chunks and a 0.05 s tail.
+        let wav = dir.join("recording.wav");
+        write_silent_wav(&wav);
+        let mut writer = hound::WavWriter::append(&wav).expect("append");
+        for _ in 0..(4000 - 160) {
+            writer.write_sample(0i16).expect("sample");
+        }
+        writer.finalize().expect("finalize");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        let mut partials = Vec::new();
+        let text = stream_transcribe_file(
+            &mut mgr,
+            &wav,
+            None,
+            Duration::from_millis(100),
+            &mut |text| partials.push(text.to_string()),
+        )
+        .expect("stream");
+
+        assert_eq!(partials, ["1600", "1600 1600", "1600 1600 800"]);
+        assert_eq!(text, "1600 1600 800");
+
+        mgr.stop().expect("stop");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stream_transcribe_to_file_renames_partial_when_done() {
+        let Ok(python) = crate::sidecar::find_python(None, None) else {
+            eprintln!("Skipping streaming file test: python not found");
+            return;
+        };
+        let dir = std::env::temp_dir().join("second_test_transcribe_stream_to_file");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        // One segment per chunk, timed within the chunk; fails the fifth.
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import json, sys
+for n, line in enumerate(sys.stdin):
+    if n == 4:
+        print(json.dumps({"type": "error", "message": "out of memory"}), flush=True)
+        continue
+    segment = {"text": "chunk%d" % n, "start": 0.01, "end": 0.05}
+    print(json.dumps({"type": "transcription", "text": segment["text"], "segments": [segment]}), flush=True)
+"#,
+        )
+        .expect("write main.py");
+        // 0.25 s of audio: three chunks of 0.1 s.
+        let wav = dir.join("recording.wav");
+        write_silent_wav(&wav);
+        let mut writer = hound::WavWriter::append(&wav).expect("append");
+        for _ in 0..(4000 - 160) {
+            writer.write_sample(0i16).expect("sample");
+        }
+        writer.finalize().expect("finalize");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        let output = dir.join("recording.json");
+        let partial = partial_transcript_path(&output);
+        assert_eq!(partial, dir.join("recording.json.partial"));
+        let chunk = Duration::from_millis(100);
+        let mut partials = Vec::new();
+        let transcript =
+            stream_transcribe_to_file(&mut mgr, &wav, &output, None, chunk, &mut |t| {
+                partials.push(t.to_string())
+            })
+            .expect("stream to file");
+
+        assert_eq!(
+            partials,
+            ["chunk0", "chunk0 chunk1", "chunk0 chunk1 chunk2"]
+        );
+
+        assert_eq!(transcript.text, "chunk0 chunk1 chunk2");
+        let starts: Vec<f64> = transcript.segments.iter().map(|s| s.start).collect();
+        assert_eq!(starts.len(), 3);
+        assert!((starts[2] - 0.21).abs() < 1e-9, "got {starts:?}");
+        assert!(!partial.exists());
+        let saved: Transcript =
+            serde_json::from_str(&std::fs::read_to_string(&output).expect("read output"))
+                .expect("parse output");
+        assert_eq!(saved.text, transcript.text);
+        assert_eq!(saved.segments.len(), 3);
+        assert!((saved.segments[1].start - 0.11).abs() < 1e-9);
+
+        // The backend fails the next file's second chunk: what was
+        // transcribed stays in the partial file, one segment per line.
+        let failed = dir.join("failed.json");
+        assert!(
+            stream_transcribe_to_file(&mut mgr, &wav, &failed, None, chunk, &mut |_| {}).is_err()
+        );
+        assert!(!failed.exists());
+        let lines = std::fs::read_to_string(partial_transcript_path(&failed)).expect("partial");
+        let segments: Vec<TranscriptSegment> = lines
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("segment line"))
+            .collect();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, "chunk3");
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_dedupe_overlap_drops_repeated_leading_words() {
+        assert_eq!(
+            dedupe_overlap("the quick brown fox", "brown fox jumps over"),
+            "jumps over"
+        );
+        // Case and punctuation at the boundary do not matter.
+        assert_eq!(
+            dedupe_overlap("Hello there, world.", "World, again"),
+            "again"
+        );
+        // The longest repeated run wins.
+        assert_eq!(dedupe_overlap("a b a b", "a b a b c"), "c");
+        assert_eq!(dedupe_overlap("one two", "three four"), "three four");
+        assert_eq!(dedupe_overlap("", "  first   words "), "first words");
+        assert_eq!(dedupe_overlap("same text", "same text"), "");
+    }
+
+    #[test]
+    fn test_is_size_error_by_kind_or_message() {
+        let error = |body: Value| {
+            let mut response = json!({"type": "error"});
+            response
+                .as_object_mut()
+                .unwrap()
+                .extend(body.as_object().unwrap().clone());
+            response
+        };
+        assert!(is_size_error(&error(
+            json!({"message": "Audio payload too large"})
+        )));
+        assert!(is_size_error(&error(
+            json!({"message": "CUDA OOM while decoding"})
+        )));
+        assert!(is_size_error(&error(
+            json!({"kind": "out_of_memory", "message": "x"})
+        )));
+        assert!(!is_size_error(&error(
+            json!({"message": "Model not loaded"})
+        )));
+        assert!(!is_size_error(&error(
+            json!({"message": "Zoom room unavailable"})
+        )));
+        assert!(!is_size_error(
+            &json!({"type": "transcription", "text": "too large"})
+        ));
+    }
+
+    #[test]
+    fn test_transcribe_file_chunked_splits_and_joins_overlaps() {
+        let Ok(python) = crate::sidecar::find_python(None, None) else {
+            eprintln!("Skipping chunked transcription test: python not found");
+            return;
+        };
+        let dir = std::env::temp_dir().join("second_test_transcribe_chunked");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        // Rejects more than 2.5 s of audio; otherwise "transcribes" each run
+        // of equal samples as the word w<value / 100>.
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import array, base64, json, sys
+for line in sys.stdin:
+    msg = json.loads(line)
+    samples = array.array("h", base64.b64decode(msg["audio_base64"]))
+    if len(samples) > 40000:
+        print(json.dumps({"type": "error", "message": "audio payload too large"}), flush=True)
+        continue
+    words = []
+    for s in samples:
+        word = "w%d" % (s // 100)
+        if not words or words[-1] != word:
+            words.append(word)
+    print(json.dumps({"type": "transcription", "text": " ".join(words)}), flush=True)
+"#,
+        )
+        .expect("write main.py");
+        // Ten 0.5 s "words", 5 s in all.
+        let wav = dir.join("recording.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&wav, spec).expect("create");
+        for word in 1..=10i16 {
+            for _ in 0..8000 {
+                writer.write_sample(word * 100).expect("sample");
+            }
+        }
+        writer.finalize().expect("finalize");
+        let expected = "w1 w2 w3 w4 w5 w6 w7 w8 w9 w10";
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+
+        // Rejected whole, then retried as two 2.5 s pieces and a tail.
+        let fallback = transcribe_file(&mut mgr, &wav, None, None).expect("fallback");
+        assert_eq!(fallback["text"], expected);
+        assert_eq!(fallback["chunks"], 3);
+
+        // Split up front into 2 s pieces overlapping by 1 s.
+        let proactive =
+            transcribe_file_chunked(&mut mgr, &wav, None, None, Some(2.0)).expect("proactive");
+        assert_eq!(proactive["text"], expected);
+        assert_eq!(proactive["chunks"], 4);
+
+        assert!(transcribe_file_chunked(&mut mgr, &wav, None, None, Some(0.0)).is_err());
+
+        mgr.stop().expect("stop");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_transcribe_with_progress_reports_fractions_until_final() {
+        let Ok(python) = crate::sidecar::find_python(None, None) else {
+            eprintln!("Skipping progress test: python not found");
+            return;
+        };
+        let dir = std::env::temp_dir().join("second_test_transcribe_progress");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        // Sends two progress lines before each transcript, and exits after
+        // the first progress line of the third request.
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import json, sys
+for n, line in enumerate(sys.stdin, 1):
+    print(json.dumps({"type": "progress", "fraction": 0.4}), flush=True)
+    if n == 3:
+        sys.exit(0)
+    print(json.dumps({"type": "progress", "fraction": 0.8}), flush=True)
+    print(json.dumps({"type": "transcription", "text": f"done {n}"}), flush=True)
+"#,
+        )
+        .expect("write main.py");
+        let wav = dir.join("recording.wav");
+        write_silent_wav(&wav);
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        let mut fractions = Vec::new();
+        let response =
+            transcribe_with_progress(&mut mgr, &wav, None, None, &mut |f| fractions.push(f))
+                .expect("transcribe");
+        assert_eq!(fractions, [0.4, 0.8]);
+        assert_eq!(response["text"], "done 1");
+
+        // Without a callback the progress lines are skipped.
+        let response = transcribe_file(&mut mgr, &wav, None, None).expect("transcribe");
+        assert_eq!(response["text"], "done 2");
+
+        fractions.clear();
+        let err = transcribe_with_progress(&mut mgr, &wav, None, None, &mut |f| fractions.push(f))
+            .unwrap_err();
+        assert!(err.starts_with(ERR_SIDECAR_CLOSED), "got: {err}");
+        assert_eq!(fractions, [0.4]);
+
+        let _ = mgr.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_real_time_factor_from_known_durations() {
+        let rtf = real_time_factor(Duration::from_secs(10), Duration::from_millis(2_500));
+        assert!((rtf - 0.25).abs() < 1e-9);
+        let rtf = real_time_factor(Duration::from_secs(2), Duration::from_secs(3));
+        assert!((rtf - 1.5).abs() < 1e-9);
+        assert_eq!(
+            real_time_factor(Duration::ZERO, Duration::from_secs(1)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_benchmark_tone_is_audible_and_sized() {
+        let tone = benchmark_tone(Duration::from_millis(250));
+        assert_eq!(tone.len(), 4_000);
+        let peak = tone.iter().map(|s| s.unsigned_abs()).max().unwrap();
+        assert!(peak > i16::MAX as u16 / 4, "peak {peak}");
+    }
+
+    #[test]
+    fn test_benchmark_transcription_times_the_reply() {
+        let Ok(python) = crate::sidecar::find_python(None, None) else {
+            eprintln!("Skipping benchmark test: python not found");
+            return;
+        };
+        let dir = std::env::temp_dir().join("second_test_transcribe_benchmark");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        // Answers the silent warm-up at once and the tone after 100 ms.
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import base64, json, sys, time
+for line in sys.stdin:
+    pcm = base64.b64decode(json.loads(line)["audio_base64"])
+    if any(pcm):
+        time.sleep(0.1)
+    print(json.dumps({"type": "transcription", "text": ""}), flush=True)
+"#,
+        )
+        .expect("write main.py");
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        let result = benchmark_transcription(&mut mgr).expect("benchmark");
+        assert!(mgr.is_ready());
+
+        assert_eq!(result.audio_duration_ms, 5_000);
+        assert!(result.processing_ms >= 100, "got {result:?}");
+        let expected = result.processing_ms as f64 / 5_000.0;
+        assert!(
+            (result.real_time_factor - expected).abs() < 0.001,
+            "got {result:?}"
+        );
+
+        mgr.stop().expect("stop");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_pipeline_timing_totals_the_stages() {
+        let timing = PipelineTiming::from_stages(
+            Duration::from_millis(12),
+            Duration::from_millis(3),
+            Duration::from_millis(250),
+        );
+        assert_eq!(
+            timing,
+            PipelineTiming {
+                read_ms: 12,
+                encode_ms: 3,
+                sidecar_round_trip_ms: 250,
+                total_ms: 265,
+            }
+        );
+
+        // Sub-millisecond stages still count towards the total.
+        let timing = PipelineTiming::from_stages(
+            Duration::from_micros(600),
+            Duration::from_micros(600),
+            Duration::ZERO,
+        );
+        assert_eq!(
+            (timing.read_ms, timing.encode_ms, timing.total_ms),
+            (0, 0, 1)
+        );
+    }
+
+    #[test]
+    fn test_timed_transcribe_measures_sidecar_round_trip() {
+        let Ok(python) = crate::sidecar::find_python(None, None) else {
+            eprintln!("Skipping timed transcribe test: python not found");
+            return;
+        };
+        let dir = std::env::temp_dir().join("second_test_transcribe_timed");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test dir");
+        std::fs::write(
+            dir.join("main.py"),
+            r#"import json, sys, time
+for line in sys.stdin:
+    time.sleep(0.1)
+    print(json.dumps({"type": "transcription", "text": ""}), flush=True)
+"#,
+        )
+        .expect("write main.py");
+        let wav = dir.join("recording.wav");
+        write_silent_wav(&wav);
+
+        let mut mgr = SidecarManager::new();
+        mgr.start(&python, dir.to_str().expect("utf-8"))
+            .expect("start backend");
+        let timing =
+            timed_transcribe(&mut mgr, &wav, None, Some(Compression::Gzip)).expect("timed");
+        assert!(timing.sidecar_round_trip_ms >= 100, "got {timing:?}");
+        // The total is rounded once, the stages each, so they may differ
+        // by a millisecond per stage.
+        let stages = timing.read_ms + timing.encode_ms + timing.sidecar_round_trip_ms;
+        assert!(
+            (stages..=stages + 2).contains(&timing.total_ms),
+            "got {timing:?}"
+        );
+
+        mgr.stop().expect("stop");
+        assert!(timed_transcribe(&mut mgr, &dir.join("missing.wav"), None, None).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stream_transcribe_file_rejects_zero_chunk() {
+        let mut mgr = SidecarManager::new();
+        let path = std::env::temp_dir().join("second_test_transcribe_zero_chunk.wav");
+        let err =
+            stream_transcribe_file(&mut mgr, &path, None, Duration::ZERO, &mut |_| {}).unwrap_err();
+        assert!(err.contains("at least one sample"), "got: {err}");
+    }
+}
@@ -107,9 +107,28 @@ export async function startAudioRecording(deviceName?: string): Promise<string>
   return invoke<string>('start_audio_recording', { deviceName: deviceName ?? null });
 }
 
-/** Stop the current audio recording. Returns the path to the finalized WAV file. */
-export async function stopAudioRecording(): Promise<string> {
-  return invoke<string>('stop_audio_recording');
+/** Summary of a finished recording, returned by `stopAudioRecording`. */
+export interface RecordingResult {
+  /** First (or only) finalized WAV file. */
+  path: string;
+  /** Every finalized WAV file, one per segment when segmentation is enabled. */
+  paths: string[];
+  duration_seconds: number;
+  frames: number;
+  /** Largest absolute sample, from 0 to 1. */
+  peak: number;
+  clipped_samples: number;
+  device: string;
+  sample_rate: number;
+  channels: number;
+}
+
+/**
+ * Stop the current audio recording. Returns the finalized WAV path(s) with
+ * the recording's duration, levels, device, and format.
+ */
+export async function stopAudioRecording(): Promise<RecordingResult> {
+  return invoke<RecordingResult>('stop_audio_recording');
 }
 
 // ---------------------------------------------------------------------------